@@ -0,0 +1,347 @@
+//! The HTTP API of the node.
+//!
+//! ```text
+//! POST /validate/header        pre-validates a block header, see [`ValidateHeaderRequest`]
+//! GET  /block_index/{a}/{b}    the block index entries of heights a..=b, newest first
+//! GET  /info                   the height and weave size of the local block index
+//! GET  /vdf/verify?height=|indep_hash=
+//!                              recomputes the VDF checkpoints of a block
+//! GET  /metrics                the validation metrics in the Prometheus text format
+//! ```
+//!
+//! Errors are answered with a JSON object holding an `error` message. A header
+//! posted while the validation queue is full is answered with
+//! `503 Service Unavailable`, and at most [`MAX_VDF_CHECKS`] VDF checks run at
+//! once, the others wait for one of them to finish.
+//!
+//! [`MAX_VDF_CHECKS`]: crate::node::MAX_VDF_CHECKS
+use crate::node::NodeState;
+use crate::validation::{QueueFull, VdfVerdict, Verdict};
+use arweave_rs_indexes::block_index_scraper::BlockIndexJson;
+use arweave_rs_types::*;
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Json, Router,
+};
+use color_eyre::eyre::Result;
+use serde_derive::{Deserialize, Serialize};
+use std::net::TcpListener;
+use std::sync::Arc;
+
+/// The most block index entries served by a single request, the same limit
+/// the Arweave peers apply.
+pub const MAX_BLOCK_INDEX_RANGE: u64 = 1000;
+
+/// Builds the router serving the API from the shared node `state`.
+pub fn router(state: Arc<NodeState>) -> Router {
+    Router::new()
+        .route("/validate/header", post(validate_header))
+        .route("/block_index/:start/:end", get(block_index))
+        .route("/info", get(info))
+        .route("/vdf/verify", get(vdf_verify))
+        .route("/metrics", get(metrics))
+        .with_state(state)
+}
+
+/// Serves the API on the `listener` until the process stops.
+pub async fn serve(listener: TcpListener, state: Arc<NodeState>) -> Result<()> {
+    listener.set_nonblocking(true)?;
+    axum::Server::from_tcp(listener)?
+        .serve(router(state).into_make_service())
+        .await?;
+    Ok(())
+}
+
+/// An error answered with its status code and a JSON `{"error": message}`.
+#[derive(Debug)]
+pub struct ApiError {
+    status: StatusCode,
+    message: String,
+}
+
+impl ApiError {
+    fn bad_request(message: impl Into<String>) -> Self {
+        ApiError {
+            status: StatusCode::BAD_REQUEST,
+            message: message.into(),
+        }
+    }
+
+    fn not_found(message: impl Into<String>) -> Self {
+        ApiError {
+            status: StatusCode::NOT_FOUND,
+            message: message.into(),
+        }
+    }
+
+    /// The peers failed to provide a header the request refers to.
+    fn bad_gateway(err: color_eyre::eyre::Report) -> Self {
+        ApiError {
+            status: StatusCode::BAD_GATEWAY,
+            message: format!("{err:#}"),
+        }
+    }
+
+    /// The validation worker failed to validate a header, answering 503 if
+    /// its queue is full.
+    fn worker(err: color_eyre::eyre::Report) -> Self {
+        match err.downcast_ref::<QueueFull>() {
+            Some(queue_full) => ApiError {
+                status: StatusCode::SERVICE_UNAVAILABLE,
+                message: queue_full.to_string(),
+            },
+            None => ApiError::internal(err),
+        }
+    }
+
+    fn internal(err: impl std::fmt::Display) -> Self {
+        ApiError {
+            status: StatusCode::INTERNAL_SERVER_ERROR,
+            message: err.to_string(),
+        }
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let body = serde_json::json!({ "error": self.message });
+        (self.status, Json(body)).into_response()
+    }
+}
+
+/// Identifies the parent of a header posted to `/validate/header`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ParentRef {
+    /// The full parent header, validated without requesting anything.
+    Header(Box<ArweaveBlockHeader>),
+    /// The `indep_hash` of the parent, requested from the peers.
+    IndepHash(String),
+    /// The height of the parent, requested from the peers.
+    Height(u64),
+}
+
+/// The body of a `/validate/header` request.
+///
+/// ```json
+/// { "header": { ... }, "parent": { "height": 1315999 }, "validate_vdf": false }
+/// ```
+///
+/// Without a `parent` the header's `previous_block` is requested from the
+/// peers.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ValidateHeaderRequest {
+    pub header: ArweaveBlockHeader,
+    #[serde(default)]
+    pub parent: Option<ParentRef>,
+    /// Also recompute the VDF checkpoints, around a second per VDF step.
+    #[serde(default)]
+    pub validate_vdf: bool,
+}
+
+/// Pre-validates the posted header and answers its [`Verdict`]. A header
+/// failing validation is still a successful request.
+async fn validate_header(
+    State(state): State<Arc<NodeState>>,
+    Json(request): Json<ValidateHeaderRequest>,
+) -> Result<Json<Verdict>, ApiError> {
+    let header = request.header;
+    let parent = match request.parent {
+        Some(ParentRef::Header(parent)) => *parent,
+        Some(ParentRef::IndepHash(indep_hash)) => {
+            let indep_hash = decode_indep_hash(&indep_hash)?;
+            state
+                .client
+                .block_by_hash(&indep_hash)
+                .await
+                .map_err(ApiError::bad_gateway)?
+        }
+        Some(ParentRef::Height(height)) => state
+            .client
+            .block_by_height(height)
+            .await
+            .map_err(ApiError::bad_gateway)?,
+        None => state
+            .client
+            .block_by_hash(&header.previous_block)
+            .await
+            .map_err(ApiError::bad_gateway)?,
+    };
+    if parent.indep_hash != header.previous_block {
+        return Err(ApiError::bad_request(format!(
+            "the parent {} at height {} is not the previous_block {} of the header",
+            base64_url::encode(&parent.indep_hash),
+            parent.height,
+            base64_url::encode(&header.previous_block)
+        )));
+    }
+
+    if !request.validate_vdf {
+        let verdict = state
+            .worker
+            .pre_validate(header, parent)
+            .await
+            .map_err(ApiError::worker)?;
+        return Ok(Json(verdict));
+    }
+
+    let (header, parent) = (Arc::new(header), Arc::new(parent));
+    let mut verdict = state
+        .worker
+        .pre_validate(header.as_ref().clone(), parent.as_ref().clone())
+        .await
+        .map_err(ApiError::worker)?;
+    let params = state.params.clone();
+    let verdict = check_vdf(&state, move || {
+        verdict.check_vdf(&header, &parent, &params);
+        verdict
+    })
+    .await?;
+    Ok(Json(verdict))
+}
+
+/// Answers the entries of heights `start..=end` newest first, in the same
+/// format as the Arweave peers.
+async fn block_index(
+    State(state): State<Arc<NodeState>>,
+    Path((start, end)): Path<(u64, u64)>,
+) -> Result<Json<Vec<BlockIndexJson>>, ApiError> {
+    if start > end {
+        return Err(ApiError::bad_request(format!(
+            "the start height {start} is above the end height {end}"
+        )));
+    }
+    if end - start >= MAX_BLOCK_INDEX_RANGE {
+        return Err(ApiError::bad_request(format!(
+            "at most {MAX_BLOCK_INDEX_RANGE} entries are served per request"
+        )));
+    }
+
+    let block_index = state.block_index();
+    let num_indexes = block_index.num_indexes();
+    if start == 0 || end > num_indexes {
+        return Err(ApiError::not_found(format!(
+            "the block index holds heights 1..={num_indexes}"
+        )));
+    }
+    // The index is requested from height 1, so item i holds height i + 1
+    let entries = (start..=end)
+        .rev()
+        .filter_map(|height| block_index.get_item(height as usize - 1))
+        .map(BlockIndexJson::from)
+        .collect();
+    Ok(Json(entries))
+}
+
+/// The body of an `/info` response. The fields share their names with the
+/// Arweave peers `/info` so an `ArweaveClient` can read it.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct InfoJson {
+    /// The height of the latest block in the local block index.
+    pub height: u64,
+    /// The `indep_hash` of the latest indexed block.
+    pub current: String,
+    /// The number of blocks in the local block index.
+    pub blocks: u64,
+    /// The weave size at the latest indexed block, as a decimal string.
+    pub weave_size: String,
+    /// The number of peers headers are requested from.
+    pub peers: u64,
+}
+
+async fn info(State(state): State<Arc<NodeState>>) -> Json<InfoJson> {
+    let block_index = state.block_index();
+    let num_indexes = block_index.num_indexes();
+    let latest = block_index.latest_item();
+    Json(InfoJson {
+        height: num_indexes,
+        current: latest.map_or_else(String::new, |item| base64_url::encode(&item.block_hash)),
+        blocks: num_indexes,
+        weave_size: latest.map_or(0, |item| item.weave_size).to_string(),
+        peers: state.client.peers().len() as u64,
+    })
+}
+
+/// Renders the metrics when the node was started with a recorder.
+async fn metrics(State(state): State<Arc<NodeState>>) -> Result<String, ApiError> {
+    match &state.metrics {
+        Some(handle) => Ok(handle.render()),
+        None => Err(ApiError::not_found("metrics are not recorded")),
+    }
+}
+
+/// Identifies the block whose VDF is verified, by exactly one of its fields.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct VdfQuery {
+    pub height: Option<u64>,
+    pub indep_hash: Option<String>,
+}
+
+/// Requests the block and its parent from the peers and recomputes the VDF
+/// checkpoints of the block.
+async fn vdf_verify(
+    State(state): State<Arc<NodeState>>,
+    Query(query): Query<VdfQuery>,
+) -> Result<Json<VdfVerdict>, ApiError> {
+    let header = match (query.height, query.indep_hash) {
+        (Some(height), None) => state.client.block_by_height(height).await,
+        (None, Some(indep_hash)) => {
+            let indep_hash = decode_indep_hash(&indep_hash)?;
+            state.client.block_by_hash(&indep_hash).await
+        }
+        _ => {
+            return Err(ApiError::bad_request(
+                "expected either height or indep_hash",
+            ))
+        }
+    }
+    .map_err(ApiError::bad_gateway)?;
+    if header.height == 0 {
+        return Err(ApiError::bad_request(
+            "the genesis block has no VDF to verify",
+        ));
+    }
+    let parent = state
+        .client
+        .block_by_hash(&header.previous_block)
+        .await
+        .map_err(ApiError::bad_gateway)?;
+
+    let params = state.params.clone();
+    let verdict = check_vdf(&state, move || VdfVerdict::new(&header, &parent, &params)).await?;
+    Ok(Json(verdict))
+}
+
+/// Runs the VDF `check` on the blocking threads once one of the VDF permits
+/// is free. The permit is held until the check returns, even if the request
+/// is dropped before then.
+async fn check_vdf<T: Send + 'static>(
+    state: &NodeState,
+    check: impl FnOnce() -> T + Send + 'static,
+) -> Result<T, ApiError> {
+    let permit = state
+        .vdf_permits
+        .clone()
+        .acquire_owned()
+        .await
+        .map_err(ApiError::internal)?;
+    tokio::task::spawn_blocking(move || {
+        let result = check();
+        drop(permit);
+        result
+    })
+    .await
+    .map_err(ApiError::internal)
+}
+
+fn decode_indep_hash(indep_hash: &str) -> Result<H384, ApiError> {
+    match base64_url::decode(indep_hash) {
+        Ok(bytes) if bytes.len() == 48 => Ok(H384::from_slice(&bytes)),
+        _ => Err(ApiError::bad_request(format!(
+            "{indep_hash} is not a base64url encoded 48 byte hash"
+        ))),
+    }
+}
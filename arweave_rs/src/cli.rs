@@ -0,0 +1,98 @@
+//! Parses the command line of the node.
+use arweave_rs_client::DEFAULT_PEER;
+use color_eyre::eyre::eyre;
+use eyre::Result;
+use std::net::SocketAddr;
+use std::time::Duration;
+
+pub const USAGE: &str = "\
+Serves Arweave block validation and the local block index over HTTP.
+
+Usage: arweave_rs [OPTIONS]
+
+Endpoints:
+  POST /validate/header          Pre-validate a block header
+  GET  /block_index/{a}/{b}      The block index entries of heights a..=b
+  GET  /info                     The height and weave size of the block index
+  GET  /vdf/verify?height=<HEIGHT>|indep_hash=<HASH>
+                                 Recompute the VDF checkpoints of a block
+  GET  /metrics                  Validation metrics for Prometheus
+
+Options:
+  --listen <ADDR>         Address the API listens on [default: 127.0.0.1:1985]
+  --peer <URL>            Peer to request headers and the block index from,
+                          repeat it to spread the requests across several
+                          peers [default: https://arweave.net]
+  --index <PATH>          File the block index is kept in
+                          [default: data/index.dat]
+  --sync-interval <SECS>  Seconds between updates of the block index
+                          [default: 60]
+  -h, --help              Print this help";
+
+#[derive(Debug, Clone)]
+pub struct Cli {
+    pub help: bool,
+    pub listen: SocketAddr,
+    pub peer_urls: Vec<String>,
+    pub index_path: String,
+    pub sync_interval: Duration,
+}
+
+/// Parses the `args` following the program name.
+pub fn parse_args<I: IntoIterator<Item = String>>(args: I) -> Result<Cli> {
+    let mut cli = Cli {
+        help: false,
+        listen: SocketAddr::from(([127, 0, 0, 1], 1985)),
+        peer_urls: Vec::new(),
+        index_path: "data/index.dat".to_string(),
+        sync_interval: Duration::from_secs(60),
+    };
+
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        // Accept both `--flag value` and `--flag=value`
+        let (flag, inline_value) = match arg.split_once('=') {
+            Some((flag, value)) if arg.starts_with("--") => {
+                (flag.to_string(), Some(value.to_string()))
+            }
+            _ => (arg.clone(), None),
+        };
+        let mut value = |name: &str| {
+            inline_value
+                .clone()
+                .or_else(|| args.next())
+                .ok_or_else(|| eyre!("{name} requires a value"))
+        };
+
+        match flag.as_str() {
+            "-h" | "--help" => {
+                cli.help = true;
+                return Ok(cli);
+            }
+            "--listen" => {
+                let listen = value("--listen")?;
+                cli.listen = listen.parse().map_err(|_| {
+                    eyre!("--listen expects an address like 127.0.0.1:1985, got {listen}")
+                })?;
+            }
+            "--peer" => cli.peer_urls.push(value("--peer")?),
+            "--index" => cli.index_path = value("--index")?,
+            "--sync-interval" => {
+                let secs = value("--sync-interval")?;
+                let secs: u64 = secs
+                    .parse()
+                    .map_err(|_| eyre!("--sync-interval expects a number, got {secs}"))?;
+                if secs == 0 {
+                    return Err(eyre!("--sync-interval must be at least 1"));
+                }
+                cli.sync_interval = Duration::from_secs(secs);
+            }
+            other => return Err(eyre!("unexpected argument {other}")),
+        }
+    }
+
+    if cli.peer_urls.is_empty() {
+        cli.peer_urls.push(DEFAULT_PEER.to_string());
+    }
+    Ok(cli)
+}
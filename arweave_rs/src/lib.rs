@@ -0,0 +1,6 @@
+//! An Arweave node built on the arweave_rs packages. For now it initializes
+//! the RandomX VM and the block index once and serves block validation
+//! results and the local block index over HTTP, see [`api`].
+pub mod api;
+pub mod node;
+pub mod validation;
@@ -0,0 +1,121 @@
+//! The state shared by the API handlers and the task keeping the block index
+//! current.
+use crate::validation::ValidationWorker;
+use arweave_rs_client::ArweaveClient;
+use arweave_rs_indexes::{BlockIndex, Initialized};
+use arweave_rs_randomx::RandomXMode;
+use arweave_rs_types::consensus::ConsensusParams;
+use arweave_rs_validator::metrics::{describe_metrics, STAGE_SECONDS, STAGE_SECONDS_BUCKETS};
+use color_eyre::eyre::Result;
+use metrics_exporter_prometheus::{Matcher, PrometheusBuilder, PrometheusHandle};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, PoisonError, RwLock, RwLockReadGuard};
+use std::time::Duration;
+use tokio::sync::Semaphore;
+use tracing::{info, warn};
+use tracing_subscriber::EnvFilter;
+
+/// The most VDF checks running at once, each one already spreads its
+/// checkpoints across the available cores.
+pub const MAX_VDF_CHECKS: usize = 2;
+
+pub struct NodeState {
+    block_index: Arc<RwLock<BlockIndex<Initialized>>>,
+    /// Requests the headers referenced by the API requests.
+    pub client: ArweaveClient,
+    pub params: ConsensusParams,
+    pub worker: ValidationWorker,
+    /// The permits of the VDF checks, see [`MAX_VDF_CHECKS`].
+    pub vdf_permits: Arc<Semaphore>,
+    /// Renders the metrics served on `/metrics`, see [`install_metrics_recorder()`].
+    pub metrics: Option<PrometheusHandle>,
+}
+
+impl NodeState {
+    /// Starts the validation thread, creating its RandomX VM in the
+    /// `randomx_mode`.
+    pub fn new(
+        block_index: BlockIndex<Initialized>,
+        client: ArweaveClient,
+        params: ConsensusParams,
+        randomx_mode: RandomXMode,
+    ) -> Result<Self> {
+        let block_index = Arc::new(RwLock::new(block_index));
+        let worker = ValidationWorker::start(block_index.clone(), params.clone(), randomx_mode)?;
+        Ok(NodeState {
+            block_index,
+            client,
+            params,
+            worker,
+            vdf_permits: Arc::new(Semaphore::new(MAX_VDF_CHECKS)),
+            metrics: None,
+        })
+    }
+
+    /// Serves the metrics rendered by `handle` on `/metrics`.
+    pub fn with_metrics(mut self, handle: PrometheusHandle) -> Self {
+        self.metrics = Some(handle);
+        self
+    }
+
+    pub fn block_index(&self) -> RwLockReadGuard<'_, BlockIndex<Initialized>> {
+        self.block_index
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Appends the blocks confirmed since the index was last updated to the
+    /// index and to the index file at `path`, returning the number appended.
+    pub async fn update_block_index(&self, path: &Path) -> Result<usize> {
+        let num_indexes = self.block_index().num_indexes();
+        let items = BlockIndex::request_new_items(num_indexes, &self.client).await?;
+        let num_items = items.len();
+        if num_items > 0 {
+            self.block_index
+                .write()
+                .unwrap_or_else(PoisonError::into_inner)
+                .append_items(path, items)?;
+        }
+        Ok(num_items)
+    }
+}
+
+/// Installs the Prometheus recorder the validation, block index and client
+/// metrics are recorded to. Only one recorder can be installed per process.
+pub fn install_metrics_recorder() -> Result<PrometheusHandle> {
+    let handle = PrometheusBuilder::new()
+        .set_buckets_for_metric(
+            Matcher::Full(STAGE_SECONDS.to_string()),
+            STAGE_SECONDS_BUCKETS,
+        )?
+        .install_recorder()?;
+    describe_metrics();
+    Ok(handle)
+}
+
+/// Writes the tracing events of the node and the packages it uses to stderr,
+/// filtered by `RUST_LOG` (`info` by default).
+pub fn init_tracing() {
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+    tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(std::io::stderr)
+        .init();
+}
+
+/// Updates the block index kept at `path` every `interval` until the process
+/// stops.
+pub async fn sync_block_index(state: Arc<NodeState>, path: PathBuf, interval: Duration) {
+    loop {
+        tokio::time::sleep(interval).await;
+        match state.update_block_index(&path).await {
+            Ok(0) => {}
+            Ok(num_items) => info!(
+                num_items,
+                num_indexes = state.block_index().num_indexes(),
+                "Appended blocks to the block index"
+            ),
+            Err(err) => warn!(error = %err, "Failed to update the block index"),
+        }
+    }
+}
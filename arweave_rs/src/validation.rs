@@ -0,0 +1,217 @@
+//! Validates block headers for the API. Pre-validation runs on a dedicated
+//! thread that owns the RandomX VM, as a VM hashes one input at a time and
+//! can't be shared between the request handlers. At most
+//! [`MAX_QUEUED_HEADERS`] headers wait for the thread, the ones posted while
+//! the queue is full are turned away with [`QueueFull`]. A header that panics
+//! the validator fails validation, the thread carries on with the next one.
+use arweave_rs_indexes::{BlockIndex, Initialized};
+use arweave_rs_randomx::{create_randomx_vm, RandomXMode};
+use arweave_rs_types::{consensus::*, *};
+use arweave_rs_validator::{pre_validate_block, vdf_is_valid};
+use color_eyre::eyre::{eyre, Result};
+use serde_derive::{Deserialize, Serialize};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::mpsc::{self, TrySendError};
+use std::sync::{Arc, PoisonError, RwLock};
+use std::time::Instant;
+use tokio::sync::oneshot;
+
+/// The most headers waiting for the validation thread.
+pub const MAX_QUEUED_HEADERS: usize = 64;
+
+/// The error of a header posted while [`MAX_QUEUED_HEADERS`] are waiting.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QueueFull;
+
+impl std::fmt::Display for QueueFull {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{MAX_QUEUED_HEADERS} headers are already waiting for validation"
+        )
+    }
+}
+
+impl std::error::Error for QueueFull {}
+
+/// The outcome of validating a block header, with the same fields as the JSON
+/// lines written by the block scraper.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Verdict {
+    pub height: u64,
+    pub indep_hash: String,
+    /// The solution hash in the block header.
+    pub hash: String,
+    /// The solution hash computed by validation, `None` if validation failed
+    /// before computing it.
+    pub solution_hash: Option<String>,
+    pub hash_matches: bool,
+    /// True if every check passed and the computed solution hash matches the
+    /// one in the header.
+    pub valid: bool,
+    /// True if the block was produced with a two chunk solution.
+    pub poa2: bool,
+    pub double_signing_proof: bool,
+    /// True if the VDF checkpoints were recomputed as well.
+    pub vdf_checked: bool,
+    /// The time spent validating the block.
+    pub elapsed_ms: f64,
+    /// Why the block failed validation.
+    pub error: Option<String>,
+}
+
+impl Verdict {
+    fn from_header(header: &ArweaveBlockHeader) -> Self {
+        Verdict {
+            height: header.height,
+            indep_hash: base64_url::encode(&header.indep_hash),
+            hash: base64_url::encode(&header.hash),
+            poa2: header.chunk2_hash.is_some(),
+            double_signing_proof: header.double_signing_proof.pub_key.is_some(),
+            ..Default::default()
+        }
+    }
+
+    /// Recomputes the VDF checkpoints of `header` if it passed pre-validation.
+    pub fn check_vdf(
+        &mut self,
+        header: &ArweaveBlockHeader,
+        parent: &ArweaveBlockHeader,
+        params: &ConsensusParams,
+    ) {
+        if !self.valid {
+            return;
+        }
+        let vdf = VdfVerdict::new(header, parent, params);
+        self.vdf_checked = true;
+        self.valid = vdf.valid;
+        self.error = vdf.error;
+        self.elapsed_ms += vdf.elapsed_ms;
+    }
+}
+
+/// The outcome of recomputing the VDF checkpoints of a block header.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct VdfVerdict {
+    pub height: u64,
+    pub indep_hash: String,
+    /// The number of VDF steps since the previous block.
+    pub steps: u64,
+    pub valid: bool,
+    pub elapsed_ms: f64,
+    /// Why the VDF failed validation.
+    pub error: Option<String>,
+}
+
+impl VdfVerdict {
+    /// Validates the VDF steps of `header` continue from its `parent` and
+    /// recomputes their checkpoints, around a second of work per step.
+    pub fn new(
+        header: &ArweaveBlockHeader,
+        parent: &ArweaveBlockHeader,
+        params: &ConsensusParams,
+    ) -> Self {
+        let start = Instant::now();
+        let result = vdf_is_valid(header, parent, params);
+        VdfVerdict {
+            height: header.height,
+            indep_hash: base64_url::encode(&header.indep_hash),
+            steps: header.nonce_limiter_info.checkpoints.len() as u64,
+            valid: result.is_ok(),
+            elapsed_ms: start.elapsed().as_micros() as f64 / 1000.0,
+            error: result.err().map(|err| err.to_string()),
+        }
+    }
+}
+
+struct Job {
+    header: ArweaveBlockHeader,
+    parent: ArweaveBlockHeader,
+    reply: oneshot::Sender<Verdict>,
+}
+
+/// Queues headers for pre-validation on the thread owning the RandomX VM.
+/// The thread stops once the worker is dropped.
+pub struct ValidationWorker {
+    jobs: mpsc::SyncSender<Job>,
+}
+
+impl ValidationWorker {
+    /// Starts the validation thread, which creates a RandomX VM in the
+    /// `randomx_mode` before validating the first header against the
+    /// `block_index`.
+    pub fn start(
+        block_index: Arc<RwLock<BlockIndex<Initialized>>>,
+        params: ConsensusParams,
+        randomx_mode: RandomXMode,
+    ) -> Result<Self> {
+        let (jobs, receiver) = mpsc::sync_channel::<Job>(MAX_QUEUED_HEADERS);
+        std::thread::Builder::new()
+            .name("validation".to_string())
+            .spawn(move || {
+                let randomx_vm = create_randomx_vm(randomx_mode, RANDOMX_PACKING_KEY);
+                for job in receiver {
+                    let mut verdict = Verdict::from_header(&job.header);
+                    let start = Instant::now();
+                    // The VM and the block index are only read, so they can
+                    // still be used after a panic
+                    let result = catch_unwind(AssertUnwindSafe(|| {
+                        let block_index =
+                            block_index.read().unwrap_or_else(PoisonError::into_inner);
+                        pre_validate_block(
+                            &job.header,
+                            &job.parent,
+                            &block_index,
+                            Some(&randomx_vm),
+                            &params,
+                            None,
+                        )
+                    }))
+                    .unwrap_or_else(|_| Err(eyre!("validation panicked")));
+                    verdict.elapsed_ms = start.elapsed().as_micros() as f64 / 1000.0;
+
+                    match result {
+                        Ok(solution_hash) => {
+                            verdict.solution_hash = Some(base64_url::encode(&solution_hash));
+                            verdict.hash_matches = solution_hash == job.header.hash.0;
+                            verdict.valid = verdict.hash_matches;
+                            if !verdict.hash_matches {
+                                verdict.error =
+                                    Some("solution hash does not match the block hash".to_string());
+                            }
+                        }
+                        Err(err) => verdict.error = Some(err.to_string()),
+                    }
+                    // The request may have been cancelled while it was queued
+                    let _ = job.reply.send(verdict);
+                }
+            })?;
+        Ok(ValidationWorker { jobs })
+    }
+
+    /// Pre-validates `header` against its `parent` once the headers queued
+    /// before it are validated, failing with [`QueueFull`] if
+    /// [`MAX_QUEUED_HEADERS`] are already queued.
+    pub async fn pre_validate(
+        &self,
+        header: ArweaveBlockHeader,
+        parent: ArweaveBlockHeader,
+    ) -> Result<Verdict> {
+        let (reply, verdict) = oneshot::channel();
+        let job = Job {
+            header,
+            parent,
+            reply,
+        };
+        match self.jobs.try_send(job) {
+            Ok(()) => {}
+            Err(TrySendError::Full(_)) => return Err(QueueFull.into()),
+            Err(TrySendError::Disconnected(_)) => {
+                return Err(eyre!("the validation thread has stopped"))
+            }
+        }
+        verdict
+            .await
+            .map_err(|_| eyre!("the validation thread has stopped"))
+    }
+}
@@ -0,0 +1,379 @@
+use arweave_rs::{api, node::*, validation::*};
+use arweave_rs_client::{backoff::Backoff, ArweaveClient, ClientConfig};
+use arweave_rs_indexes::BlockIndex;
+use arweave_rs_randomx::RandomXMode;
+use arweave_rs_test_support::{devnet_fixture, fixtures::*, serial, MockPeer};
+use arweave_rs_types::{
+    block_hash::compute_indep_hash, consensus::ConsensusParams, ArweaveBlockHeader,
+};
+use metrics_exporter_prometheus::PrometheusHandle;
+use serde_json::{json, Value};
+use std::{
+    net::TcpListener,
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+use tempfile::TempDir;
+use tokio::runtime::Runtime;
+
+fn config() -> ClientConfig {
+    ClientConfig {
+        backoff: Backoff {
+            base: Duration::from_millis(10),
+            max: Duration::from_millis(50),
+        },
+        timeout: Duration::from_millis(500),
+        ..Default::default()
+    }
+}
+
+/// A node serving the block index of a synthetic chain 0..=60 requested from
+/// a mock peer, returning the peer, the chain, the url of the node and the
+/// directory of its index file.
+fn synthetic_node(
+    runtime: &Runtime,
+    metrics: Option<PrometheusHandle>,
+) -> (MockPeer, Vec<Value>, String, TempDir) {
+    let chain = synthetic_chain(&block_header_json(cases::POA2), 0..=60, 0, None);
+    let peer = MockPeer::start().unwrap();
+    peer.insert_headers(chain.clone()).unwrap();
+
+    let client = ArweaveClient::with_config([peer.url()], config()).unwrap();
+    let index_dir = tempfile::tempdir().unwrap();
+    let block_index = runtime
+        .block_on(BlockIndex::new().init_at(index_dir.path().join("index.dat"), &client))
+        .unwrap();
+    let mut state = NodeState::new(
+        block_index,
+        client,
+        ConsensusParams::mainnet(),
+        RandomXMode::FastInitialization,
+    )
+    .unwrap();
+    if let Some(handle) = metrics {
+        state = state.with_metrics(handle);
+    }
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    runtime.spawn(api::serve(listener, Arc::new(state)));
+    (peer, chain, url, index_dir)
+}
+
+#[test]
+fn block_index_and_info_are_served() {
+    let runtime = Runtime::new().unwrap();
+    let (peer, chain, url, _index_dir) = synthetic_node(&runtime, None);
+    let node_client = ArweaveClient::with_config([url.as_str()], config()).unwrap();
+
+    // The node serves the index in the format of the peers
+    let info = runtime.block_on(node_client.info()).unwrap();
+    assert!(info.height >= 40);
+    assert_eq!(info.blocks, info.height);
+    assert_eq!(
+        Some(base64_url::encode(&info.current)),
+        indep_hash(&chain[info.height as usize]).map(String::from)
+    );
+    let index = runtime.block_on(node_client.block_index(10, 12)).unwrap();
+    assert_eq!(index.len(), 3);
+    assert_eq!(Some(index[0].hash.as_str()), indep_hash(&chain[12]));
+    assert_eq!(index[2].weave_size, "10000");
+    let peer_client = ArweaveClient::with_config([peer.url()], config()).unwrap();
+    let peer_index = runtime.block_on(peer_client.block_index(10, 12)).unwrap();
+    for (node_item, peer_item) in index.iter().zip(&peer_index) {
+        assert_eq!(node_item.hash, peer_item.hash);
+        assert_eq!(node_item.weave_size, peer_item.weave_size);
+    }
+    assert_eq!(chain.len(), 61);
+
+    // Heights outside the index or ranges that are reversed or too long fail
+    let status = |path: String| {
+        runtime
+            .block_on(reqwest::get(format!("{url}{path}")))
+            .unwrap()
+            .status()
+            .as_u16()
+    };
+    assert_eq!(
+        status(format!("/block_index/{}/{}", info.height, info.height + 1)),
+        404
+    );
+    assert_eq!(status("/block_index/0/3".to_string()), 404);
+    assert_eq!(status("/block_index/12/10".to_string()), 400);
+    assert_eq!(status("/block_index/1/1001".to_string()), 400);
+}
+
+#[test]
+fn headers_are_validated_against_their_parent() {
+    let runtime = Runtime::new().unwrap();
+    let (_peer, chain, url, _index_dir) = synthetic_node(&runtime, None);
+    let http = reqwest::Client::new();
+    let post = |body: Value| {
+        runtime.block_on(async {
+            let response = http
+                .post(format!("{url}/validate/header"))
+                .json(&body)
+                .send()
+                .await
+                .unwrap();
+            let status = response.status().as_u16();
+            (status, response.json::<Value>().await.unwrap())
+        })
+    };
+
+    // The parent is requested from the peers, the synthetic header is
+    // answered as invalid
+    let (status, body) = post(json!({ "header": chain[50] }));
+    assert_eq!(status, 200);
+    let verdict: Verdict = serde_json::from_value(body).unwrap();
+    assert_eq!(verdict.height, 50);
+    assert_eq!(Some(verdict.indep_hash.as_str()), indep_hash(&chain[50]));
+    assert!(!verdict.valid);
+    assert!(verdict.error.is_some());
+
+    let (status, body) = post(json!({ "header": chain[50], "parent": { "height": 49 } }));
+    assert_eq!(status, 200);
+    assert_eq!(body["height"], 50);
+
+    // A parent that isn't the previous block is rejected
+    let (status, body) = post(json!({ "header": chain[50], "parent": { "height": 30 } }));
+    assert_eq!(status, 400);
+    assert!(body["error"].as_str().unwrap().contains("previous_block"));
+    let (status, _) = post(json!({ "header": chain[50], "parent": { "indep_hash": "abc" } }));
+    assert_eq!(status, 400);
+    let (status, _) = post(json!({ "header": chain[50], "parent": { "header": chain[40] } }));
+    assert_eq!(status, 400);
+}
+
+#[test]
+fn vdf_is_verified() {
+    let runtime = Runtime::new().unwrap();
+    let (_peer, chain, url, _index_dir) = synthetic_node(&runtime, None);
+    let get = |query: &str| {
+        runtime.block_on(async {
+            let response = reqwest::get(format!("{url}/vdf/verify?{query}"))
+                .await
+                .unwrap();
+            let status = response.status().as_u16();
+            (status, response.json::<Value>().await.unwrap())
+        })
+    };
+
+    // The synthetic headers copy the VDF of the template, which doesn't
+    // continue from their parents
+    let (status, body) = get("height=50");
+    assert_eq!(status, 200);
+    let verdict: VdfVerdict = serde_json::from_value(body).unwrap();
+    assert_eq!(verdict.height, 50);
+    assert!(!verdict.valid);
+    let hash = indep_hash(&chain[50]).unwrap();
+    let (status, body) = get(&format!("indep_hash={hash}"));
+    assert_eq!(status, 200);
+    assert_eq!(body["height"], 50);
+
+    assert_eq!(get("").0, 400);
+    assert_eq!(get(&format!("height=50&indep_hash={hash}")).0, 400);
+    assert_eq!(get("height=100").0, 502);
+}
+
+#[test]
+fn metrics_are_served() {
+    // The only test of this binary installing the process wide recorder
+    let runtime = Runtime::new().unwrap();
+    let handle = install_metrics_recorder().unwrap();
+    let (_peer, chain, url, _index_dir) = synthetic_node(&runtime, Some(handle));
+    let http = reqwest::Client::new();
+    let metrics = runtime.block_on(async {
+        http.post(format!("{url}/validate/header"))
+            .json(&json!({ "header": chain[50] }))
+            .send()
+            .await
+            .unwrap();
+        let response = reqwest::get(format!("{url}/metrics")).await.unwrap();
+        assert_eq!(response.status().as_u16(), 200);
+        response.text().await.unwrap()
+    });
+
+    // The synthetic header fails validation and its parent was requested
+    assert!(metrics.contains("# TYPE arweave_blocks_validated_total counter"));
+    assert!(metrics.contains(r#"arweave_blocks_validated_total{proof="poa2",result="invalid"}"#));
+    assert!(metrics.contains("arweave_validation_failures_total{reason="));
+    assert!(metrics.contains(r#"arweave_http_requests_total{result="ok"}"#));
+    assert!(metrics.contains("arweave_block_index_height"));
+}
+
+#[test]
+fn full_validation_queue_is_answered_503() {
+    let _serial = serial();
+    let runtime = Runtime::new().unwrap();
+    let client = ArweaveClient::new(["http://127.0.0.1:1"]).unwrap();
+    let params = ConsensusParams::mainnet();
+    let empty_index = || BlockIndex::new().init_from_items(vec![]);
+    let mut state = NodeState::new(
+        empty_index(),
+        client,
+        params.clone(),
+        RandomXMode::FastInitialization,
+    )
+    .unwrap();
+
+    // The worker waits for the block index while the test holds it
+    let block_index = Arc::new(RwLock::new(empty_index()));
+    let index_guard = block_index.write().unwrap();
+    state.worker =
+        ValidationWorker::start(block_index.clone(), params, RandomXMode::FastInitialization)
+            .unwrap();
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    runtime.spawn(api::serve(listener, Arc::new(state)));
+
+    // A tampered header fails validation as soon as the worker gets to it
+    let chain = synthetic_chain(&block_header_json(cases::POA2), 49..=50, 0, None);
+    let mut header = chain[1].clone();
+    header["timestamp"] = json!(header["timestamp"].as_u64().unwrap() + 1);
+    let body = json!({ "header": header, "parent": { "header": chain[0] } });
+    let http = reqwest::Client::new();
+    let post = |timeout: Duration| {
+        let request = http
+            .post(format!("{url}/validate/header"))
+            .json(&body)
+            .timeout(timeout);
+        async move { request.send().await }
+    };
+
+    // Once the queue is full the headers are turned away
+    let queued: Vec<_> = (0..=MAX_QUEUED_HEADERS)
+        .map(|_| runtime.spawn(post(Duration::from_secs(60))))
+        .collect();
+    let turned_away = (0..MAX_QUEUED_HEADERS).find_map(|_| {
+        let response = runtime.block_on(post(Duration::from_millis(100))).ok()?;
+        Some(runtime.block_on(async {
+            let status = response.status().as_u16();
+            (status, response.json::<Value>().await.unwrap())
+        }))
+    });
+    let (status, body) = turned_away.expect("a full queue");
+    assert_eq!(status, 503);
+    let error = body["error"].as_str().unwrap();
+    assert!(error.contains("waiting for validation"));
+
+    // The queued headers are validated once the worker continues
+    drop(index_guard);
+    let statuses: Vec<u16> = queued
+        .into_iter()
+        .map(|request| {
+            let response = runtime.block_on(request).unwrap().unwrap();
+            response.status().as_u16()
+        })
+        .collect();
+    let num_validated = statuses.iter().filter(|&&status| status == 200).count();
+    assert!(num_validated >= MAX_QUEUED_HEADERS, "{statuses:?}");
+    let response = runtime.block_on(post(Duration::from_secs(60))).unwrap();
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[test]
+fn devnet_blocks_pass_the_validation_worker() {
+    // The worker creates its own VM once this one is freed
+    let (devnet, _serial) = {
+        let mut fixture = devnet_fixture(b"test_node");
+        for _ in 0..3 {
+            fixture
+                .devnet
+                .produce_block(Some(&fixture.randomx_vm))
+                .unwrap();
+        }
+        (fixture.devnet, fixture.serial)
+    };
+
+    let params = devnet.params().clone();
+    let client = ArweaveClient::new(["http://127.0.0.1:1"]).unwrap();
+    let state = NodeState::new(
+        devnet.block_index(),
+        client,
+        params.clone(),
+        RandomXMode::FastInitialization,
+    )
+    .unwrap();
+    let runtime = Runtime::new().unwrap();
+    let blocks = devnet.blocks();
+    for window in blocks.windows(2) {
+        let (parent, header) = (&window[0], &window[1]);
+        let mut verdict = runtime
+            .block_on(state.worker.pre_validate(header.clone(), parent.clone()))
+            .unwrap();
+        assert!(verdict.valid, "{:?}", verdict.error);
+        assert!(verdict.hash_matches);
+        verdict.check_vdf(header, parent, &params);
+        assert!(verdict.vdf_checked);
+        assert!(verdict.valid, "{:?}", verdict.error);
+    }
+
+    // A tampered header is answered as invalid
+    let mut header = devnet.tip().clone();
+    header.nonce.0 += 1;
+    let parent = blocks[blocks.len() - 2].clone();
+    let verdict = runtime
+        .block_on(state.worker.pre_validate(header, parent))
+        .unwrap();
+    assert!(!verdict.valid);
+    assert!(verdict.error.is_some());
+}
+
+#[test]
+fn malformed_headers_do_not_stop_the_worker() {
+    // The worker creates its own VM once this one is freed
+    let (devnet, _serial) = {
+        let mut fixture = devnet_fixture(b"test_malformed_headers");
+        for _ in 0..3 {
+            fixture
+                .devnet
+                .produce_block(Some(&fixture.randomx_vm))
+                .unwrap();
+        }
+        (fixture.devnet, fixture.serial)
+    };
+
+    let params = devnet.params().clone();
+    let client = ArweaveClient::new(["http://127.0.0.1:1"]).unwrap();
+    let state = NodeState::new(
+        devnet.block_index(),
+        client,
+        params.clone(),
+        RandomXMode::FastInitialization,
+    )
+    .unwrap();
+    let runtime = Runtime::new().unwrap();
+    let pre_validate = |header: &ArweaveBlockHeader, parent: &ArweaveBlockHeader| {
+        runtime
+            .block_on(state.worker.pre_validate(header.clone(), parent.clone()))
+            .unwrap()
+    };
+
+    // The headers are tampered with after signing, their indep_hash is
+    // recomputed so they get past the hash check to the malformed fields
+    let blocks = devnet.blocks();
+    let (parent, header) = (&blocks[blocks.len() - 2], devnet.tip());
+    let reindexed = |mut header: ArweaveBlockHeader| {
+        header.indep_hash = compute_indep_hash(&header, &params);
+        header
+    };
+    let mut stalled_vdf = header.clone();
+    stalled_vdf.nonce_limiter_info.global_step_number =
+        parent.nonce_limiter_info.global_step_number;
+    let mut short_tx_path = header.clone();
+    short_tx_path.poa.tx_path.0.pop();
+    let mut short_data_path = header.clone();
+    short_data_path.poa.data_path.0.truncate(10);
+
+    for malformed in [stalled_vdf, short_tx_path, short_data_path] {
+        let verdict = pre_validate(&reindexed(malformed), parent);
+        assert!(!verdict.valid);
+        let error = verdict.error.unwrap();
+        assert!(!error.contains("panicked"), "{error}");
+    }
+
+    // The worker carries on with the next header
+    let verdict = pre_validate(header, parent);
+    assert!(verdict.valid, "{:?}", verdict.error);
+}
@@ -0,0 +1,210 @@
+//! Parses the command line of the block scraper.
+use arweave_rs_client::DEFAULT_PEER;
+use color_eyre::eyre::eyre;
+use eyre::Result;
+use std::net::SocketAddr;
+use std::time::Duration;
+
+pub const USAGE: &str = "\
+Validates Arweave block headers against the consensus rules.
+
+Usage: block_scraper [OPTIONS] <COMMAND>
+
+Commands:
+  validate [--from <HEIGHT>] [--to <HEIGHT>] [--profile]
+                 Validate the headers from..=to, defaults to the latest
+                 <BATCH_SIZE> blocks. --profile writes the time spent in
+                 each validation stage across the range to stderr
+  follow [--from <HEIGHT>]
+                 Validate new blocks, including their VDF checkpoints, as
+                 they are produced and follow reorgs, starting after the
+                 current block by default
+  fetch --from <HEIGHT> --to <HEIGHT>
+                 Fetch the headers from..=to without validating them
+
+Options:
+  --peer <URL>            Peer to request headers from, repeat it to spread the
+                          requests across several peers
+                          [default: https://arweave.net]
+  --batch-size <N>        Headers validated per batch [default: 100]
+  --concurrency <N>       Concurrent requests across all peers [default: 10]
+  --format <FORMAT>       Output format: text, jsonl or csv [default: text]
+  --metrics <ADDR>        Serve Prometheus metrics on http://<ADDR>/metrics
+                          while the command runs
+  --poll-interval <SECS>  Seconds between polls of the current block when
+                          following [default: 5]
+  --store <DIR>           Directory the fetched headers are archived in, only
+                          headers missing from it are requested when
+                          validating or fetching [default: data/headers]
+  --window <N>            Recent headers kept when following, the deepest
+                          reorg that can be followed [default: 50]
+  -h, --help              Print this help
+
+Exit codes: 0 all blocks are valid, 1 a block failed validation, 2 the
+command line or a request failed.";
+
+/// How the per block results are written to stdout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// One human readable line per block.
+    Text,
+    /// One JSON object per line.
+    JsonLines,
+    /// Comma separated values with a header row.
+    Csv,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    Validate {
+        from: Option<u64>,
+        to: Option<u64>,
+        /// Aggregate the time spent in each validation stage.
+        profile: bool,
+    },
+    Follow { from: Option<u64> },
+    Fetch { from: u64, to: u64 },
+    Help,
+}
+
+#[derive(Debug, Clone)]
+pub struct Cli {
+    pub command: Command,
+    pub peer_urls: Vec<String>,
+    pub batch_size: u64,
+    pub concurrency: usize,
+    pub format: OutputFormat,
+    pub metrics_addr: Option<SocketAddr>,
+    pub poll_interval: Duration,
+    pub window_size: u64,
+    pub store_dir: String,
+}
+
+/// Parses the `args` following the program name. Options may appear before or
+/// after the command.
+pub fn parse_args<I: IntoIterator<Item = String>>(args: I) -> Result<Cli> {
+    let mut command_name: Option<String> = None;
+    let mut from: Option<u64> = None;
+    let mut to: Option<u64> = None;
+    let mut profile = false;
+    let mut cli = Cli {
+        command: Command::Help,
+        peer_urls: Vec::new(),
+        batch_size: 100,
+        concurrency: 10,
+        format: OutputFormat::Text,
+        metrics_addr: None,
+        poll_interval: Duration::from_secs(5),
+        window_size: 50,
+        store_dir: "data/headers".to_string(),
+    };
+
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        // Accept both `--flag value` and `--flag=value`
+        let (flag, inline_value) = match arg.split_once('=') {
+            Some((flag, value)) if arg.starts_with("--") => {
+                (flag.to_string(), Some(value.to_string()))
+            }
+            _ => (arg.clone(), None),
+        };
+        let mut value = |name: &str| {
+            inline_value
+                .clone()
+                .or_else(|| args.next())
+                .ok_or_else(|| eyre!("{name} requires a value"))
+        };
+
+        match flag.as_str() {
+            "-h" | "--help" => {
+                return Ok(Cli {
+                    command: Command::Help,
+                    ..cli
+                })
+            }
+            "--peer" => cli.peer_urls.push(value("--peer")?),
+            "--batch-size" => {
+                cli.batch_size = parse_number("--batch-size", &value("--batch-size")?)?
+            }
+            "--concurrency" => {
+                cli.concurrency = parse_number("--concurrency", &value("--concurrency")?)? as usize
+            }
+            "--format" => {
+                cli.format = match value("--format")?.as_str() {
+                    "text" => OutputFormat::Text,
+                    "jsonl" => OutputFormat::JsonLines,
+                    "csv" => OutputFormat::Csv,
+                    other => {
+                        return Err(eyre!("unknown format {other}, expected text, jsonl or csv"))
+                    }
+                }
+            }
+            "--metrics" => {
+                let addr = value("--metrics")?;
+                let addr = addr
+                    .parse()
+                    .map_err(|_| eyre!("--metrics expects an address like 127.0.0.1:9100, got {addr}"))?;
+                cli.metrics_addr = Some(addr);
+            }
+            "--poll-interval" => {
+                let secs = parse_number("--poll-interval", &value("--poll-interval")?)?;
+                cli.poll_interval = Duration::from_secs(secs);
+            }
+            "--store" => cli.store_dir = value("--store")?,
+            "--window" => cli.window_size = parse_number("--window", &value("--window")?)?,
+            "--from" => from = Some(parse_number("--from", &value("--from")?)?),
+            "--to" => to = Some(parse_number("--to", &value("--to")?)?),
+            "--profile" => profile = true,
+            "validate" | "follow" | "fetch" if command_name.is_none() => command_name = Some(flag),
+            other => return Err(eyre!("unexpected argument {other}")),
+        }
+    }
+
+    if cli.peer_urls.is_empty() {
+        cli.peer_urls.push(DEFAULT_PEER.to_string());
+    }
+    if cli.batch_size == 0 {
+        return Err(eyre!("--batch-size must be at least 1"));
+    }
+    if cli.concurrency == 0 {
+        return Err(eyre!("--concurrency must be at least 1"));
+    }
+    if cli.window_size < 2 {
+        return Err(eyre!("--window must be at least 2"));
+    }
+    // Validating a block needs its parent
+    if from == Some(0) && command_name.as_deref() != Some("fetch") {
+        return Err(eyre!("the genesis block can't be validated"));
+    }
+    if let (Some(from), Some(to)) = (from, to) {
+        if from > to {
+            return Err(eyre!("--from {from} is after --to {to}"));
+        }
+    }
+
+    if profile && command_name.as_deref() != Some("validate") {
+        return Err(eyre!("only validate takes --profile"));
+    }
+
+    cli.command = match command_name.as_deref() {
+        None => return Err(eyre!("a command is required")),
+        Some("validate") => Command::Validate { from, to, profile },
+        Some("follow") => {
+            if to.is_some() {
+                return Err(eyre!("follow doesn't take --to"));
+            }
+            Command::Follow { from }
+        }
+        _ => match (from, to) {
+            (Some(from), Some(to)) => Command::Fetch { from, to },
+            _ => return Err(eyre!("fetch requires --from and --to")),
+        },
+    };
+    Ok(cli)
+}
+
+fn parse_number(flag: &str, value: &str) -> Result<u64> {
+    value
+        .parse()
+        .map_err(|_| eyre!("{flag} expects a number, got {value}"))
+}
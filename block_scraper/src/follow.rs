@@ -0,0 +1,300 @@
+//! Follows the tip of the chain, validating each new block against its parent
+//! and rolling back to the fork point when the peer switches to another
+//! branch.
+use crate::{batches, cli::Cli, report::ReportWriter, validate_header};
+use arweave_rs_client::ArweaveClient;
+use arweave_rs_indexes::{header_store::HeaderStore, header_window::HeaderWindow, *};
+use std::ops::RangeInclusive;
+use tracing::warn;
+use arweave_rs_randomx::RandomXVM;
+use arweave_rs_types::*;
+use color_eyre::eyre::eyre;
+use eyre::Result;
+
+/// The state of a followed chain, the recent headers and the block index
+/// extended with every followed block. Every requested header is archived in
+/// the header store.
+pub struct ChainFollower<'a> {
+    client: &'a ArweaveClient,
+    cli: &'a Cli,
+    block_index: BlockIndex<Initialized>,
+    vm: RandomXVM,
+    store: HeaderStore,
+    window: HeaderWindow,
+}
+
+impl<'a> ChainFollower<'a> {
+    /// Starts following the chain after the block at `start_height`, filling
+    /// the window with it and its ancestors so reorgs below it can be followed.
+    pub async fn new(
+        client: &'a ArweaveClient,
+        cli: &'a Cli,
+        block_index: BlockIndex<Initialized>,
+        vm: RandomXVM,
+        store: HeaderStore,
+        start_height: u64,
+    ) -> Result<ChainFollower<'a>> {
+        let mut follower = ChainFollower {
+            client,
+            cli,
+            block_index,
+            vm,
+            store,
+            window: HeaderWindow::new(cli.window_size as usize),
+        };
+        follower.fill_window(start_height).await?;
+        Ok(follower)
+    }
+
+    /// Requests the current block from the peer and validates every block
+    /// between the followed tip and it, following a reorg if the current block
+    /// is on another branch.
+    pub async fn poll(&mut self, writer: &mut ReportWriter) -> Result<()> {
+        let current = self.client.current_block().await?;
+        if self.window.contains(&current.indep_hash) {
+            return Ok(());
+        }
+
+        // Catch up by height, the blocks are checked to extend the tip as they
+        // are applied in case the peer switched branches in the meantime
+        let tip_height = self.tip().height;
+        if current.height > tip_height + 1 {
+            for batch in batches(tip_height + 1, current.height - 1, self.cli.batch_size) {
+                for header in self.request_headers(batch).await? {
+                    self.apply(header, writer).await?;
+                }
+            }
+        }
+        self.apply(current, writer).await
+    }
+
+    /// The block index, extended with the followed blocks.
+    pub fn block_index(&self) -> &BlockIndex<Initialized> {
+        &self.block_index
+    }
+
+    /// (Re)starts following from the block at `start_height`.
+    async fn fill_window(&mut self, start_height: u64) -> Result<()> {
+        let first_height = (start_height + 1).saturating_sub(self.window.capacity() as u64);
+        self.window = HeaderWindow::new(self.window.capacity());
+        for header in self.request_headers(first_height..=start_height).await? {
+            // Restart the window if the peer switched branches during the requests
+            if let Err(err) = self.window.push(header.clone()) {
+                warn!(error = %err, "restarting the header window");
+                self.window = HeaderWindow::new(self.window.capacity());
+                self.window.push(header)?;
+            }
+        }
+        self.sync_block_index().await
+    }
+
+    fn tip(&self) -> &ArweaveBlockHeader {
+        self.window
+            .tip()
+            .expect("the window should contain at least the starting block")
+    }
+
+    /// Validates the `header` and the blocks between it and the followed chain
+    /// and makes it the new tip.
+    async fn apply(&mut self, header: ArweaveBlockHeader, writer: &mut ReportWriter) -> Result<()> {
+        if self.window.contains(&header.indep_hash) {
+            return Ok(());
+        }
+
+        // Walk back along the new branch until it joins the followed chain
+        let mut branch = vec![header];
+        while !self.window.contains(&branch[0].previous_block) {
+            let oldest_height = self.window.oldest().map_or(0, |oldest| oldest.height);
+            if branch[0].height <= oldest_height {
+                // The fork point is unknown so the branch can't be validated,
+                // start over from its tip
+                let tip = branch.last().unwrap();
+                warn!(
+                    height = tip.height,
+                    indep_hash = %base64_url::encode(&tip.indep_hash),
+                    window_len = self.window.len(),
+                    "block forks below the oldest header in the window, restarting from it"
+                );
+                return self.fill_window(tip.height).await;
+            }
+            let parent = self.request_header_by_hash(&branch[0].previous_block).await?;
+            branch.insert(0, parent);
+        }
+
+        let fork_hash = branch[0].previous_block;
+        if fork_hash != self.tip().indep_hash {
+            self.rollback_to(&fork_hash, &branch)?;
+        }
+
+        for header in branch {
+            let report =
+                validate_header(&header, self.tip(), &self.block_index, &self.vm, true, None);
+            writer.write(&report)?;
+
+            // The network builds on the block whether we agree with it or not,
+            // so it's followed either way to keep validating its children
+            self.block_index.push_item((&header).into())?;
+            self.window.push(header)?;
+        }
+        Ok(())
+    }
+
+    /// Rolls the window and block index back to the fork point of a reorg.
+    fn rollback_to(&mut self, fork_hash: &H384, branch: &[ArweaveBlockHeader]) -> Result<()> {
+        let orphaned = self.window.rollback_to(fork_hash)?;
+        let position = self.block_index.position(fork_hash).ok_or_else(|| {
+            eyre!(
+                "fork point {} is not in the block index",
+                base64_url::encode(fork_hash)
+            )
+        })?;
+        self.block_index.truncate(position as u64 + 1);
+
+        let new_tip = branch.last().unwrap();
+        warn!(
+            fork_height = self.tip().height,
+            num_orphaned = orphaned.len(),
+            orphaned_height = orphaned.last().map_or(0, |header| header.height),
+            orphaned_hash = %orphaned
+                .last()
+                .map(|header| base64_url::encode(&header.indep_hash))
+                .unwrap_or_default(),
+            branch_len = branch.len(),
+            new_tip_height = new_tip.height,
+            new_tip_hash = %base64_url::encode(&new_tip.indep_hash),
+            "reorg"
+        );
+        Ok(())
+    }
+
+    /// Makes the block index end at the tip of the window. An index ahead of
+    /// the window is truncated, one behind it is extended with the headers
+    /// between them and one on another branch is rolled back to the fork point.
+    async fn sync_block_index(&mut self) -> Result<()> {
+        let indexed = self
+            .window
+            .iter()
+            .rev()
+            .find_map(|header| self.block_index.position(&header.indep_hash));
+        if let Some(position) = indexed {
+            self.block_index.truncate(position as u64 + 1);
+            let hash = self.block_index.latest_item().unwrap().block_hash;
+            let first_unindexed = self.window.get(&hash).unwrap().height + 1;
+            let headers: Vec<_> = self
+                .window
+                .iter()
+                .filter(|header| header.height >= first_unindexed)
+                .cloned()
+                .collect();
+            return self.push_headers(&headers);
+        }
+
+        let oldest = self.window.oldest().unwrap().clone();
+        let Some(latest_hash) = self.block_index.latest_item().map(|item| item.block_hash) else {
+            let headers: Vec<_> = self.window.iter().cloned().collect();
+            return self.push_headers(&headers);
+        };
+        let latest_height = self.request_header_by_hash(&latest_hash).await?.height;
+
+        // Fill the gap below the window from the store, the stored headers may
+        // have been orphaned since they were stored so they're requested again
+        // if they don't link the block index to the window
+        let mut gap = Vec::new();
+        if latest_height + 1 < oldest.height {
+            let (from, to) = (latest_height + 1, oldest.height - 1);
+            for batch in batches(from, to, self.cli.batch_size) {
+                self.store.ensure_present(self.client, batch.clone()).await?;
+                gap.extend(self.store.read_range(batch)?);
+            }
+            if !links(&latest_hash, &gap, &oldest.previous_block) {
+                warn!(from, to, "stored headers are orphaned, requesting them again");
+                gap.clear();
+                for batch in batches(from, to, self.cli.batch_size) {
+                    gap.extend(self.request_headers(batch).await?);
+                }
+            }
+        }
+
+        // Otherwise the index is on another branch, the reorg is deeper than
+        // the window
+        if !links(&latest_hash, &gap, &oldest.previous_block) {
+            gap = self.rollback_to_branch(&oldest).await?;
+        }
+        let headers: Vec<_> = gap.into_iter().chain(self.window.iter().cloned()).collect();
+        self.push_headers(&headers)
+    }
+
+    /// Rolls the block index back to the fork point of the branch ending at
+    /// `header`, returning the headers of the branch between the fork point and
+    /// `header`.
+    async fn rollback_to_branch(
+        &mut self,
+        header: &ArweaveBlockHeader,
+    ) -> Result<Vec<ArweaveBlockHeader>> {
+        let mut branch = Vec::new();
+        let mut parent_hash = header.previous_block;
+        let position = loop {
+            if let Some(position) = self.block_index.position(&parent_hash) {
+                break position;
+            }
+            let parent = self.request_header_by_hash(&parent_hash).await?;
+            if parent.height == 0 {
+                return Err(eyre!("the branch doesn't fork from the block index"));
+            }
+            parent_hash = parent.previous_block;
+            branch.push(parent);
+        };
+        self.block_index.truncate(position as u64 + 1);
+        warn!(
+            fork_position = position,
+            branch_len = branch.len(),
+            "reorg below the header window"
+        );
+        branch.reverse();
+        Ok(branch)
+    }
+
+    fn push_headers(&mut self, headers: &[ArweaveBlockHeader]) -> Result<()> {
+        for header in headers {
+            self.block_index.push_item(header.into())?;
+        }
+        Ok(())
+    }
+
+    /// Requests the headers of the `heights` from the peer, the stored headers
+    /// may be stale near the tip so they're always requested.
+    async fn request_headers(
+        &mut self,
+        heights: RangeInclusive<u64>,
+    ) -> Result<Vec<ArweaveBlockHeader>> {
+        self.client
+            .block_jsons_by_height(heights)
+            .await?
+            .iter()
+            .map(|json| self.store.put_json(json))
+            .collect()
+    }
+
+    /// Reads the header with `indep_hash` from the store, requesting it from
+    /// the peer if it's not there.
+    async fn request_header_by_hash(&mut self, indep_hash: &H384) -> Result<ArweaveBlockHeader> {
+        if let Some(header) = self.store.get_by_hash(indep_hash)? {
+            return Ok(header);
+        }
+        let json = self.client.block_json_by_hash(indep_hash).await?;
+        self.store.put_json(&json)
+    }
+}
+
+/// True if the `headers` form a chain from the block `first_parent` to the
+/// block `last_hash`, the parent of the block following them.
+fn links(first_parent: &H384, headers: &[ArweaveBlockHeader], last_hash: &H384) -> bool {
+    let mut previous = first_parent;
+    for header in headers {
+        if header.previous_block != *previous {
+            return false;
+        }
+        previous = &header.indep_hash;
+    }
+    previous == last_hash
+}
@@ -0,0 +1,57 @@
+//! Validates Arweave block headers requested from peers against the consensus
+//! rules, the library half of the `block_scraper` binary.
+use arweave_rs_indexes::*;
+use arweave_rs_randomx::RandomXVM;
+use arweave_rs_types::{consensus::*, *};
+use arweave_rs_validator::{pre_validate_block, report::ValidationReport, vdf_is_valid};
+use report::BlockReport;
+use std::{ops::RangeInclusive, time::Instant};
+
+pub mod cli;
+pub mod follow;
+pub mod profile;
+pub mod report;
+
+/// Validates `current` against its parent `previous`, and optionally its VDF
+/// checkpoints, recording the solution hash, timing and the reason it failed,
+/// if it did. The time spent in each pre-validation stage is recorded in
+/// `stages`, if given.
+pub fn validate_header(
+    current: &ArweaveBlockHeader,
+    previous: &ArweaveBlockHeader,
+    block_index: &BlockIndex<Initialized>,
+    vm: &RandomXVM,
+    validate_vdf: bool,
+    stages: Option<&mut ValidationReport>,
+) -> BlockReport {
+    let mut report = BlockReport::from_header(current);
+    let params = ConsensusParams::mainnet();
+
+    let start = Instant::now();
+    let result = pre_validate_block(current, previous, block_index, Some(vm), &params, stages)
+        .and_then(|solution_hash| match validate_vdf {
+            true => vdf_is_valid(current, previous, &params).map(|_| solution_hash),
+            false => Ok(solution_hash),
+        });
+    // Get the elapsed time for validating the block
+    report.elapsed_ms = start.elapsed().as_micros() as f64 / 1000.0;
+
+    match result {
+        Ok(solution_hash) => {
+            report.solution_hash = Some(solution_hash);
+            if !report.hash_matches() {
+                report.error = Some("solution hash does not match the block hash".to_string());
+            }
+        }
+        Err(err) => report.error = Some(err.to_string()),
+    }
+    report
+}
+
+/// Splits the heights `from..=to` into ranges of at most `batch_size` heights.
+pub fn batches(from: u64, to: u64, batch_size: u64) -> Vec<RangeInclusive<u64>> {
+    (from..=to)
+        .step_by(batch_size as usize)
+        .map(|start| start..=to.min(start + batch_size - 1))
+        .collect()
+}
@@ -1,249 +1,190 @@
-use arweave_rs_indexes::*;
+use arweave_rs_client::{ArweaveClient, ClientConfig};
+use arweave_rs_indexes::{header_store::HeaderStore, *};
 use arweave_rs_randomx::{create_randomx_vm, RandomXMode, RandomXVM};
 use arweave_rs_types::{consensus::*, *};
-use arweave_rs_validator::pre_validate_block;
-use color_eyre::eyre::eyre;
-use eyre::{Report, Result};
-use futures::future::try_join_all;
-use reqwest::{Client as ReqwestClient, StatusCode};
-use std::{time::{Duration, Instant}, io::{self, Write}};
+use arweave_rs_validator::{
+    metrics::{describe_metrics, STAGE_SECONDS, STAGE_SECONDS_BUCKETS},
+    report::ValidationReport,
+};
+use block_scraper::{
+    batches,
+    cli::{self, Cli, Command},
+    follow::ChainFollower,
+    profile::ValidationProfile,
+    report::ReportWriter,
+    validate_header,
+};
+use eyre::Result;
+use metrics_exporter_prometheus::{Matcher, PrometheusBuilder};
+use std::{process::ExitCode, time::Instant};
+use tracing::{error, info};
+use tracing_subscriber::EnvFilter;
 
 #[tokio::main]
-async fn main() -> Result<()> {
-    color_eyre::install()?;
-
-    let client = ReqwestClient::new();
-    let url = format!("https://arweave.net/block/{}", "current");
-
-    // Can get this error here and panic
-    // Error:
-    // 0: HTTP status client error (429 Too Many Requests) for url (https://arweave.net/block/current)
-    let res = client.get(url).send().await?.error_for_status()?;
-
-    #[allow(unused_assignments)]
-    let mut current_block_height: u64 = 0;
-
-    if res.status() == StatusCode::OK {
-        let block_header_res = res.json::<ArweaveBlockHeader>().await;
-        match block_header_res {
-            Ok(current_block_header) => {
-                current_block_height = current_block_header.height;
-            }
-            Err(err) => {
-                println!("Error getting current block {err}");
-                return Err(eyre!(err));
-            }
-        }
-        //println!("{}",serde_json::to_string_pretty(&current_block_header).unwrap());
-    } else {
-        return Err(eyre!("HTTP request returned Status Code {}", res.status()));
+async fn main() -> ExitCode {
+    if let Err(err) = color_eyre::install() {
+        eprintln!("{err}");
     }
+    init_tracing();
 
-    // Initialize the block_index, which may mean polling new blocks from arweave
-    let block_index: BlockIndex = BlockIndex::new();
-    let init_block_index = Instant::now();
-    let block_index = block_index.init().await.expect("block index to initialize");
-    let end_init_block_index = init_block_index.elapsed();
-    println!("BlockIndex initialization: {:?}", end_init_block_index);
-
-    // Initialize the randomx vm for FastHashing (consumes more memory and takes
-    // longer to initialize but produces hashes significantly faster)
-    let start_vm = Instant::now();
-    let vm = create_randomx_vm(RandomXMode::FastHashing, RANDOMX_PACKING_KEY);
-    let end_vm = start_vm.elapsed();
-    println!("RandomX VM initialization: {:?}", end_vm);
-
-    let batch_size = 100;
-    let mut end_height = current_block_height;
-    process_block_header_batch(&block_index, &vm, batch_size, end_height).await?;
-
-    let mut should_continue = true;
-
-    while should_continue {
-        println!("Validate the next 100 headers? [Y/n]:");
-        // Flush stdout to ensure the message is displayed before blocking for input
-        io::stdout().flush().unwrap();
-
-        let mut input = String::new();
-        match io::stdin().read_line(&mut input) {
-            Ok(_) => {
-                
-                match input.trim().to_uppercase().as_str() {
-                    "" => {
-                        should_continue = true;
-                    },
-                    "Y" => {
-                        should_continue = true;
-                    }
-                    _ => {
-                        should_continue = false;
-                    }
-                }
-            },
-            Err(error) => println!("Error reading from stdin: {}", error),
+    let cli = match cli::parse_args(std::env::args().skip(1)) {
+        Ok(cli) => cli,
+        Err(err) => {
+            eprintln!("error: {err}\n\n{}", cli::USAGE);
+            return ExitCode::from(2);
         }
-        println!("should_continue: {should_continue}");
-        if should_continue {
-            end_height -= batch_size as u64 - 1;
-            process_block_header_batch(&block_index, &vm, batch_size, end_height).await?;
+    };
+
+    match run(&cli).await {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::from(1),
+        Err(err) => {
+            eprintln!("Error: {err:?}");
+            ExitCode::from(2)
         }
     }
-
-    Ok(())
 }
 
-pub async fn process_block_header_batch(
-    block_index: &BlockIndex<Initialized>,
-    vm: &RandomXVM,
-    batch_size: usize,
-    end_height: u64,
-) -> Result<()> {
-    let mut batch: Vec<u64> = vec![];
-    let start_block_height = end_height - batch_size as u64;
-
-    for index in (start_block_height..end_height).rev() {
-        let block_height = index;
-
-        batch.push(block_height);
-
-        // Is it time to make the batch request?
-        if batch.len() == batch_size || index == start_block_height {
-            // Await completion of the reqwest batch
-            let mut res = get_block_headers(&batch)
-                .await
-                .expect("all block headers to be retrieved and parsed");
-
-            // Sort the headers
-            res.sort_by_key(|bh| bh.height);
-            res.reverse();
-
-            // Windows iterator iterates a set using sliding windows, in this case, of size 2
-            for window in res.windows(2) {
-                let current = &window[0];
-                let previous = &window[1];
-
-                let start = Instant::now();
-                let solution_hash =
-                    pre_validate_block(current, previous, block_index, Some(vm))?;
-                // Get the elapsed time for validating the block
-                let duration = start.elapsed(); 
-
-                if let Some(_pre) = current.double_signing_proof.preimage1 {
-                    println!("🌟 Double Signing proof for {}", current.height);
-                }
-
-                // Encode the computed solution_hash and the observed one.
-                let encoded = base64_url::encode(&solution_hash);
-                let encoded2 = base64_url::encode(&current.hash);
+/// Runs the command, returning false if any block failed validation.
+async fn run(cli: &Cli) -> Result<bool> {
+    if let Command::Help = cli.command {
+        println!("{}", cli::USAGE);
+        return Ok(true);
+    }
 
-                let milliseconds = duration.as_micros() as f64 / 1000.0;
+    if let Some(addr) = cli.metrics_addr {
+        PrometheusBuilder::new()
+            .set_buckets_for_metric(
+                Matcher::Full(STAGE_SECONDS.to_string()),
+                STAGE_SECONDS_BUCKETS,
+            )?
+            .with_http_listener(addr)
+            .install()?;
+        describe_metrics();
+        info!("Serving metrics on http://{addr}/metrics");
+    }
 
-                // Compare them, printing out the correct emoji if they match or not
-                if encoded == encoded2 {
-                    if let Some(_chunk2) = current.chunk2_hash {
-                        println!("✅{} {} {:.2}ms  2️⃣ PoA2", current.height, encoded, milliseconds);
-                    } else {
-                        println!("✅{} {} {:.2}ms", current.height, encoded, milliseconds);
-                    }
-                } else {
-                    println!(
-                        "❌{} {} {} {:.2}ms",
-                        current.height, encoded, encoded2, milliseconds
-                    );
+    let config = ClientConfig {
+        concurrency: cli.concurrency,
+        ..Default::default()
+    };
+    let client = ArweaveClient::with_config(&cli.peer_urls, config)?;
+    let mut writer = ReportWriter::new(cli.format);
+
+    match cli.command {
+        Command::Help => unreachable!("help is printed before the client is created"),
+        Command::Fetch { from, to } => {
+            let mut store = HeaderStore::open(&cli.store_dir)?;
+            for batch in batches(from, to, cli.batch_size) {
+                store.ensure_present(&client, batch.clone()).await?;
+                for json in store.read_json_range(batch)? {
+                    let header: ArweaveBlockHeader = serde_json::from_value(json.clone())?;
+                    writer.write_header(&header, &json)?;
                 }
             }
-
-            // TODO: Inspect the results to find blocks where the entropy reset happens on the first or last step
-            // #[allow(unused_variables)]
-            // for header in res {
-            // The first step of the next block is a reset
-            // let remainder = (header.nonce_limiter_info.global_step_number + 1) as f64 / NONCE_LIMITER_RESET_FREQUENCY as f64;
-            // if remainder.fract() == 0.0 {
-            //     println!("next height is reset: {}", header.height);
-            // }
-
-            println!(
-                "✅ Finished validating headers! {}/{}!",
-                batch.len(),
-                batch.len()
-            );
-
-            // Reset the batch, we're going again.
-            batch.clear();
+            Ok(true)
         }
-    }
-
-    Ok(())
-}
-
-pub async fn request_block_header_with_retry(
-    block_height: &u64,
-    url: String,
-    client2: &ReqwestClient,
-) -> Result<ArweaveBlockHeader, Report> {
-    let url2 = url;
-    let mut retry_count = 0;
-    let max_retries = 3;
-    let mut last_error: Option<Report>;
-
-    let result: Result<ArweaveBlockHeader, Report> = loop {
-        // Make the async HTTP request and await the response
-        let result = client2.get(&url2).send().await; // retry logic?
-
-        match result {
-            Ok(res) => {
-                if res.status() == StatusCode::OK {
-                    let parsed_res = res.json::<ArweaveBlockHeader>().await;
-                    match parsed_res {
-                        Ok(parsed) => break Ok(parsed),
-                        Err(err) => {
-                            println!("{:?} {url2}", err);
-                            break Err(eyre!(err));
-                        }
+        Command::Validate { from, to, profile } => {
+            let to = match to {
+                Some(to) => to,
+                None => client.current_block().await?.height,
+            };
+            let from = from.unwrap_or(to.saturating_sub(cli.batch_size - 1).max(1));
+            let (block_index, vm) = init_validation(&client).await?;
+            let mut store = HeaderStore::open(&cli.store_dir)?;
+
+            let mut num_valid = 0;
+            let mut num_failed = 0;
+            let mut validation_profile = ValidationProfile::default();
+            for batch in batches(from, to, cli.batch_size) {
+                // Each block is validated against its parent
+                let parent_height = batch.start() - 1;
+                let heights = parent_height..=*batch.end();
+                store.ensure_present(&client, heights.clone()).await?;
+                let headers = store.read_range(heights)?;
+                for window in headers.windows(2) {
+                    let mut stages = ValidationReport::default();
+                    let report = validate_header(
+                        &window[1],
+                        &window[0],
+                        &block_index,
+                        &vm,
+                        false,
+                        profile.then_some(&mut stages),
+                    );
+                    if profile {
+                        validation_profile.add(&stages);
                     }
-                    // .expect(
-                    //     format!("JSON should be parsable to BlockHeaderData {url2}").as_str(),
-                    // );
-                    // Possible error(s) here
-                    // reqwest::Error { kind: Body, source: hyper::Error(Body, Error { kind: Io(Kind(ConnectionReset)) }) }
-                    //println!("Got JSON for block_height: {}", bh);
-                } else {
-                    last_error = Some(eyre!("Reqwest HTTP Status code was {}", res.status()));
+                    match report.is_valid() {
+                        true => num_valid += 1,
+                        false => num_failed += 1,
+                    }
+                    writer.write(&report)?;
                 }
-                retry_count += 1;
             }
-            Err(err) => {
-                // error trying to connect: dns error: failed to lookup address information: nodename nor servname provided, or not known
-                println!("Request to {} failed with error: {}", url2, err);
-                retry_count += 1;
-                last_error = Some(eyre!(err));
+            info!(
+                from,
+                to,
+                num_valid,
+                num_failed,
+                "Finished validating headers"
+            );
+            if profile {
+                eprintln!("{validation_profile}");
             }
+            Ok(num_failed == 0)
         }
-
-        if retry_count == max_retries {
-            break Err(last_error.expect("last_error should contain the most recent error"));
+        Command::Follow { from } => {
+            let (block_index, vm) = init_validation(&client).await?;
+            let start_height = match from {
+                Some(from) => from - 1,
+                None => client.current_block().await?.height,
+            };
+            let store = HeaderStore::open(&cli.store_dir)?;
+            let mut follower =
+                ChainFollower::new(&client, cli, block_index, vm, store, start_height).await?;
+
+            // Poll for new blocks until the process is stopped
+            loop {
+                if let Err(err) = follower.poll(&mut writer).await {
+                    error!(error = %err, "Failed to follow the chain");
+                }
+                tokio::time::sleep(cli.poll_interval).await;
+            }
         }
+    }
+}
 
-        println!("Retrying... {}", block_height);
-        tokio::time::sleep(Duration::from_secs(1)).await; // Add a delay before retrying
-    };
-    result
+/// Writes the tracing events of the scraper and the packages it uses to
+/// stderr, filtered by `RUST_LOG` (`info` by default), e.g.
+/// `RUST_LOG=arweave_rs_validator=debug` to see why blocks fail validation.
+fn init_tracing() {
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+    tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(std::io::stderr)
+        .init();
 }
 
-pub async fn get_block_headers(block_heights: &[u64]) -> Result<Vec<ArweaveBlockHeader>> {
-    let mut complete_count = 0;
-    let client2 = ReqwestClient::new();
-    let requests = block_heights.iter().map(|bh| {
-        let url = format!("https://arweave.net/block/height/{}", bh);
-        let results = request_block_header_with_retry(bh, url, &client2);
-        complete_count += 1;
-        results
-    });
+/// Initializes the block index and a RandomX VM for validating headers.
+async fn init_validation(client: &ArweaveClient) -> Result<(BlockIndex<Initialized>, RandomXVM)> {
+    // Initialize the block_index, which may mean polling new blocks from arweave
+    let block_index: BlockIndex = BlockIndex::new();
+    let init_block_index = Instant::now();
+    let block_index = block_index.init_with_client(client).await?;
+    let end_init_block_index = init_block_index.elapsed();
+    info!(
+        elapsed = ?end_init_block_index,
+        num_indexes = block_index.num_indexes(),
+        "BlockIndex initialization"
+    );
 
-    // Execute the requests concurrently
-    let results = try_join_all(requests).await;
-    match results {
-        Ok(res) => Ok(res),
-        Err(e) => Err(eyre!(e)),
-    }
+    // Initialize the randomx vm for FastHashing (consumes more memory and takes
+    // longer to initialize but produces hashes significantly faster)
+    let start_vm = Instant::now();
+    let vm = create_randomx_vm(RandomXMode::FastHashing, RANDOMX_PACKING_KEY);
+    let end_vm = start_vm.elapsed();
+    info!(elapsed = ?end_vm, "RandomX VM initialization");
+
+    Ok((block_index, vm))
 }
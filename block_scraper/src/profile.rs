@@ -0,0 +1,75 @@
+//! Aggregates the time spent in each validation stage across the blocks of
+//! `validate --profile`.
+use arweave_rs_validator::report::{ValidationReport, ValidationStage};
+use std::fmt;
+use std::time::Duration;
+
+/// The time spent in a stage across the blocks that ran it.
+#[derive(Debug, Clone, Copy, Default)]
+struct StageTotals {
+    num_blocks: u64,
+    total: Duration,
+    max: Duration,
+}
+
+/// The per stage totals of the validated blocks, displayed as a table with
+/// each stage's share of the time spent in all of them.
+#[derive(Debug, Clone, Default)]
+pub struct ValidationProfile {
+    num_blocks: u64,
+    stages: [StageTotals; ValidationStage::ALL.len()],
+}
+
+impl ValidationProfile {
+    pub fn add(&mut self, report: &ValidationReport) {
+        self.num_blocks += 1;
+        for (stage, elapsed) in report.stages() {
+            let totals = &mut self.stages[stage as usize];
+            totals.num_blocks += 1;
+            totals.total += elapsed;
+            totals.max = totals.max.max(elapsed);
+        }
+    }
+
+    fn total(&self) -> Duration {
+        self.stages.iter().map(|totals| totals.total).sum()
+    }
+}
+
+impl fmt::Display for ValidationProfile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let total = self.total();
+        writeln!(f, "Time spent in each validation stage of {} blocks", self.num_blocks)?;
+        writeln!(
+            f,
+            "{:<18} {:>7} {:>12} {:>10} {:>10} {:>7}",
+            "stage", "blocks", "total_ms", "mean_ms", "max_ms", "share"
+        )?;
+        for stage in ValidationStage::ALL {
+            let totals = &self.stages[stage as usize];
+            let mean_ms = match totals.num_blocks {
+                0 => 0.0,
+                num_blocks => as_millis(totals.total) / num_blocks as f64,
+            };
+            let share = match total.is_zero() {
+                true => 0.0,
+                false => totals.total.as_secs_f64() / total.as_secs_f64() * 100.0,
+            };
+            writeln!(
+                f,
+                "{:<18} {:>7} {:>12.2} {:>10.3} {:>10.3} {:>6.1}%",
+                stage.name(),
+                totals.num_blocks,
+                as_millis(totals.total),
+                mean_ms,
+                as_millis(totals.max),
+                share
+            )?;
+        }
+        write!(f, "{:<18} {:>7} {:>12.2}", "total", "", as_millis(total))
+    }
+}
+
+fn as_millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
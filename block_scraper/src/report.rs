@@ -0,0 +1,185 @@
+//! Writes the per block results of the scraper as text, JSON lines or CSV.
+use crate::cli::OutputFormat;
+use arweave_rs_types::*;
+use serde_json::json;
+use std::io::{self, Write};
+
+/// The outcome of validating (or fetching) a single block header.
+#[derive(Debug, Clone, Default)]
+pub struct BlockReport {
+    pub height: u64,
+    pub indep_hash: H384,
+    /// The solution hash in the block header.
+    pub hash: H256,
+    /// The solution hash computed by validation, `None` if validation failed
+    /// before computing it or the block was only fetched.
+    pub solution_hash: Option<[u8; 32]>,
+    /// True if the block was produced with a two chunk solution.
+    pub poa2: bool,
+    pub double_signing_proof: bool,
+    /// The time spent validating the block.
+    pub elapsed_ms: f64,
+    /// Why the block failed validation.
+    pub error: Option<String>,
+}
+
+impl BlockReport {
+    pub fn from_header(header: &ArweaveBlockHeader) -> Self {
+        BlockReport {
+            height: header.height,
+            indep_hash: header.indep_hash,
+            hash: header.hash,
+            poa2: header.chunk2_hash.is_some(),
+            double_signing_proof: header.double_signing_proof.pub_key.is_some(),
+            ..Default::default()
+        }
+    }
+
+    /// True if the computed solution hash matches the one in the header.
+    pub fn hash_matches(&self) -> bool {
+        self.solution_hash
+            .is_some_and(|solution_hash| solution_hash == self.hash.0)
+    }
+
+    pub fn is_valid(&self) -> bool {
+        self.error.is_none() && self.hash_matches()
+    }
+}
+
+/// Writes the result rows to stdout, or another output, the CSV header is
+/// printed before the first row.
+pub struct ReportWriter {
+    format: OutputFormat,
+    header_written: bool,
+    out: Box<dyn Write + Send>,
+}
+
+impl ReportWriter {
+    pub fn new(format: OutputFormat) -> Self {
+        ReportWriter::with_output(format, Box::new(io::stdout()))
+    }
+
+    pub fn with_output(format: OutputFormat, out: Box<dyn Write + Send>) -> Self {
+        ReportWriter {
+            format,
+            header_written: false,
+            out,
+        }
+    }
+
+    pub fn write(&mut self, report: &BlockReport) -> io::Result<()> {
+        let line = match self.format {
+            OutputFormat::Text => text_line(report),
+            OutputFormat::JsonLines => json_line(report),
+            OutputFormat::Csv => {
+                if !self.header_written {
+                    writeln!(self.out, "height,indep_hash,hash,solution_hash,hash_matches,poa2,double_signing_proof,elapsed_ms,error")?;
+                    self.header_written = true;
+                }
+                csv_line(report)
+            }
+        };
+        writeln!(self.out, "{line}")
+    }
+
+    /// Writes a fetched header, the raw `json` of the header for JSON lines.
+    pub fn write_header(
+        &mut self,
+        header: &ArweaveBlockHeader,
+        json: &serde_json::Value,
+    ) -> io::Result<()> {
+        match self.format {
+            OutputFormat::Text => writeln!(
+                self.out,
+                "{} {} {}",
+                header.height,
+                base64_url::encode(&header.indep_hash),
+                header.timestamp
+            ),
+            OutputFormat::JsonLines => writeln!(self.out, "{json}"),
+            OutputFormat::Csv => {
+                if !self.header_written {
+                    writeln!(self.out, "height,indep_hash,timestamp,weave_size,block_size")?;
+                    self.header_written = true;
+                }
+                writeln!(
+                    self.out,
+                    "{},{},{},{},{}",
+                    header.height,
+                    base64_url::encode(&header.indep_hash),
+                    header.timestamp,
+                    header.weave_size,
+                    header.block_size
+                )
+            }
+        }
+    }
+}
+
+fn text_line(report: &BlockReport) -> String {
+    let hash = base64_url::encode(&report.hash);
+    let mut line = if report.is_valid() {
+        format!("✅{} {} {:.2}ms", report.height, hash, report.elapsed_ms)
+    } else {
+        let solution_hash = report
+            .solution_hash
+            .map(|solution_hash| base64_url::encode(&solution_hash))
+            .unwrap_or_default();
+        format!(
+            "❌{} {} {} {:.2}ms",
+            report.height, solution_hash, hash, report.elapsed_ms
+        )
+    };
+    if report.poa2 {
+        line.push_str("  2️⃣ PoA2");
+    }
+    if report.double_signing_proof {
+        line.push_str("  🌟 Double Signing proof");
+    }
+    if let Some(error) = &report.error {
+        line.push_str(&format!("  {error}"));
+    }
+    line
+}
+
+fn json_line(report: &BlockReport) -> String {
+    json!({
+        "height": report.height,
+        "indep_hash": base64_url::encode(&report.indep_hash),
+        "hash": base64_url::encode(&report.hash),
+        "solution_hash": report.solution_hash.map(|hash| base64_url::encode(&hash)),
+        "hash_matches": report.hash_matches(),
+        "poa2": report.poa2,
+        "double_signing_proof": report.double_signing_proof,
+        "elapsed_ms": report.elapsed_ms,
+        "error": report.error,
+    })
+    .to_string()
+}
+
+fn csv_line(report: &BlockReport) -> String {
+    [
+        report.height.to_string(),
+        base64_url::encode(&report.indep_hash),
+        base64_url::encode(&report.hash),
+        report
+            .solution_hash
+            .map(|hash| base64_url::encode(&hash))
+            .unwrap_or_default(),
+        report.hash_matches().to_string(),
+        report.poa2.to_string(),
+        report.double_signing_proof.to_string(),
+        format!("{:.2}", report.elapsed_ms),
+        csv_field(report.error.as_deref().unwrap_or_default()),
+    ]
+    .join(",")
+}
+
+/// Quotes a CSV field containing a separator, quote or line break.
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
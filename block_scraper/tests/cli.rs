@@ -0,0 +1,110 @@
+use block_scraper::cli::{parse_args, Cli, Command, OutputFormat};
+use eyre::Result;
+use std::time::Duration;
+
+fn parse(args: &str) -> Result<Cli> {
+    parse_args(args.split_whitespace().map(str::to_string))
+}
+
+#[test]
+fn flag_values() {
+    // `--flag value` and `--flag=value` are equivalent
+    let cli = parse("--format=csv --concurrency 4 validate --from=100 --to 200").unwrap();
+    assert_eq!(
+        cli.command,
+        Command::Validate {
+            from: Some(100),
+            to: Some(200),
+            profile: false,
+        }
+    );
+    assert_eq!(cli.format, OutputFormat::Csv);
+    assert_eq!(cli.concurrency, 4);
+    assert_eq!(cli.batch_size, 100);
+
+    // An inline value keeps everything after the first `=`
+    let cli = parse("--peer=http://127.0.0.1:1984/?a=b fetch --from 1 --to 1").unwrap();
+    assert_eq!(cli.peer_urls, vec!["http://127.0.0.1:1984/?a=b"]);
+
+    assert!(parse("validate --from").is_err());
+    assert!(parse("validate --from=").is_err());
+    assert!(parse("validate --from=ten").is_err());
+    assert!(parse("--format=xml validate").is_err());
+}
+
+#[test]
+fn flags_after_the_command() {
+    let cli = parse(
+        "follow --peer http://a --peer=http://b --poll-interval 1 --window=10 --metrics 127.0.0.1:9100",
+    )
+    .unwrap();
+    assert_eq!(cli.command, Command::Follow { from: None });
+    assert_eq!(cli.peer_urls, vec!["http://a", "http://b"]);
+    assert_eq!(cli.poll_interval, Duration::from_secs(1));
+    assert_eq!(cli.window_size, 10);
+    assert_eq!(cli.metrics_addr, Some("127.0.0.1:9100".parse().unwrap()));
+
+    // The peer defaults to arweave.net and only one command is accepted
+    let cli = parse("validate").unwrap();
+    assert_eq!(cli.peer_urls.len(), 1);
+    assert!(parse("validate follow").is_err());
+    assert!(parse("--to 5").is_err());
+    assert!(parse("validate --unknown").is_err());
+}
+
+#[test]
+fn command_specific_flags() {
+    // Only validate takes --profile
+    let cli = parse("validate --profile --from 5").unwrap();
+    assert_eq!(
+        cli.command,
+        Command::Validate {
+            from: Some(5),
+            to: None,
+            profile: true,
+        }
+    );
+    assert!(parse("follow --profile").is_err());
+    assert!(parse("fetch --from 1 --to 2 --profile").is_err());
+
+    // Follow starts at --from and runs until stopped
+    assert_eq!(
+        parse("follow --from 7").unwrap().command,
+        Command::Follow { from: Some(7) }
+    );
+    assert!(parse("follow --to 10").is_err());
+    assert!(parse("follow --from 1 --to 10").is_err());
+
+    // Fetch requires both ends of the range
+    assert_eq!(
+        parse("fetch --from 1 --to 2").unwrap().command,
+        Command::Fetch { from: 1, to: 2 }
+    );
+    assert!(parse("fetch --from 1").is_err());
+    assert!(parse("fetch --from 3 --to 2").is_err());
+}
+
+#[test]
+fn from_genesis() {
+    // The genesis block has no parent to be validated against, but it can be
+    // fetched
+    assert!(parse("validate --from 0 --to 10").is_err());
+    assert!(parse("follow --from=0").is_err());
+    assert_eq!(
+        parse("fetch --from 0 --to 0").unwrap().command,
+        Command::Fetch { from: 0, to: 0 }
+    );
+}
+
+#[test]
+fn help_and_limits() {
+    assert_eq!(
+        parse("validate --from 0 -h").unwrap().command,
+        Command::Help
+    );
+    assert_eq!(parse("--help").unwrap().command, Command::Help);
+    assert!(parse("").is_err());
+    assert!(parse("validate --batch-size 0").is_err());
+    assert!(parse("validate --concurrency=0").is_err());
+    assert!(parse("follow --window 1").is_err());
+}
@@ -0,0 +1,151 @@
+//! Follows a synthetic chain served by a mock peer through a reorg below its
+//! tip and one below the oldest header of the window. The synthetic headers
+//! don't pass validation, but they're followed and reported either way.
+use arweave_rs_client::{ArweaveClient, ClientConfig};
+use arweave_rs_indexes::{
+    block_index::BlockIndexItem, header_store::HeaderStore, BlockIndex, Initialized,
+};
+use arweave_rs_randomx::{create_randomx_vm, RandomXMode};
+use arweave_rs_test_support::{fixtures::*, serial, MockPeer};
+use arweave_rs_types::{consensus::RANDOMX_PACKING_KEY, ArweaveBlockHeader};
+use block_scraper::{
+    cli::{parse_args, OutputFormat},
+    follow::ChainFollower,
+    report::ReportWriter,
+};
+use serde_json::Value;
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+
+/// The rows written by a [`ReportWriter`].
+#[derive(Clone, Default)]
+struct Output(Arc<Mutex<Vec<u8>>>);
+
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Output {
+    /// The height and `indep_hash` of the blocks reported since the last call,
+    /// checking each failed validation.
+    fn take_reports(&self) -> Vec<(u64, String)> {
+        let bytes = std::mem::take(&mut *self.0.lock().unwrap());
+        String::from_utf8(bytes)
+            .unwrap()
+            .lines()
+            .map(|line| {
+                let report: Value = serde_json::from_str(line).unwrap();
+                assert!(report["error"].is_string());
+                (
+                    report["height"].as_u64().unwrap(),
+                    report["indep_hash"].as_str().unwrap().to_string(),
+                )
+            })
+            .collect()
+    }
+}
+
+fn header(json: &Value) -> ArweaveBlockHeader {
+    serde_json::from_value(json.clone()).unwrap()
+}
+
+fn expected_reports(headers: &[Value]) -> Vec<(u64, String)> {
+    headers
+        .iter()
+        .map(|json| (height(json).unwrap(), indep_hash(json).unwrap().to_string()))
+        .collect()
+}
+
+fn assert_indexed(block_index: &BlockIndex<Initialized>, chain: &[Value]) {
+    assert_eq!(block_index.num_indexes(), chain.len() as u64);
+    for (position, json) in chain.iter().enumerate() {
+        assert_eq!(
+            block_index.get_item(position).unwrap().block_hash,
+            header(json).indep_hash,
+            "block index position {position}"
+        );
+    }
+}
+
+#[test]
+fn follows_reorgs() {
+    let _serial = serial();
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let template = block_header_json(cases::POA2);
+    let chain = synthetic_chain(&template, 0..=60, 0, None);
+    let peer = MockPeer::start().unwrap();
+    peer.insert_headers(chain.clone()).unwrap();
+    peer.set_tip(40).unwrap();
+
+    let args = [
+        "follow",
+        "--peer",
+        peer.url(),
+        "--window",
+        "10",
+        "--batch-size",
+        "4",
+    ];
+    let cli = parse_args(args.map(String::from)).unwrap();
+    let client = ArweaveClient::with_config(&cli.peer_urls, ClientConfig::default()).unwrap();
+
+    // The block index ends below the window, with a header orphaned since it
+    // was stored in the gap between them
+    let store_dir = tempfile::tempdir().unwrap();
+    let mut store = HeaderStore::open(store_dir.path()).unwrap();
+    let orphan = synthetic_chain(&template, 28..=28, 3, Some(&chain[27]));
+    store.put_json(&orphan[0]).unwrap();
+    let items: Vec<BlockIndexItem> = chain[..=25]
+        .iter()
+        .map(|json| (&header(json)).into())
+        .collect();
+    let block_index = BlockIndex::new().init_from_items(items);
+
+    let vm = create_randomx_vm(RandomXMode::FastInitialization, RANDOMX_PACKING_KEY);
+    let output = Output::default();
+    let mut writer = ReportWriter::with_output(OutputFormat::JsonLines, Box::new(output.clone()));
+    let mut follower = runtime
+        .block_on(ChainFollower::new(
+            &client,
+            &cli,
+            block_index,
+            vm,
+            store,
+            40,
+        ))
+        .unwrap();
+    assert_indexed(follower.block_index(), &chain[..=40]);
+
+    // Nothing is reported until the peer has new blocks
+    runtime.block_on(follower.poll(&mut writer)).unwrap();
+    assert!(output.take_reports().is_empty());
+    peer.set_tip(45).unwrap();
+    runtime.block_on(follower.poll(&mut writer)).unwrap();
+    assert_eq!(output.take_reports(), expected_reports(&chain[41..=45]));
+    assert_indexed(follower.block_index(), &chain[..=45]);
+
+    // A reorg below the tip rolls back to the fork point and validates the
+    // new branch
+    let fork = synthetic_chain(&template, 43..=48, 1, Some(&chain[42]));
+    peer.reorg(fork.clone()).unwrap();
+    runtime.block_on(follower.poll(&mut writer)).unwrap();
+    assert_eq!(output.take_reports(), expected_reports(&fork));
+    let followed: Vec<Value> = chain[..=42].iter().chain(&fork).cloned().collect();
+    assert_indexed(follower.block_index(), &followed);
+
+    // A reorg below the window restarts the window at the new branch and rolls
+    // the block index back to the fork point, the blocks of the new branch in
+    // the restarted window aren't validated
+    let deep_fork = synthetic_chain(&template, 31..=52, 2, Some(&chain[30]));
+    peer.reorg(deep_fork.clone()).unwrap();
+    runtime.block_on(follower.poll(&mut writer)).unwrap();
+    assert_eq!(output.take_reports(), expected_reports(&deep_fork[19..]));
+    let followed: Vec<Value> = chain[..=30].iter().chain(&deep_fork).cloned().collect();
+    assert_indexed(follower.block_index(), &followed);
+}
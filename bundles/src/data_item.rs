@@ -0,0 +1,274 @@
+//! Decodes ANS-104 data items and verifies their signatures.
+//!
+//! A data item is laid out as follows (all integers are little-endian):
+//!
+//! | Bytes            | Field                                        |
+//! |------------------|----------------------------------------------|
+//! | 2                | signature type                               |
+//! | depends on type  | signature                                    |
+//! | depends on type  | owner (public key)                           |
+//! | 1 (+32)          | target presence byte (and target)            |
+//! | 1 (+32)          | anchor presence byte (and anchor)            |
+//! | 8                | number of tags                               |
+//! | 8                | number of tag bytes                          |
+//! | number of bytes  | Avro serialized tags                         |
+//! | remaining bytes  | data                                         |
+use crate::tags::*;
+use arweave_rs_types::{
+    deep_hash::{deep_hash, DeepHashItem},
+    transaction::Tag,
+    Base64, H256,
+};
+use arweave_rs_validator::transaction::rsa_signature_is_valid;
+use color_eyre::eyre::{eyre, Result};
+use openssl::{
+    bn::{BigNum, BigNumContext},
+    ec::{EcGroup, EcKey, EcPoint},
+    ecdsa::EcdsaSig,
+    nid::Nid,
+    pkey::{Id, PKey},
+    sha,
+    sign::Verifier,
+};
+use sha3::{Digest, Keccak256};
+use std::io::Read;
+
+/// The signature schemes a data item may be signed with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SignatureType {
+    /// RSA-PSS with a 4096 bit Arweave key.
+    Arweave,
+    /// Ed25519, used by Ed25519 wallets.
+    Ed25519,
+    /// secp256k1 ECDSA over an EIP-191 personal message, used by Ethereum
+    /// wallets.
+    Ethereum,
+    /// Ed25519, used by Solana wallets.
+    Solana,
+}
+
+impl SignatureType {
+    /// Maps the 2 byte signature type of a data item to a [`SignatureType`].
+    pub fn from_u16(value: u16) -> Result<Self> {
+        match value {
+            1 => Ok(SignatureType::Arweave),
+            2 => Ok(SignatureType::Ed25519),
+            3 => Ok(SignatureType::Ethereum),
+            4 => Ok(SignatureType::Solana),
+            _ => Err(eyre!("unsupported data item signature type {value}")),
+        }
+    }
+
+    pub fn as_u16(&self) -> u16 {
+        match self {
+            SignatureType::Arweave => 1,
+            SignatureType::Ed25519 => 2,
+            SignatureType::Ethereum => 3,
+            SignatureType::Solana => 4,
+        }
+    }
+
+    /// The size in bytes of a signature of this type.
+    pub fn signature_size(&self) -> usize {
+        match self {
+            SignatureType::Arweave => 512,
+            SignatureType::Ed25519 | SignatureType::Solana => 64,
+            SignatureType::Ethereum => 65,
+        }
+    }
+
+    /// The size in bytes of the owners public key for this type.
+    pub fn owner_size(&self) -> usize {
+        match self {
+            SignatureType::Arweave => 512,
+            SignatureType::Ed25519 | SignatureType::Solana => 32,
+            SignatureType::Ethereum => 65,
+        }
+    }
+}
+
+/// Stores the decoded fields of an ANS-104 data item.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DataItem {
+    pub signature_type: SignatureType,
+    pub signature: Base64,
+    /// The public key of the signer.
+    pub owner: Base64,
+    /// The optional address of the recipient of the data item.
+    pub target: Option<H256>,
+    /// The optional 32 byte anchor of the data item.
+    pub anchor: Option<H256>,
+    pub tags: Vec<Tag>,
+    /// The Avro serialized tags exactly as they were signed.
+    pub raw_tags: Base64,
+    pub data: Base64,
+}
+
+impl DataItem {
+    /// Decodes a data item from its serialized `bytes`.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let mut reader = bytes;
+        Self::from_reader(&mut reader)
+    }
+
+    /// Decodes a data item by reading from `reader` until it is exhausted. To
+    /// read a single data item out of a larger stream limit the reader to the
+    /// size of the item, e.g. with [`Read::take()`].
+    pub fn from_reader<R: Read>(reader: &mut R) -> Result<Self> {
+        let signature_type = SignatureType::from_u16(read_u16_le(reader)?)?;
+        let signature = read_exact_vec(reader, signature_type.signature_size())?;
+        let owner = read_exact_vec(reader, signature_type.owner_size())?;
+        let target = read_optional_hash(reader)?;
+        let anchor = read_optional_hash(reader)?;
+
+        let tag_count = read_u64_le(reader)?;
+        let tag_bytes_size = read_u64_le(reader)?;
+        if tag_count > MAX_TAG_COUNT {
+            return Err(eyre!("data item has too many tags {tag_count}"));
+        }
+        // Bounded before allocating, the size is read from untrusted input
+        if tag_bytes_size > MAX_TAG_BYTES {
+            return Err(eyre!("data item tags are too large {tag_bytes_size}"));
+        }
+        let raw_tags = read_exact_vec(reader, tag_bytes_size as usize)?;
+        let tags = decode_tags(&raw_tags)?;
+        if tags.len() as u64 != tag_count {
+            return Err(eyre!(
+                "data item tag count {tag_count} does not match decoded tags {}",
+                tags.len()
+            ));
+        }
+
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+
+        Ok(DataItem {
+            signature_type,
+            signature: Base64(signature),
+            owner: Base64(owner),
+            target,
+            anchor,
+            tags,
+            raw_tags: Base64(raw_tags),
+            data: Base64(data),
+        })
+    }
+
+    /// Serializes the data item to its binary layout.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(&self.signature_type.as_u16().to_le_bytes());
+        bytes.extend_from_slice(self.signature.as_slice());
+        bytes.extend_from_slice(self.owner.as_slice());
+        for optional_hash in [&self.target, &self.anchor] {
+            match optional_hash {
+                Some(hash) => {
+                    bytes.push(1);
+                    bytes.extend_from_slice(hash.as_bytes());
+                }
+                None => bytes.push(0),
+            }
+        }
+        bytes.extend_from_slice(&(self.tags.len() as u64).to_le_bytes());
+        bytes.extend_from_slice(&(self.raw_tags.len() as u64).to_le_bytes());
+        bytes.extend_from_slice(self.raw_tags.as_slice());
+        bytes.extend_from_slice(self.data.as_slice());
+        bytes
+    }
+
+    /// The data item identifier, the `SHA-256` hash of the `signature`.
+    pub fn id(&self) -> H256 {
+        H256(sha::sha256(self.signature.as_slice()))
+    }
+
+    /// Returns the `deep_hash` of the data items fields, the message signed by
+    /// the owner.
+    pub fn signature_data(&self) -> [u8; 48] {
+        let optional_bytes = |hash: &Option<H256>| hash.map(|h| h.to_vec()).unwrap_or_default();
+
+        deep_hash(&DeepHashItem::List(vec![
+            DeepHashItem::from_bytes(b"dataitem"),
+            DeepHashItem::from_bytes(b"1"),
+            DeepHashItem::from_bytes(self.signature_type.as_u16().to_string().as_bytes()),
+            DeepHashItem::from_bytes(self.owner.as_slice()),
+            DeepHashItem::Blob(optional_bytes(&self.target)),
+            DeepHashItem::Blob(optional_bytes(&self.anchor)),
+            DeepHashItem::from_bytes(self.raw_tags.as_slice()),
+            DeepHashItem::from_bytes(self.data.as_slice()),
+        ]))
+    }
+}
+
+/// Verifies the tags and the signature of a data item.
+pub fn data_item_is_valid(data_item: &DataItem) -> bool {
+    tags_are_valid(&data_item.tags) && data_item_signature_is_valid(data_item)
+}
+
+/// Verifies the `signature` of the data item was produced by its `owner`
+/// using the scheme indicated by its `signature_type`.
+pub fn data_item_signature_is_valid(data_item: &DataItem) -> bool {
+    let message = data_item.signature_data();
+    let owner = data_item.owner.as_slice();
+    let signature = data_item.signature.as_slice();
+
+    match data_item.signature_type {
+        SignatureType::Arweave => rsa_signature_is_valid(owner, &message, signature),
+        SignatureType::Ed25519 | SignatureType::Solana => {
+            ed25519_verify(owner, &message, signature).unwrap_or(false)
+        }
+        SignatureType::Ethereum => ethereum_verify(owner, &message, signature).unwrap_or(false),
+    }
+}
+
+fn ed25519_verify(public_key: &[u8], message: &[u8], signature: &[u8]) -> Result<bool> {
+    let pkey = PKey::public_key_from_raw_bytes(public_key, Id::ED25519)?;
+    let mut verifier = Verifier::new_without_digest(&pkey)?;
+    Ok(verifier.verify_oneshot(signature, message)?)
+}
+
+/// Ethereum wallets sign the `keccak-256` hash of the message prefixed as an
+/// EIP-191 personal message. The signature is `r || s || v`.
+fn ethereum_verify(public_key: &[u8], message: &[u8], signature: &[u8]) -> Result<bool> {
+    let mut hasher = Keccak256::new();
+    hasher.update(format!("\x19Ethereum Signed Message:\n{}", message.len()).as_bytes());
+    hasher.update(message);
+    let message_hash = hasher.finalize();
+
+    let group = EcGroup::from_curve_name(Nid::SECP256K1)?;
+    let mut ctx = BigNumContext::new()?;
+    let point = EcPoint::from_bytes(&group, public_key, &mut ctx)?;
+    let key = EcKey::from_public_key(&group, &point)?;
+
+    let r = BigNum::from_slice(&signature[0..32])?;
+    let s = BigNum::from_slice(&signature[32..64])?;
+    let ecdsa_signature = EcdsaSig::from_private_components(r, s)?;
+    Ok(ecdsa_signature.verify(&message_hash, &key)?)
+}
+
+fn read_exact_vec<R: Read>(reader: &mut R, size: usize) -> Result<Vec<u8>> {
+    let mut bytes = vec![0u8; size];
+    reader.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+fn read_u16_le<R: Read>(reader: &mut R) -> Result<u16> {
+    let mut bytes = [0u8; 2];
+    reader.read_exact(&mut bytes)?;
+    Ok(u16::from_le_bytes(bytes))
+}
+
+fn read_u64_le<R: Read>(reader: &mut R) -> Result<u64> {
+    let mut bytes = [0u8; 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+fn read_optional_hash<R: Read>(reader: &mut R) -> Result<Option<H256>> {
+    let mut presence = [0u8; 1];
+    reader.read_exact(&mut presence)?;
+    match presence[0] {
+        0 => Ok(None),
+        1 => Ok(Some(H256::from_slice(&read_exact_vec(reader, 32)?))),
+        _ => Err(eyre!("invalid presence byte {}", presence[0])),
+    }
+}
@@ -0,0 +1,174 @@
+//! Parses ANS-104 bundles, transactions whose data contains many independently
+//! signed data items, and verifies the data items they contain.
+//!
+//! A bundle starts with a header listing the size and id of each of its data
+//! items, followed by the data items themselves (all integers are 32 byte
+//! little-endian values).
+//!
+//! | Bytes   | Field                                             |
+//! |---------|---------------------------------------------------|
+//! | 32      | number of data items                              |
+//! | 64 * N  | `{size, id}` pairs, one for each of the N items   |
+//! | ...     | the data items, in the order of the header        |
+use arweave_rs_types::H256;
+use color_eyre::eyre::{eyre, Result};
+use data_item::*;
+use std::io::Read;
+
+pub mod data_item;
+pub mod tags;
+
+/// The size of the integers in the bundle header.
+const HEADER_INT_SIZE: usize = 32;
+
+/// The size of each `{size, id}` entry in the bundle header.
+const HEADER_ENTRY_SIZE: usize = 64;
+
+/// An entry in the [`BundleHeader`] describing one of the data items.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BundleHeaderEntry {
+    /// The size of the serialized data item in bytes.
+    pub size: u64,
+    /// The id of the data item.
+    pub id: H256,
+}
+
+/// The header of an ANS-104 bundle.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BundleHeader {
+    pub entries: Vec<BundleHeaderEntry>,
+}
+
+impl BundleHeader {
+    /// Parses the bundle header from the beginning of the `bytes` of a bundle.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let mut reader = bytes;
+        Self::from_reader(&mut reader)
+    }
+
+    /// Parses the bundle header from a `reader` positioned at the beginning of
+    /// a bundle. On success the reader is positioned at the first data item.
+    pub fn from_reader<R: Read>(reader: &mut R) -> Result<Self> {
+        let item_count = read_u64_from_le_u256(reader)?;
+
+        let mut entries = Vec::new();
+        for _ in 0..item_count {
+            let size = read_u64_from_le_u256(reader)?;
+            let mut id = [0u8; 32];
+            reader.read_exact(&mut id)?;
+            entries.push(BundleHeaderEntry { size, id: H256(id) });
+        }
+        Ok(BundleHeader { entries })
+    }
+
+    /// The number of data items in the bundle.
+    pub fn item_count(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// The size of the header in bytes, which is also the offset of the first
+    /// data item in the bundle.
+    pub fn size(&self) -> u64 {
+        (HEADER_INT_SIZE + self.entries.len() * HEADER_ENTRY_SIZE) as u64
+    }
+
+    /// Returns the offset of each data item relative to the start of the
+    /// bundle, paired with its header entry.
+    pub fn item_offsets(&self) -> Vec<(u64, &BundleHeaderEntry)> {
+        let mut offset = self.size();
+        self.entries
+            .iter()
+            .map(|entry| {
+                let item_offset = offset;
+                offset += entry.size;
+                (item_offset, entry)
+            })
+            .collect()
+    }
+}
+
+/// Streams the data items out of a bundle one at a time, so that only a single
+/// data item needs to be held in memory.
+pub struct BundleReader<R: Read> {
+    reader: R,
+    header: BundleHeader,
+    next_index: usize,
+}
+
+impl<R: Read> BundleReader<R> {
+    /// Reads the bundle header from `reader`, leaving the data items to be
+    /// read by iterating the [`BundleReader`].
+    pub fn new(mut reader: R) -> Result<Self> {
+        let header = BundleHeader::from_reader(&mut reader)?;
+        Ok(BundleReader {
+            reader,
+            header,
+            next_index: 0,
+        })
+    }
+
+    pub fn header(&self) -> &BundleHeader {
+        &self.header
+    }
+}
+
+impl<R: Read> Iterator for BundleReader<R> {
+    type Item = Result<DataItem>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let entry = self.header.entries.get(self.next_index)?.clone();
+        self.next_index += 1;
+
+        let mut item_reader = (&mut self.reader).take(entry.size);
+        let result = DataItem::from_reader(&mut item_reader).and_then(|data_item| {
+            if data_item.id() == entry.id {
+                Ok(data_item)
+            } else {
+                Err(eyre!(
+                    "data item id {} does not match the bundle header",
+                    base64_url::encode(&entry.id)
+                ))
+            }
+        });
+
+        // Stop iterating after an error as the position of the reader in
+        // the stream is no longer known
+        if result.is_err() {
+            self.next_index = self.header.entries.len();
+        }
+        Some(result)
+    }
+}
+
+/// Parses all of the data items from the `bytes` of a bundle, checking that
+/// each data items id matches its entry in the header.
+pub fn parse_bundle(bytes: &[u8]) -> Result<(BundleHeader, Vec<DataItem>)> {
+    let bundle_reader = BundleReader::new(bytes)?;
+    let header = bundle_reader.header().clone();
+    let data_items = bundle_reader.collect::<Result<Vec<DataItem>>>()?;
+    Ok((header, data_items))
+}
+
+/// Parses a bundle and verifies the tags and signature of each of its data
+/// items, returning the first invalid item as an error.
+pub fn verify_bundle(bytes: &[u8]) -> Result<Vec<DataItem>> {
+    let (_header, data_items) = parse_bundle(bytes)?;
+    match data_items.iter().find(|item| !data_item_is_valid(item)) {
+        Some(invalid_item) => Err(eyre!(
+            "data item {} is invalid",
+            base64_url::encode(&invalid_item.id())
+        )),
+        None => Ok(data_items),
+    }
+}
+
+/// Reads a 32 byte little-endian integer, failing if it doesn't fit a `u64`.
+fn read_u64_from_le_u256<R: Read>(reader: &mut R) -> Result<u64> {
+    let mut bytes = [0u8; HEADER_INT_SIZE];
+    reader.read_exact(&mut bytes)?;
+    let (low, high) = bytes.split_at(8);
+    if high.iter().any(|&b| b != 0) {
+        return Err(eyre!("bundle header integer is too large"));
+    }
+    Ok(u64::from_le_bytes(low.try_into()?))
+}
@@ -0,0 +1,138 @@
+//! Encodes and decodes the Avro serialized tags of ANS-104 data items.
+//!
+//! The tags are an Avro array of `{name: bytes, value: bytes}` records. Avro
+//! arrays are written as a series of blocks, each starting with a zigzag
+//! encoded item count, and terminated by a block with a count of 0.
+use arweave_rs_types::{transaction::Tag, Base64};
+use color_eyre::eyre::{eyre, Result};
+
+/// The maximum number of tags a data item may have.
+pub const MAX_TAG_COUNT: u64 = 128;
+
+/// The maximum size in bytes of a tag name.
+pub const MAX_TAG_NAME_SIZE: usize = 1024;
+
+/// The maximum size in bytes of a tag value.
+pub const MAX_TAG_VALUE_SIZE: usize = 3072;
+
+/// The maximum size in bytes of the serialized tags of a data item.
+pub const MAX_TAG_BYTES: u64 = 4096;
+
+/// Decodes the Avro serialized `bytes` into a list of [`Tag`]s.
+pub fn decode_tags(bytes: &[u8]) -> Result<Vec<Tag>> {
+    let mut tags = Vec::new();
+    if bytes.is_empty() {
+        return Ok(tags);
+    }
+
+    let mut cursor = AvroCursor { bytes, position: 0 };
+    loop {
+        let mut count = cursor.read_long()?;
+        if count == 0 {
+            break;
+        }
+        if count < 0 {
+            // A negative count is followed by the size of the block in bytes
+            count = count
+                .checked_neg()
+                .ok_or_else(|| eyre!("invalid block count in the tags"))?;
+            cursor.read_long()?;
+        }
+        for _ in 0..count {
+            let name = cursor.read_bytes()?;
+            let value = cursor.read_bytes()?;
+            tags.push(Tag {
+                name: Base64(name.to_vec()),
+                value: Base64(value.to_vec()),
+            });
+        }
+    }
+
+    if cursor.position != bytes.len() {
+        return Err(eyre!("unexpected bytes after the end of the tags"));
+    }
+    Ok(tags)
+}
+
+/// Serializes a list of [`Tag`]s into Avro bytes. An empty list of tags is
+/// serialized as an empty byte array.
+pub fn encode_tags(tags: &[Tag]) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    if tags.is_empty() {
+        return bytes;
+    }
+
+    write_long(&mut bytes, tags.len() as i64);
+    for tag in tags {
+        write_long(&mut bytes, tag.name.len() as i64);
+        bytes.extend_from_slice(tag.name.as_slice());
+        write_long(&mut bytes, tag.value.len() as i64);
+        bytes.extend_from_slice(tag.value.as_slice());
+    }
+    write_long(&mut bytes, 0);
+    bytes
+}
+
+/// Checks the ANS-104 limits on the number and sizes of the tags.
+pub fn tags_are_valid(tags: &[Tag]) -> bool {
+    tags.len() as u64 <= MAX_TAG_COUNT
+        && tags.iter().all(|tag| {
+            !tag.name.is_empty()
+                && !tag.value.is_empty()
+                && tag.name.len() <= MAX_TAG_NAME_SIZE
+                && tag.value.len() <= MAX_TAG_VALUE_SIZE
+        })
+}
+
+struct AvroCursor<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> AvroCursor<'a> {
+    /// Reads a zigzag encoded variable length integer.
+    fn read_long(&mut self) -> Result<i64> {
+        let mut value: u64 = 0;
+        let mut shift = 0;
+        loop {
+            let byte = *self
+                .bytes
+                .get(self.position)
+                .ok_or_else(|| eyre!("unexpected end of the tags"))?;
+            self.position += 1;
+            value |= ((byte & 0x7f) as u64) << shift;
+            if byte & 0x80 == 0 {
+                break;
+            }
+            shift += 7;
+            if shift > 63 {
+                return Err(eyre!("invalid variable length integer in the tags"));
+            }
+        }
+        Ok((value >> 1) as i64 ^ -((value & 1) as i64))
+    }
+
+    /// Reads a length prefixed byte array.
+    fn read_bytes(&mut self) -> Result<&'a [u8]> {
+        let length = self.read_long()?;
+        if length < 0 {
+            return Err(eyre!("negative byte array length in the tags"));
+        }
+        let end = self.position + length as usize;
+        let bytes = self
+            .bytes
+            .get(self.position..end)
+            .ok_or_else(|| eyre!("unexpected end of the tags"))?;
+        self.position = end;
+        Ok(bytes)
+    }
+}
+
+fn write_long(bytes: &mut Vec<u8>, value: i64) {
+    let mut zigzag = ((value << 1) ^ (value >> 63)) as u64;
+    while zigzag >= 0x80 {
+        bytes.push((zigzag as u8 & 0x7f) | 0x80);
+        zigzag >>= 7;
+    }
+    bytes.push(zigzag as u8);
+}
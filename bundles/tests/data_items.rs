@@ -0,0 +1,141 @@
+use arweave_rs_bundles::{data_item::*, tags::*, *};
+use arweave_rs_types::{transaction::Tag, Base64, H256};
+use openssl::{
+    bn::BigNumContext,
+    ec::{EcGroup, EcKey, PointConversionForm},
+    ecdsa::EcdsaSig,
+    nid::Nid,
+    pkey::PKey,
+    rand::rand_bytes,
+    sign::Signer,
+};
+use sha3::Digest;
+
+/// A data item signed with an Ed25519 key.
+fn ed25519_item() -> DataItem {
+    let tags = vec![Tag {
+        name: Base64::from_utf8_str("Content-Type").unwrap(),
+        value: Base64::from_utf8_str("text/plain").unwrap(),
+    }];
+    let ed25519_key = PKey::generate_ed25519().unwrap();
+    let mut item = DataItem {
+        signature_type: SignatureType::Ed25519,
+        signature: Base64(vec![0u8; 64]),
+        owner: Base64(ed25519_key.raw_public_key().unwrap()),
+        target: None,
+        anchor: Some(H256::repeat_byte(3)),
+        raw_tags: Base64(encode_tags(&tags)),
+        tags,
+        data: Base64::from_utf8_str("hello").unwrap(),
+    };
+    let mut signer = Signer::new_without_digest(&ed25519_key).unwrap();
+    let signature = signer.sign_oneshot_to_vec(&item.signature_data()).unwrap();
+    item.signature = Base64(signature);
+    item
+}
+
+/// A data item signed with a secp256k1 (Ethereum) key.
+fn ethereum_item() -> DataItem {
+    let group = EcGroup::from_curve_name(Nid::SECP256K1).unwrap();
+    let ec_key = EcKey::generate(&group).unwrap();
+    let mut ctx = BigNumContext::new().unwrap();
+    let owner = ec_key
+        .public_key()
+        .to_bytes(&group, PointConversionForm::UNCOMPRESSED, &mut ctx)
+        .unwrap();
+    let mut item = DataItem {
+        signature_type: SignatureType::Ethereum,
+        signature: Base64(vec![0u8; 65]),
+        owner: Base64(owner),
+        target: Some(H256::repeat_byte(9)),
+        anchor: None,
+        tags: vec![],
+        raw_tags: Base64(vec![]),
+        data: Base64(vec![7u8; 1000]),
+    };
+    let message = item.signature_data();
+    let prefix = format!("\x19Ethereum Signed Message:\n{}", message.len());
+    let mut prefixed_message = prefix.into_bytes();
+    prefixed_message.extend_from_slice(&message);
+    let message_hash = sha3::Keccak256::digest(&prefixed_message);
+    let signature = EcdsaSig::sign(&message_hash, &ec_key).unwrap();
+    let mut signature_bytes = signature.r().to_vec_padded(32).unwrap();
+    signature_bytes.extend(signature.s().to_vec_padded(32).unwrap());
+    signature_bytes.push(27);
+    item.signature = Base64(signature_bytes);
+    item
+}
+
+/// Assembles a bundle from `items`.
+fn bundle(items: &[DataItem]) -> Vec<u8> {
+    let mut bundle = Vec::new();
+    let mut item_count = [0u8; 32];
+    item_count[0] = items.len() as u8;
+    bundle.extend_from_slice(&item_count);
+    for item in items.iter() {
+        let mut size = [0u8; 32];
+        size[..8].copy_from_slice(&(item.to_bytes().len() as u64).to_le_bytes());
+        bundle.extend_from_slice(&size);
+        bundle.extend_from_slice(item.id().as_bytes());
+    }
+    for item in items.iter() {
+        bundle.extend(item.to_bytes());
+    }
+    bundle
+}
+
+#[test]
+fn bundle_data_items() {
+    let items = [ed25519_item(), ethereum_item()];
+    let mut bundle = bundle(&items);
+
+    // Stream the items back out of the bundle and verify them
+    let streamed_items: Vec<DataItem> = BundleReader::new(bundle.as_slice())
+        .unwrap()
+        .map(|item| item.unwrap())
+        .collect();
+    assert!(streamed_items == items);
+    assert!(verify_bundle(&bundle).is_ok());
+
+    // Corrupting the data of an item must invalidate its signature
+    let last_index = bundle.len() - 1;
+    bundle[last_index] ^= 1;
+    assert!(verify_bundle(&bundle).is_err());
+}
+
+#[test]
+fn oversized_tags_are_rejected() {
+    let mut bytes = ed25519_item().to_bytes();
+
+    // The tag byte size follows the signature type, signature, owner, empty
+    // target, anchor and tag count
+    let tag_bytes_size_offset = 2 + 64 + 32 + 1 + 33 + 8;
+    for size in [MAX_TAG_BYTES + 1, u32::MAX as u64, u64::MAX] {
+        bytes[tag_bytes_size_offset..tag_bytes_size_offset + 8]
+            .copy_from_slice(&size.to_le_bytes());
+        assert!(DataItem::from_bytes(&bytes).is_err());
+    }
+}
+
+#[test]
+fn malformed_tags_are_rejected() {
+    // A block count of i64::MIN, zigzag encoded as u64::MAX
+    let min_count = [0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x01];
+    assert!(decode_tags(&min_count).is_err());
+
+    // Neither random tags nor randomly corrupted data items may panic
+    let item_bytes = ethereum_item().to_bytes();
+    let mut random = [0u8; 64];
+    for _ in 0..10_000 {
+        rand_bytes(&mut random).unwrap();
+        let length = random[0] as usize % random.len();
+        let _ = decode_tags(&random[1..=length]);
+
+        let mut bytes = item_bytes.clone();
+        for pair in random.chunks(2).take(4) {
+            let index = u16::from_le_bytes([pair[0], pair[1]]) as usize % bytes.len();
+            bytes[index] = random[(index % 63) + 1];
+        }
+        let _ = DataItem::from_bytes(&bytes);
+    }
+}
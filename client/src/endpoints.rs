@@ -0,0 +1,159 @@
+//! Typed requests for the endpoints of the Arweave peer API.
+use crate::ArweaveClient;
+use arweave_rs_types::{transaction::ArweaveTransaction, *};
+use color_eyre::eyre::{eyre, Result};
+use futures::{stream, StreamExt, TryStreamExt};
+use serde_derive::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::ops::RangeInclusive;
+
+// This is the format of the JSON
+// {
+//   "tx_root" : "FDQNxgnKyW3ugAPJNipcA8jIplL0Jw8yD7j1dm3iViI",
+//   "weave_size" : "152674506940662",
+//   "hash" : "rRJ-5cTFVeTxtQDlTJgITpnDFfU58Fi2WYy4jNvBY7xQPK9HpgrEdacpUj1HbHAh"
+// }
+
+/// Stores the deserialized JSON block index data returned by the the peers
+/// `/block_index/<start_height>/<end_height>` endpoint.
+#[derive(Default, Clone, Debug, Serialize, Deserialize)]
+pub struct BlockIndexJson {
+    pub tx_root: String,
+    pub weave_size: String,
+    pub hash: String,
+}
+
+// This is the format of the JSON
+// {
+//   "chunk": "<base64url packed or unpacked chunk bytes>",
+//   "data_path": "<base64url merkle proof>",
+//   "tx_path": "<base64url merkle proof>",
+//   "packing": "unpacked" | "spora_2_5" | "spora_2_6_<base64url reward address>"
+// }
+
+/// Stores the deserialized JSON returned by the peers `/chunk/{offset}`
+/// endpoint.
+#[derive(Default, Clone, Debug, Serialize, Deserialize)]
+pub struct ChunkJson {
+    pub chunk: Base64,
+    pub data_path: Base64,
+    pub tx_path: Base64,
+    pub packing: String,
+}
+
+/// Stores the deserialized JSON returned by the peers `/info` endpoint.
+#[derive(Default, Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct NodeInfo {
+    pub network: String,
+    pub version: u64,
+    pub release: u64,
+    pub height: u64,
+    /// The `indep_hash` of the peers current block.
+    pub current: H384,
+    pub blocks: u64,
+    pub peers: u64,
+    pub queue_length: u64,
+    pub node_state_latency: u64,
+}
+
+impl ArweaveClient {
+    pub async fn block_json_by_height(&self, height: u64) -> Result<serde_json::Value> {
+        self.get_json(&format!("/block/height/{height}"), &[]).await
+    }
+
+    pub async fn block_by_height(&self, height: u64) -> Result<ArweaveBlockHeader> {
+        self.get_json(&format!("/block/height/{height}"), &[]).await
+    }
+
+    /// Requests the JSON of the headers of the `heights`, ordered by height.
+    pub async fn block_jsons_by_height(
+        &self,
+        heights: RangeInclusive<u64>,
+    ) -> Result<Vec<serde_json::Value>> {
+        stream::iter(heights)
+            .map(|height| self.block_json_by_height(height))
+            .buffered(self.config.concurrency)
+            .try_collect()
+            .await
+    }
+
+    /// Requests the headers of the `heights`, ordered by height.
+    pub async fn blocks_by_height(
+        &self,
+        heights: RangeInclusive<u64>,
+    ) -> Result<Vec<ArweaveBlockHeader>> {
+        stream::iter(heights)
+            .map(|height| self.block_by_height(height))
+            .buffered(self.config.concurrency)
+            .try_collect()
+            .await
+    }
+
+    pub async fn block_json_by_hash(&self, indep_hash: &H384) -> Result<serde_json::Value> {
+        let path = format!("/block/hash/{}", base64_url::encode(indep_hash));
+        self.get_json(&path, &[]).await
+    }
+
+    pub async fn block_by_hash(&self, indep_hash: &H384) -> Result<ArweaveBlockHeader> {
+        let path = format!("/block/hash/{}", base64_url::encode(indep_hash));
+        self.get_json(&path, &[]).await
+    }
+
+    /// Requests the header of the peers current block.
+    pub async fn current_block(&self) -> Result<ArweaveBlockHeader> {
+        self.get_json("/block/current", &[]).await
+    }
+
+    /// Requests the block index entries of the heights `start..=end`, most
+    /// recent first.
+    pub async fn block_index(&self, start: u64, end: u64) -> Result<Vec<BlockIndexJson>> {
+        // The x-block-format header includes the weave_size and tx_root in
+        // the response
+        self.get_json(&format!("/block_index/{start}/{end}"), &[("x-block-format", "1")])
+            .await
+    }
+
+    pub async fn info(&self) -> Result<NodeInfo> {
+        self.get_json("/info", &[]).await
+    }
+
+    /// Requests the chunk containing the weave byte at `offset`. Peers
+    /// address chunks by `(start, end]` ranges so the byte is requested as
+    /// `offset + 1`. The chunk is returned in whichever packing the peer
+    /// stores it, asking for unpacked chunks makes the peer unpack them first.
+    pub async fn chunk(&self, offset: u64) -> Result<ChunkJson> {
+        self.get_json(&format!("/chunk/{}", offset + 1), &[("x-packing", "any")])
+            .await
+    }
+
+    /// Requests the `[start, end)` byte ranges of the weave the peer stores.
+    /// The peer returns its sync record as a list of `{end: start}` objects.
+    pub async fn data_sync_record(&self) -> Result<Vec<(u64, u64)>> {
+        let intervals: Vec<HashMap<String, String>> = self
+            .get_json(
+                "/data_sync_record",
+                &[("accept", "application/json"), ("content-type", "application/json")],
+            )
+            .await?;
+        let mut sync_record = Vec::with_capacity(intervals.len());
+        for interval in intervals.iter().flatten() {
+            let (end, start) = interval;
+            let end: u64 = end
+                .parse()
+                .map_err(|e| eyre!("Failed to parse interval end: {}", e))?;
+            let start: u64 = start
+                .parse()
+                .map_err(|e| eyre!("Failed to parse interval start: {}", e))?;
+            sync_record.push((start, end));
+        }
+        sync_record.sort_unstable();
+        Ok(sync_record)
+    }
+
+    /// Requests the transaction with the `id`.
+    pub async fn tx(&self, id: &H256) -> Result<ArweaveTransaction> {
+        self.get_json(&format!("/tx/{}", base64_url::encode(id)), &[])
+            .await
+    }
+}
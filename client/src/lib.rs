@@ -0,0 +1,192 @@
+//! HTTP client for the Arweave peer API shared by the crates that request
+//! data from the network. Requests are spread across a pool of peers scored
+//! by their health, bounded to a number in flight at once, and retried with
+//! exponential backoff that honours the `Retry-After` of rate limited peers.
+use ::metrics::increment_counter;
+use backoff::{retry_after, Backoff};
+use color_eyre::eyre::{eyre, Result};
+use metrics::{HTTP_REQUESTS, HTTP_RETRIES};
+use peer_pool::PeerPool;
+use reqwest::{Client as ReqwestClient, StatusCode};
+use serde::de::DeserializeOwned;
+use std::collections::HashSet;
+use std::time::{Duration, Instant};
+use tokio::sync::Semaphore;
+use tracing::{debug, warn};
+
+pub mod backoff;
+pub mod endpoints;
+pub mod metrics;
+pub mod peer_pool;
+
+pub use endpoints::*;
+
+/// The peer requested when no peers are configured.
+pub const DEFAULT_PEER: &str = "https://arweave.net";
+
+#[derive(Debug, Clone)]
+pub struct ClientConfig {
+    /// The number of requests in flight at once, across all peers.
+    pub concurrency: usize,
+    /// The attempts made at a request, across peers, before it fails.
+    pub max_attempts: u32,
+    pub backoff: Backoff,
+    /// The time a single attempt may take.
+    pub timeout: Duration,
+}
+
+impl Default for ClientConfig {
+    fn default() -> Self {
+        ClientConfig {
+            concurrency: 10,
+            max_attempts: 5,
+            backoff: Backoff::default(),
+            timeout: Duration::from_secs(30),
+        }
+    }
+}
+
+/// Requests JSON from a pool of Arweave peers, see the [`endpoints`] for the
+/// typed requests.
+pub struct ArweaveClient {
+    http: ReqwestClient,
+    peers: PeerPool,
+    permits: Semaphore,
+    config: ClientConfig,
+}
+
+impl Default for ArweaveClient {
+    fn default() -> Self {
+        ArweaveClient::new([DEFAULT_PEER]).expect("the default peer to be valid")
+    }
+}
+
+impl ArweaveClient {
+    pub fn new<I, S>(peer_urls: I) -> Result<Self>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        ArweaveClient::with_config(peer_urls, ClientConfig::default())
+    }
+
+    pub fn with_config<I, S>(peer_urls: I, config: ClientConfig) -> Result<Self>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        if config.concurrency == 0 {
+            return Err(eyre!("concurrency must be at least 1"));
+        }
+        if config.max_attempts == 0 {
+            return Err(eyre!("max_attempts must be at least 1"));
+        }
+        Ok(ArweaveClient {
+            http: ReqwestClient::new(),
+            peers: PeerPool::new(peer_urls)?,
+            permits: Semaphore::new(config.concurrency),
+            config,
+        })
+    }
+
+    pub fn peers(&self) -> &PeerPool {
+        &self.peers
+    }
+
+    pub fn config(&self) -> &ClientConfig {
+        &self.config
+    }
+
+    /// Requests the JSON at `path` with the request `headers`. Failed attempts
+    /// are retried on the healthiest peer after a backoff delay, rate limited
+    /// peers aren't requested until their `Retry-After`, capped at the maximum
+    /// backoff delay, has passed, and a `404 Not Found` is only retried on
+    /// peers that weren't requested yet. A concurrency permit is only held
+    /// while a request is in flight, not while waiting to retry it.
+    pub async fn get_json<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        headers: &[(&'static str, &str)],
+    ) -> Result<T> {
+        let mut tried: Vec<usize> = Vec::new();
+        let mut last_error = None;
+
+        for attempt in 0..self.config.max_attempts {
+            // Wait out the Retry-After when every peer is rate limited
+            let wait = self.peers.time_until_available();
+            if !wait.is_zero() {
+                tokio::time::sleep(wait).await;
+            }
+
+            let peer_index = self.peers.select(&tried);
+            tried.push(peer_index);
+            let peer = self.peers.get(peer_index);
+            let url = format!("{}{path}", peer.url());
+
+            let mut request = self.http.get(&url).timeout(self.config.timeout);
+            for (name, value) in headers {
+                request = request.header(*name, *value);
+            }
+
+            let permit = self.permits.acquire().await?;
+            let start = Instant::now();
+            let (delay, reason) = match request.send().await {
+                Ok(res) if res.status() == StatusCode::OK => match res.json::<T>().await {
+                    Ok(parsed) => {
+                        peer.record_success(start.elapsed());
+                        increment_counter!(HTTP_REQUESTS, "result" => "ok");
+                        return Ok(parsed);
+                    }
+                    Err(err) => {
+                        peer.record_failure();
+                        last_error = Some(eyre!("{url} returned invalid JSON: {err}"));
+                        (self.config.backoff.delay(attempt), "invalid_json")
+                    }
+                },
+                Ok(res) if res.status() == StatusCode::TOO_MANY_REQUESTS => {
+                    // A peer can't stall the request for longer than a backoff
+                    let retry_after = retry_after(res.headers())
+                        .map(|retry_after| retry_after.min(self.config.backoff.max))
+                        .unwrap_or_else(|| self.config.backoff.delay(attempt));
+                    peer.record_rate_limited(retry_after);
+                    last_error = Some(eyre!("{url} returned {}", res.status()));
+                    // Another peer may be available straight away
+                    (Duration::ZERO, "rate_limited")
+                }
+                Ok(res) if res.status() == StatusCode::NOT_FOUND => {
+                    last_error = Some(eyre!("{url} returned {}", res.status()));
+                    let num_tried = tried.iter().collect::<HashSet<_>>().len();
+                    if num_tried == self.peers.len() {
+                        break;
+                    }
+                    (Duration::ZERO, "not_found")
+                }
+                Ok(res) => {
+                    peer.record_failure();
+                    last_error = Some(eyre!("{url} returned {}", res.status()));
+                    (self.config.backoff.delay(attempt), "status")
+                }
+                Err(err) => {
+                    peer.record_failure();
+                    last_error = Some(eyre!("Request to {url} failed with error: {err}"));
+                    (self.config.backoff.delay(attempt), "connection")
+                }
+            };
+            drop(permit);
+
+            if attempt + 1 < self.config.max_attempts {
+                increment_counter!(HTTP_RETRIES, "reason" => reason);
+                if let Some(err) = &last_error {
+                    debug!(attempt, reason, ?delay, error = %err, "retrying request");
+                }
+                if !delay.is_zero() {
+                    tokio::time::sleep(delay).await;
+                }
+            }
+        }
+        increment_counter!(HTTP_REQUESTS, "result" => "failed");
+        let err = last_error.expect("last_error should contain the most recent error");
+        warn!(path, error = %err, "request failed");
+        Err(err)
+    }
+}
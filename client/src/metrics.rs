@@ -0,0 +1,24 @@
+//! The metrics recorded by the client, see `arweave_rs_validator::metrics`.
+use metrics::{describe_counter, Unit};
+
+/// Requests made by [`ArweaveClient::get_json()`](crate::ArweaveClient::get_json),
+/// labelled with their `result` (`ok` or `failed`) once every attempt is made.
+pub const HTTP_REQUESTS: &str = "arweave_http_requests_total";
+/// Failed attempts at a request that are retried, labelled with the `reason`
+/// the attempt failed: `invalid_json`, `rate_limited`, `not_found`, `status`
+/// or `connection`.
+pub const HTTP_RETRIES: &str = "arweave_http_retries_total";
+
+/// Describes the metrics of the client to a recorder.
+pub fn describe_metrics() {
+    describe_counter!(
+        HTTP_REQUESTS,
+        Unit::Count,
+        "Requests to the Arweave peers, by result"
+    );
+    describe_counter!(
+        HTTP_RETRIES,
+        Unit::Count,
+        "Retried attempts at requests to the Arweave peers, by the reason the attempt failed"
+    );
+}
@@ -0,0 +1,174 @@
+//! Tracks the health of the peers requests are spread across.
+use color_eyre::eyre::{eyre, Result};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// How much the latest request outcome moves a peers score, the score is an
+/// exponential moving average of the outcomes (1 success, 0 failure).
+const SCORE_WEIGHT: f64 = 0.2;
+
+/// The request history of a peer.
+#[derive(Debug, Clone, Copy)]
+pub struct PeerHealth {
+    /// Between 0 and 1, starting at 1, higher is healthier.
+    pub score: f64,
+    pub successes: u64,
+    pub failures: u64,
+    /// The number of `429 Too Many Requests` responses.
+    pub rate_limits: u64,
+    /// The moving average latency of successful requests.
+    pub latency: Option<Duration>,
+    /// The peer isn't sent requests before this time, set by `Retry-After`.
+    pub rate_limited_until: Option<Instant>,
+}
+
+impl Default for PeerHealth {
+    fn default() -> Self {
+        PeerHealth {
+            score: 1.0,
+            successes: 0,
+            failures: 0,
+            rate_limits: 0,
+            latency: None,
+            rate_limited_until: None,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Peer {
+    url: String,
+    health: Mutex<PeerHealth>,
+}
+
+impl Peer {
+    fn new(url: &str) -> Self {
+        Peer {
+            url: url.trim_end_matches('/').to_string(),
+            health: Mutex::new(PeerHealth::default()),
+        }
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    pub fn health(&self) -> PeerHealth {
+        *self.health.lock().unwrap()
+    }
+
+    pub fn record_success(&self, latency: Duration) {
+        let mut health = self.health.lock().unwrap();
+        health.score = health.score * (1.0 - SCORE_WEIGHT) + SCORE_WEIGHT;
+        health.successes += 1;
+        health.latency = Some(match health.latency {
+            Some(average) => average.mul_f64(1.0 - SCORE_WEIGHT) + latency.mul_f64(SCORE_WEIGHT),
+            None => latency,
+        });
+    }
+
+    pub fn record_failure(&self) {
+        let mut health = self.health.lock().unwrap();
+        health.score *= 1.0 - SCORE_WEIGHT;
+        health.failures += 1;
+    }
+
+    /// Stops sending the peer requests for `retry_after`.
+    pub fn record_rate_limited(&self, retry_after: Duration) {
+        let mut health = self.health.lock().unwrap();
+        health.score *= 1.0 - SCORE_WEIGHT;
+        health.rate_limits += 1;
+        health.rate_limited_until = Some(Instant::now() + retry_after);
+    }
+
+    /// The time until the peer can be sent requests again, zero if it's not
+    /// rate limited.
+    pub fn time_until_available(&self) -> Duration {
+        self.health()
+            .rate_limited_until
+            .map_or(Duration::ZERO, |until| {
+                until.saturating_duration_since(Instant::now())
+            })
+    }
+}
+
+/// The peers a client spreads its requests across.
+#[derive(Debug)]
+pub struct PeerPool {
+    peers: Vec<Peer>,
+    /// Rotates the starting peer so equally healthy peers share the load.
+    next: AtomicUsize,
+}
+
+impl PeerPool {
+    pub fn new<I, S>(peer_urls: I) -> Result<Self>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let peers: Vec<Peer> = peer_urls
+            .into_iter()
+            .map(|url| Peer::new(url.as_ref()))
+            .collect();
+        if peers.is_empty() {
+            return Err(eyre!("the peer pool needs at least one peer"));
+        }
+        Ok(PeerPool {
+            peers,
+            next: AtomicUsize::new(0),
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.peers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.peers.is_empty()
+    }
+
+    pub fn get(&self, index: usize) -> &Peer {
+        &self.peers[index]
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, Peer> {
+        self.peers.iter()
+    }
+
+    /// Selects the peer for the next attempt of a request. Peers that aren't
+    /// rate limited are preferred, then peers the request hasn't `tried`,
+    /// then the healthiest peer.
+    pub fn select(&self, tried: &[usize]) -> usize {
+        let rank = |index: usize| {
+            let peer = &self.peers[index];
+            (
+                peer.time_until_available().is_zero(),
+                !tried.contains(&index),
+                peer.health().score,
+            )
+        };
+
+        // Only a strictly better peer replaces the best so far, so equally
+        // ranked peers are selected in rotation
+        let start = self.next.fetch_add(1, Ordering::Relaxed);
+        let mut best = start % self.peers.len();
+        for offset in 1..self.peers.len() {
+            let index = (start + offset) % self.peers.len();
+            if rank(index).partial_cmp(&rank(best)) == Some(std::cmp::Ordering::Greater) {
+                best = index;
+            }
+        }
+        best
+    }
+
+    /// The time until any peer can be sent requests, zero if one isn't rate
+    /// limited.
+    pub fn time_until_available(&self) -> Duration {
+        self.peers
+            .iter()
+            .map(Peer::time_until_available)
+            .min()
+            .unwrap_or_default()
+    }
+}
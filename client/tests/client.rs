@@ -0,0 +1,131 @@
+use arweave_rs_client::{
+    backoff::{parse_retry_after, Backoff},
+    ArweaveClient, ClientConfig,
+};
+use arweave_rs_test_support::{fixtures::data_dir, Fault, MockPeer};
+use std::time::{Duration, Instant, SystemTime};
+
+fn config() -> ClientConfig {
+    ClientConfig {
+        backoff: Backoff {
+            base: Duration::from_millis(10),
+            max: Duration::from_millis(50),
+        },
+        ..Default::default()
+    }
+}
+
+#[test]
+fn retry_after_is_honoured() {
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let peer = MockPeer::from_dir(data_dir().join("blocks")).unwrap();
+    peer.inject("/info", Fault::RateLimit { retry_after: Some(1) }, 1);
+    let config = ClientConfig {
+        backoff: Backoff {
+            base: Duration::from_millis(10),
+            max: Duration::from_secs(2),
+        },
+        ..Default::default()
+    };
+    let client = ArweaveClient::with_config([peer.url()], config).unwrap();
+
+    let start = Instant::now();
+    let info = runtime.block_on(client.info()).unwrap();
+    assert_eq!(info.height, peer.tip().unwrap());
+    assert!(start.elapsed() >= Duration::from_secs(1));
+    assert_eq!(peer.num_requests("/info"), 2);
+    let health = client.peers().get(0).health();
+    assert_eq!(health.rate_limits, 1);
+    assert_eq!(health.successes, 1);
+}
+
+#[test]
+fn retry_after_is_capped() {
+    // A peer asking to wait an hour is retried after the maximum backoff
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let peer = MockPeer::from_dir(data_dir().join("blocks")).unwrap();
+    peer.inject("/info", Fault::RateLimit { retry_after: Some(3600) }, 2);
+    let client = ArweaveClient::with_config([peer.url()], config()).unwrap();
+
+    let start = Instant::now();
+    runtime.block_on(client.info()).unwrap();
+    assert!(start.elapsed() < Duration::from_secs(5));
+    assert_eq!(peer.num_requests("/info"), 3);
+    assert_eq!(client.peers().get(0).health().rate_limits, 2);
+}
+
+#[test]
+fn permits_are_released_while_waiting() {
+    // With a single permit, a request waiting to be retried doesn't hold up
+    // the others
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let peer = MockPeer::from_dir(data_dir().join("blocks")).unwrap();
+    peer.inject("/info", Fault::Status(500), 1);
+    let config = ClientConfig {
+        concurrency: 1,
+        backoff: Backoff {
+            base: Duration::from_millis(800),
+            max: Duration::from_millis(800),
+        },
+        ..Default::default()
+    };
+    let client = ArweaveClient::with_config([peer.url()], config).unwrap();
+
+    let (retried, other) = runtime.block_on(async {
+        let retried = async {
+            client.info().await.unwrap();
+            Instant::now()
+        };
+        let other = async {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            client.block_by_height(1315909).await.unwrap();
+            Instant::now()
+        };
+        tokio::join!(retried, other)
+    });
+    assert!(other < retried);
+    assert_eq!(peer.num_requests("/info"), 2);
+}
+
+#[test]
+fn requests_move_to_healthy_peers() {
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let peer = MockPeer::from_dir(data_dir().join("blocks")).unwrap();
+    let failing_peer = MockPeer::start().unwrap();
+    failing_peer.inject_always("/", Fault::Status(500));
+    let client = ArweaveClient::with_config([failing_peer.url(), peer.url()], config()).unwrap();
+
+    for _ in 0..4 {
+        runtime.block_on(client.info()).unwrap();
+    }
+    let failing = client.peers().get(0).health();
+    let healthy = client.peers().get(1).health();
+    assert!(failing.failures >= 1);
+    assert_eq!(failing.successes, 0);
+    assert_eq!(healthy.successes, 4);
+    assert!(failing.score < healthy.score);
+}
+
+#[test]
+fn not_found_is_final() {
+    // A 404 from the only peer isn't retried
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let peer = MockPeer::from_dir(data_dir().join("blocks")).unwrap();
+    let client = ArweaveClient::with_config([peer.url()], config()).unwrap();
+    assert!(runtime.block_on(client.block_by_height(1)).is_err());
+    assert_eq!(peer.num_requests("/block/height/1"), 1);
+}
+
+#[test]
+fn retry_after_is_parsed() {
+    assert_eq!(parse_retry_after("3"), Some(Duration::from_secs(3)));
+    let in_a_minute = SystemTime::now() + Duration::from_secs(60);
+    let delay = parse_retry_after(&httpdate::fmt_http_date(in_a_minute)).unwrap();
+    assert!(delay > Duration::from_secs(55));
+    assert!(parse_retry_after("soon").is_none());
+}
+
+#[test]
+fn a_peer_is_required() {
+    assert!(ArweaveClient::new(Vec::<String>::new()).is_err());
+}
@@ -0,0 +1,431 @@
+//! Produces the blocks of a deterministic local Arweave network. A devnet
+//! starts from a genesis block holding a small synthetic weave and mines each
+//! following block the way a 2.7 miner would: it computes VDF steps at a tiny
+//! difficulty, mines the recall ranges of every partition with
+//! `arweave_rs_mining` and signs the resulting [`ArweaveBlockHeader`]. The blocks pass
+//! `pre_validate_block` with the devnet [`ConsensusParams`], so tests don't
+//! depend on mainnet fixtures.
+use arweave_rs_indexes::{block_index::BlockIndexItem, BlockIndex, Initialized};
+use arweave_rs_mining::{mine_step, MiningStep, SolutionCandidate};
+use arweave_rs_randomx::RandomXVM;
+use arweave_rs_types::{
+    block_hash::compute_indep_hash, consensus::*, pricing::*, reward_history::*, *,
+};
+use arweave_rs_validator::double_signing::{signature_preimage, signed_preimage};
+use arweave_rs_vdf::verify::{apply_reset_seed, step_number_to_salt_number, vdf_sha2};
+use color_eyre::eyre::{eyre, Result};
+use openssl::sha;
+use wallet::DevnetWallet;
+use weave::SyntheticWeave;
+
+pub mod wallet;
+pub mod weave;
+
+/// The settings of a devnet. Two devnets with the same config and wallet
+/// produce identical blocks.
+#[derive(Debug, Clone)]
+pub struct DevnetConfig {
+    /// The consensus parameters of the network, all forks must be active
+    /// from the genesis block. See [`DevnetConfig::localnet_params()`].
+    pub params: ConsensusParams,
+    /// Entropy for the synthetic weave and the genesis VDF state.
+    pub seed: Vec<u8>,
+    /// The timestamp of the genesis block, block `n` is timestamped
+    /// `n * TARGET_TIME` seconds later so the difficulty never retargets.
+    pub genesis_timestamp: u64,
+    /// The mining difficulty of every block.
+    pub diff: U256,
+    /// The minimum number of VDF steps in a block, at least 2 as the last
+    /// step checkpoints are computed from the second to last step.
+    pub min_steps_per_block: u64,
+    /// The number of transactions in the genesis block.
+    pub num_txs: usize,
+    /// The number of 256 KiB chunks in each genesis transaction.
+    pub chunks_per_tx: usize,
+}
+
+impl Default for DevnetConfig {
+    fn default() -> Self {
+        DevnetConfig {
+            params: DevnetConfig::localnet_params(),
+            seed: b"arweave_rs devnet".to_vec(),
+            genesis_timestamp: 1_700_000_000,
+            // One in four hashes is a solution
+            diff: U256::max_value() - U256::max_value() / 4,
+            min_steps_per_block: 2,
+            num_txs: 4,
+            chunks_per_tx: 4,
+        }
+    }
+}
+
+impl DevnetConfig {
+    /// The parameters of a tiny network with the 2.7 rules active from
+    /// genesis. Partitions are 8 chunks with 2 chunk recall ranges, the VDF
+    /// resets every 10 steps and a step is 25 checkpoints of 100 iterations.
+    pub fn localnet_params() -> ConsensusParams {
+        ConsensusParams::builder()
+            .fork_heights(0)
+            .strict_data_split_threshold(0)
+            .partition_size(8 * DATA_CHUNK_SIZE as u64)
+            .recall_range_size(2 * DATA_CHUNK_SIZE)
+            .nonce_limiter_reset_frequency(10)
+            .vdf_sha_1s(100 * NUM_CHECKPOINTS_IN_VDF_STEP as u64)
+            .build()
+            .expect("the localnet params are valid")
+    }
+
+    /// The number of SHA-256 iterations between VDF checkpoints.
+    pub fn vdf_difficulty(&self) -> u64 {
+        self.params.vdf_sha_1s / NUM_CHECKPOINTS_IN_VDF_STEP as u64
+    }
+
+    fn validate(&self) -> Result<()> {
+        if self.params.fork_2_7_height != 0 {
+            return Err(eyre!("devnet blocks follow the 2.7 rules from genesis"));
+        }
+        if self.min_steps_per_block < 2 {
+            return Err(eyre!("blocks need at least 2 VDF steps"));
+        }
+        let weave_size = (self.num_txs * self.chunks_per_tx) as u64 * DATA_CHUNK_SIZE as u64;
+        if weave_size < self.params.partition_size {
+            return Err(eyre!(
+                "the weave ({weave_size} bytes) must fill at least one partition ({} bytes)",
+                self.params.partition_size
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// A local chain of blocks mined by a single wallet.
+pub struct Devnet {
+    config: DevnetConfig,
+    wallet: DevnetWallet,
+    weave: SyntheticWeave,
+    blocks: Vec<ArweaveBlockHeader>,
+    reward_history: RewardHistory,
+    block_index_items: Vec<BlockIndexItem>,
+}
+
+impl Devnet {
+    /// Packs the synthetic weave for the `wallet` and produces the signed
+    /// genesis block.
+    pub fn new(
+        config: DevnetConfig,
+        wallet: DevnetWallet,
+        randomx_vm: Option<&RandomXVM>,
+    ) -> Result<Self> {
+        config.validate()?;
+        let weave = SyntheticWeave::new(
+            &config.seed,
+            config.num_txs,
+            config.chunks_per_tx,
+            &wallet.reward_addr(),
+            randomx_vm,
+        )?;
+
+        let mut devnet = Devnet {
+            config,
+            wallet,
+            weave,
+            blocks: Vec::new(),
+            reward_history: RewardHistory::default(),
+            block_index_items: Vec::new(),
+        };
+        let genesis = devnet.genesis_block()?;
+        devnet.push_block(genesis);
+        Ok(devnet)
+    }
+
+    pub fn config(&self) -> &DevnetConfig {
+        &self.config
+    }
+
+    pub fn params(&self) -> &ConsensusParams {
+        &self.config.params
+    }
+
+    pub fn wallet(&self) -> &DevnetWallet {
+        &self.wallet
+    }
+
+    pub fn weave(&self) -> &SyntheticWeave {
+        &self.weave
+    }
+
+    pub fn genesis(&self) -> &ArweaveBlockHeader {
+        &self.blocks[0]
+    }
+
+    /// The most recently produced block.
+    pub fn tip(&self) -> &ArweaveBlockHeader {
+        self.blocks.last().expect("the devnet has a genesis block")
+    }
+
+    /// All blocks produced so far, indexed by height.
+    pub fn blocks(&self) -> &[ArweaveBlockHeader] {
+        &self.blocks
+    }
+
+    /// The reward history committed to by the [`tip()`](Devnet::tip).
+    pub fn reward_history(&self) -> &RewardHistory {
+        &self.reward_history
+    }
+
+    /// A block index of the produced blocks for validating their proofs of
+    /// access.
+    pub fn block_index(&self) -> BlockIndex<Initialized> {
+        BlockIndex::new().init_from_items(self.block_index_items.clone())
+    }
+
+    /// Mines, signs and appends the next block. VDF steps are computed from
+    /// the output of the tip until one of them yields a solution, the block
+    /// is produced at that step.
+    pub fn produce_block(&mut self, randomx_vm: Option<&RandomXVM>) -> Result<&ArweaveBlockHeader> {
+        let params = &self.config.params;
+        let previous_block = self.tip();
+        let previous_info = &previous_block.nonce_limiter_info;
+        let reset_frequency = params.nonce_limiter_reset_frequency as u64;
+        let vdf_difficulty = self.config.vdf_difficulty();
+
+        let mut steps: Vec<H256> = Vec::new();
+        let mut output = previous_info.output;
+        let mut global_step_number = previous_info.global_step_number;
+        let (solution, last_step_checkpoints) = loop {
+            global_step_number += 1;
+
+            // The seed data can only change once per block
+            let resets_crossed = global_step_number / reset_frequency
+                - previous_info.global_step_number / reset_frequency;
+            if resets_crossed > 1 {
+                return Err(eyre!(
+                    "no solution found before step {global_step_number} crossed a second VDF reset"
+                ));
+            }
+            let seed_data = get_seed_data(global_step_number, previous_block, params)?;
+
+            let mut seed = output;
+            if global_step_number % reset_frequency == 0 {
+                seed = apply_reset_seed(seed, seed_data.seed);
+            }
+            let salt = step_number_to_salt_number(global_step_number as usize - 1);
+            let checkpoints = vdf_sha2(
+                U256::from(salt),
+                seed,
+                NUM_CHECKPOINTS_IN_VDF_STEP,
+                vdf_difficulty as usize,
+            );
+            output = *checkpoints.last().unwrap();
+            steps.push(output);
+
+            if (steps.len() as u64) < self.config.min_steps_per_block {
+                continue;
+            }
+            // Mirrors the validator, H0 is seeded by the previous blocks seed
+            let step = MiningStep {
+                vdf_output: output,
+                global_step_number,
+                seed: previous_info.seed,
+                partition_upper_bound: seed_data.partition_upper_bound,
+                mining_addr: self.wallet.reward_addr(),
+                diff: previous_block.diff,
+            };
+            if let Some(solution) = mine_step(&step, &self.weave, params, randomx_vm)? {
+                break (solution, checkpoints);
+            }
+        };
+
+        let block = self.assemble_block(
+            previous_block,
+            global_step_number,
+            steps,
+            last_step_checkpoints,
+            solution,
+        )?;
+        self.push_block(block);
+        Ok(self.tip())
+    }
+
+    /// Fills in the header of the block mined at `global_step_number`.
+    /// `steps` are the outputs of the blocks VDF steps, oldest first, and
+    /// `last_step_checkpoints` the checkpoints of the final step.
+    fn assemble_block(
+        &self,
+        previous_block: &ArweaveBlockHeader,
+        global_step_number: u64,
+        mut steps: Vec<H256>,
+        mut last_step_checkpoints: Vec<H256>,
+        solution: SolutionCandidate,
+    ) -> Result<ArweaveBlockHeader> {
+        let params = &self.config.params;
+        let height = previous_block.height + 1;
+        let timestamp = self.config.genesis_timestamp + height * TARGET_TIME;
+        let last_retarget = if height % params.retarget_blocks == 0 {
+            timestamp
+        } else {
+            previous_block.last_retarget
+        };
+        let diff = previous_block.diff;
+        let seed_data = get_seed_data(global_step_number, previous_block, params)?;
+        let output = *steps.last().unwrap();
+
+        // Headers list the steps and checkpoints most recent first
+        steps.reverse();
+        last_step_checkpoints.reverse();
+
+        let chunk_hash = solution.chunk_hash();
+        let chunk2_hash = solution.chunk2_hash();
+
+        let (price_per_gib_minute, scheduled_price_per_gib_minute) =
+            recalculate_price_per_gib_minute(previous_block, params, || {
+                let (hash_rate_total, reward_total) =
+                    self.reward_history.totals(previous_block.denomination);
+                get_price_per_gib_minute(hash_rate_total, reward_total, params)
+            });
+        let weave_size = previous_block.weave_size;
+        let args = MinerRewardArgs {
+            height,
+            weave_size,
+            price_per_gib_minute,
+            denomination: previous_block.denomination,
+            block_interval: TARGET_TIME,
+            fees: distribute_transaction_fees(&[], previous_block.denomination),
+        };
+        let reward = get_miner_reward_endowment_pool_debt_supply(previous_block, &args);
+
+        let mut block = ArweaveBlockHeader {
+            block_size: 0,
+            chunk2_hash,
+            chunk_hash,
+            cumulative_diff: previous_block.cumulative_diff + get_hash_rate(diff),
+            debt_supply: reward.debt_supply,
+            denomination: previous_block.denomination,
+            diff,
+            hash: H256(solution.solution_hash),
+            hash_preimage: solution.hash_preimage,
+            height,
+            kryder_plus_rate_multiplier: reward.kryder_plus_rate_multiplier,
+            kryder_plus_rate_multiplier_latch: reward.kryder_plus_rate_multiplier_latch,
+            last_retarget,
+            nonce: Nonce(solution.nonce),
+            nonce_limiter_info: NonceLimiterInfo {
+                output,
+                global_step_number,
+                seed: seed_data.seed,
+                next_seed: seed_data.next_seed,
+                zone_upper_bound: seed_data.partition_upper_bound,
+                next_zone_upper_bound: seed_data.next_partition_upper_bound,
+                prev_output: previous_block.nonce_limiter_info.output,
+                last_step_checkpoints: H256List(last_step_checkpoints),
+                checkpoints: H256List(steps),
+                vdf_difficulty: Some(seed_data.vdf_difficulty),
+                next_vdf_difficulty: Some(self.config.vdf_difficulty()),
+            },
+            partition_number: solution.partition_number,
+            poa: solution.poa,
+            poa2: solution.poa2,
+            previous_block: previous_block.indep_hash,
+            previous_cumulative_diff: previous_block.cumulative_diff,
+            previous_solution_hash: previous_block.hash,
+            price_per_gib_minute,
+            recall_byte: solution.recall_byte,
+            recall_byte2: solution.recall_byte2.map(U256::from),
+            redenomination_height: previous_block.redenomination_height,
+            reward: reward.reward.as_u64(),
+            reward_addr: self.wallet.reward_addr(),
+            reward_key: self.wallet.reward_key(),
+            reward_pool: reward.reward_pool.as_u64(),
+            scheduled_price_per_gib_minute,
+            scheduled_usd_to_ar_rate: previous_block.scheduled_usd_to_ar_rate.clone(),
+            strict_data_split_threshold: params.strict_data_split_threshold as u64,
+            timestamp,
+            usd_to_ar_rate: previous_block.usd_to_ar_rate.clone(),
+            weave_size,
+            ..Default::default()
+        };
+        block.reward_history_hash = self.reward_history.with_block(&block).hash();
+
+        self.sign_block(&mut block)?;
+        Ok(block)
+    }
+
+    /// The genesis block includes the whole synthetic weave. Its VDF seeds
+    /// are derived from the config `seed`.
+    fn genesis_block(&self) -> Result<ArweaveBlockHeader> {
+        let config = &self.config;
+        let weave_size = self.weave.weave_size();
+        let price = U256::from(PRICE_PER_GIB_MINUTE_PRE_TRANSITION);
+        let txs = self
+            .weave
+            .data_roots()
+            .iter()
+            .map(|data_root| Base64(sha::sha256(data_root.as_bytes()).to_vec()))
+            .collect();
+
+        let mut block = ArweaveBlockHeader {
+            block_size: weave_size,
+            cumulative_diff: get_hash_rate(config.diff),
+            denomination: U256::one(),
+            diff: config.diff,
+            kryder_plus_rate_multiplier: U256::one(),
+            last_retarget: config.genesis_timestamp,
+            nonce_limiter_info: NonceLimiterInfo {
+                output: H256(derive_hash(&config.seed, b"vdf output")),
+                seed: H384(sha::sha384(&derive_hash(&config.seed, b"vdf seed"))),
+                next_seed: H384(sha::sha384(&derive_hash(&config.seed, b"next vdf seed"))),
+                zone_upper_bound: weave_size,
+                next_zone_upper_bound: weave_size,
+                vdf_difficulty: Some(config.vdf_difficulty()),
+                next_vdf_difficulty: Some(config.vdf_difficulty()),
+                ..Default::default()
+            },
+            price_per_gib_minute: price,
+            reward_addr: self.wallet.reward_addr(),
+            reward_key: self.wallet.reward_key(),
+            scheduled_price_per_gib_minute: price,
+            scheduled_usd_to_ar_rate: USDToARRate([1, 1]),
+            strict_data_split_threshold: config.params.strict_data_split_threshold as u64,
+            timestamp: config.genesis_timestamp,
+            tx_root: Some(self.weave.tx_root()),
+            txs: Base64List(txs),
+            usd_to_ar_rate: USDToARRate([1, 1]),
+            weave_size,
+            ..Default::default()
+        };
+        block.reward_history_hash = RewardHistory::default().with_block(&block).hash();
+
+        self.sign_block(&mut block)?;
+        Ok(block)
+    }
+
+    /// Signs `cdiff || prev_cdiff || previous_solution_hash || signed_hash`
+    /// and derives the `indep_hash` from the signature.
+    fn sign_block(&self, block: &mut ArweaveBlockHeader) -> Result<()> {
+        let params = &self.config.params;
+        let preimage = signed_preimage(block, params);
+        let message = signature_preimage(
+            block.cumulative_diff,
+            block.previous_cumulative_diff,
+            &preimage,
+        );
+        block.signature = self.wallet.sign(&message)?;
+
+        block.indep_hash = compute_indep_hash(block, params);
+        Ok(())
+    }
+
+    fn push_block(&mut self, block: ArweaveBlockHeader) {
+        self.reward_history.push_block(&block);
+        self.block_index_items.push((&block).into());
+        self.blocks.push(block);
+    }
+}
+
+/// `SHA-256(seed || label)`
+fn derive_hash(seed: &[u8], label: &[u8]) -> [u8; 32] {
+    let mut hasher = sha::Sha256::new();
+    hasher.update(seed);
+    hasher.update(label);
+    hasher.finish()
+}
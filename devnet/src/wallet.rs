@@ -0,0 +1,139 @@
+//! RSA wallets for signing devnet blocks. Keys can be derived from a seed so
+//! every run of a devnet produces the same blocks.
+use arweave_rs_types::*;
+use color_eyre::eyre::Result;
+use openssl::{
+    bn::{BigNum, BigNumContext},
+    hash::MessageDigest,
+    pkey::{PKey, Private},
+    rsa::{Padding, Rsa},
+    sha,
+    sign::{RsaPssSaltlen, Signer},
+};
+
+/// The modulus size of Arweave RSA keys.
+const RSA_KEY_BITS: usize = 4096;
+
+/// The public exponent of all Arweave RSA keys.
+const RSA_PUBLIC_EXPONENT: u32 = 65537;
+
+/// Number of Miller-Rabin rounds when testing prime candidates, enough for an
+/// error probability below 2^-128 at this key size.
+const PRIME_CHECKS: i32 = 64;
+
+/// An Arweave RSA-PSS (`SHA-256`) key pair used as the devnet miner.
+pub struct DevnetWallet {
+    rsa: Rsa<Private>,
+}
+
+impl DevnetWallet {
+    /// Derives a 4096 bit key from `seed`, the same seed always yields the
+    /// same key. The primes are the first primes following candidates read
+    /// from a `SHA-256` stream of the seed.
+    ///
+    /// Note: Only meant for test networks, the key is as secret as the seed.
+    pub fn from_seed(seed: &[u8]) -> Result<Self> {
+        let mut ctx = BigNumContext::new()?;
+        let e = BigNum::from_u32(RSA_PUBLIC_EXPONENT)?;
+
+        let p = deterministic_prime(seed, b"p", &e, &mut ctx)?;
+        let q = deterministic_prime(seed, b"q", &e, &mut ctx)?;
+
+        let one = BigNum::from_u32(1)?;
+        let mut p1 = BigNum::new()?;
+        p1.checked_sub(&p, &one)?;
+        let mut q1 = BigNum::new()?;
+        q1.checked_sub(&q, &one)?;
+
+        let mut n = BigNum::new()?;
+        n.checked_mul(&p, &q, &mut ctx)?;
+        let mut phi = BigNum::new()?;
+        phi.checked_mul(&p1, &q1, &mut ctx)?;
+
+        let mut d = BigNum::new()?;
+        d.mod_inverse(&e, &phi, &mut ctx)?;
+        let mut dmp1 = BigNum::new()?;
+        dmp1.nnmod(&d, &p1, &mut ctx)?;
+        let mut dmq1 = BigNum::new()?;
+        dmq1.nnmod(&d, &q1, &mut ctx)?;
+        let mut iqmp = BigNum::new()?;
+        iqmp.mod_inverse(&q, &p, &mut ctx)?;
+
+        let rsa = Rsa::from_private_components(n, e, d, p, q, dmp1, dmq1, iqmp)?;
+        Ok(DevnetWallet { rsa })
+    }
+
+    /// Generates a random wallet.
+    pub fn generate() -> Result<Self> {
+        let e = BigNum::from_u32(RSA_PUBLIC_EXPONENT)?;
+        let rsa = Rsa::generate_with_e(RSA_KEY_BITS as u32, &e)?;
+        Ok(DevnetWallet { rsa })
+    }
+
+    /// The public key of the wallet, the `reward_key` of the blocks it signs.
+    pub fn reward_key(&self) -> Base64 {
+        Base64(self.rsa.n().to_vec())
+    }
+
+    /// The `SHA-256` hash of the public key, the `reward_addr` of the blocks
+    /// it signs and the address its chunks are packed for.
+    pub fn reward_addr(&self) -> H256 {
+        H256(sha::sha256(&self.rsa.n().to_vec()))
+    }
+
+    /// Signs `message` with RSA-PSS (`SHA-256`). The salt is left empty so
+    /// signatures are deterministic, verifiers recover the salt length from
+    /// the signature.
+    pub fn sign(&self, message: &[u8]) -> Result<Base64> {
+        let pkey = PKey::from_rsa(self.rsa.clone())?;
+        let mut signer = Signer::new(MessageDigest::sha256(), &pkey)?;
+        signer.set_rsa_padding(Padding::PKCS1_PSS)?;
+        signer.set_rsa_pss_saltlen(RsaPssSaltlen::custom(0))?;
+        signer.update(message)?;
+        Ok(Base64(signer.sign_to_vec()?))
+    }
+}
+
+/// Finds the first prime `p`, with `gcd(e, p - 1) = 1`, at or above a
+/// `RSA_KEY_BITS / 2` bit candidate read from `SHA-256(seed || label || i)`.
+/// The top two bits of the candidate are set so the modulus has the full
+/// `RSA_KEY_BITS`.
+fn deterministic_prime(
+    seed: &[u8],
+    label: &[u8],
+    e: &BigNum,
+    ctx: &mut BigNumContext,
+) -> Result<BigNum> {
+    let num_bytes = RSA_KEY_BITS / 2 / 8;
+    let mut bytes = Vec::with_capacity(num_bytes);
+    let mut counter: u32 = 0;
+    while bytes.len() < num_bytes {
+        let mut hasher = sha::Sha256::new();
+        hasher.update(seed);
+        hasher.update(label);
+        hasher.update(&counter.to_be_bytes());
+        bytes.extend_from_slice(&hasher.finish());
+        counter += 1;
+    }
+    bytes.truncate(num_bytes);
+    bytes[0] |= 0xC0;
+    bytes[num_bytes - 1] |= 0x01;
+
+    let mut candidate = BigNum::from_slice(&bytes)?;
+    let two = BigNum::from_u32(2)?;
+    let one = BigNum::from_u32(1)?;
+    loop {
+        if candidate.is_prime_fasttest(PRIME_CHECKS, ctx, true)? {
+            let mut candidate_1 = BigNum::new()?;
+            candidate_1.checked_sub(&candidate, &one)?;
+            let mut gcd = BigNum::new()?;
+            gcd.gcd(e, &candidate_1, ctx)?;
+            if gcd == one {
+                return Ok(candidate);
+            }
+        }
+        let mut next = BigNum::new()?;
+        next.checked_add(&candidate, &two)?;
+        candidate = next;
+    }
+}
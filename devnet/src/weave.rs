@@ -0,0 +1,158 @@
+//! A small synthetic weave of deterministic chunks, packed for the devnet
+//! miner, along with the merkle proofs needed to assemble `poa` data for any
+//! of its chunks.
+use arweave_rs_mining::storage::ChunkStorage;
+use arweave_rs_packing::{compute_entropy, feistel::feistel_encrypt};
+use arweave_rs_randomx::RandomXVM;
+use arweave_rs_types::{consensus::*, *};
+use arweave_rs_validator::merkle::{generate_leaf, generate_root, resolve_proofs};
+use color_eyre::eyre::{eyre, Result};
+use openssl::sha;
+
+/// A packed chunk of the weave and its proofs.
+struct WeaveChunk {
+    packed_chunk: Vec<u8>,
+    tx_path: Vec<u8>,
+    data_path: Vec<u8>,
+}
+
+/// The data of the devnet, `num_txs` transactions of `chunks_per_tx` full
+/// 256 KiB chunks all included in the genesis block. The chunks are packed for
+/// a single `reward_addr` with the 2.6 packing.
+pub struct SyntheticWeave {
+    tx_root: H256,
+    data_roots: Vec<H256>,
+    chunks: Vec<WeaveChunk>,
+}
+
+impl SyntheticWeave {
+    /// Generates the chunks from `seed`, builds the data and tx merkle trees
+    /// and packs every chunk for `reward_addr`. Packing computes RandomX
+    /// entropy for each chunk so it takes a moment with a real RandomX VM.
+    pub fn new(
+        seed: &[u8],
+        num_txs: usize,
+        chunks_per_tx: usize,
+        reward_addr: &H256,
+        randomx_vm: Option<&RandomXVM>,
+    ) -> Result<Self> {
+        if num_txs == 0 || chunks_per_tx == 0 {
+            return Err(eyre!("the synthetic weave needs at least one chunk"));
+        }
+        let chunk_size = DATA_CHUNK_SIZE as usize;
+        let tx_size = chunks_per_tx * chunk_size;
+
+        // Build the data tree of each transaction
+        let mut data_roots = Vec::with_capacity(num_txs);
+        let mut data_paths = Vec::with_capacity(num_txs);
+        let mut tx_leaves = Vec::with_capacity(num_txs);
+        for tx_index in 0..num_txs {
+            let mut leaves = Vec::with_capacity(chunks_per_tx);
+            for chunk_index in 0..chunks_per_tx {
+                let chunk = chunk_data(seed, tx_index * chunks_per_tx + chunk_index);
+                let chunk_id = generate_chunk_id(&chunk);
+                let start = chunk_index * chunk_size;
+                leaves.push(generate_leaf(&chunk_id, start, start + chunk_size)?);
+            }
+            let root = generate_root(leaves)?.ok_or_else(|| eyre!("empty data tree"))?;
+            let data_root = root.id;
+            data_paths.push(resolve_proofs(root, None)?);
+
+            let tx_start = tx_index * tx_size;
+            tx_leaves.push(generate_leaf(&data_root, tx_start, tx_start + tx_size)?);
+            data_roots.push(H256(data_root));
+        }
+
+        let tx_tree = generate_root(tx_leaves)?.ok_or_else(|| eyre!("empty tx tree"))?;
+        let tx_root = H256(tx_tree.id);
+        let tx_paths = resolve_proofs(tx_tree, None)?;
+
+        // Pack the chunks, the packing entropy is derived from the absolute
+        // end offset of each chunk
+        let mut chunks = Vec::with_capacity(num_txs * chunks_per_tx);
+        for (tx_index, (tx_path, data_paths)) in tx_paths.iter().zip(data_paths).enumerate() {
+            for (chunk_index, data_path) in data_paths.into_iter().enumerate() {
+                let index = tx_index * chunks_per_tx + chunk_index;
+                let chunk_end_offset = ((index + 1) * chunk_size) as u64;
+                let input =
+                    get_chunk_entropy_input(U256::from(chunk_end_offset), &tx_root, reward_addr);
+                let entropy = compute_entropy(&input, RANDOMX_PACKING_ROUNDS_2_6, randomx_vm);
+                let packed_chunk = feistel_encrypt(&chunk_data(seed, index), &entropy);
+
+                chunks.push(WeaveChunk {
+                    packed_chunk,
+                    tx_path: tx_path.proof.clone(),
+                    data_path: data_path.proof,
+                });
+            }
+        }
+
+        Ok(SyntheticWeave {
+            tx_root,
+            data_roots,
+            chunks,
+        })
+    }
+
+    /// The number of bytes in the weave.
+    pub fn weave_size(&self) -> u64 {
+        self.chunks.len() as u64 * DATA_CHUNK_SIZE as u64
+    }
+
+    /// The `tx_root` of the genesis block.
+    pub fn tx_root(&self) -> H256 {
+        self.tx_root
+    }
+
+    /// The data_roots of the transactions, in weave order.
+    pub fn data_roots(&self) -> &[H256] {
+        &self.data_roots
+    }
+
+    /// The packed bytes of the chunk containing `recall_byte`.
+    pub fn packed_chunk(&self, recall_byte: u64) -> Option<&[u8]> {
+        self.chunk(recall_byte)
+            .map(|chunk| chunk.packed_chunk.as_slice())
+    }
+
+    /// Assembles the proof of access of the chunk containing `recall_byte`.
+    pub fn poa(&self, recall_byte: u64) -> Option<PoaData> {
+        self.chunk(recall_byte).map(|chunk| PoaData {
+            option: "1".to_string(),
+            tx_path: Base64(chunk.tx_path.clone()),
+            data_path: Base64(chunk.data_path.clone()),
+            chunk: Base64(chunk.packed_chunk.clone()),
+        })
+    }
+
+    fn chunk(&self, recall_byte: u64) -> Option<&WeaveChunk> {
+        self.chunks
+            .get((recall_byte / DATA_CHUNK_SIZE as u64) as usize)
+    }
+}
+
+impl ChunkStorage for SyntheticWeave {
+    fn read_packed_chunk(&self, offset: u64) -> Result<Option<Vec<u8>>> {
+        Ok(self.packed_chunk(offset).map(|chunk| chunk.to_vec()))
+    }
+
+    fn read_poa(&self, offset: u64) -> Result<Option<PoaData>> {
+        Ok(self.poa(offset))
+    }
+}
+
+/// The unpacked bytes of the chunk at `index`, a `SHA-256` stream of the
+/// `seed` and the index.
+pub fn chunk_data(seed: &[u8], index: usize) -> Vec<u8> {
+    let mut chunk = Vec::with_capacity(DATA_CHUNK_SIZE as usize);
+    let mut counter: u32 = 0;
+    while chunk.len() < DATA_CHUNK_SIZE as usize {
+        let mut hasher = sha::Sha256::new();
+        hasher.update(seed);
+        hasher.update(&(index as u64).to_be_bytes());
+        hasher.update(&counter.to_be_bytes());
+        chunk.extend_from_slice(&hasher.finish());
+        counter += 1;
+    }
+    chunk
+}
@@ -0,0 +1,73 @@
+use arweave_rs_test_support::devnet_fixture;
+use arweave_rs_types::consensus::TARGET_TIME;
+use arweave_rs_validator::{
+    pre_validate_block,
+    pricing::{validate_block_rewards, validate_reward_history},
+    transaction::validate_block_txs,
+};
+use arweave_rs_vdf::verify::{checkpoints_is_valid, last_step_checkpoints_is_valid};
+
+#[test]
+fn produced_blocks_are_valid() {
+    let mut fixture = devnet_fixture(b"test_devnet");
+    let (devnet, randomx_vm) = (&mut fixture.devnet, &fixture.randomx_vm);
+    let params = devnet.params().clone();
+
+    // Produce enough blocks to cross a few VDF resets and a retarget height
+    let mut reward_history = devnet.reward_history().clone();
+    for _ in 0..12 {
+        let block_header = devnet.produce_block(Some(randomx_vm)).unwrap().clone();
+        let blocks = devnet.blocks();
+        let previous_block_header = &blocks[blocks.len() - 2];
+        let block_index = devnet.block_index();
+        let nonce_info = &block_header.nonce_limiter_info;
+
+        pre_validate_block(
+            &block_header,
+            previous_block_header,
+            &block_index,
+            Some(randomx_vm),
+            &params,
+            None,
+        )
+        .unwrap();
+        assert!(checkpoints_is_valid(nonce_info, &params));
+        assert!(last_step_checkpoints_is_valid(nonce_info, &params));
+        validate_block_txs(&block_header, previous_block_header, &[], &params).unwrap();
+        validate_block_rewards(
+            &block_header,
+            previous_block_header,
+            &[],
+            TARGET_TIME,
+            &params,
+        )
+        .unwrap();
+        reward_history = validate_reward_history(
+            &block_header,
+            previous_block_header,
+            &reward_history,
+            &params,
+        )
+        .unwrap();
+    }
+
+    let tip = devnet.tip();
+    assert_ne!(
+        tip.nonce_limiter_info.seed,
+        devnet.genesis().nonce_limiter_info.seed
+    );
+
+    // Tampering with a produced block invalidates it
+    let mut tampered_block = tip.clone();
+    tampered_block.timestamp += 1;
+    let blocks = devnet.blocks();
+    let result = pre_validate_block(
+        &tampered_block,
+        &blocks[blocks.len() - 2],
+        &devnet.block_index(),
+        Some(randomx_vm),
+        &params,
+        None,
+    );
+    assert!(result.is_err());
+}
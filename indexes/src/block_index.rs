@@ -6,10 +6,13 @@ use color_eyre::eyre::{eyre, Result};
 use std::fs::{File, OpenOptions, self};
 use std::io::{self, Read, Seek, SeekFrom, Write};
 use std::path::Path;
-use std::sync::Arc;
 
 use super::{BlockIndex, Uninitialized, Initialized};
-use super::block_index_scraper::{current_block_height_async, request_indexes, BlockIndexJson};
+use super::block_index_scraper::{default_client, request_indexes, BlockIndexJson};
+use super::metrics::{BLOCK_INDEX_HEIGHT, BLOCK_INDEX_ITEMS_REQUESTED, BLOCK_INDEX_TARGET_HEIGHT};
+use arweave_rs_client::ArweaveClient;
+use metrics::{counter, gauge};
+use tracing::{info, info_span, warn, Instrument};
 
 const HASH_INDEX_ITEM_SIZE: u64 = 48 + 16 + 32;
 const FILE_PATH: &str = "data/index.dat";
@@ -19,7 +22,7 @@ const FILE_PATH: &str = "data/index.dat";
 impl BlockIndex {
     pub fn new() -> Self {
         BlockIndex {
-            indexes: Arc::new([]),
+            indexes: Vec::new(),
             state: Uninitialized,
         }
     }
@@ -36,78 +39,66 @@ impl Default for BlockIndex<Uninitialized> {
 }
 
 impl BlockIndex<Uninitialized> {
-    pub async fn init(mut self) -> Result<BlockIndex<Initialized>> {
-        // Get the current block height from the network
-        let current_block_height: u64 = current_block_height_async().await;
+    /// Initializes the index from disk, requesting the blocks missing from it
+    /// from the [`default_client()`] peers.
+    pub async fn init(self) -> Result<BlockIndex<Initialized>> {
+        self.init_with_client(&default_client()).await
+    }
+
+    /// Initializes the index from disk, requesting the blocks missing from it
+    /// with the `client`.
+    pub async fn init_with_client(self, client: &ArweaveClient) -> Result<BlockIndex<Initialized>> {
+        self.init_at(FILE_PATH, client).await
+    }
 
+    /// Initializes the index from the file at `path` instead of
+    /// `data/index.dat`, requesting the blocks missing from it with the
+    /// `client`.
+    pub async fn init_at(
+        mut self,
+        path: impl AsRef<Path>,
+        client: &ArweaveClient,
+    ) -> Result<BlockIndex<Initialized>> {
          // Ensure the path exists
-         let path = Path::new(FILE_PATH);
+         let path = path.as_ref();
 
          if let Some(dir) = path.parent() {
              fs::create_dir_all(dir)?;
          }
 
         // Try to load the hash index from disk
-        match load_index_from_file() {
-            Ok(indexes) => self.indexes = indexes.into(),
-            Err(err) => println!("Error encountered\n {:?}", err),
-        }
-
-        // Get the most recent blockheight from the index
-        let latest_height = self.indexes.len() as u64;
-
-        // EARLY OUT: if the index is already current
-        if latest_height >= current_block_height - 20 {
-            // Return the "Initialized" state of the BlockIndex type
-            return Ok(BlockIndex {
-                indexes: self.indexes,
-                state: Initialized,
-            });
-        }
-
-        // Otherwise, request updates to the hash index in batches of 720,
-        // starting from the last known blockheight to current_block_height - 20
-        // (preferring confirmed blocks to account for forks & reorgs)
-        let new_index_count = (current_block_height - 20) - latest_height;
-        let num_batches = new_index_count / 720;
-        let remainder = new_index_count % 720; // indexes remaining after full batches
-
-        // Build a vec of tuples containing starting block heights and the
-        // number of indexes to load
-        let mut start_block_heights: Vec<(u64, u64)> = Vec::new();
-        for i in 0..num_batches {
-            let height = latest_height + 1 + i * 720;
-            start_block_heights.push((height, 720 - 1)); // -1 to avoid duplicate hash entries
-        }
-
-        // Handle the final batch with less than 720 indexes if necessary
-        if remainder > 0 {
-            let final_height = latest_height + 1 + num_batches * 720;
-            start_block_heights.push((final_height, remainder));
+        match load_index_from_file(path) {
+            Ok(indexes) => self.indexes = indexes,
+            Err(err) => warn!(path = %path.display(), error = %err, "failed to load the block index"),
         }
 
-        // Make concurrent requests to retrieve the batches of indexes. Utilize
-        // exponential backoff when getting 429 (Too Many Requests) responses.
-        let index_jsons =
-            request_indexes("http://188.166.200.45:1984", &start_block_heights).await?;
-
-        // Once the batches have completed, write them  to the block_index
-        // transforming the JSONS to bytes so they take up less space on disk
-        // and in memory.
-        let index_items = index_jsons
-            .iter()
-            .flatten()
-            .map(BlockIndexItem::from)
-            .collect::<Result<Vec<BlockIndexItem>>>()
-            .unwrap();
+        // Get the current block height from the network, working offline with
+        // the index on disk if the network can't be reached
+        let current_block_height: u64 = match client.current_block().await {
+            Ok(header) => header.height,
+            Err(err) if !self.indexes.is_empty() => {
+                warn!(
+                    num_indexes = self.indexes.len(),
+                    error = %err,
+                    "using the block index on disk, failed to get the current block"
+                );
+                gauge!(BLOCK_INDEX_HEIGHT, self.indexes.len() as f64);
+                return Ok(BlockIndex {
+                    indexes: self.indexes,
+                    state: Initialized,
+                });
+            }
+            Err(err) => return Err(err),
+        };
 
-        // Write the updates to the index and to disk
-        append_items_to_file(&index_items)?;
+        // Request the blocks missing from the index and write them to disk
+        let index_items =
+            request_items_after(self.indexes.len() as u64, current_block_height, client).await?;
+        append_items_to_file(path, &index_items)?;
 
         // Append the updates to the existing in memory items
-        let mut vec = self.indexes.to_vec();
-        vec.extend(index_items);
-        self.indexes = vec.into();
+        self.indexes.extend(index_items);
+        gauge!(BLOCK_INDEX_HEIGHT, self.indexes.len() as f64);
 
         // Return the "Initialized" state of the BlockIndex type
         Ok(BlockIndex {
@@ -117,6 +108,18 @@ impl BlockIndex<Uninitialized> {
     }
 }
 
+impl BlockIndex<Uninitialized> {
+    /// Initializes the index from a list of `items` ordered by block height
+    /// instead of loading it from disk and the network. Used for local networks
+    /// whose blocks aren't known to the Arweave peers.
+    pub fn init_from_items(self, items: Vec<BlockIndexItem>) -> BlockIndex<Initialized> {
+        BlockIndex {
+            indexes: items,
+            state: Initialized,
+        }
+    }
+}
+
 //==============================================================================
 // Initialized State
 //------------------------------------------------------------------------------
@@ -130,13 +133,77 @@ impl BlockIndex<Initialized> {
         self.indexes.get(index)
     }
 
+    /// The most recently indexed block.
+    pub fn latest_item(&self) -> Option<&BlockIndexItem> {
+        self.indexes.last()
+    }
+
+    /// Returns the index of the item of the block with `block_hash`, searching
+    /// from the most recent block as that's where lookups during chain
+    /// following happen.
+    pub fn position(&self, block_hash: &H384) -> Option<usize> {
+        self.indexes
+            .iter()
+            .rposition(|item| item.block_hash == *block_hash)
+    }
+
+    /// Appends the `item` of the block following the latest indexed block.
+    /// Only the in memory index is updated, the items on disk are written by
+    /// [`BlockIndex::init()`] which stays 20 blocks behind the tip so it never
+    /// needs to undo a reorg.
+    pub fn push_item(&mut self, item: BlockIndexItem) -> Result<()> {
+        if let Some(latest) = self.indexes.last() {
+            if item.weave_size < latest.weave_size {
+                return Err(eyre!(
+                    "weave_size {} is below the latest indexed weave_size {}",
+                    item.weave_size,
+                    latest.weave_size
+                ));
+            }
+        }
+        self.indexes.push(item);
+        gauge!(BLOCK_INDEX_HEIGHT, self.indexes.len() as f64);
+        Ok(())
+    }
+
+    /// Removes the items after the first `num_indexes`, used to roll the index
+    /// back to the fork point of a reorg.
+    pub fn truncate(&mut self, num_indexes: u64) {
+        self.indexes.truncate(num_indexes as usize);
+        gauge!(BLOCK_INDEX_HEIGHT, self.indexes.len() as f64);
+    }
+
+    /// Requests the items of the blocks confirmed since the index was
+    /// initialized, staying 20 blocks behind the current block of the `client`
+    /// peers like [`BlockIndex::init()`]. Pass the items to
+    /// [`BlockIndex::append_items()`] to add them to the index.
+    pub async fn request_new_items(
+        num_indexes: u64,
+        client: &ArweaveClient,
+    ) -> Result<Vec<BlockIndexItem>> {
+        let current_block_height = client.current_block().await?.height;
+        request_items_after(num_indexes, current_block_height, client).await
+    }
+
+    /// Appends the `items` requested by [`BlockIndex::request_new_items()`] to
+    /// the index and to the index file at `path`.
+    pub fn append_items(&mut self, path: impl AsRef<Path>, items: Vec<BlockIndexItem>) -> Result<()> {
+        append_items_to_file(path.as_ref(), &items)?;
+        self.indexes.extend(items);
+        gauge!(BLOCK_INDEX_HEIGHT, self.indexes.len() as f64);
+        Ok(())
+    }
+
     pub fn get_block_bounds(&self, recall_byte: u128) -> BlockBounds {
         let mut block_bounds: BlockBounds = Default::default();
 
         let result = self.get_block_index_item(recall_byte);
         if let Ok((index, found_item)) = result {
-            let previous_item = self.get_item(index - 1).unwrap();
-            block_bounds.block_start_offset = previous_item.weave_size;
+            // The genesis block starts at the beginning of the weave
+            block_bounds.block_start_offset = match index {
+                0 => 0,
+                _ => self.get_item(index - 1).unwrap().weave_size,
+            };
             block_bounds.block_end_offset = found_item.weave_size;
             block_bounds.tx_root = found_item.tx_root;
             block_bounds.height = (index + 1) as u128;
@@ -206,6 +273,33 @@ impl BlockIndexItem {
     }
 }
 
+/// The JSON the peers serve from their `/block_index/<start_height>/<end_height>`
+/// endpoint, the inverse of [`BlockIndexItem::from()`].
+impl From<&BlockIndexItem> for BlockIndexJson {
+    fn from(item: &BlockIndexItem) -> Self {
+        // Blocks without transactions have an empty tx_root
+        let tx_root = match item.tx_root.is_zero() {
+            true => String::new(),
+            false => base64_url::encode(&item.tx_root),
+        };
+        BlockIndexJson {
+            tx_root,
+            weave_size: item.weave_size.to_string(),
+            hash: base64_url::encode(&item.block_hash),
+        }
+    }
+}
+
+impl From<&ArweaveBlockHeader> for BlockIndexItem {
+    fn from(block_header: &ArweaveBlockHeader) -> Self {
+        BlockIndexItem {
+            block_hash: block_header.indep_hash,
+            weave_size: block_header.weave_size as u128,
+            tx_root: block_header.tx_root.unwrap_or_default(),
+        }
+    }
+}
+
 impl BlockIndexItem {
     // Serialize the BlockIndexItem to bytes
     fn to_bytes(&self) -> [u8; 48 + 16 + 32] {
@@ -234,6 +328,70 @@ impl BlockIndexItem {
     }
 }
 
+/// Requests the items of the blocks after the first `num_indexes` items up to
+/// 20 blocks behind `current_block_height`, preferring confirmed blocks to
+/// account for forks & reorgs. Returns no items if the index is already
+/// current.
+async fn request_items_after(
+    num_indexes: u64,
+    current_block_height: u64,
+    client: &ArweaveClient,
+) -> Result<Vec<BlockIndexItem>> {
+    // EARLY OUT: if the index is already current
+    let latest_height = num_indexes;
+    gauge!(
+        BLOCK_INDEX_TARGET_HEIGHT,
+        current_block_height.saturating_sub(20) as f64
+    );
+    if latest_height >= current_block_height.saturating_sub(20) {
+        return Ok(Vec::new());
+    }
+
+    // Otherwise, request updates to the hash index in batches of 720,
+    // starting from the last known blockheight to current_block_height - 20
+    let new_index_count = (current_block_height - 20) - latest_height;
+    let num_batches = new_index_count / 720;
+    let remainder = new_index_count % 720; // indexes remaining after full batches
+
+    // Build a vec of tuples containing starting block heights and the
+    // number of indexes to load
+    let mut start_block_heights: Vec<(u64, u64)> = Vec::new();
+    for i in 0..num_batches {
+        let height = latest_height + 1 + i * 720;
+        start_block_heights.push((height, 720 - 1)); // -1 to avoid duplicate hash entries
+    }
+
+    // Handle the final batch with less than 720 indexes if necessary
+    if remainder > 0 {
+        let final_height = latest_height + 1 + num_batches * 720;
+        start_block_heights.push((final_height, remainder));
+    }
+
+    // Make concurrent requests to retrieve the batches of indexes, the
+    // client backs off when getting 429 (Too Many Requests) responses.
+    let target_height = current_block_height - 20;
+    let span = info_span!("block_index_sync", from = latest_height + 1, to = target_height);
+    info!(
+        parent: &span,
+        num_items = new_index_count,
+        num_batches = start_block_heights.len(),
+        "requesting block index items"
+    );
+    let index_jsons = request_indexes(client, &start_block_heights)
+        .instrument(span.clone())
+        .await?;
+    counter!(BLOCK_INDEX_ITEMS_REQUESTED, new_index_count);
+    info!(parent: &span, num_items = new_index_count, "requested block index items");
+
+    // Once the batches have completed, transform the JSONS to bytes so they
+    // take up less space on disk and in memory.
+    index_jsons
+        .iter()
+        .flatten()
+        .map(BlockIndexItem::from)
+        .collect()
+}
+
 #[allow(dead_code)]
 fn save_index(block_index_items: &[BlockIndexItem]) -> io::Result<()> {
     let mut file = File::create(FILE_PATH)?;
@@ -260,8 +418,8 @@ fn append_item(item: BlockIndexItem) -> io::Result<()> {
     Ok(())
 }
 
-fn append_items_to_file(items: &Vec<BlockIndexItem>) -> io::Result<()> {
-    let mut file = OpenOptions::new().append(true).open(FILE_PATH)?;
+fn append_items_to_file(path: &Path, items: &Vec<BlockIndexItem>) -> io::Result<()> {
+    let mut file = OpenOptions::new().append(true).open(path)?;
 
     for item in items {
         file.write_all(&item.to_bytes())?;
@@ -278,12 +436,12 @@ fn update_file_item_at(block_height: u64, item: BlockIndexItem) -> io::Result<()
     Ok(())
 }
 
-fn load_index_from_file() -> io::Result<Vec<BlockIndexItem>> {
+fn load_index_from_file(path: &Path) -> io::Result<Vec<BlockIndexItem>> {
     let mut file = OpenOptions::new()
         .read(true)
         .write(true)
         .create(true)
-        .open(FILE_PATH)?;
+        .open(path)?;
 
     // Determine the file size
     let file_size = file.seek(SeekFrom::End(0))?;
@@ -0,0 +1,298 @@
+//! Archives block headers on disk so they only have to be requested from the
+//! peers once. Headers are stored as gzip compressed JSON files named after
+//! their `indep_hash`, with a fixed width file mapping each height from the
+//! lowest stored height to the hash of the header stored at it.
+use arweave_rs_types::{block_hash::compute_indep_hash, consensus::ConsensusParams, *};
+use color_eyre::eyre::{eyre, Result};
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use futures::{stream, StreamExt, TryStreamExt};
+use arweave_rs_client::ArweaveClient;
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+use tracing::debug;
+
+const HEIGHT_ITEM_SIZE: u64 = 48;
+/// The lowest mapped height, as little endian bytes, followed by the hash of
+/// each height from it.
+const HEIGHTS_FILE: &str = "height_index.dat";
+const HEIGHTS_HEADER_SIZE: u64 = 8;
+const BLOCKS_DIR: &str = "blocks";
+
+/// A local archive of mainnet block headers readable by height or by
+/// `indep_hash`. Storing a header at a height already in the store remaps the
+/// height to it, the replaced header stays readable by its hash.
+pub struct HeaderStore {
+    dir: PathBuf,
+    heights: File,
+    /// The lowest height in the heights file, `None` until a header is stored.
+    first_height: Option<u64>,
+    params: ConsensusParams,
+}
+
+impl HeaderStore {
+    /// Opens the store in `dir`, creating it if it doesn't exist.
+    pub fn open(dir: impl AsRef<Path>) -> Result<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(dir.join(BLOCKS_DIR))?;
+        let heights = open_heights(&dir.join(HEIGHTS_FILE))?;
+        let first_height = read_first_height(&heights)?;
+        Ok(HeaderStore {
+            dir,
+            heights,
+            first_height,
+            params: ConsensusParams::mainnet(),
+        })
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Stores the `json` of a block header, returning the parsed header. The
+    /// header is rejected if its `indep_hash` doesn't match its contents.
+    pub fn put_json(&mut self, json: &serde_json::Value) -> Result<ArweaveBlockHeader> {
+        let header = self.parse_header(json)?;
+        self.put(json, &header)?;
+        Ok(header)
+    }
+
+    /// Parses the `json` of a block header, checking the `indep_hash` is the
+    /// hash of the header.
+    fn parse_header(&self, json: &serde_json::Value) -> Result<ArweaveBlockHeader> {
+        let header: ArweaveBlockHeader = serde_json::from_value(json.clone())?;
+        let indep_hash = compute_indep_hash(&header, &self.params);
+        if indep_hash != header.indep_hash {
+            return Err(eyre!(
+                "header {} has indep_hash {}, its contents hash to {}",
+                header.height,
+                base64_url::encode(&header.indep_hash),
+                base64_url::encode(&indep_hash)
+            ));
+        }
+        Ok(header)
+    }
+
+    fn put(&mut self, json: &serde_json::Value, header: &ArweaveBlockHeader) -> Result<()> {
+        // Write to a temporary file first so a header file is never partially
+        // written
+        let path = self.block_path(&header.indep_hash);
+        let tmp_path = path.with_extension("tmp");
+        let mut encoder = GzEncoder::new(File::create(&tmp_path)?, Compression::default());
+        serde_json::to_writer(&mut encoder, json)?;
+        encoder.finish()?.sync_all()?;
+        fs::rename(&tmp_path, &path)?;
+
+        let first_height = match self.first_height {
+            Some(first_height) if first_height <= header.height => first_height,
+            Some(_) => self.rebase_heights(header.height)?,
+            None => {
+                self.heights.set_len(0)?;
+                self.heights.seek(SeekFrom::Start(0))?;
+                self.heights.write_all(&header.height.to_le_bytes())?;
+                self.first_height = Some(header.height);
+                header.height
+            }
+        };
+        let offset = HEIGHTS_HEADER_SIZE + (header.height - first_height) * HEIGHT_ITEM_SIZE;
+        self.heights.seek(SeekFrom::Start(offset))?;
+        self.heights.write_all(header.indep_hash.as_bytes())?;
+        Ok(())
+    }
+
+    /// Rewrites the heights file to start at `first_height`, below the current
+    /// first height. The new file replaces the old one atomically.
+    fn rebase_heights(&mut self, first_height: u64) -> Result<u64> {
+        let previous_first_height = self.first_height.unwrap_or(first_height);
+        let mut hashes = Vec::new();
+        self.heights.seek(SeekFrom::Start(HEIGHTS_HEADER_SIZE))?;
+        self.heights.read_to_end(&mut hashes)?;
+
+        let path = self.dir.join(HEIGHTS_FILE);
+        let tmp_path = path.with_extension("tmp");
+        let mut file = File::create(&tmp_path)?;
+        file.write_all(&first_height.to_le_bytes())?;
+        let num_new_heights = previous_first_height - first_height;
+        file.set_len(HEIGHTS_HEADER_SIZE + num_new_heights * HEIGHT_ITEM_SIZE)?;
+        file.seek(SeekFrom::End(0))?;
+        file.write_all(&hashes)?;
+        file.sync_all()?;
+        fs::rename(&tmp_path, &path)?;
+
+        self.heights = open_heights(&path)?;
+        self.first_height = Some(first_height);
+        Ok(first_height)
+    }
+
+    /// Copies the header JSON files in `dir`, such as the `data/blocks` test
+    /// fixtures, into the store. Returns the number of headers imported.
+    pub fn import_dir(&mut self, dir: impl AsRef<Path>) -> Result<usize> {
+        let mut num_imported = 0;
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.extension().is_some_and(|extension| extension == "json") {
+                let json: serde_json::Value = serde_json::from_reader(File::open(&path)?)
+                    .map_err(|err| eyre!("{}: {err}", path.display()))?;
+                self.put_json(&json)?;
+                num_imported += 1;
+            }
+        }
+        Ok(num_imported)
+    }
+
+    /// The `indep_hash` of the header stored at `height`.
+    pub fn hash_at(&self, height: u64) -> Result<Option<H384>> {
+        let Some(first_height) = self.first_height.filter(|first| *first <= height) else {
+            return Ok(None);
+        };
+        let offset = HEIGHTS_HEADER_SIZE + (height - first_height) * HEIGHT_ITEM_SIZE;
+        if offset + HEIGHT_ITEM_SIZE > self.heights.metadata()?.len() {
+            return Ok(None);
+        }
+        let mut file = &self.heights;
+        let mut hash = H384::zero();
+        file.seek(SeekFrom::Start(offset))?;
+        file.read_exact(&mut hash.0)?;
+
+        // Heights skipped when storing are left zero filled
+        Ok((hash != H384::zero()).then_some(hash))
+    }
+
+    pub fn contains_height(&self, height: u64) -> Result<bool> {
+        Ok(self.hash_at(height)?.is_some())
+    }
+
+    /// The heights in `heights` without a stored header.
+    pub fn missing_heights(&self, heights: RangeInclusive<u64>) -> Result<Vec<u64>> {
+        let mut missing = Vec::new();
+        for height in heights {
+            if !self.contains_height(height)? {
+                missing.push(height);
+            }
+        }
+        Ok(missing)
+    }
+
+    pub fn get_json_by_hash(&self, indep_hash: &H384) -> Result<Option<serde_json::Value>> {
+        let path = self.block_path(indep_hash);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let mut buf = String::new();
+        GzDecoder::new(File::open(&path)?).read_to_string(&mut buf)?;
+        Ok(Some(serde_json::from_str(&buf)?))
+    }
+
+    /// Reads the header with `indep_hash`, checking the stored header is the
+    /// one its file is named after.
+    pub fn get_by_hash(&self, indep_hash: &H384) -> Result<Option<ArweaveBlockHeader>> {
+        let Some(json) = self.get_json_by_hash(indep_hash)? else {
+            return Ok(None);
+        };
+        let header: ArweaveBlockHeader = serde_json::from_value(json)?;
+        if header.indep_hash != *indep_hash {
+            return Err(eyre!(
+                "stored header {} has indep_hash {}",
+                base64_url::encode(indep_hash),
+                base64_url::encode(&header.indep_hash)
+            ));
+        }
+        Ok(Some(header))
+    }
+
+    pub fn get_json_by_height(&self, height: u64) -> Result<Option<serde_json::Value>> {
+        match self.hash_at(height)? {
+            Some(hash) => self.get_json_by_hash(&hash),
+            None => Ok(None),
+        }
+    }
+
+    pub fn get_by_height(&self, height: u64) -> Result<Option<ArweaveBlockHeader>> {
+        match self.hash_at(height)? {
+            Some(hash) => self.get_by_hash(&hash),
+            None => Ok(None),
+        }
+    }
+
+    /// Reads the headers of the `heights` in order, all of them must be stored.
+    pub fn read_range(&self, heights: RangeInclusive<u64>) -> Result<Vec<ArweaveBlockHeader>> {
+        heights
+            .map(|height| {
+                self.get_by_height(height)?
+                    .ok_or_else(|| eyre!("header {height} is not in the store"))
+            })
+            .collect()
+    }
+
+    /// Reads the JSON of the headers of the `heights` in order, all of them
+    /// must be stored.
+    pub fn read_json_range(&self, heights: RangeInclusive<u64>) -> Result<Vec<serde_json::Value>> {
+        heights
+            .map(|height| {
+                self.get_json_by_height(height)?
+                    .ok_or_else(|| eyre!("header {height} is not in the store"))
+            })
+            .collect()
+    }
+
+    /// Requests the headers of the `heights` missing from the store with the
+    /// `client`. Returns the number of headers requested, zero if the store
+    /// already had all of them.
+    pub async fn ensure_present(
+        &mut self,
+        client: &ArweaveClient,
+        heights: RangeInclusive<u64>,
+    ) -> Result<usize> {
+        let missing = self.missing_heights(heights.clone())?;
+        if missing.is_empty() {
+            return Ok(0);
+        }
+        debug!(
+            from = heights.start(),
+            to = heights.end(),
+            num_missing = missing.len(),
+            "requesting the headers missing from the store"
+        );
+
+        let jsons: Vec<serde_json::Value> = stream::iter(&missing)
+            .map(|height| client.block_json_by_height(*height))
+            .buffered(client.config().concurrency)
+            .try_collect()
+            .await?;
+        for (height, json) in missing.iter().zip(&jsons) {
+            let header = self.parse_header(json)?;
+            if header.height != *height {
+                return Err(eyre!("requested header {height}, got {}", header.height));
+            }
+            self.put(json, &header)?;
+        }
+        Ok(missing.len())
+    }
+
+    fn block_path(&self, indep_hash: &H384) -> PathBuf {
+        self.dir
+            .join(BLOCKS_DIR)
+            .join(format!("{}.json.gz", base64_url::encode(indep_hash)))
+    }
+}
+
+fn open_heights(path: &Path) -> Result<File> {
+    Ok(OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(path)?)
+}
+
+/// The lowest height in the heights `file`, `None` for an empty file.
+fn read_first_height(mut file: &File) -> Result<Option<u64>> {
+    if file.metadata()?.len() < HEIGHTS_HEADER_SIZE {
+        return Ok(None);
+    }
+    let mut bytes = [0u8; HEIGHTS_HEADER_SIZE as usize];
+    file.seek(SeekFrom::Start(0))?;
+    file.read_exact(&mut bytes)?;
+    Ok(Some(u64::from_le_bytes(bytes)))
+}
@@ -0,0 +1,103 @@
+//! Keeps the most recent block headers of the chain being followed in memory,
+//! so new blocks can be validated against their parents and reorgs can be
+//! rolled back to their fork point without requesting the headers again.
+use arweave_rs_types::*;
+use color_eyre::eyre::{eyre, Result};
+use std::collections::{vec_deque, VecDeque};
+
+/// A rolling window of the latest `capacity` headers of a chain, ordered by
+/// height. Pushing a header to a full window evicts the oldest header, which
+/// limits the depth of the reorgs that can be followed.
+#[derive(Debug, Clone)]
+pub struct HeaderWindow {
+    headers: VecDeque<ArweaveBlockHeader>,
+    capacity: usize,
+}
+
+impl HeaderWindow {
+    pub fn new(capacity: usize) -> Self {
+        HeaderWindow {
+            headers: VecDeque::with_capacity(capacity),
+            capacity: capacity.max(1),
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn len(&self) -> usize {
+        self.headers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.headers.is_empty()
+    }
+
+    /// The most recent header of the chain.
+    pub fn tip(&self) -> Option<&ArweaveBlockHeader> {
+        self.headers.back()
+    }
+
+    /// The oldest header still in the window.
+    pub fn oldest(&self) -> Option<&ArweaveBlockHeader> {
+        self.headers.front()
+    }
+
+    /// Returns the header with the `indep_hash` if it's in the window.
+    pub fn get(&self, indep_hash: &H384) -> Option<&ArweaveBlockHeader> {
+        self.headers
+            .iter()
+            .rev()
+            .find(|header| header.indep_hash == *indep_hash)
+    }
+
+    pub fn contains(&self, indep_hash: &H384) -> bool {
+        self.get(indep_hash).is_some()
+    }
+
+    /// The headers from oldest to most recent.
+    pub fn iter(&self) -> vec_deque::Iter<'_, ArweaveBlockHeader> {
+        self.headers.iter()
+    }
+
+    /// Appends the `header` to the tip, it must be the child of the current
+    /// tip. Returns the evicted oldest header if the window was full.
+    pub fn push(&mut self, header: ArweaveBlockHeader) -> Result<Option<ArweaveBlockHeader>> {
+        if let Some(tip) = self.tip() {
+            if header.previous_block != tip.indep_hash || header.height != tip.height + 1 {
+                return Err(eyre!(
+                    "block {} {} does not extend the tip {} {}",
+                    header.height,
+                    base64_url::encode(&header.indep_hash),
+                    tip.height,
+                    base64_url::encode(&tip.indep_hash)
+                ));
+            }
+        }
+        let evicted = match self.headers.len() == self.capacity {
+            true => self.headers.pop_front(),
+            false => None,
+        };
+        self.headers.push_back(header);
+        Ok(evicted)
+    }
+
+    /// Removes the headers after the fork point `indep_hash`, making it the
+    /// new tip. Returns the orphaned headers from oldest to most recent, or an
+    /// error if the fork point is not in the window.
+    pub fn rollback_to(&mut self, indep_hash: &H384) -> Result<Vec<ArweaveBlockHeader>> {
+        let position = self
+            .headers
+            .iter()
+            .rposition(|header| header.indep_hash == *indep_hash)
+            .ok_or_else(|| {
+                eyre!(
+                    "fork point {} is not in the last {} headers",
+                    base64_url::encode(indep_hash),
+                    self.headers.len()
+                )
+            })?;
+        Ok(self.headers.split_off(position + 1).into())
+    }
+}
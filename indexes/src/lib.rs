@@ -1,10 +1,12 @@
 //! Implementation of indexes for caching network state (and the modules that 
 //! initialize them from the Arweave peers).
-use std::sync::Arc;
 use self::block_index::BlockIndexItem;
 
 pub mod block_index;
 pub mod block_index_scraper;
+pub mod header_store;
+pub mod header_window;
+pub mod metrics;
 
 
 /// This struct represents the `Uninitialized` type state.
@@ -21,5 +23,5 @@ pub struct Initialized;
 pub struct BlockIndex<State = Uninitialized> {
     #[allow(dead_code)]
     state: State,
-    indexes: Arc<[BlockIndexItem]>,
+    indexes: Vec<BlockIndexItem>,
 }
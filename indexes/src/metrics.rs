@@ -0,0 +1,32 @@
+//! The metrics recorded while syncing the block index, see
+//! `arweave_rs_validator::metrics`.
+use metrics::{describe_counter, describe_gauge, Unit};
+
+/// The height of the latest block in the block index.
+pub const BLOCK_INDEX_HEIGHT: &str = "arweave_block_index_height";
+/// The height the block index is being synced to, 20 blocks behind the
+/// current block of the peers.
+pub const BLOCK_INDEX_TARGET_HEIGHT: &str = "arweave_block_index_target_height";
+/// Block index items requested from the peers.
+pub const BLOCK_INDEX_ITEMS_REQUESTED: &str = "arweave_block_index_items_requested_total";
+
+/// Describes the metrics of the block index and of the client it uses to a
+/// recorder.
+pub fn describe_metrics() {
+    describe_gauge!(
+        BLOCK_INDEX_HEIGHT,
+        Unit::Count,
+        "Height of the latest block in the block index"
+    );
+    describe_gauge!(
+        BLOCK_INDEX_TARGET_HEIGHT,
+        Unit::Count,
+        "Height the block index is being synced to"
+    );
+    describe_counter!(
+        BLOCK_INDEX_ITEMS_REQUESTED,
+        Unit::Count,
+        "Block index items requested from the peers"
+    );
+    arweave_rs_client::metrics::describe_metrics();
+}
@@ -0,0 +1,11 @@
+use arweave_rs_test_support::fixtures::mainnet_block_index;
+
+#[test]
+#[ignore = "needs the mainnet block index"]
+fn mainnet_block_index_initializes() {
+    let block_index = mainnet_block_index();
+    assert!(block_index.num_indexes() > 1_000_000);
+    let latest = block_index.latest_item().unwrap();
+    let position = block_index.position(&latest.block_hash);
+    assert_eq!(position, Some(block_index.num_indexes() as usize - 1));
+}
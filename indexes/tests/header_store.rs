@@ -0,0 +1,92 @@
+use arweave_rs_client::ArweaveClient;
+use arweave_rs_indexes::header_store::HeaderStore;
+use arweave_rs_test_support::{fixtures::*, MockPeer};
+use arweave_rs_types::H384;
+
+#[test]
+fn header_store() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut store = HeaderStore::open(dir.path()).unwrap();
+
+    // Only the missing heights are requested from the peer
+    let peer = MockPeer::start().unwrap();
+    peer.insert_headers([1315908, 1315909, 1315910].map(block_header_json))
+        .unwrap();
+    let client = ArweaveClient::new([peer.url()]).unwrap();
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let num_requested = runtime.block_on(store.ensure_present(&client, 1315909..=1315910));
+    assert_eq!(num_requested.unwrap(), 2);
+    let num_requested = runtime.block_on(store.ensure_present(&client, 1315908..=1315910));
+    assert_eq!(num_requested.unwrap(), 1);
+    assert_eq!(peer.num_requests("/block/height/"), 3);
+    assert_eq!(
+        store.missing_heights(1315907..=1315911).unwrap(),
+        vec![1315907, 1315911]
+    );
+
+    // The heights are mapped from the lowest stored height, storing a lower
+    // one moves the start of the mapping
+    let heights_size = || {
+        std::fs::metadata(dir.path().join("height_index.dat"))
+            .unwrap()
+            .len()
+    };
+    assert_eq!(heights_size(), 8 + 3 * 48);
+
+    // Stored headers are read offline after reopening the store
+    let offline_client = ArweaveClient::new(["http://127.0.0.1:1"]).unwrap();
+    let mut store = HeaderStore::open(dir.path()).unwrap();
+    let expected = block_header(1315909);
+    let headers = store.read_range(1315908..=1315910).unwrap();
+    let num_requested =
+        runtime.block_on(store.ensure_present(&offline_client, 1315908..=1315910));
+    assert_eq!(num_requested.unwrap(), 0);
+    assert_eq!(headers[1].indep_hash, expected.indep_hash);
+    assert_eq!(headers[1].previous_block, headers[0].indep_hash);
+    assert_eq!(headers[2].previous_block, headers[1].indep_hash);
+    let stored = store.get_by_hash(&expected.indep_hash).unwrap().unwrap();
+    assert_eq!(stored.height, 1315909);
+    assert_eq!(
+        store.get_json_by_height(1315909).unwrap().unwrap(),
+        block_header_json(1315909)
+    );
+    assert!(store.read_range(1315908..=1315911).is_err());
+    assert!(store.get_by_height(1315911).unwrap().is_none());
+
+    // Headers are compressed and checked against the hash they're stored as
+    let block_path = |hash: &H384| {
+        dir.path()
+            .join("blocks")
+            .join(format!("{}.json.gz", base64_url::encode(hash)))
+    };
+    let stored_size = std::fs::metadata(block_path(&expected.indep_hash))
+        .unwrap()
+        .len();
+    let json_size = std::fs::metadata(data_dir().join("blocks/1315909.json"))
+        .unwrap()
+        .len();
+    assert!(stored_size < json_size);
+    std::fs::copy(
+        block_path(&headers[0].indep_hash),
+        block_path(&expected.indep_hash),
+    )
+    .unwrap();
+    assert!(store.get_by_hash(&expected.indep_hash).is_err());
+    assert!(store.get_by_hash(&H384::zero()).unwrap().is_none());
+
+    // Headers whose indep_hash doesn't match their contents aren't stored
+    let mut tampered = block_header_json(cases::RESET2);
+    tampered["height"] = 1315911.into();
+    assert!(store.put_json(&tampered).is_err());
+    assert_eq!(
+        store.missing_heights(1315911..=1315911).unwrap(),
+        vec![1315911]
+    );
+    peer.insert_headers([tampered]).unwrap();
+    let client = ArweaveClient::new([peer.url()]).unwrap();
+    assert!(runtime
+        .block_on(store.ensure_present(&client, 1315911..=1315911))
+        .is_err());
+    assert!(store.get_by_height(1315911).unwrap().is_none());
+    assert_eq!(heights_size(), 8 + 3 * 48);
+}
@@ -0,0 +1,91 @@
+use arweave_rs_indexes::{block_index::BlockIndexItem, header_window::HeaderWindow, BlockIndex};
+use arweave_rs_types::{ArweaveBlockHeader, H384};
+
+/// A header identified by its height, `branch` distinguishes the blocks of a
+/// fork.
+fn header(height: u64, branch: u8, previous_branch: u8) -> ArweaveBlockHeader {
+    ArweaveBlockHeader {
+        height,
+        indep_hash: H384([height as u8 ^ branch; 48]),
+        previous_block: H384([(height as u8).wrapping_sub(1) ^ previous_branch; 48]),
+        weave_size: height * 1000 + branch as u64,
+        ..Default::default()
+    }
+}
+
+fn chain() -> Vec<ArweaveBlockHeader> {
+    (1..=5).map(|height| header(height, 0, 0)).collect()
+}
+
+/// The branch 4' 5' 6' forking from height 4.
+fn fork() -> [ArweaveBlockHeader; 3] {
+    [header(4, 0x80, 0), header(5, 0x80, 0x80), header(6, 0x80, 0x80)]
+}
+
+fn window(chain: &[ArweaveBlockHeader]) -> HeaderWindow {
+    let mut window = HeaderWindow::new(3);
+    for header in chain {
+        window.push(header.clone()).unwrap();
+    }
+    window
+}
+
+#[test]
+fn window_evicts_the_oldest_headers() {
+    let chain = chain();
+    let mut window = HeaderWindow::new(3);
+    let mut evicted = Vec::new();
+    for header in &chain {
+        evicted.extend(window.push(header.clone()).unwrap().map(|header| header.height));
+    }
+    assert_eq!(evicted, vec![1, 2]);
+    assert_eq!(window.len(), 3);
+    assert_eq!(window.oldest().unwrap().height, 3);
+    assert_eq!(window.tip().unwrap().height, 5);
+    assert!(window.contains(&chain[3].indep_hash));
+    assert!(!window.contains(&chain[0].indep_hash));
+
+    // Only children of the tip can be pushed
+    assert!(window.clone().push(header(7, 0, 0)).is_err());
+    assert!(window.clone().push(header(6, 0x80, 0x80)).is_err());
+}
+
+#[test]
+fn window_follows_reorgs() {
+    let chain = chain();
+    let fork = fork();
+    let mut window = window(&chain);
+    let orphaned = window.rollback_to(&fork[0].previous_block).unwrap();
+    assert!(orphaned.iter().map(|header| header.height).eq([4, 5]));
+    assert_eq!(window.tip().unwrap().indep_hash, chain[2].indep_hash);
+    for header in &fork {
+        window.push(header.clone()).unwrap();
+    }
+    assert_eq!(window.tip().unwrap().height, 6);
+
+    // Reorgs deeper than the window can't be followed
+    assert!(window.rollback_to(&chain[0].indep_hash).is_err());
+}
+
+#[test]
+fn block_index_follows_reorgs() {
+    let chain = chain();
+    let fork = fork();
+    let items: Vec<BlockIndexItem> = chain.iter().map(|header| header.into()).collect();
+    let mut block_index = BlockIndex::new().init_from_items(items);
+    let position = block_index.position(&chain[2].indep_hash);
+    assert_eq!(position, Some(2));
+    block_index.truncate(position.unwrap() as u64 + 1);
+    assert_eq!(block_index.num_indexes(), 3);
+    assert!(block_index.position(&chain[3].indep_hash).is_none());
+    for header in &fork {
+        block_index.push_item(header.into()).unwrap();
+    }
+    assert_eq!(
+        block_index.latest_item().unwrap().block_hash,
+        fork[2].indep_hash
+    );
+
+    // The weave can't shrink
+    assert!(block_index.push_item((&chain[0]).into()).is_err());
+}
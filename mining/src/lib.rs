@@ -0,0 +1,180 @@
+//! Searches the recall ranges of a VDF step for mining solutions. For each
+//! partition the mining hash (H0) is derived from the step output, the packed
+//! chunks of both recall ranges are read from a [`ChunkStorage`] and each
+//! nonce is hashed into a one chunk (H1) and, failing that, a two chunk (H2)
+//! solution that is compared against the difficulty.
+use arweave_rs_randomx::RandomXVM;
+use arweave_rs_types::{consensus::*, *};
+use color_eyre::eyre::{eyre, Result};
+use openssl::sha;
+use storage::ChunkStorage;
+
+pub mod storage;
+
+/// The inputs for mining the partitions of a single VDF step.
+#[derive(Debug, Clone)]
+pub struct MiningStep {
+    /// The output of the VDF step.
+    pub vdf_output: H256,
+    /// The global step number of the VDF step.
+    pub global_step_number: u64,
+    /// The VDF seed H0 is computed with.
+    pub seed: H384,
+    /// The weave size the partitions and recall ranges are bounded by, the
+    /// `zone_upper_bound` of the block being mined.
+    pub partition_upper_bound: u64,
+    /// The reward address the chunks are packed for.
+    pub mining_addr: H256,
+    /// The difficulty a solution hash has to exceed.
+    pub diff: U256,
+}
+
+/// A solution found in the recall ranges of a [`MiningStep`], with the proofs
+/// needed to include it in a block header.
+#[derive(Debug, Clone)]
+pub struct SolutionCandidate {
+    pub global_step_number: u64,
+    pub partition_number: u64,
+    pub nonce: u64,
+    /// The mining hash (H0) of the partition.
+    pub mining_hash: [u8; 32],
+    /// The absolute offset of the first recall range chunk.
+    pub recall_byte: u64,
+    /// The absolute offset of the second recall range chunk, if the solution
+    /// was found with both chunks.
+    pub recall_byte2: Option<u64>,
+    /// H1 or H2, the `hash_preimage` of the block header.
+    pub hash_preimage: H256,
+    /// The solution hash, the `hash` of the block header.
+    pub solution_hash: [u8; 32],
+    pub poa: PoaData,
+    /// The proof of the second chunk, empty for one chunk solutions.
+    pub poa2: PoaData,
+}
+
+impl SolutionCandidate {
+    /// `SHA-256` of the first packed chunk, the `chunk_hash` of the block.
+    pub fn chunk_hash(&self) -> H256 {
+        H256(sha::sha256(self.poa.chunk.as_slice()))
+    }
+
+    /// `SHA-256` of the second packed chunk, the `chunk2_hash` of the block.
+    pub fn chunk2_hash(&self) -> Option<H256> {
+        self.recall_byte2
+            .map(|_| H256(sha::sha256(self.poa2.chunk.as_slice())))
+    }
+}
+
+/// Mines every partition below the `partition_upper_bound` of the `step`,
+/// returning the first solution found.
+pub fn mine_step<S: ChunkStorage + ?Sized>(
+    step: &MiningStep,
+    storage: &S,
+    params: &ConsensusParams,
+    randomx_vm: Option<&RandomXVM>,
+) -> Result<Option<SolutionCandidate>> {
+    let num_partitions = step.partition_upper_bound / params.partition_size;
+    for partition_number in 0..num_partitions {
+        if let Some(solution) = mine_partition(step, partition_number, storage, params, randomx_vm)?
+        {
+            return Ok(Some(solution));
+        }
+    }
+    Ok(None)
+}
+
+/// Searches the two recall ranges of `partition_number` for a solution. For
+/// each nonce the first recall range chunk is tried on its own (H1) before it
+/// is combined with the chunk of the second recall range (H2). Nonces whose
+/// chunks aren't stored are skipped.
+pub fn mine_partition<S: ChunkStorage + ?Sized>(
+    step: &MiningStep,
+    partition_number: u64,
+    storage: &S,
+    params: &ConsensusParams,
+    randomx_vm: Option<&RandomXVM>,
+) -> Result<Option<SolutionCandidate>> {
+    let mining_hash = compute_mining_hash(
+        step.vdf_output,
+        partition_number as u32,
+        step.seed,
+        step.mining_addr,
+        randomx_vm,
+    );
+    let (recall_range1_start, recall_range2_start) = get_recall_range(
+        &mining_hash,
+        partition_number,
+        step.partition_upper_bound,
+        params,
+    );
+
+    for nonce in 0..params.max_nonce() as u64 {
+        let recall_byte = recall_range1_start.as_u64() + nonce * DATA_CHUNK_SIZE as u64;
+        let Some(chunk1) = read_chunk(storage, recall_byte, step.partition_upper_bound)? else {
+            continue;
+        };
+        let (h1, h1_preimage) = compute_h1(&mining_hash, nonce, &chunk1);
+        if solution_is_valid(&h1, step.diff) {
+            let poa = read_poa(storage, recall_byte)?;
+            return Ok(Some(SolutionCandidate {
+                global_step_number: step.global_step_number,
+                partition_number,
+                nonce,
+                mining_hash,
+                recall_byte,
+                recall_byte2: None,
+                hash_preimage: h1_preimage,
+                solution_hash: h1,
+                poa,
+                poa2: PoaData::default(),
+            }));
+        }
+
+        let recall_byte2 = recall_range2_start.as_u64() + nonce * DATA_CHUNK_SIZE as u64;
+        let Some(chunk2) = read_chunk(storage, recall_byte2, step.partition_upper_bound)? else {
+            continue;
+        };
+        let (h2, h2_preimage) = compute_h2(&h1, &chunk2, &mining_hash);
+        if solution_is_valid(&h2, step.diff) {
+            let poa = read_poa(storage, recall_byte)?;
+            let poa2 = read_poa(storage, recall_byte2)?;
+            return Ok(Some(SolutionCandidate {
+                global_step_number: step.global_step_number,
+                partition_number,
+                nonce,
+                mining_hash,
+                recall_byte,
+                recall_byte2: Some(recall_byte2),
+                hash_preimage: h2_preimage,
+                solution_hash: h2,
+                poa,
+                poa2,
+            }));
+        }
+    }
+    Ok(None)
+}
+
+/// A solution is valid if its hash, as a big-endian integer, exceeds `diff`.
+pub fn solution_is_valid(solution_hash: &[u8; 32], diff: U256) -> bool {
+    U256::from_big_endian(solution_hash) > diff
+}
+
+/// Reads the packed chunk at `recall_byte` unless it lies beyond the
+/// `partition_upper_bound`, chunks past it can't be part of a solution.
+fn read_chunk<S: ChunkStorage + ?Sized>(
+    storage: &S,
+    recall_byte: u64,
+    partition_upper_bound: u64,
+) -> Result<Option<Vec<u8>>> {
+    if recall_byte >= partition_upper_bound {
+        return Ok(None);
+    }
+    storage.read_packed_chunk(recall_byte)
+}
+
+fn read_poa<S: ChunkStorage + ?Sized>(storage: &S, recall_byte: u64) -> Result<PoaData> {
+    storage
+        .read_poa(recall_byte)?
+        .ok_or_else(|| eyre!("the proof of the chunk at {recall_byte} is not stored"))
+}
@@ -0,0 +1,15 @@
+//! The interface between the miner and the weave data it has stored.
+use arweave_rs_types::*;
+use color_eyre::eyre::Result;
+
+/// A store of packed chunks the miner reads its recall ranges from. Chunks
+/// are addressed by any byte `offset` in the weave that falls within them,
+/// `None` means the chunk is not stored (the miner skips those nonces).
+pub trait ChunkStorage {
+    /// The packed bytes of the chunk containing `offset`.
+    fn read_packed_chunk(&self, offset: u64) -> Result<Option<Vec<u8>>>;
+
+    /// The proof of access of the chunk containing `offset`, its `tx_path`,
+    /// `data_path` and packed bytes.
+    fn read_poa(&self, offset: u64) -> Result<Option<PoaData>>;
+}
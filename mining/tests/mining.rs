@@ -0,0 +1,94 @@
+use arweave_rs_mining::{mine_step, storage::ChunkStorage, MiningStep};
+use arweave_rs_randomx::{create_randomx_vm, RandomXMode};
+use arweave_rs_test_support::{devnet_fixture, fixtures::*, serial};
+use arweave_rs_types::{
+    consensus::{
+        compute_h1, compute_h2, compute_mining_hash, compute_solution_hash, RANDOMX_PACKING_KEY,
+    },
+    PoaData, H256, U256,
+};
+use eyre::Result;
+use openssl::sha;
+
+/// Storage holding none of the chunks.
+struct EmptyStorage;
+
+impl ChunkStorage for EmptyStorage {
+    fn read_packed_chunk(&self, _offset: u64) -> Result<Option<Vec<u8>>> {
+        Ok(None)
+    }
+    fn read_poa(&self, _offset: u64) -> Result<Option<PoaData>> {
+        Ok(None)
+    }
+}
+
+#[test]
+fn mine_step_finds_solutions() {
+    let fixture = devnet_fixture(b"test_mining");
+    let (devnet, randomx_vm) = (&fixture.devnet, &fixture.randomx_vm);
+    let params = devnet.params();
+    let weave = devnet.weave();
+    let genesis_info = &devnet.genesis().nonce_limiter_info;
+
+    let step = MiningStep {
+        vdf_output: genesis_info.output,
+        global_step_number: 1,
+        seed: genesis_info.seed,
+        partition_upper_bound: weave.weave_size(),
+        mining_addr: devnet.wallet().reward_addr(),
+        diff: U256::zero(),
+    };
+
+    // With no difficulty the first stored chunk of partition 0 is a solution
+    let solution = mine_step(&step, weave, params, Some(randomx_vm))
+        .unwrap()
+        .unwrap();
+    assert_eq!(solution.partition_number, 0);
+    assert!(solution.recall_byte2.is_none());
+    assert!(solution.recall_byte < params.partition_size);
+    assert_eq!(
+        compute_solution_hash(&solution.mining_hash, &solution.hash_preimage),
+        solution.solution_hash
+    );
+    assert_eq!(
+        Some(solution.poa.chunk.as_slice()),
+        weave.packed_chunk(solution.recall_byte)
+    );
+    assert_eq!(
+        solution.chunk_hash(),
+        H256(sha::sha256(solution.poa.chunk.as_slice()))
+    );
+
+    // Nothing beats the maximum difficulty
+    let max_diff_step = MiningStep {
+        diff: U256::max_value(),
+        ..step.clone()
+    };
+    let solution = mine_step(&max_diff_step, weave, params, Some(randomx_vm)).unwrap();
+    assert!(solution.is_none());
+
+    // Nonces whose chunks aren't stored are skipped
+    let solution = mine_step(&step, &EmptyStorage, params, Some(randomx_vm)).unwrap();
+    assert!(solution.is_none());
+}
+
+#[test]
+fn two_chunk_solution_matches_mainnet() {
+    // H2 hashes the H1 solution hash with the second chunk, so recomputing a
+    // mainnet PoA2 solution gives the hash_preimage and hash of its header
+    let _serial = serial();
+    let (block_header, previous_block_header) = block_header_pair(cases::POA2);
+    let randomx_vm = create_randomx_vm(RandomXMode::FastInitialization, RANDOMX_PACKING_KEY);
+    let mining_hash = compute_mining_hash(
+        block_header.nonce_limiter_info.output,
+        block_header.partition_number as u32,
+        previous_block_header.nonce_limiter_info.seed,
+        block_header.reward_addr,
+        Some(&randomx_vm),
+    );
+
+    let (h1, _) = compute_h1(&mining_hash, block_header.nonce.0, block_header.poa.chunk.as_slice());
+    let (h2, h2_preimage) = compute_h2(&h1, block_header.poa2.chunk.as_slice(), &mining_hash);
+    assert_eq!(h2_preimage, block_header.hash_preimage);
+    assert_eq!(H256(h2), block_header.hash);
+}
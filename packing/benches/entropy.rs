@@ -0,0 +1,20 @@
+use arweave_rs_packing::compute_entropy;
+use arweave_rs_randomx::{create_randomx_vm, RandomXMode};
+use arweave_rs_types::consensus::{RANDOMX_PACKING_KEY, RANDOMX_PACKING_ROUNDS_2_6};
+use criterion::{criterion_group, criterion_main, Criterion};
+
+fn entropy(c: &mut Criterion) {
+    let randomx_vm = create_randomx_vm(RandomXMode::FastHashing, RANDOMX_PACKING_KEY);
+    let input = [7u8; 48];
+
+    let mut group = c.benchmark_group("packing");
+    group.sample_size(10);
+    group.bench_function("compute_entropy", |b| {
+        // Returns a byte rather than all 256 KiB of entropy from each iteration
+        b.iter(|| compute_entropy(&input, RANDOMX_PACKING_ROUNDS_2_6, Some(&randomx_vm))[0])
+    });
+    group.finish();
+}
+
+criterion_group!(benches, entropy);
+criterion_main!(benches);
@@ -1,6 +1,7 @@
-//! Decryption methods for separating the original chunk data from the randomX
-//! entropy using a feistel block cypher.
+//! Encryption and decryption methods for combining the original chunk data with
+//! (and separating it from) the randomX entropy using a feistel block cypher.
 use openssl::sha;
+use tracing::trace_span;
 
 const FEISTEL_BLOCK_LENGTH: usize = 32;
 
@@ -64,10 +65,70 @@ fn feistel_decrypt_block(
     (out_left, out_right)
 }
 
+/// Takes the plaintext `left` and `right` feistel blocks and uses the `key` to
+/// encrypt them, the inverse of [`feistel_decrypt_block()`].
+fn feistel_encrypt_block(
+    in_left: &[u8],
+    in_right: &[u8],
+    in_key: &[u8],
+) -> ([u8; FEISTEL_BLOCK_LENGTH], [u8; FEISTEL_BLOCK_LENGTH]) {
+    let mut out_left = [0u8; FEISTEL_BLOCK_LENGTH];
+    let mut out_right = [0u8; FEISTEL_BLOCK_LENGTH];
+
+    // XOR [in_left] with the feistel_hash of [in_right] and the first
+    // FEISTEL_BLOCK of [in_key]
+    let key_hash = feistel_hash(in_right, in_key);
+    for j in 0..FEISTEL_BLOCK_LENGTH {
+        out_left[j] = in_left[j] ^ key_hash[j];
+    }
+
+    // XOR [in_right] with the feistel_hash of [out_left] and the second
+    // FEISTEL_BLOCK of [in_key]
+    let key_hash = feistel_hash(&out_left, &in_key[FEISTEL_BLOCK_LENGTH..]);
+    for j in 0..FEISTEL_BLOCK_LENGTH {
+        out_right[j] = in_right[j] ^ key_hash[j];
+    }
+
+    (out_left, out_right)
+}
+
+/// Encrypts the `plaintext` chunk with the `in_key` RandomX entropy, both are
+/// `RANDOMX_ENTROPY_SIZE` when packing Arweave chunks. Each pair of feistel
+/// blocks after the first is encrypted with the entropy XORed with the
+/// preceding ciphertext, [`feistel_decrypt()`] reverses it.
+pub fn feistel_encrypt(plaintext: &[u8], in_key: &[u8]) -> Vec<u8> {
+    let _span = trace_span!("feistel_encrypt", len = plaintext.len()).entered();
+    let mut ciphertext = vec![0u8; plaintext.len()];
+    let mut feed_key = [0u8; 2 * FEISTEL_BLOCK_LENGTH];
+
+    for offset in (0..plaintext.len()).step_by(2 * FEISTEL_BLOCK_LENGTH) {
+        // The first step uses the entropy as is, the following steps feed the
+        // previous steps ciphertext into the key
+        if offset == 0 {
+            feed_key.copy_from_slice(&in_key[..2 * FEISTEL_BLOCK_LENGTH]);
+        } else {
+            for j in 0..2 * FEISTEL_BLOCK_LENGTH {
+                feed_key[j] = in_key[offset + j] ^ ciphertext[offset - 2 * FEISTEL_BLOCK_LENGTH + j];
+            }
+        }
+
+        let block_bytes = &plaintext[offset..offset + 2 * FEISTEL_BLOCK_LENGTH];
+        let (in_left, in_right) = block_bytes.split_at(FEISTEL_BLOCK_LENGTH);
+        let (out_left, out_right) = feistel_encrypt_block(in_left, in_right, &feed_key);
+
+        ciphertext[offset..offset + FEISTEL_BLOCK_LENGTH].copy_from_slice(&out_left);
+        ciphertext[offset + FEISTEL_BLOCK_LENGTH..offset + 2 * FEISTEL_BLOCK_LENGTH]
+            .copy_from_slice(&out_right);
+    }
+
+    ciphertext
+}
+
 /// Given a `ciphertext` array and an `in_key` array, both will be
 /// `RANDOMX_ENTROPY_SIZE` when decrypting Arweave chunks. `ciphertext` will
 /// be the encrypted chunk and `key` will be the RandomX entropy.
 pub fn feistel_decrypt(ciphertext: &[u8], in_key: &[u8]) -> Vec<u8> {
+    let _span = trace_span!("feistel_decrypt", len = ciphertext.len()).entered();
     let num_steps = ciphertext.len() / (2 * FEISTEL_BLOCK_LENGTH);
     let mut plaintext = vec![0u8; ciphertext.len()];
     let mut feed_key = [0u8; 2 * FEISTEL_BLOCK_LENGTH];
@@ -2,6 +2,7 @@
 #![allow(dead_code)]
 use arweave_rs_randomx::{create_randomx_vm, RandomXCache, RandomXFlag, RandomXMode, RandomXVM};
 use arweave_rs_types::{consensus::*, *};
+use tracing::{debug, trace, trace_span};
 
 pub mod feistel;
 
@@ -12,6 +13,7 @@ pub fn compute_entropy(
     randomx_program_count: usize,
     randomx_vm: Option<&RandomXVM>,
 ) -> [u8; RANDOMX_ENTROPY_SIZE] {
+    let _span = trace_span!("compute_entropy", randomx_program_count).entered();
     // These variables extend the life of the created RandomX instance outside
     // the scope of the [None] match arm below
     let vm: &RandomXVM;
@@ -24,6 +26,7 @@ pub fn compute_entropy(
         }
         None => {
             // Creates a disposable RandomXVM instance for use in this function
+            debug!("creating a disposable RandomX VM to compute entropy");
             vm_storage = Some(create_randomx_vm(
                 RandomXMode::FastInitialization,
                 RANDOMX_PACKING_KEY,
@@ -55,6 +58,7 @@ fn compute_randomx_hash_with_entropy(
         }
         None => {
             // Creates a disposable RandomXVM instance for use in this function
+            debug!("creating a disposable RandomX VM to compute a hash with entropy");
             vm_storage = Some(create_randomx_vm(
                 RandomXMode::FastHashing,
                 RANDOMX_PACKING_KEY,
@@ -78,18 +82,12 @@ fn pack_chunk(chunk_offset: U256, reward_address: &H256, tx_root: &H256) -> Vec<
     let randomx_program_count = RANDOMX_PACKING_ROUNDS_2_6;
     let entropy = vm.calculate_entropy(&input, randomx_program_count).unwrap();
 
-    println!();
-    for byte in entropy.iter().take(10.min(entropy.len())) {
-        println!("{}", byte);
-    }
+    trace!(entropy = ?&entropy[..10.min(entropy.len())], "vm entropy");
 
     let (_hash, entropy) =
         compute_randomx_hash_with_entropy(&input, randomx_program_count, Some(&vm));
 
-    println!();
-    for byte in entropy.iter().take(10.min(entropy.len())) {
-        println!("{}", byte);
-    }
+    trace!(entropy = ?&entropy[..10.min(entropy.len())], "hash with entropy");
 
     entropy.to_vec()
 }
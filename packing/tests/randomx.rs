@@ -0,0 +1,48 @@
+//! Checks RandomX hashing and entropy against the values of the Arweave
+//! reference implementation.
+use arweave_rs_packing::compute_entropy;
+use arweave_rs_randomx::*;
+use arweave_rs_test_support::{fixtures, serial};
+use arweave_rs_types::{decode::DecodeHash, H256, H384};
+
+const ENCODED_KEY: &str = "UbkeSd5Det8s6uLyuNJwCDFOZMQFa2zvsdKJ0k694LM";
+const ENCODED_HASH: &str = "QQYWA46qnFENL4OTQdGU8bWBj5OKZ2OOPyynY3izung";
+const ENCODED_NONCE: &str = "f_z7RLug8etm3SrmRf-xPwXEL0ZQ_xHng2A5emRDQBw";
+const ENCODED_SEGMENT: &str = "7XM3fgTCAY2GFpDjPZxlw4yw5cv8jNzZSZawywZGQ6_Ca-JDy2nX_MC2vjrIoDGp";
+
+fn randomx_input() -> Vec<u8> {
+    let nonce: H256 = DecodeHash::from(ENCODED_NONCE).unwrap();
+    let segment: H384 = DecodeHash::from(ENCODED_SEGMENT).unwrap();
+    let mut input = nonce.to_vec();
+    input.extend_from_slice(segment.as_bytes());
+    input
+}
+
+#[test]
+fn randomx_hash() {
+    let _serial = serial();
+    let key: H256 = DecodeHash::from(ENCODED_KEY).unwrap();
+    let expected_hash: H256 = DecodeHash::from(ENCODED_HASH).unwrap();
+
+    let flags = RandomXFlag::get_recommended_flags();
+    let cache = RandomXCache::new(flags, key.as_bytes()).unwrap();
+    let vm = RandomXVM::new(flags, Some(cache), None).unwrap();
+    let hash = vm.calculate_hash(&randomx_input()).unwrap();
+
+    assert_eq!(hash, expected_hash.as_bytes());
+}
+
+#[test]
+#[ignore = "initializes the RandomX dataset for fast hashing"]
+fn randomx_entropy() {
+    let _serial = serial();
+    let packing_key: H256 = DecodeHash::from(ENCODED_KEY).unwrap();
+    let randomx_vm = create_randomx_vm(RandomXMode::FastHashing, packing_key.as_bytes());
+    let randomx_program_count = 8;
+
+    let entropy = compute_entropy(&randomx_input(), randomx_program_count, Some(&randomx_vm));
+
+    let expected_entropy = fixtures::encoded_bytes("entropy/randomx_entropy.dat");
+    // Compared without assert_eq to keep 256 KiB of bytes out of the output
+    assert!(entropy.as_slice() == expected_entropy.as_slice());
+}
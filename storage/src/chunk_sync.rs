@@ -0,0 +1,263 @@
+//! Syncs the chunks of a [`StorageModule`] from Arweave peers using the
+//! `/data_sync_record` and `/chunk/{offset}` endpoints. Every chunk is
+//! verified against the `tx_root` of its block in the [`BlockIndex`] before it
+//! is packed for the mining address and stored.
+use crate::{bucket, ChunkProofs, StorageModule};
+use arweave_rs_client::ArweaveClient;
+use arweave_rs_indexes::{BlockIndex, Initialized};
+use arweave_rs_packing::{
+    compute_entropy,
+    feistel::{feistel_decrypt, feistel_encrypt},
+};
+use arweave_rs_randomx::RandomXVM;
+use arweave_rs_types::{consensus::*, *};
+use arweave_rs_validator::merkle::validate_path;
+use color_eyre::eyre::{eyre, Report, Result};
+
+/// The prefix of the packing format of chunks packed with the 2.6 packing,
+/// followed by the base64url encoded reward address.
+const SPORA_2_6_PACKING_PREFIX: &str = "spora_2_6_";
+
+pub use arweave_rs_client::ChunkJson;
+
+/// The packing format of a chunk returned by a peer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Packing {
+    Unpacked,
+    /// Packed with the 2.5 packing, which isn't supported.
+    Spora2_5,
+    /// Packed for the reward address with the 2.6 packing.
+    Spora2_6(H256),
+}
+
+impl Packing {
+    pub fn parse(packing: &str) -> Result<Self> {
+        match packing {
+            "unpacked" => Ok(Packing::Unpacked),
+            "spora_2_5" => Ok(Packing::Spora2_5),
+            _ => {
+                let encoded_addr = packing
+                    .strip_prefix(SPORA_2_6_PACKING_PREFIX)
+                    .ok_or_else(|| eyre!("unknown packing {packing}"))?;
+                let addr: Base64 = encoded_addr
+                    .parse()
+                    .map_err(|e| eyre!("Failed to decode packing address: {}", e))?;
+                if addr.len() != 32 {
+                    return Err(eyre!("packing address is {} bytes", addr.len()));
+                }
+                Ok(Packing::Spora2_6(H256::from_slice(addr.as_slice())))
+            }
+        }
+    }
+}
+
+/// A chunk whose proofs were validated against the block index.
+#[derive(Debug, Clone)]
+pub struct VerifiedChunk {
+    /// The absolute end offset of the chunk in the weave, the offset its
+    /// packing entropy is derived from.
+    pub chunk_end_offset: u128,
+    /// The `tx_root` of the block containing the chunk.
+    pub tx_root: H256,
+    /// The original bytes of the chunk.
+    pub unpacked_chunk: Vec<u8>,
+    /// The chunk as received if it's already packed for the mining address.
+    pub packed_chunk: Option<Vec<u8>>,
+    pub proofs: ChunkProofs,
+}
+
+/// The outcome of syncing the chunks of a [`StorageModule`].
+#[derive(Debug, Default)]
+pub struct SyncSummary {
+    /// The number of chunks stored.
+    pub synced: u64,
+    /// The offsets of the chunks that couldn't be synced and why.
+    pub failed: Vec<(u64, Report)>,
+}
+
+/// Fetches chunks from the peers of a client and stores them, packed for the
+/// `mining_addr`, in storage modules.
+pub struct ChunkSyncer<'a> {
+    client: &'a ArweaveClient,
+    block_index: &'a BlockIndex<Initialized>,
+    mining_addr: H256,
+    params: &'a ConsensusParams,
+    randomx_vm: Option<&'a RandomXVM>,
+}
+
+impl<'a> ChunkSyncer<'a> {
+    pub fn new(
+        client: &'a ArweaveClient,
+        block_index: &'a BlockIndex<Initialized>,
+        mining_addr: H256,
+        params: &'a ConsensusParams,
+        randomx_vm: Option<&'a RandomXVM>,
+    ) -> Self {
+        ChunkSyncer {
+            client,
+            block_index,
+            mining_addr,
+            params,
+            randomx_vm,
+        }
+    }
+
+    /// Requests the `[start, end)` byte ranges of the weave the peer stores.
+    pub async fn request_data_sync_record(&self) -> Result<Vec<(u64, u64)>> {
+        self.client.data_sync_record().await
+    }
+
+    /// Requests the chunk containing the weave byte at `offset`, in whichever
+    /// packing the peer stores it.
+    pub async fn request_chunk(&self, offset: u64) -> Result<ChunkJson> {
+        self.client.chunk(offset).await
+    }
+
+    /// Validates the `tx_path` and `data_path` of the chunk containing the
+    /// weave byte at `offset` against the `tx_root` in the block index, and
+    /// checks the unpacked chunk matches the leaf of the `data_path`.
+    pub fn verify_chunk(&self, chunk_json: &ChunkJson, offset: u64) -> Result<VerifiedChunk> {
+        let packing = Packing::parse(&chunk_json.packing)?;
+
+        // get_block_bounds expects the byte to be inside the indexed weave
+        let weave_size = match self.block_index.num_indexes() {
+            0 => 0,
+            num_indexes => {
+                self.block_index
+                    .get_item(num_indexes as usize - 1)
+                    .unwrap()
+                    .weave_size
+            }
+        };
+        if offset as u128 >= weave_size {
+            return Err(eyre!("offset {offset} is beyond the indexed weave"));
+        }
+        let block_bounds = self.block_index.get_block_bounds(offset as u128);
+        let byte_offset_in_block = get_byte_offset(
+            U256::from(offset),
+            block_bounds.block_start_offset,
+            block_bounds.block_end_offset,
+            self.params,
+        );
+
+        // The tx_path proves the data_root of the transaction in the block
+        let tx_path_result = validate_path(
+            block_bounds.tx_root.0,
+            &chunk_json.tx_path,
+            byte_offset_in_block,
+        )
+        .map_err(|e| eyre!("tx_path is invalid: {e}"))?;
+
+        // The data_path proves the chunk id in the transactions data
+        let byte_offset_in_tx = byte_offset_in_block - tx_path_result.left_bound;
+        let data_path_result = validate_path(
+            tx_path_result.leaf_hash,
+            &chunk_json.data_path,
+            byte_offset_in_tx,
+        )
+        .map_err(|e| eyre!("data_path is invalid: {e}"))?;
+
+        let chunk_size = (data_path_result.right_bound - data_path_result.left_bound) as usize;
+        let chunk_end_offset = block_bounds.block_start_offset
+            + tx_path_result.left_bound
+            + data_path_result.right_bound;
+
+        let (unpacked_chunk, packed_chunk) = match packing {
+            Packing::Unpacked => (chunk_json.chunk.0.clone(), None),
+            Packing::Spora2_5 => return Err(eyre!("the spora_2_5 packing is not supported")),
+            Packing::Spora2_6(reward_addr) => {
+                if chunk_json.chunk.len() != DATA_CHUNK_SIZE as usize {
+                    return Err(eyre!("packed chunk is {} bytes", chunk_json.chunk.len()));
+                }
+                let entropy =
+                    self.compute_entropy(chunk_end_offset, &block_bounds.tx_root, &reward_addr);
+                let mut unpacked_chunk = feistel_decrypt(chunk_json.chunk.as_slice(), &entropy);
+                unpacked_chunk.truncate(chunk_size);
+                let packed_chunk =
+                    (reward_addr == self.mining_addr).then(|| chunk_json.chunk.0.clone());
+                (unpacked_chunk, packed_chunk)
+            }
+        };
+
+        if unpacked_chunk.len() != chunk_size {
+            return Err(eyre!(
+                "chunk is {} bytes, the data_path proves {chunk_size}",
+                unpacked_chunk.len()
+            ));
+        }
+        if generate_chunk_id(&unpacked_chunk) != data_path_result.leaf_hash {
+            return Err(eyre!("chunk does not match the data_path"));
+        }
+
+        Ok(VerifiedChunk {
+            chunk_end_offset,
+            tx_root: block_bounds.tx_root,
+            unpacked_chunk,
+            packed_chunk,
+            proofs: ChunkProofs {
+                tx_path: chunk_json.tx_path.0.clone(),
+                data_path: chunk_json.data_path.0.clone(),
+            },
+        })
+    }
+
+    /// Packs a verified chunk for the mining address, reusing the chunk as
+    /// received if it's already packed for it.
+    pub fn pack_chunk(&self, chunk: &VerifiedChunk) -> Vec<u8> {
+        if let Some(packed_chunk) = &chunk.packed_chunk {
+            return packed_chunk.clone();
+        }
+        // Chunks smaller than 256 KiB are padded with zeros before packing
+        let mut plaintext = chunk.unpacked_chunk.clone();
+        plaintext.resize(DATA_CHUNK_SIZE as usize, 0);
+        let entropy =
+            self.compute_entropy(chunk.chunk_end_offset, &chunk.tx_root, &self.mining_addr);
+        feistel_encrypt(&plaintext, &entropy)
+    }
+
+    /// Fetches, verifies, packs and stores the chunk containing the weave
+    /// byte at `offset`.
+    pub async fn sync_chunk(&self, module: &mut StorageModule, offset: u64) -> Result<()> {
+        let chunk_json = self.request_chunk(offset).await?;
+        let verified_chunk = self.verify_chunk(&chunk_json, offset)?;
+        let packed_chunk = self.pack_chunk(&verified_chunk);
+        module.write_chunk(offset, &packed_chunk, &verified_chunk.proofs)
+    }
+
+    /// Syncs every chunk of the module the peer stores and the module is
+    /// missing. Chunks that fail to sync are reported in the summary rather
+    /// than stopping the sync.
+    pub async fn sync_module(&self, module: &mut StorageModule) -> Result<SyncSummary> {
+        let (range_start, range_end) = module.range();
+        let mut summary = SyncSummary::default();
+
+        for (start, end) in self.request_data_sync_record().await? {
+            let start = start.max(range_start);
+            let end = end.min(range_end);
+            if start >= end {
+                continue;
+            }
+            for bucket in bucket(start)..=bucket(end - 1) {
+                let offset = (bucket * DATA_CHUNK_SIZE as u64).max(start);
+                if module.has_chunk(offset) {
+                    continue;
+                }
+                match self.sync_chunk(module, offset).await {
+                    Ok(()) => summary.synced += 1,
+                    Err(err) => summary.failed.push((offset, err)),
+                }
+            }
+        }
+        Ok(summary)
+    }
+
+    fn compute_entropy(
+        &self,
+        chunk_end_offset: u128,
+        tx_root: &H256,
+        reward_addr: &H256,
+    ) -> [u8; RANDOMX_ENTROPY_SIZE] {
+        let input = get_chunk_entropy_input(chunk_end_offset.into(), tx_root, reward_addr);
+        compute_entropy(&input, RANDOMX_PACKING_ROUNDS_2_6, self.randomx_vm)
+    }
+}
@@ -0,0 +1,426 @@
+//! Stores the packed chunks of one partition of the weave on disk along with
+//! the `tx_path` and `data_path` proofs needed to assemble their PoA.
+//!
+//! A [`StorageModule`] directory holds three files:
+//! - `chunks.dat` - a sparse file with a `DATA_CHUNK_SIZE` slot for every
+//!   256 KiB bucket of the partition.
+//! - `proofs.dat` - an append only log of `{bucket, tx_path, data_path}`
+//!   entries, the latest entry of a bucket wins.
+//! - `metadata.dat` - the partition and its [`SyncRecord`], replaced
+//!   atomically after every write.
+//!
+//! Chunks and proofs are flushed to disk before the bucket is added to the
+//! sync record, and a stored bucket is removed from the persisted sync record
+//! before its slot is overwritten, so after a crash a bucket is either fully
+//! stored or absent.
+use arweave_rs_mining::storage::ChunkStorage;
+use arweave_rs_types::{consensus::*, *};
+use color_eyre::eyre::{eyre, Result};
+use openssl::sha;
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use sync_record::SyncRecord;
+
+pub mod chunk_sync;
+pub mod sync_record;
+
+const CHUNKS_FILE: &str = "chunks.dat";
+const PROOFS_FILE: &str = "proofs.dat";
+const METADATA_FILE: &str = "metadata.dat";
+const METADATA_TMP_FILE: &str = "metadata.tmp";
+
+/// `{bucket: u64, tx_path_size: u32, data_path_size: u32}`, little-endian like
+/// the items of the block index file.
+const PROOF_HEADER_SIZE: usize = 8 + 4 + 4;
+
+/// The `tx_path` and `data_path` merkle proofs of a stored chunk.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ChunkProofs {
+    pub tx_path: Vec<u8>,
+    pub data_path: Vec<u8>,
+}
+
+/// Location of a buckets proofs in the proof log.
+#[derive(Debug, Clone, Copy)]
+struct ProofLocation {
+    position: u64,
+    tx_path_size: u32,
+    data_path_size: u32,
+}
+
+/// The packed chunks of partition `partition_number`, the weave bytes
+/// `[partition_number * partition_size, (partition_number + 1) * partition_size)`.
+/// Chunks are addressed by absolute weave offsets, any offset within a 256 KiB
+/// bucket addresses the chunk stored in it.
+pub struct StorageModule {
+    dir: PathBuf,
+    partition_number: u64,
+    partition_size: u64,
+    max_nonce: u32,
+    chunks_file: Mutex<File>,
+    proofs_file: Mutex<File>,
+    proof_locations: HashMap<u64, ProofLocation>,
+    sync_record: SyncRecord,
+}
+
+impl StorageModule {
+    /// Opens the storage module in `dir`, creating it if it doesn't exist.
+    /// The partition size and recall range size come from `params`. Reopening
+    /// a module recovers its sync record and discards a proof entry left
+    /// incomplete by a crash.
+    pub fn open(dir: &Path, partition_number: u64, params: &ConsensusParams) -> Result<Self> {
+        fs::create_dir_all(dir)?;
+        let partition_size = params.partition_size;
+
+        let sync_record = match read_metadata(&dir.join(METADATA_FILE))? {
+            Some(metadata) => {
+                if metadata.partition_number != partition_number
+                    || metadata.partition_size != partition_size
+                {
+                    return Err(eyre!(
+                        "{} stores partition {} of size {}, not partition {} of size {}",
+                        dir.display(),
+                        metadata.partition_number,
+                        metadata.partition_size,
+                        partition_number,
+                        partition_size
+                    ));
+                }
+                metadata.sync_record
+            }
+            None => SyncRecord::new(),
+        };
+
+        let chunks_file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(dir.join(CHUNKS_FILE))?;
+
+        let mut proofs_file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(dir.join(PROOFS_FILE))?;
+        let proof_locations = load_proof_locations(&mut proofs_file)?;
+
+        let module = StorageModule {
+            dir: dir.to_path_buf(),
+            partition_number,
+            partition_size,
+            max_nonce: params.max_nonce(),
+            chunks_file: Mutex::new(chunks_file),
+            proofs_file: Mutex::new(proofs_file),
+            proof_locations,
+            sync_record,
+        };
+
+        // Size the (sparse) chunk file to hold every bucket of the partition
+        let num_buckets = module.last_bucket() - module.first_bucket() + 1;
+        module
+            .chunks_file
+            .lock()
+            .unwrap()
+            .set_len(num_buckets * DATA_CHUNK_SIZE as u64)?;
+
+        Ok(module)
+    }
+
+    pub fn partition_number(&self) -> u64 {
+        self.partition_number
+    }
+
+    /// The `[start, end)` weave offsets of the partition.
+    pub fn range(&self) -> (u64, u64) {
+        let start = self.partition_number * self.partition_size;
+        (start, start + self.partition_size)
+    }
+
+    /// The buckets that are fully stored.
+    pub fn sync_record(&self) -> &SyncRecord {
+        &self.sync_record
+    }
+
+    /// True if the chunk of the bucket containing `offset` is stored.
+    pub fn has_chunk(&self, offset: u64) -> bool {
+        self.sync_record.contains(bucket(offset))
+    }
+
+    /// Writes the `packed_chunk` of the bucket containing `offset` and its
+    /// proofs, replacing a previously stored chunk. Returns once the chunk,
+    /// the proofs and the updated sync record are flushed to disk.
+    pub fn write_chunk(
+        &mut self,
+        offset: u64,
+        packed_chunk: &[u8],
+        proofs: &ChunkProofs,
+    ) -> Result<()> {
+        if packed_chunk.len() != DATA_CHUNK_SIZE as usize {
+            return Err(eyre!(
+                "packed chunks are {} bytes, got {}",
+                DATA_CHUNK_SIZE,
+                packed_chunk.len()
+            ));
+        }
+        let slot = self
+            .slot_position(offset)
+            .ok_or_else(|| eyre!("offset {offset} is outside of the partition"))?;
+        let bucket = bucket(offset);
+
+        // Overwriting a stored bucket tears it until the write completes,
+        // drop it from the sync record first
+        if self.sync_record.contains(bucket) {
+            let mut sync_record = self.sync_record.clone();
+            sync_record.remove(bucket);
+            self.persist_sync_record(sync_record)?;
+        }
+
+        {
+            let mut chunks_file = self.chunks_file.lock().unwrap();
+            chunks_file.seek(SeekFrom::Start(slot))?;
+            chunks_file.write_all(packed_chunk)?;
+            chunks_file.sync_data()?;
+        }
+
+        let location = {
+            let mut proofs_file = self.proofs_file.lock().unwrap();
+            let position = proofs_file.seek(SeekFrom::End(0))?;
+            let mut entry = Vec::with_capacity(
+                PROOF_HEADER_SIZE + proofs.tx_path.len() + proofs.data_path.len(),
+            );
+            entry.extend_from_slice(&bucket.to_le_bytes());
+            entry.extend_from_slice(&(proofs.tx_path.len() as u32).to_le_bytes());
+            entry.extend_from_slice(&(proofs.data_path.len() as u32).to_le_bytes());
+            entry.extend_from_slice(&proofs.tx_path);
+            entry.extend_from_slice(&proofs.data_path);
+            proofs_file.write_all(&entry)?;
+            proofs_file.sync_data()?;
+            ProofLocation {
+                position: position + PROOF_HEADER_SIZE as u64,
+                tx_path_size: proofs.tx_path.len() as u32,
+                data_path_size: proofs.data_path.len() as u32,
+            }
+        };
+        self.proof_locations.insert(bucket, location);
+
+        let mut sync_record = self.sync_record.clone();
+        sync_record.insert(bucket);
+        self.persist_sync_record(sync_record)
+    }
+
+    /// Writes `sync_record` to the metadata file and makes it the current
+    /// record once it's on disk.
+    fn persist_sync_record(&mut self, sync_record: SyncRecord) -> Result<()> {
+        write_metadata(
+            &self.dir,
+            &Metadata {
+                partition_number: self.partition_number,
+                partition_size: self.partition_size,
+                sync_record: sync_record.clone(),
+            },
+        )?;
+        self.sync_record = sync_record;
+        Ok(())
+    }
+
+    /// Reads the packed chunk of the bucket containing `offset`, `None` if it
+    /// isn't stored.
+    pub fn read_chunk(&self, offset: u64) -> Result<Option<Vec<u8>>> {
+        if !self.has_chunk(offset) {
+            return Ok(None);
+        }
+        let Some(slot) = self.slot_position(offset) else {
+            return Ok(None);
+        };
+        let mut chunk = vec![0u8; DATA_CHUNK_SIZE as usize];
+        let mut chunks_file = self.chunks_file.lock().unwrap();
+        chunks_file.seek(SeekFrom::Start(slot))?;
+        chunks_file.read_exact(&mut chunk)?;
+        Ok(Some(chunk))
+    }
+
+    /// Reads the proofs of the chunk in the bucket containing `offset`.
+    pub fn read_proofs(&self, offset: u64) -> Result<Option<ChunkProofs>> {
+        if !self.has_chunk(offset) {
+            return Ok(None);
+        }
+        let Some(location) = self.proof_locations.get(&bucket(offset)) else {
+            return Ok(None);
+        };
+        let mut tx_path = vec![0u8; location.tx_path_size as usize];
+        let mut data_path = vec![0u8; location.data_path_size as usize];
+        let mut proofs_file = self.proofs_file.lock().unwrap();
+        proofs_file.seek(SeekFrom::Start(location.position))?;
+        proofs_file.read_exact(&mut tx_path)?;
+        proofs_file.read_exact(&mut data_path)?;
+        Ok(Some(ChunkProofs { tx_path, data_path }))
+    }
+
+    /// Reads the `max_nonce` contiguous chunks of the recall range starting at
+    /// `recall_range_start`, `None` for the chunks that aren't stored.
+    pub fn read_recall_range(&self, recall_range_start: u64) -> Result<Vec<Option<Vec<u8>>>> {
+        (0..self.max_nonce as u64)
+            .map(|nonce| self.read_chunk(recall_range_start + nonce * DATA_CHUNK_SIZE as u64))
+            .collect()
+    }
+
+    fn first_bucket(&self) -> u64 {
+        bucket(self.range().0)
+    }
+
+    fn last_bucket(&self) -> u64 {
+        bucket(self.range().1 - 1)
+    }
+
+    /// The position of the slot of the bucket containing `offset` in the
+    /// chunk file, `None` if the bucket isn't part of the partition.
+    fn slot_position(&self, offset: u64) -> Option<u64> {
+        let bucket = bucket(offset);
+        if bucket < self.first_bucket() || bucket > self.last_bucket() {
+            return None;
+        }
+        Some((bucket - self.first_bucket()) * DATA_CHUNK_SIZE as u64)
+    }
+}
+
+impl ChunkStorage for StorageModule {
+    fn read_packed_chunk(&self, offset: u64) -> Result<Option<Vec<u8>>> {
+        self.read_chunk(offset)
+    }
+
+    fn read_poa(&self, offset: u64) -> Result<Option<PoaData>> {
+        let (Some(chunk), Some(proofs)) = (self.read_chunk(offset)?, self.read_proofs(offset)?)
+        else {
+            return Ok(None);
+        };
+        Ok(Some(PoaData {
+            option: "1".to_string(),
+            tx_path: Base64(proofs.tx_path),
+            data_path: Base64(proofs.data_path),
+            chunk: Base64(chunk),
+        }))
+    }
+}
+
+/// The index of the 256 KiB bucket of the weave containing `offset`.
+pub fn bucket(offset: u64) -> u64 {
+    offset / DATA_CHUNK_SIZE as u64
+}
+
+struct Metadata {
+    partition_number: u64,
+    partition_size: u64,
+    sync_record: SyncRecord,
+}
+
+/// Serializes the metadata as little-endian `{partition_number,
+/// partition_size, num_intervals, [start, end]...}` followed by the `SHA-256`
+/// checksum of those bytes, and replaces the metadata file with it.
+fn write_metadata(dir: &Path, metadata: &Metadata) -> Result<()> {
+    let intervals = metadata.sync_record.intervals();
+    let mut bytes = Vec::with_capacity(24 + intervals.len() * 16 + 32);
+    bytes.extend_from_slice(&metadata.partition_number.to_le_bytes());
+    bytes.extend_from_slice(&metadata.partition_size.to_le_bytes());
+    bytes.extend_from_slice(&(intervals.len() as u64).to_le_bytes());
+    for (start, end) in intervals {
+        bytes.extend_from_slice(&start.to_le_bytes());
+        bytes.extend_from_slice(&end.to_le_bytes());
+    }
+    let checksum = sha::sha256(&bytes);
+    bytes.extend_from_slice(&checksum);
+
+    // Write a temporary file and rename it over the metadata file, the rename
+    // is atomic so a crash leaves either the old or the new metadata
+    let tmp_path = dir.join(METADATA_TMP_FILE);
+    let mut file = File::create(&tmp_path)?;
+    file.write_all(&bytes)?;
+    file.sync_all()?;
+    fs::rename(&tmp_path, dir.join(METADATA_FILE))?;
+
+    // Persist the rename, directories can't be opened as files on Windows
+    #[cfg(unix)]
+    File::open(dir)?.sync_all()?;
+
+    Ok(())
+}
+
+/// Reads the metadata file, `None` if the module is new.
+fn read_metadata(path: &Path) -> Result<Option<Metadata>> {
+    let bytes = match fs::read(path) {
+        Ok(bytes) => bytes,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(err.into()),
+    };
+    if bytes.len() < 24 + 32 {
+        return Err(eyre!("{} is truncated", path.display()));
+    }
+    let (body, checksum) = bytes.split_at(bytes.len() - 32);
+    if sha::sha256(body) != checksum {
+        return Err(eyre!("{} checksum is invalid", path.display()));
+    }
+
+    let read_u64 = |index: usize| {
+        let mut le_bytes = [0u8; 8];
+        le_bytes.copy_from_slice(&body[index * 8..index * 8 + 8]);
+        u64::from_le_bytes(le_bytes)
+    };
+    let num_intervals = read_u64(2) as usize;
+    if body.len() != 24 + num_intervals * 16 {
+        return Err(eyre!(
+            "{} has an invalid number of intervals",
+            path.display()
+        ));
+    }
+    let intervals: Vec<(u64, u64)> = (0..num_intervals)
+        .map(|i| (read_u64(3 + i * 2), read_u64(4 + i * 2)))
+        .collect();
+
+    Ok(Some(Metadata {
+        partition_number: read_u64(0),
+        partition_size: read_u64(1),
+        sync_record: SyncRecord::from_intervals(&intervals),
+    }))
+}
+
+/// Replays the proof log, mapping each bucket to its latest proofs. A
+/// trailing entry that was only partially written is truncated.
+fn load_proof_locations(file: &mut File) -> Result<HashMap<u64, ProofLocation>> {
+    let mut buffer = Vec::new();
+    file.seek(SeekFrom::Start(0))?;
+    file.read_to_end(&mut buffer)?;
+
+    let mut locations = HashMap::new();
+    let mut position = 0;
+    while position + PROOF_HEADER_SIZE <= buffer.len() {
+        let header = &buffer[position..position + PROOF_HEADER_SIZE];
+        let bucket = u64::from_le_bytes(header[0..8].try_into().unwrap());
+        let tx_path_size = u32::from_le_bytes(header[8..12].try_into().unwrap());
+        let data_path_size = u32::from_le_bytes(header[12..16].try_into().unwrap());
+
+        let entry_end =
+            position + PROOF_HEADER_SIZE + tx_path_size as usize + data_path_size as usize;
+        if entry_end > buffer.len() {
+            break;
+        }
+        locations.insert(
+            bucket,
+            ProofLocation {
+                position: (position + PROOF_HEADER_SIZE) as u64,
+                tx_path_size,
+                data_path_size,
+            },
+        );
+        position = entry_end;
+    }
+
+    if position < buffer.len() {
+        file.set_len(position as u64)?;
+        file.sync_all()?;
+    }
+    Ok(locations)
+}
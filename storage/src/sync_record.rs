@@ -0,0 +1,104 @@
+//! Tracks which 256 KiB buckets of the weave are stored as a sparse set of
+//! intervals (ar_intervals).
+use std::collections::BTreeMap;
+
+/// A set of bucket indexes (absolute offset / `DATA_CHUNK_SIZE`) stored as
+/// non-overlapping, non-adjacent `[start, end)` intervals, so a fully synced
+/// partition is a single entry.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SyncRecord {
+    /// Interval start mapped to its (exclusive) end.
+    intervals: BTreeMap<u64, u64>,
+}
+
+impl SyncRecord {
+    pub fn new() -> Self {
+        SyncRecord::default()
+    }
+
+    /// Builds a record from `[start, end)` intervals, merging any that
+    /// overlap or touch.
+    pub fn from_intervals(intervals: &[(u64, u64)]) -> Self {
+        let mut record = SyncRecord::new();
+        for &(start, end) in intervals {
+            record.insert_range(start, end);
+        }
+        record
+    }
+
+    /// Adds `bucket` to the record.
+    pub fn insert(&mut self, bucket: u64) {
+        self.insert_range(bucket, bucket + 1);
+    }
+
+    /// Adds the buckets `[start, end)` to the record.
+    pub fn insert_range(&mut self, start: u64, end: u64) {
+        if start >= end {
+            return;
+        }
+        let mut start = start;
+        let mut end = end;
+
+        // Merge with the interval starting at or before `start` if it
+        // overlaps or touches
+        if let Some((&prev_start, &prev_end)) = self.intervals.range(..=start).next_back() {
+            if prev_end >= start {
+                start = prev_start;
+                end = end.max(prev_end);
+                self.intervals.remove(&prev_start);
+            }
+        }
+
+        // Absorb the following intervals that start within the new one
+        let following: Vec<(u64, u64)> = self
+            .intervals
+            .range(start..=end)
+            .map(|(&s, &e)| (s, e))
+            .collect();
+        for (next_start, next_end) in following {
+            end = end.max(next_end);
+            self.intervals.remove(&next_start);
+        }
+
+        self.intervals.insert(start, end);
+    }
+
+    /// Removes `bucket` from the record, splitting the interval holding it.
+    pub fn remove(&mut self, bucket: u64) {
+        let Some((&start, &end)) = self.intervals.range(..=bucket).next_back() else {
+            return;
+        };
+        if bucket >= end {
+            return;
+        }
+        self.intervals.remove(&start);
+        if start < bucket {
+            self.intervals.insert(start, bucket);
+        }
+        if bucket + 1 < end {
+            self.intervals.insert(bucket + 1, end);
+        }
+    }
+
+    /// True if `bucket` is in the record.
+    pub fn contains(&self, bucket: u64) -> bool {
+        self.intervals
+            .range(..=bucket)
+            .next_back()
+            .is_some_and(|(_, &end)| bucket < end)
+    }
+
+    /// The `[start, end)` intervals of the record in ascending order.
+    pub fn intervals(&self) -> Vec<(u64, u64)> {
+        self.intervals.iter().map(|(&s, &e)| (s, e)).collect()
+    }
+
+    /// The number of buckets in the record.
+    pub fn num_buckets(&self) -> u64 {
+        self.intervals.iter().map(|(s, e)| e - s).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.intervals.is_empty()
+    }
+}
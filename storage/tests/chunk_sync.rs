@@ -0,0 +1,102 @@
+use arweave_rs_client::ArweaveClient;
+use arweave_rs_devnet::{wallet::DevnetWallet, weave};
+use arweave_rs_storage::{
+    chunk_sync::{ChunkJson, ChunkSyncer},
+    ChunkProofs, StorageModule,
+};
+use arweave_rs_test_support::{devnet_fixture, MockPeer};
+use arweave_rs_types::{consensus::DATA_CHUNK_SIZE, Base64};
+
+#[test]
+fn chunk_sync() {
+    let fixture = devnet_fixture(b"test_chunk_sync");
+    let (devnet, randomx_vm) = (&fixture.devnet, &fixture.randomx_vm);
+    let params = devnet.params();
+    let weave = devnet.weave();
+    let block_index = devnet.block_index();
+    let mining_addr = devnet.wallet().reward_addr();
+    let chunk_size = DATA_CHUNK_SIZE as u64;
+
+    // The peer serves partition 0 (chunks 0..8). Chunks 0..3 are packed for
+    // the mining address, 3..6 are unpacked, chunk 6 is corrupt and chunk 7
+    // is missing.
+    let peer = MockPeer::start().unwrap();
+    peer.set_data_sync_record(vec![(0, 8 * chunk_size)]);
+    for index in 0..7 {
+        let offset = index * chunk_size;
+        let poa = weave.poa(offset).unwrap();
+        let mut chunk_json = ChunkJson {
+            chunk: poa.chunk,
+            data_path: poa.data_path,
+            tx_path: poa.tx_path,
+            packing: format!("spora_2_6_{}", Base64(mining_addr.to_vec())),
+        };
+        if (3..6).contains(&index) {
+            chunk_json.chunk = Base64(weave::chunk_data(&devnet.config().seed, index as usize));
+            chunk_json.packing = "unpacked".to_string();
+        }
+        if index == 6 {
+            chunk_json.chunk.0[0] ^= 1;
+        }
+        peer.insert_chunk(offset, chunk_json);
+    }
+    let client = ArweaveClient::new([peer.url()]).unwrap();
+
+    let dir = tempfile::tempdir().unwrap();
+    let mut module = StorageModule::open(dir.path(), 0, params).unwrap();
+
+    let syncer = ChunkSyncer::new(&client, &block_index, mining_addr, params, Some(randomx_vm));
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let sync_record = runtime.block_on(syncer.request_data_sync_record()).unwrap();
+    let summary = runtime.block_on(syncer.sync_module(&mut module)).unwrap();
+
+    assert_eq!(sync_record, vec![(0, 8 * chunk_size)]);
+    let failed_offsets: Vec<u64> = summary.failed.iter().map(|(offset, _)| *offset).collect();
+    assert_eq!(summary.synced, 6);
+    assert_eq!(failed_offsets, vec![6 * chunk_size, 7 * chunk_size]);
+
+    // Packed and unpacked chunks are both stored packed for the mining address
+    for index in 0..6 {
+        let offset = index * chunk_size;
+        let poa = weave.poa(offset).unwrap();
+        assert_eq!(
+            module.read_chunk(offset).unwrap().as_deref(),
+            weave.packed_chunk(offset)
+        );
+        let expected_proofs = ChunkProofs {
+            tx_path: poa.tx_path.0,
+            data_path: poa.data_path.0,
+        };
+        assert!(module.read_proofs(offset).unwrap() == Some(expected_proofs));
+    }
+    assert_eq!(module.sync_record().intervals(), vec![(0, 6)]);
+
+    // A chunk packed for another address is unpacked and verified
+    let other_wallet = DevnetWallet::from_seed(b"test_chunk_sync_other").unwrap();
+    let other_syncer = ChunkSyncer::new(
+        &client,
+        &block_index,
+        other_wallet.reward_addr(),
+        params,
+        Some(randomx_vm),
+    );
+    let chunk_json = runtime.block_on(other_syncer.request_chunk(chunk_size)).unwrap();
+    let verified_chunk = other_syncer.verify_chunk(&chunk_json, chunk_size).unwrap();
+    assert!(verified_chunk.packed_chunk.is_none());
+    assert_eq!(
+        verified_chunk.unpacked_chunk,
+        weave::chunk_data(&devnet.config().seed, 1)
+    );
+    assert_ne!(other_syncer.pack_chunk(&verified_chunk), chunk_json.chunk.0);
+
+    // A proof for a different offset doesn't verify
+    assert!(syncer.verify_chunk(&chunk_json, 5 * chunk_size).is_err());
+
+    // Truncated merkle paths are rejected
+    let mut short_tx_path = chunk_json.clone();
+    short_tx_path.tx_path.0.pop();
+    assert!(syncer.verify_chunk(&short_tx_path, chunk_size).is_err());
+    let mut short_data_path = chunk_json.clone();
+    short_data_path.data_path.0.truncate(10);
+    assert!(syncer.verify_chunk(&short_data_path, chunk_size).is_err());
+}
@@ -0,0 +1,113 @@
+use arweave_rs_mining::{mine_step, storage::ChunkStorage, MiningStep};
+use arweave_rs_storage::{sync_record::SyncRecord, ChunkProofs, StorageModule};
+use arweave_rs_test_support::devnet_fixture;
+use arweave_rs_types::{consensus::DATA_CHUNK_SIZE, PoaData, U256};
+use std::io::Write;
+
+#[test]
+fn storage_module() {
+    let fixture = devnet_fixture(b"test_storage_module");
+    let (devnet, randomx_vm) = (&fixture.devnet, &fixture.randomx_vm);
+    let params = devnet.params();
+    let weave = devnet.weave();
+    let chunk_size = DATA_CHUNK_SIZE as u64;
+
+    let dir = tempfile::tempdir().unwrap();
+
+    let poa_matches = |poa: Option<PoaData>, offset: u64| {
+        let expected = weave.poa(offset).unwrap();
+        poa.is_some_and(|poa| {
+            poa.chunk.0 == expected.chunk.0
+                && poa.tx_path.0 == expected.tx_path.0
+                && poa.data_path.0 == expected.data_path.0
+        })
+    };
+    let write_chunk = |module: &mut StorageModule, index: u64| {
+        let offset = index * chunk_size;
+        let poa = weave.poa(offset).unwrap();
+        let proofs = ChunkProofs {
+            tx_path: poa.tx_path.0,
+            data_path: poa.data_path.0,
+        };
+        module.write_chunk(offset, &poa.chunk.0, &proofs)
+    };
+
+    // Store a few chunks of partition 0, leaving gaps in the sync record
+    let mut module = StorageModule::open(dir.path(), 0, params).unwrap();
+    for index in [0, 1, 2, 5] {
+        write_chunk(&mut module, index).unwrap();
+    }
+    assert!(write_chunk(&mut module, 8).is_err());
+    drop(module);
+
+    // Reopening recovers the sync record and the stored chunks
+    let module = StorageModule::open(dir.path(), 0, params).unwrap();
+    assert_eq!(module.sync_record().intervals(), vec![(0, 3), (5, 6)]);
+    assert_eq!(
+        module.read_chunk(chunk_size + 100).unwrap().as_deref(),
+        weave.packed_chunk(chunk_size)
+    );
+    assert!(poa_matches(module.read_poa(chunk_size).unwrap(), chunk_size));
+    assert!(module.read_chunk(3 * chunk_size).unwrap().is_none());
+
+    // Recall ranges read contiguous chunks, skipping the ones not stored
+    let recall_range = module.read_recall_range(4 * chunk_size).unwrap();
+    assert_eq!(recall_range.len(), params.max_nonce() as usize);
+    assert!(recall_range[0].is_none());
+    assert_eq!(
+        recall_range[1].as_deref(),
+        weave.packed_chunk(5 * chunk_size)
+    );
+    drop(module);
+
+    // A proof entry torn by a crash is discarded on open
+    let mut proofs_file = std::fs::OpenOptions::new()
+        .append(true)
+        .open(dir.path().join("proofs.dat"))
+        .unwrap();
+    proofs_file.write_all(&[7u8; 20]).unwrap();
+    drop(proofs_file);
+    let mut module = StorageModule::open(dir.path(), 0, params).unwrap();
+    assert!(poa_matches(
+        module.read_poa(5 * chunk_size).unwrap(),
+        5 * chunk_size
+    ));
+    assert!(StorageModule::open(dir.path(), 1, params).is_err());
+
+    // Once the partition is fully stored the miner finds a solution in it
+    for index in [3, 4, 6, 7] {
+        write_chunk(&mut module, index).unwrap();
+    }
+    assert_eq!(module.sync_record().intervals(), vec![(0, 8)]);
+
+    // Overwriting a stored chunk keeps it in the sync record
+    write_chunk(&mut module, 2).unwrap();
+    assert_eq!(module.sync_record().intervals(), vec![(0, 8)]);
+    assert!(poa_matches(module.read_poa(2 * chunk_size).unwrap(), 2 * chunk_size));
+    let genesis_info = &devnet.genesis().nonce_limiter_info;
+    let step = MiningStep {
+        vdf_output: genesis_info.output,
+        global_step_number: 1,
+        seed: genesis_info.seed,
+        partition_upper_bound: params.partition_size,
+        mining_addr: devnet.wallet().reward_addr(),
+        diff: U256::zero(),
+    };
+    let solution = mine_step(&step, &module, params, Some(randomx_vm)).unwrap();
+    assert!(solution.is_some_and(|solution| poa_matches(Some(solution.poa), solution.recall_byte)));
+}
+
+#[test]
+fn sync_record_remove() {
+    let mut sync_record = SyncRecord::from_intervals(&[(0, 4), (6, 7)]);
+    sync_record.remove(2);
+    assert_eq!(sync_record.intervals(), vec![(0, 2), (3, 4), (6, 7)]);
+    sync_record.remove(0);
+    sync_record.remove(3);
+    sync_record.remove(6);
+    assert_eq!(sync_record.intervals(), vec![(1, 2)]);
+    sync_record.remove(5);
+    assert_eq!(sync_record.intervals(), vec![(1, 2)]);
+    sync_record.insert(2);
+    assert_eq!(sync_record.intervals(), vec![(1, 3)]);
+}
@@ -0,0 +1,38 @@
+//! A devnet with the default config and the RandomX VM it packs and mines
+//! with, the setup of the tests that produce and validate devnet blocks.
+use crate::serial;
+use arweave_rs_devnet::{wallet::DevnetWallet, Devnet, DevnetConfig};
+use arweave_rs_randomx::{create_randomx_vm, RandomXMode, RandomXVM};
+use arweave_rs_types::consensus::RANDOMX_PACKING_KEY;
+use std::sync::MutexGuard;
+
+/// A devnet and its RandomX VM. The fields are dropped in order, so the
+/// [`serial()`] guard is released once the devnet and the VM are freed.
+pub struct DevnetFixture {
+    pub devnet: Devnet,
+    pub randomx_vm: RandomXVM,
+    pub serial: MutexGuard<'static, ()>,
+}
+
+impl DevnetFixture {
+    /// Takes the [`serial()`] guard and starts a devnet with the default config
+    /// and a wallet derived from `seed`, with a VM in `mode`.
+    pub fn with_mode(seed: &[u8], mode: RandomXMode) -> Self {
+        let serial = serial();
+        let randomx_vm = create_randomx_vm(mode, RANDOMX_PACKING_KEY);
+        let wallet = DevnetWallet::from_seed(seed).expect("a wallet derived from the seed");
+        let devnet = Devnet::new(DevnetConfig::default(), wallet, Some(&randomx_vm))
+            .expect("the default devnet config is valid");
+        DevnetFixture {
+            devnet,
+            randomx_vm,
+            serial,
+        }
+    }
+}
+
+/// A [`DevnetFixture`] with a VM that initializes quickly, as the tests only
+/// compute a few hashes.
+pub fn devnet_fixture(seed: &[u8]) -> DevnetFixture {
+    DevnetFixture::with_mode(seed, RandomXMode::FastInitialization)
+}
@@ -0,0 +1,43 @@
+//! Faults the mock peer injects into its responses.
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fault {
+    /// Responds `429 Too Many Requests`, with a `Retry-After` of the seconds
+    /// if there are any.
+    RateLimit { retry_after: Option<u64> },
+    /// Waits before responding, so a client with a shorter timeout times out.
+    Delay(Duration),
+    /// Responds with the first half of the JSON body.
+    TruncatedJson,
+    /// Responds with the status code and an empty body.
+    Status(u16),
+    /// Closes the connection without responding.
+    Disconnect,
+}
+
+/// Injects the `fault` into the responses to paths starting with
+/// `path_prefix`, `remaining` more times or indefinitely.
+#[derive(Debug, Clone)]
+pub(crate) struct FaultRule {
+    pub path_prefix: String,
+    pub fault: Fault,
+    pub remaining: Option<usize>,
+}
+
+/// Takes the fault of the first rule matching the `path`, using up one of the
+/// rules remaining faults.
+pub(crate) fn take_fault(rules: &mut Vec<FaultRule>, path: &str) -> Option<Fault> {
+    let index = rules
+        .iter()
+        .position(|rule| path.starts_with(&rule.path_prefix))?;
+    let rule = &mut rules[index];
+    let fault = rule.fault;
+    if let Some(remaining) = rule.remaining.as_mut() {
+        *remaining -= 1;
+        if *remaining == 0 {
+            rules.remove(index);
+        }
+    }
+    Some(fault)
+}
@@ -0,0 +1,170 @@
+//! Loaders for the fixtures in the workspace `data` directory, and block
+//! header fixtures synthesized from a template header. The loaders panic when
+//! a fixture is missing or malformed, as the test can't run without it.
+use arweave_rs_indexes::{block_index_scraper::default_client, BlockIndex, Initialized};
+use arweave_rs_types::{
+    block_hash::compute_indep_hash, consensus::ConsensusParams, ArweaveBlockHeader,
+    NonceLimiterInfo, H384,
+};
+use color_eyre::eyre::{eyre, Result};
+use serde_json::Value;
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+
+/// The heights of the mainnet blocks in `data/blocks` the validation tests
+/// use, each stored along with its parent.
+pub mod cases {
+    pub const PACKING: u64 = 1287795;
+    pub const BLOCK1: u64 = 1309131;
+    pub const BLOCK2: u64 = 1309645;
+    pub const BLOCK3: u64 = 1309705;
+    pub const DIFF: u64 = 1315850;
+    pub const POA2: u64 = 1315909;
+    pub const NO_TX: u64 = 1315910;
+    pub const RESET2: u64 = 1325673;
+    pub const MAX_NONCE: u64 = 1337235;
+    /// A block whose `poa` doesn't validate.
+    pub const POA_FAILED: u64 = 1338015;
+    /// A block whose `poa` has a `tx_path` that doesn't validate.
+    pub const BAD_TX_PATH: u64 = 1338549;
+    pub const DOUBLE_SIGNING_PROOF: u64 = 1374310;
+}
+
+/// The `nonce_limiter_info` fixtures in `data` of consecutive mainnet blocks,
+/// named after the VDF reset they contain.
+pub mod vdf_cases {
+    pub const NO_RESET: &[&str] = &["1278893_no_reset", "1278894_no_reset"];
+    pub const RESET: &[&str] = &["1278899_reset", "1278900_reset"];
+    pub const RESET_FIRST_STEP: &[&str] = &["1132455_reset_first_step"];
+    pub const RESET_LAST_STEP: &[&str] = &["1276407_reset_last_step", "1276408_reset_last_step"];
+    pub const RESET_2ND_TO_LAST_STEP: &[&str] = &[
+        "1277324_reset_2nd_to_last_step",
+        "1277325_reset_2nd_to_last_step",
+    ];
+    pub const RESET_3RD_TO_LAST_STEP: &[&str] = &["1276736_reset_3rd_to_last_step"];
+}
+
+/// The workspace `data` directory, test binaries run in their own crates
+/// directory.
+pub fn data_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("../data")
+}
+
+pub fn block_header_json(height: u64) -> Value {
+    let path = data_dir().join(format!("blocks/{height}.json"));
+    let json = std::fs::read_to_string(&path)
+        .unwrap_or_else(|err| panic!("failed to read {}: {err}", path.display()));
+    serde_json::from_str(&json).expect("valid json for ArweaveBlockHeader")
+}
+
+pub fn block_header(height: u64) -> ArweaveBlockHeader {
+    serde_json::from_value(block_header_json(height)).expect("valid json for ArweaveBlockHeader")
+}
+
+/// The header of the block at `height` and the header of its parent.
+pub fn block_header_pair(height: u64) -> (ArweaveBlockHeader, ArweaveBlockHeader) {
+    (block_header(height), block_header(height - 1))
+}
+
+/// Loads the `nonce_limiter_info` fixtures of a [`vdf_cases`] case.
+pub fn nonce_limiter_infos(case: &[&str]) -> Vec<NonceLimiterInfo> {
+    case.iter()
+        .map(|name| {
+            let path = data_dir().join(format!("{name}.json"));
+            let json = std::fs::read_to_string(&path)
+                .unwrap_or_else(|err| panic!("failed to read {}: {err}", path.display()));
+            serde_json::from_str(&json).expect("valid json for NonceLimiterInfo")
+        })
+        .collect()
+}
+
+/// Decodes the base64url encoded fixture at `path`, relative to `data`.
+pub fn encoded_bytes(path: &str) -> Vec<u8> {
+    let path = data_dir().join(path);
+    let encoded = std::fs::read_to_string(&path)
+        .unwrap_or_else(|err| panic!("failed to read {}: {err}", path.display()));
+    base64_url::decode(encoded.trim()).expect("valid base64url")
+}
+
+/// The mainnet block index, loaded from `data/index.dat` and updated from
+/// the default peers. Initializing it from scratch needs the network.
+pub fn mainnet_block_index() -> BlockIndex<Initialized> {
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    runtime
+        .block_on(BlockIndex::new().init_at(data_dir().join("index.dat"), &default_client()))
+        .expect("the mainnet block index, from data/index.dat or the network")
+}
+
+pub fn empty_block_index() -> BlockIndex<Initialized> {
+    BlockIndex::new().init_from_items(vec![])
+}
+
+/// Loads every `*.json` block header in `dir`, such as `data/blocks`, ordered
+/// by height.
+pub fn load_headers(dir: impl AsRef<Path>) -> Result<Vec<Value>> {
+    let mut headers = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.extension().is_some_and(|ext| ext == "json") {
+            let header: Value = serde_json::from_str(&std::fs::read_to_string(&path)?)?;
+            if height(&header).is_none() || indep_hash(&header).is_none() {
+                return Err(eyre!("{} is not a block header", path.display()));
+            }
+            headers.push(header);
+        }
+    }
+    headers.sort_by_key(height);
+    Ok(headers)
+}
+
+pub fn height(header: &Value) -> Option<u64> {
+    header.get("height")?.as_u64()
+}
+
+/// The base64url encoded `indep_hash` of the header.
+pub fn indep_hash(header: &Value) -> Option<&str> {
+    header.get("indep_hash")?.as_str()
+}
+
+/// The `previous_block` of a synthetic chain without a `parent`.
+fn unknown_parent(height: u64) -> H384 {
+    let mut bytes = [0xab; 48];
+    bytes[..8].copy_from_slice(&height.to_be_bytes());
+    H384(bytes)
+}
+
+/// Builds a chain of headers at the `heights` from copies of `template`, each
+/// linked to the header below it. The first header is linked to `parent`, or
+/// to a header that isn't part of any chain, so a fork is built with another
+/// `branch` and the header it forks from as the `parent`.
+///
+/// Only the `height`, `indep_hash`, `previous_block` and `weave_size` differ
+/// from the template, the `indep_hash` is computed from the other fields so
+/// each `branch` has its own hashes. The headers deserialize but don't pass
+/// validation.
+pub fn synthetic_chain(
+    template: &Value,
+    heights: RangeInclusive<u64>,
+    branch: u8,
+    parent: Option<&Value>,
+) -> Vec<Value> {
+    let params = ConsensusParams::mainnet();
+    let mut previous_block = match parent.and_then(indep_hash) {
+        Some(parent_hash) => parent_hash.to_string(),
+        None => base64_url::encode(&unknown_parent(heights.start().saturating_sub(1))),
+    };
+    heights
+        .map(|height| {
+            let mut header = template.clone();
+            header["height"] = height.into();
+            header["previous_block"] = previous_block.clone().into();
+            // Forks weigh a little more than the blocks they replace
+            header["weave_size"] = (height * 1000 + branch as u64).to_string().into();
+            let parsed: ArweaveBlockHeader = serde_json::from_value(header.clone())
+                .expect("the template deserializes as a block header");
+            previous_block = base64_url::encode(&compute_indep_hash(&parsed, &params));
+            header["indep_hash"] = previous_block.clone().into();
+            header
+        })
+        .collect()
+}
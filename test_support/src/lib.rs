@@ -0,0 +1,24 @@
+//! Support for the arweave_rs tests: loaders for the fixtures in `data`, a
+//! mock Arweave peer, a devnet fixture, and a guard for running the heavily
+//! parallelized tests one at a time.
+use std::sync::{Mutex, MutexGuard};
+
+pub mod devnet;
+pub mod fault;
+pub mod fixtures;
+pub mod mock_peer;
+
+pub use devnet::{devnet_fixture, DevnetFixture};
+pub use fault::Fault;
+pub use mock_peer::MockPeer;
+
+static SERIAL: Mutex<()> = Mutex::new(());
+
+/// Holds off the other tests of the test binary that take the guard until it
+/// is dropped. cargo runs tests on parallel threads, but the VDF, RandomX and
+/// mining tests already use every core and slow each other down when run
+/// together.
+pub fn serial() -> MutexGuard<'static, ()> {
+    // A failing test poisons the lock, which doesn't affect the other tests
+    SERIAL.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
@@ -0,0 +1,213 @@
+//! An in process mock Arweave peer for testing the networked components
+//! offline. It serves block headers, the block index and chunks from fixture
+//! data, and injects faults such as rate limits, timeouts and truncated JSON
+//! into its responses. The chain it serves can be advanced and reorged while
+//! it runs.
+use crate::fault::{Fault, FaultRule};
+use crate::fixtures::{self, height, indep_hash};
+use arweave_rs_client::ChunkJson;
+use color_eyre::eyre::{eyre, Result};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+mod server;
+
+/// The chain, chunks and faults a `MockPeer` serves.
+#[derive(Default)]
+pub(crate) struct PeerState {
+    /// The headers of the served chain.
+    by_height: BTreeMap<u64, Value>,
+    /// Every header ever inserted, including those reorged out of the chain.
+    by_hash: HashMap<String, Value>,
+    /// Headers above the tip aren't served.
+    tip: Option<u64>,
+    /// Chunks by the weave offset they start at.
+    chunks: BTreeMap<u64, ChunkJson>,
+    data_sync_record: Option<Vec<(u64, u64)>>,
+    faults: Vec<FaultRule>,
+    /// The paths requested, in order.
+    requests: Vec<String>,
+}
+
+impl PeerState {
+    fn header_by_height(&self, height: u64) -> Option<&Value> {
+        match self.tip {
+            Some(tip) if height <= tip => self.by_height.get(&height),
+            _ => None,
+        }
+    }
+
+    fn tip_header(&self) -> Option<&Value> {
+        self.by_height.get(&self.tip?)
+    }
+
+    /// The chunk containing the weave byte, chunks are requested by the
+    /// `(start, end]` offset of a byte within them.
+    fn chunk_containing(&self, byte: u64) -> Option<&ChunkJson> {
+        let (start, chunk) = self.chunks.range(..byte).next_back()?;
+        (byte <= start + chunk.chunk.0.len() as u64).then_some(chunk)
+    }
+
+    /// The `[start, end)` ranges of the chunks, unless the record was set.
+    fn sync_record(&self) -> Vec<(u64, u64)> {
+        if let Some(sync_record) = &self.data_sync_record {
+            return sync_record.clone();
+        }
+        let mut intervals: Vec<(u64, u64)> = Vec::new();
+        for (start, chunk) in &self.chunks {
+            let end = start + chunk.chunk.0.len() as u64;
+            match intervals.last_mut() {
+                Some(last) if last.1 == *start => last.1 = end,
+                _ => intervals.push((*start, end)),
+            }
+        }
+        intervals
+    }
+}
+
+/// A mock Arweave peer listening on a local port. It's stopped when dropped.
+pub struct MockPeer {
+    url: String,
+    addr: SocketAddr,
+    state: Arc<Mutex<PeerState>>,
+    shutdown: Arc<AtomicBool>,
+}
+
+impl MockPeer {
+    /// Starts a peer without any blocks or chunks.
+    pub fn start() -> Result<Self> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let addr = listener.local_addr()?;
+        let state = Arc::new(Mutex::new(PeerState::default()));
+        let shutdown = Arc::new(AtomicBool::new(false));
+        let (server_state, server_shutdown) = (state.clone(), shutdown.clone());
+        std::thread::spawn(move || server::serve(listener, server_state, server_shutdown));
+        Ok(MockPeer {
+            url: format!("http://{addr}"),
+            addr,
+            state,
+            shutdown,
+        })
+    }
+
+    /// Starts a peer serving the block headers in `dir`, see
+    /// [`fixtures::load_headers`].
+    pub fn from_dir(dir: impl AsRef<Path>) -> Result<Self> {
+        let peer = MockPeer::start()?;
+        peer.insert_headers(fixtures::load_headers(dir)?)?;
+        Ok(peer)
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Adds the `headers` to the chain, replacing those at the same heights,
+    /// and moves the tip to the highest header.
+    pub fn insert_headers(&self, headers: impl IntoIterator<Item = Value>) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        for header in headers {
+            let (Some(height), Some(hash)) = (height(&header), indep_hash(&header)) else {
+                return Err(eyre!("a block header needs a height and an indep_hash"));
+            };
+            state.by_hash.insert(hash.to_string(), header.clone());
+            state.by_height.insert(height, header);
+        }
+        state.tip = state.by_height.keys().next_back().copied();
+        Ok(())
+    }
+
+    /// Replaces the chain from the height of the first `fork` header with the
+    /// `fork`, moving the tip to its last header. The replaced headers can
+    /// still be requested by hash, like the orphaned blocks of a real peer.
+    pub fn reorg(&self, fork: Vec<Value>) -> Result<()> {
+        let fork_height = fork
+            .first()
+            .and_then(height)
+            .ok_or_else(|| eyre!("the fork needs at least one block header"))?;
+        self.state.lock().unwrap().by_height.split_off(&fork_height);
+        self.insert_headers(fork)
+    }
+
+    /// The height of the peers current block.
+    pub fn tip(&self) -> Option<u64> {
+        self.state.lock().unwrap().tip
+    }
+
+    /// Moves the tip to `height`, headers above it aren't served until the
+    /// tip is moved past them, as if they were mined later.
+    pub fn set_tip(&self, height: u64) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        if !state.by_height.contains_key(&height) {
+            return Err(eyre!("there is no block header at height {height}"));
+        }
+        state.tip = Some(height);
+        Ok(())
+    }
+
+    /// Serves the `chunk` starting at the weave `offset`.
+    pub fn insert_chunk(&self, offset: u64, chunk: ChunkJson) {
+        self.state.lock().unwrap().chunks.insert(offset, chunk);
+    }
+
+    /// Overrides the `/data_sync_record`, which otherwise lists the ranges of
+    /// the inserted chunks.
+    pub fn set_data_sync_record(&self, intervals: Vec<(u64, u64)>) {
+        self.state.lock().unwrap().data_sync_record = Some(intervals);
+    }
+
+    /// Injects the `fault` into the next `times` responses to paths starting
+    /// with `path_prefix`. Faults injected earlier are used up first.
+    pub fn inject(&self, path_prefix: &str, fault: Fault, times: usize) {
+        if times == 0 {
+            return;
+        }
+        self.add_fault(path_prefix, fault, Some(times));
+    }
+
+    /// Injects the `fault` into every response to paths starting with
+    /// `path_prefix`, until the faults are cleared.
+    pub fn inject_always(&self, path_prefix: &str, fault: Fault) {
+        self.add_fault(path_prefix, fault, None);
+    }
+
+    pub fn clear_faults(&self) {
+        self.state.lock().unwrap().faults.clear();
+    }
+
+    fn add_fault(&self, path_prefix: &str, fault: Fault, remaining: Option<usize>) {
+        self.state.lock().unwrap().faults.push(FaultRule {
+            path_prefix: path_prefix.to_string(),
+            fault,
+            remaining,
+        });
+    }
+
+    /// The paths requested so far, in order.
+    pub fn requests(&self) -> Vec<String> {
+        self.state.lock().unwrap().requests.clone()
+    }
+
+    /// The number of requests for paths starting with `path_prefix`.
+    pub fn num_requests(&self, path_prefix: &str) -> usize {
+        self.state
+            .lock()
+            .unwrap()
+            .requests
+            .iter()
+            .filter(|path| path.starts_with(path_prefix))
+            .count()
+    }
+}
+
+impl Drop for MockPeer {
+    fn drop(&mut self) {
+        self.shutdown.store(true, Ordering::SeqCst);
+        // Wake the accept loop so it sees the shutdown
+        let _ = TcpStream::connect(self.addr);
+    }
+}
@@ -0,0 +1,192 @@
+//! The HTTP side of the mock peer, a thread per connection answering each
+//! request from the shared `PeerState`.
+use crate::fault::{take_fault, Fault};
+use crate::fixtures::indep_hash;
+use super::PeerState;
+use serde_json::{json, Value};
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+pub(crate) struct Response {
+    status: u16,
+    headers: Vec<(&'static str, String)>,
+    body: String,
+}
+
+impl Response {
+    fn json(body: &Value) -> Self {
+        Response {
+            status: 200,
+            headers: vec![("Content-Type", "application/json".to_string())],
+            body: body.to_string(),
+        }
+    }
+
+    fn status(status: u16) -> Self {
+        Response {
+            status,
+            headers: Vec::new(),
+            body: String::new(),
+        }
+    }
+
+    fn to_http(&self) -> String {
+        let reason = match self.status {
+            200 => "OK",
+            404 => "Not Found",
+            429 => "Too Many Requests",
+            500 => "Internal Server Error",
+            502 => "Bad Gateway",
+            503 => "Service Unavailable",
+            _ => "Unknown",
+        };
+        let headers: String = self
+            .headers
+            .iter()
+            .map(|(name, value)| format!("{name}: {value}\r\n"))
+            .collect();
+        format!(
+            "HTTP/1.1 {} {reason}\r\n{headers}Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+            self.status,
+            self.body.len(),
+            self.body
+        )
+    }
+}
+
+/// Accepts connections until `shutdown` is set.
+pub(crate) fn serve(listener: TcpListener, state: Arc<Mutex<PeerState>>, shutdown: Arc<AtomicBool>) {
+    for stream in listener.incoming() {
+        if shutdown.load(Ordering::SeqCst) {
+            break;
+        }
+        let Ok(stream) = stream else { continue };
+        let state = state.clone();
+        std::thread::spawn(move || handle_connection(stream, &state));
+    }
+}
+
+fn handle_connection(mut stream: TcpStream, state: &Mutex<PeerState>) {
+    let Ok(reader_stream) = stream.try_clone() else {
+        return;
+    };
+    let mut reader = BufReader::new(reader_stream);
+    let mut request_line = String::new();
+    if reader.read_line(&mut request_line).is_err() {
+        return;
+    }
+    // Skip the request headers
+    let mut line = String::new();
+    while reader.read_line(&mut line).is_ok() && line != "\r\n" && !line.is_empty() {
+        line.clear();
+    }
+    let path = request_line.split_whitespace().nth(1).unwrap_or_default();
+
+    let fault = {
+        let mut state = state.lock().unwrap();
+        state.requests.push(path.to_string());
+        take_fault(&mut state.faults, path)
+    };
+    let response = match fault {
+        Some(Fault::RateLimit { retry_after }) => {
+            let mut response = Response::status(429);
+            if let Some(secs) = retry_after {
+                response.headers.push(("Retry-After", secs.to_string()));
+            }
+            response
+        }
+        Some(Fault::Status(status)) => Response::status(status),
+        Some(Fault::Disconnect) => return,
+        Some(Fault::Delay(delay)) => {
+            std::thread::sleep(delay);
+            route(&state.lock().unwrap(), path)
+        }
+        Some(Fault::TruncatedJson) => {
+            let mut response = route(&state.lock().unwrap(), path);
+            let half = response.body.len() / 2;
+            response.body.truncate(half);
+            response
+        }
+        None => route(&state.lock().unwrap(), path),
+    };
+    // The client may have given up on a delayed response
+    let _ = stream.write_all(response.to_http().as_bytes());
+}
+
+fn route(state: &PeerState, path: &str) -> Response {
+    let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+    let response = match segments.as_slice() {
+        ["info"] => Some(info(state)),
+        ["block", "current"] => state.tip_header().cloned(),
+        ["block", "height", height] => height
+            .parse()
+            .ok()
+            .and_then(|height| state.header_by_height(height))
+            .cloned(),
+        ["block", "hash", hash] => state.by_hash.get(*hash).cloned(),
+        ["block_index", start, end] => match (start.parse(), end.parse()) {
+            (Ok(start), Ok(end)) => block_index(state, start, end),
+            _ => None,
+        },
+        ["chunk", byte] => byte
+            .parse()
+            .ok()
+            .and_then(|byte| state.chunk_containing(byte))
+            .map(|chunk| serde_json::to_value(chunk).unwrap()),
+        ["data_sync_record"] => Some(data_sync_record(state)),
+        _ => None,
+    };
+    match response {
+        Some(body) => Response::json(&body),
+        None => Response::status(404),
+    }
+}
+
+fn info(state: &PeerState) -> Value {
+    let tip = state.tip_header();
+    let height = state.tip.unwrap_or_default();
+    json!({
+        "network": "arweave.N.1",
+        "version": 5,
+        "release": 69,
+        "height": height,
+        "current": tip.and_then(indep_hash).unwrap_or_default(),
+        "blocks": tip.map_or(0, |_| height + 1),
+        "peers": 0,
+        "queue_length": 0,
+        "node_state_latency": 0,
+    })
+}
+
+/// The block index entries of the heights `start..=end`, most recent first,
+/// in the `x-block-format: 1` format.
+fn block_index(state: &PeerState, start: u64, end: u64) -> Option<Value> {
+    let entries = (start..=end)
+        .rev()
+        .map(|height| {
+            let header = state.header_by_height(height)?;
+            let weave_size = match &header["weave_size"] {
+                Value::String(weave_size) => weave_size.clone(),
+                weave_size => weave_size.to_string(),
+            };
+            Some(json!({
+                "tx_root": header["tx_root"].as_str().unwrap_or_default(),
+                "weave_size": weave_size,
+                "hash": indep_hash(header)?,
+            }))
+        })
+        .collect::<Option<Vec<Value>>>()?;
+    Some(Value::Array(entries))
+}
+
+/// The sync record as a list of `{end: start}` objects.
+fn data_sync_record(state: &PeerState) -> Value {
+    let intervals = state
+        .sync_record()
+        .into_iter()
+        .map(|(start, end)| json!({ end.to_string(): start.to_string() }))
+        .collect();
+    Value::Array(intervals)
+}
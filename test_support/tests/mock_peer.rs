@@ -0,0 +1,145 @@
+use arweave_rs_client::{backoff::Backoff, ArweaveClient, ClientConfig};
+use arweave_rs_indexes::BlockIndex;
+use arweave_rs_test_support::{fixtures::*, Fault, MockPeer};
+use arweave_rs_types::ArweaveBlockHeader;
+use serde_json::Value;
+use std::time::Duration;
+
+fn config() -> ClientConfig {
+    ClientConfig {
+        backoff: Backoff {
+            base: Duration::from_millis(10),
+            max: Duration::from_millis(50),
+        },
+        timeout: Duration::from_millis(500),
+        ..Default::default()
+    }
+}
+
+/// A peer serving a synthetic chain 0..=60 built from a fixture header.
+fn synthetic_peer() -> (MockPeer, Vec<Value>) {
+    let chain = synthetic_chain(&block_header_json(cases::POA2), 0..=60, 0, None);
+    let peer = MockPeer::start().unwrap();
+    peer.insert_headers(chain.clone()).unwrap();
+    (peer, chain)
+}
+
+fn header(chain: &[Value], height: u64) -> ArweaveBlockHeader {
+    serde_json::from_value(chain[height as usize].clone()).unwrap()
+}
+
+#[test]
+fn endpoints_serve_the_chain() {
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let (peer, chain) = synthetic_peer();
+    let client = ArweaveClient::with_config([peer.url()], config()).unwrap();
+
+    let info = runtime.block_on(client.info()).unwrap();
+    assert_eq!(info.height, 60);
+    assert_eq!(info.current, header(&chain, 60).indep_hash);
+    let current = runtime.block_on(client.current_block()).unwrap();
+    assert_eq!(current.indep_hash, header(&chain, 60).indep_hash);
+    assert_eq!(current.previous_block, header(&chain, 59).indep_hash);
+    let index = runtime.block_on(client.block_index(10, 12)).unwrap();
+    assert_eq!(index.len(), 3);
+    assert_eq!(index[0].hash, base64_url::encode(&header(&chain, 12).indep_hash));
+    assert_eq!(index[0].weave_size, "12000");
+    assert!(runtime.block_on(client.block_by_height(100)).is_err());
+}
+
+#[test]
+fn block_index_initializes_from_the_peer() {
+    // The block index initializes from the peer and then offline from disk,
+    // despite being rate limited
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let (peer, chain) = synthetic_peer();
+    let client = ArweaveClient::with_config([peer.url()], config()).unwrap();
+    let index_dir = tempfile::tempdir().unwrap();
+    let index_path = index_dir.path().join("index.dat");
+
+    peer.inject("/block_index", Fault::RateLimit { retry_after: None }, 2);
+    let block_index = runtime
+        .block_on(BlockIndex::new().init_at(&index_path, &client))
+        .unwrap();
+    assert!(block_index.num_indexes() >= 40);
+    for index in 0..block_index.num_indexes() as usize {
+        assert_eq!(
+            block_index.get_item(index).unwrap().block_hash,
+            header(&chain, index as u64 + 1).indep_hash
+        );
+    }
+
+    let offline_client = ArweaveClient::with_config(["http://127.0.0.1:1"], config()).unwrap();
+    let offline_index = runtime
+        .block_on(BlockIndex::new().init_at(&index_path, &offline_client))
+        .unwrap();
+    assert!(offline_index.num_indexes() >= 40);
+}
+
+#[test]
+fn faults_are_injected() {
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let (peer, _) = synthetic_peer();
+    let client = ArweaveClient::with_config([peer.url()], config()).unwrap();
+
+    // Faults are injected the given number of times and then retried
+    peer.inject("/block/height/5", Fault::TruncatedJson, 1);
+    peer.inject("/block/height/6", Fault::Status(503), 2);
+    peer.inject("/block/height/7", Fault::Disconnect, 1);
+    let headers = runtime.block_on(client.blocks_by_height(5..=7)).unwrap();
+    assert_eq!(headers[2].height, 7);
+    assert_eq!(peer.num_requests("/block/height/5"), 2);
+    assert_eq!(peer.num_requests("/block/height/6"), 3);
+    assert_eq!(peer.num_requests("/block/height/7"), 2);
+
+    let strict_config = ClientConfig {
+        max_attempts: 1,
+        ..config()
+    };
+    let strict_client = ArweaveClient::with_config([peer.url()], strict_config).unwrap();
+    peer.inject("/block/height/8", Fault::Delay(Duration::from_secs(2)), 1);
+    assert!(runtime.block_on(strict_client.block_by_height(8)).is_err());
+    peer.inject_always("/block/height/9", Fault::TruncatedJson);
+    assert!(runtime.block_on(strict_client.block_by_height(9)).is_err());
+    peer.clear_faults();
+    assert!(runtime.block_on(strict_client.block_by_height(9)).is_ok());
+}
+
+#[test]
+fn reorgs_replace_the_tip() {
+    // A fork from height 58 replaces the tip, orphaned blocks are still
+    // served by hash
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let (peer, chain) = synthetic_peer();
+    let client = ArweaveClient::with_config([peer.url()], config()).unwrap();
+    let fork = synthetic_chain(&block_header_json(cases::POA2), 58..=62, 1, Some(&chain[57]));
+    peer.reorg(fork.clone()).unwrap();
+
+    let reorged = runtime.block_on(client.current_block()).unwrap();
+    assert_eq!(reorged.height, 62);
+    assert_eq!(reorged.indep_hash, header(&fork, 4).indep_hash);
+    let fork_base = runtime.block_on(client.block_by_height(58)).unwrap();
+    assert_eq!(fork_base.previous_block, header(&chain, 57).indep_hash);
+    let orphan = runtime
+        .block_on(client.block_by_hash(&header(&chain, 59).indep_hash))
+        .unwrap();
+    assert_eq!(orphan.height, 59);
+
+    peer.set_tip(60).unwrap();
+    let info = runtime.block_on(client.info()).unwrap();
+    assert_eq!(info.current, header(&fork, 2).indep_hash);
+    assert!(peer.set_tip(63).is_err());
+}
+
+#[test]
+fn fixtures_are_served() {
+    // The block fixtures on disk are served as they are
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let peer = MockPeer::from_dir(data_dir().join("blocks")).unwrap();
+    let client = ArweaveClient::new([peer.url()]).unwrap();
+    let expected = block_header(cases::POA2);
+    let header = runtime.block_on(client.block_by_height(cases::POA2)).unwrap();
+    assert_eq!(header.indep_hash, expected.indep_hash);
+    let current = runtime.block_on(client.current_block()).unwrap();
+    assert_eq!(current.height, peer.tip().unwrap());
+}
@@ -0,0 +1,252 @@
+//! Computes the `indep_hash` of a block header from the header fields and
+//! its signature (ar_block:generate_signed_hash and ar_block:indep_hash).
+use crate::{consensus::*, *};
+use openssl::sha;
+
+/// The `indep_hash` of the block, the `SHA-384` hash of its signed hash and
+/// `signature`.
+pub fn compute_indep_hash(block_header: &ArweaveBlockHeader, params: &ConsensusParams) -> H384 {
+    let mut hasher = sha::Sha384::new();
+    hasher.update(&compute_signed_hash(block_header, params));
+    hasher.update(block_header.signature.as_slice());
+    H384(hasher.finish())
+}
+
+trait DoubleSigningProofBytes {
+    fn bytes(&self) -> Vec<u8>;
+}
+
+impl DoubleSigningProofBytes for DoubleSigningProof {
+    fn bytes(&self) -> Vec<u8> {
+        // If no DoubleSigningProof is provided, return a 0 byte
+        if self.pub_key.is_none() {
+            return vec![0];
+        }
+
+        let mut buff: Vec<u8> = Vec::new();
+
+        // If a DoubleSigningProof exists, the first byte should be 1
+        buff.extend_raw_buf(1, &[1])
+            .extend_optional_raw_buf(512, &self.pub_key)
+            .extend_optional_raw_buf(512, &self.sig1)
+            .extend_big(2, &self.cdiff1.unwrap_or_default())
+            .extend_big(2, &self.prev_cdiff1.unwrap_or_default())
+            .extend_raw_buf(64, self.preimage1.unwrap_or_default().as_bytes())
+            .extend_optional_raw_buf(512, &self.sig2)
+            .extend_big(2, &self.cdiff2.unwrap_or_default())
+            .extend_big(2, &self.prev_cdiff2.unwrap_or_default())
+            .extend_raw_buf(64, self.preimage2.unwrap_or_default().as_bytes());
+        buff
+    }
+}
+
+/// The extend_raw_* functions do not prepend any kind of size bytes to the
+/// bytes they append. The other extend_<type> functions append bigEndian size
+/// bytes before appending the bytes of <type>.
+trait ExtendBytes {
+    fn extend_raw_buf(&mut self, raw_size: usize, val: &[u8]) -> &mut Self;
+    fn extend_optional_raw_buf(&mut self, raw_size: usize, val: &Option<Base64>) -> &mut Self;
+    fn extend_raw_big(&mut self, raw_size: usize, val: &U256) -> &mut Self;
+    fn extend_u64(&mut self, size_bytes: usize, val: &u64) -> &mut Self;
+    fn extend_big(&mut self, size_bytes: usize, val: &U256) -> &mut Self;
+    fn extend_optional_big(&mut self, size_bytes: usize, val: &Option<U256>) -> &mut Self;
+    fn extend_optional_hash(&mut self, size_bytes: usize, val: &Option<H256>) -> &mut Self;
+    fn extend_buf(&mut self, size_bytes: usize, val: &[u8]) -> &mut Self;
+    fn extend_buf_list(&mut self, size_bytes: usize, val: &[Base64]) -> &mut Self;
+    fn extend_hash_list(&mut self, val: &[H256]) -> &mut Self;
+    fn trim_leading_zero_bytes(slice: &[u8]) -> &[u8] {
+        let mut non_zero_index = slice.iter().position(|&x| x != 0).unwrap_or(slice.len());
+        non_zero_index = std::cmp::min(non_zero_index, slice.len() - 1);
+        &slice[non_zero_index..]
+    }
+}
+
+impl ExtendBytes for Vec<u8> {
+    /// Extends a Vec<u8> by [raw_size] amount of bytes by copying the last
+    /// [raw_size] bytes from [val] and appending them to the Vec<u8>
+    fn extend_raw_buf(&mut self, raw_size: usize, val: &[u8]) -> &mut Self {
+        let mut bytes = vec![0u8; raw_size];
+
+        // Calculate the start position in 'val' to copy from
+        let start = if val.len() > raw_size {
+            val.len() - raw_size
+        } else {
+            0
+        };
+
+        // Copy the last 'buf_size' bytes of 'val' into 'bytes'
+        let insert = raw_size.saturating_sub(val.len());
+        bytes[insert..].copy_from_slice(&val[start..]);
+
+        // Extend 'self' with 'bytes'
+        self.extend_from_slice(&bytes);
+        self
+    }
+
+    fn extend_optional_raw_buf(&mut self, raw_size: usize, val: &Option<Base64>) -> &mut Self {
+        let mut bytes: Vec<u8> = Vec::new();
+        if let Some(val_bytes) = val {
+            bytes.extend_from_slice(val_bytes.as_slice());
+        }
+        self.extend_raw_buf(raw_size, &bytes)
+    }
+
+    fn extend_raw_big(&mut self, raw_size: usize, val: &U256) -> &mut Self {
+        let mut bytes = [0u8; 32];
+        val.to_big_endian(&mut bytes);
+        self.extend_raw_buf(raw_size, &bytes)
+    }
+
+    fn extend_u64(&mut self, num_size_bytes: usize, val: &u64) -> &mut Self {
+        let bytes = &val.to_be_bytes();
+        let bytes = Self::trim_leading_zero_bytes(bytes);
+        let num_val_bytes = bytes.len();
+        let size_bytes = num_val_bytes.to_be_bytes();
+        let start = size_bytes.len().saturating_sub(num_size_bytes);
+        self.extend_from_slice(&Vec::from(&size_bytes[start..]));
+        self.extend_from_slice(bytes);
+        self
+    }
+
+    fn extend_big(&mut self, num_size_bytes: usize, val: &U256) -> &mut Self {
+        let mut be_bytes = [0u8; 32];
+        val.to_big_endian(&mut be_bytes);
+        let bytes = Self::trim_leading_zero_bytes(&be_bytes);
+        let num_val_bytes = bytes.len();
+        let size_bytes = num_val_bytes.to_be_bytes();
+        let start = size_bytes.len().saturating_sub(num_size_bytes);
+        self.extend_from_slice(&Vec::from(&size_bytes[start..]));
+        self.extend_from_slice(bytes);
+        self
+    }
+
+    fn extend_optional_big(&mut self, size_bytes: usize, val: &Option<U256>) -> &mut Self {
+        if let Some(big_int) = val {
+            self.extend_big(size_bytes, big_int)
+        } else {
+            // This will append the correct number of size_bytes to store a size of 0
+            self.extend_buf(size_bytes, &[])
+        }
+    }
+
+    fn extend_buf(&mut self, num_size_bytes: usize, val: &[u8]) -> &mut Self {
+        let bytes = val;
+        let num_val_bytes = bytes.len();
+        let size_bytes = num_val_bytes.to_be_bytes();
+        let start = size_bytes.len().saturating_sub(num_size_bytes);
+        self.extend_from_slice(&Vec::from(&size_bytes[start..]));
+        self.extend_from_slice(bytes);
+        self
+    }
+
+    fn extend_optional_hash(&mut self, size_bytes: usize, val: &Option<H256>) -> &mut Self {
+        let mut bytes: Vec<u8> = Vec::new();
+        if let Some(val_bytes) = val {
+            bytes.extend_from_slice(&val_bytes[..]);
+        }
+        self.extend_buf(size_bytes, &bytes)
+    }
+
+    fn extend_buf_list(&mut self, size_bytes: usize, data: &[Base64]) -> &mut Self {
+        // Number of elements in the list, as 2 bytes
+        let num_elements = data.len() as u16;
+        self.extend_from_slice(&num_elements.to_be_bytes());
+        // Iterate over each element in the data vector
+        for elem in data.iter().rev() {
+            self.extend_buf(size_bytes, elem.as_slice());
+        }
+        self
+    }
+
+    fn extend_hash_list(&mut self, data: &[H256]) -> &mut Self {
+        // Number of hashes in the list, as 2 bytes
+        let num_elements = data.len() as u16;
+        self.extend_from_slice(&num_elements.to_be_bytes());
+        // Iterate over each hash in the data vector and append it
+        for elem in data.iter() {
+            self.extend_from_slice(elem.as_bytes());
+        }
+        self
+    }
+}
+
+/// Computes the `SHA-256` hash of all of the block header fields except the
+/// `signature` and `indep_hash` (ar_block:generate_signed_hash). The miner
+/// signs it and the `indep_hash` is derived from it and the `signature`.
+pub fn compute_signed_hash(block_header: &ArweaveBlockHeader, params: &ConsensusParams) -> [u8; 32] {
+    let b = block_header;
+    let nonce_info = &b.nonce_limiter_info;
+    let mut diff_bytes: [u8; 32] = Default::default();
+    b.diff.to_big_endian(&mut diff_bytes);
+
+    let proof_bytes = b.double_signing_proof.bytes();
+
+    let mut buff: Vec<u8> = Vec::new();
+    buff.extend_buf(1, b.previous_block.as_bytes())
+        .extend_u64(1, &b.timestamp)
+        .extend_u64(2, &b.nonce.0)
+        .extend_u64(1, &b.height)
+        .extend_buf(2, &diff_bytes)
+        .extend_big(2, &b.cumulative_diff)
+        .extend_u64(1, &b.last_retarget)
+        .extend_buf(1, b.hash.as_bytes())
+        .extend_u64(2, &b.block_size)
+        .extend_u64(2, &b.weave_size)
+        .extend_buf(1, b.reward_addr.as_bytes())
+        .extend_optional_hash(1, &b.tx_root)
+        .extend_buf(1, b.wallet_list.as_bytes())
+        .extend_buf(1, b.hash_list_merkle.as_bytes())
+        .extend_u64(1, &b.reward_pool)
+        .extend_u64(1, &b.packing_2_5_threshold)
+        .extend_u64(1, &b.strict_data_split_threshold)
+        .extend_u64(1, &b.usd_to_ar_rate[0])
+        .extend_u64(1, &b.usd_to_ar_rate[1])
+        .extend_u64(1, &b.scheduled_usd_to_ar_rate[0])
+        .extend_u64(1, &b.scheduled_usd_to_ar_rate[1])
+        .extend_buf_list(2, &b.tags.0)
+        .extend_buf_list(1, &b.txs.0)
+        .extend_u64(1, &b.reward)
+        .extend_u64(2, &b.recall_byte)
+        .extend_buf(1, b.hash_preimage.as_bytes())
+        .extend_optional_big(2, &b.recall_byte2)
+        .extend_buf(2, b.reward_key.as_slice())
+        .extend_u64(1, &b.partition_number)
+        .extend_raw_buf(32, nonce_info.output.as_bytes())
+        .extend_raw_buf(8, &nonce_info.global_step_number.to_be_bytes())
+        .extend_raw_buf(48, nonce_info.seed.as_bytes())
+        .extend_raw_buf(48, nonce_info.next_seed.as_bytes())
+        .extend_raw_buf(32, &nonce_info.zone_upper_bound.to_be_bytes())
+        .extend_raw_buf(32, &nonce_info.next_zone_upper_bound.to_be_bytes())
+        .extend_buf(1, b.nonce_limiter_info.prev_output.as_bytes())
+        .extend_hash_list(&b.nonce_limiter_info.checkpoints.0)
+        .extend_hash_list(&b.nonce_limiter_info.last_step_checkpoints.0)
+        .extend_buf(1, b.previous_solution_hash.as_bytes())
+        .extend_big(1, &b.price_per_gib_minute)
+        .extend_big(1, &b.scheduled_price_per_gib_minute)
+        .extend_raw_buf(32, b.reward_history_hash.as_bytes())
+        .extend_big(1, &b.debt_supply)
+        .extend_raw_big(3, &b.kryder_plus_rate_multiplier)
+        .extend_raw_big(1, &b.kryder_plus_rate_multiplier_latch)
+        .extend_raw_big(3, &b.denomination)
+        .extend_u64(1, &b.redenomination_height)
+        .extend_raw_buf(proof_bytes.len(), &proof_bytes)
+        .extend_big(2, &b.previous_cumulative_diff);
+
+    // 2.6 blocks end with the previous_cumulative_diff
+    if params.fork_at_height(b.height) >= ConsensusFork::Fork2_7 {
+        buff.extend_big(2, &b.merkle_rebase_support_threshold)
+            .extend_buf(3, b.poa.data_path.as_slice())
+            .extend_buf(3, b.poa.tx_path.as_slice())
+            .extend_buf(3, b.poa2.data_path.as_slice())
+            .extend_buf(3, b.poa2.tx_path.as_slice())
+            .extend_raw_buf(32, b.chunk_hash.as_bytes())
+            .extend_optional_hash(1, &b.chunk2_hash)
+            .extend_raw_buf(32, b.block_time_history_hash.as_bytes())
+            .extend_u64(1, &nonce_info.vdf_difficulty.unwrap_or_default())
+            .extend_u64(1, &nonce_info.next_vdf_difficulty.unwrap_or_default());
+    }
+
+    let mut hasher = sha::Sha256::new();
+    hasher.update(&buff);
+    hasher.finish()
+}
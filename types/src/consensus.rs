@@ -17,8 +17,247 @@ pub const RANDOMX_ENTROPY_SIZE: usize = 256 * 1024; //256KiB
 
 pub const FORK_2_5_HEIGHT: u64 = 812970;
 pub const FORK_2_6_HEIGHT: u64 = 1132210;
+pub const FORK_2_6_8_HEIGHT: u64 = 1189560;
 pub const FORK_2_7_HEIGHT: u64 = 1275480;
 
+/// The consensus rule sets of the protocol, each active from its fork height
+/// until the next fork.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ConsensusFork {
+    /// Blocks before the 2.5 fork.
+    Pre2_5,
+    /// SPoRA mining with 2.5 packing.
+    Fork2_5,
+    /// Partitioned mining with the VDF (nonce limiter) and 2.6 packing.
+    Fork2_6,
+    /// Adds chunk hashes, merkle rebases, proof size limits and the
+    /// block_time_history.
+    Fork2_7,
+}
+
+impl ConsensusFork {
+    /// Returns the consensus rules that apply to the block at `height` on the
+    /// network configured by `params`.
+    pub fn at_height(height: u64, params: &ConsensusParams) -> Self {
+        if height >= params.fork_2_7_height {
+            ConsensusFork::Fork2_7
+        } else if height >= params.fork_2_6_height {
+            ConsensusFork::Fork2_6
+        } else if height >= params.fork_2_5_height {
+            ConsensusFork::Fork2_5
+        } else {
+            ConsensusFork::Pre2_5
+        }
+    }
+}
+
+/// The consensus parameters that differ between networks. The `pub const`
+/// values in this module are the mainnet parameters, [`ConsensusParams`] lets
+/// the validator, the VDF verifiers and the seed data and recall range
+/// computations run against a testnet or a local network instead.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConsensusParams {
+    pub fork_2_5_height: u64,
+    pub fork_2_6_height: u64,
+    /// Starts the transition of the price per GiB minute away from
+    /// [`PRICE_PER_GIB_MINUTE_PRE_TRANSITION`], it doesn't change the header
+    /// format or the mining rules so it has no [`ConsensusFork`] variant.
+    pub fork_2_6_8_height: u64,
+    pub fork_2_7_height: u64,
+    /// The number of bytes of the weave a mining partition covers.
+    pub partition_size: u64,
+    /// The number of bytes a miner reads from a partition for each VDF step.
+    pub recall_range_size: u32,
+    /// The number of VDF steps between the nonce limiter entropy resets.
+    pub nonce_limiter_reset_frequency: usize,
+    /// The number of SHA-256 iterations of a VDF step when the block doesn't
+    /// specify a `vdf_difficulty` (the 2.6 rules).
+    pub vdf_sha_1s: u64,
+    /// The number of blocks between difficulty retargets.
+    pub retarget_blocks: u64,
+    /// The weave offset after which chunks are padded to 256 KiB buckets.
+    pub strict_data_split_threshold: u128,
+}
+
+impl Default for ConsensusParams {
+    fn default() -> Self {
+        ConsensusParams::mainnet()
+    }
+}
+
+impl ConsensusParams {
+    /// The parameters of the Arweave mainnet.
+    pub const fn mainnet() -> Self {
+        ConsensusParams {
+            fork_2_5_height: FORK_2_5_HEIGHT,
+            fork_2_6_height: FORK_2_6_HEIGHT,
+            fork_2_6_8_height: FORK_2_6_8_HEIGHT,
+            fork_2_7_height: FORK_2_7_HEIGHT,
+            partition_size: PARTITION_SIZE,
+            recall_range_size: RECALL_RANGE_SIZE,
+            nonce_limiter_reset_frequency: NONCE_LIMITER_RESET_FREQUENCY,
+            vdf_sha_1s: VDF_SHA_1S,
+            retarget_blocks: RETARGET_BLOCKS,
+            strict_data_split_threshold: STRICT_DATA_SPLIT_THRESHOLD,
+        }
+    }
+
+    /// The parameters of a test network started with the current (2.7)
+    /// consensus rules active from its genesis block. Mining and VDF
+    /// parameters match the mainnet.
+    pub const fn testnet() -> Self {
+        ConsensusParams {
+            fork_2_5_height: 0,
+            fork_2_6_height: 0,
+            fork_2_6_8_height: 0,
+            fork_2_7_height: 0,
+            strict_data_split_threshold: 0,
+            ..ConsensusParams::mainnet()
+        }
+    }
+
+    /// Returns a [`ConsensusParamsBuilder`] starting from the mainnet
+    /// parameters, use [`ConsensusParamsBuilder::from_params()`] to start from
+    /// another preset.
+    pub fn builder() -> ConsensusParamsBuilder {
+        ConsensusParamsBuilder::from_params(ConsensusParams::mainnet())
+    }
+
+    /// The consensus rules of the block at `height`.
+    pub fn fork_at_height(&self, height: u64) -> ConsensusFork {
+        ConsensusFork::at_height(height, self)
+    }
+
+    /// The number of nonces (chunks) in a recall range.
+    pub fn max_nonce(&self) -> u32 {
+        self.recall_range_size / DATA_CHUNK_SIZE
+    }
+
+    /// The `(lower, upper)` bounds of the time between two retargets within
+    /// which the difficulty is left unchanged.
+    pub fn retarget_tolerance_bounds(&self) -> (u64, u64) {
+        let target_time = self.retarget_blocks * TARGET_TIME;
+        (target_time - TARGET_TIME, target_time + TARGET_TIME)
+    }
+}
+
+/// Builds the [`ConsensusParams`] of a custom (local) network, e.g. with small
+/// partitions and frequent VDF resets for tests.
+#[derive(Debug, Clone)]
+pub struct ConsensusParamsBuilder {
+    params: ConsensusParams,
+}
+
+impl ConsensusParamsBuilder {
+    pub fn from_params(params: ConsensusParams) -> Self {
+        ConsensusParamsBuilder { params }
+    }
+
+    /// Sets all fork heights, activating the rules of every fork up to 2.7 at
+    /// `height`.
+    pub fn fork_heights(mut self, height: u64) -> Self {
+        self.params.fork_2_5_height = height;
+        self.params.fork_2_6_height = height;
+        self.params.fork_2_6_8_height = height;
+        self.params.fork_2_7_height = height;
+        self
+    }
+
+    pub fn fork_2_5_height(mut self, height: u64) -> Self {
+        self.params.fork_2_5_height = height;
+        self
+    }
+
+    pub fn fork_2_6_height(mut self, height: u64) -> Self {
+        self.params.fork_2_6_height = height;
+        self
+    }
+
+    pub fn fork_2_6_8_height(mut self, height: u64) -> Self {
+        self.params.fork_2_6_8_height = height;
+        self
+    }
+
+    pub fn fork_2_7_height(mut self, height: u64) -> Self {
+        self.params.fork_2_7_height = height;
+        self
+    }
+
+    pub fn partition_size(mut self, partition_size: u64) -> Self {
+        self.params.partition_size = partition_size;
+        self
+    }
+
+    pub fn recall_range_size(mut self, recall_range_size: u32) -> Self {
+        self.params.recall_range_size = recall_range_size;
+        self
+    }
+
+    pub fn nonce_limiter_reset_frequency(mut self, reset_frequency: usize) -> Self {
+        self.params.nonce_limiter_reset_frequency = reset_frequency;
+        self
+    }
+
+    pub fn vdf_sha_1s(mut self, iterations: u64) -> Self {
+        self.params.vdf_sha_1s = iterations;
+        self
+    }
+
+    pub fn retarget_blocks(mut self, retarget_blocks: u64) -> Self {
+        self.params.retarget_blocks = retarget_blocks;
+        self
+    }
+
+    pub fn strict_data_split_threshold(mut self, threshold: u128) -> Self {
+        self.params.strict_data_split_threshold = threshold;
+        self
+    }
+
+    /// Checks the parameters are consistent with each other and returns them.
+    pub fn build(self) -> eyre::Result<ConsensusParams> {
+        let p = self.params;
+        if !(p.fork_2_5_height <= p.fork_2_6_height
+            && p.fork_2_6_height <= p.fork_2_6_8_height
+            && p.fork_2_6_8_height <= p.fork_2_7_height)
+        {
+            return Err(eyre::eyre!(
+                "fork heights must be ascending, 2.5: {} 2.6: {} 2.6.8: {} 2.7: {}",
+                p.fork_2_5_height,
+                p.fork_2_6_height,
+                p.fork_2_6_8_height,
+                p.fork_2_7_height
+            ));
+        }
+        if p.recall_range_size == 0 || p.recall_range_size % DATA_CHUNK_SIZE != 0 {
+            return Err(eyre::eyre!(
+                "recall_range_size {} is not a multiple of the chunk size {}",
+                p.recall_range_size,
+                DATA_CHUNK_SIZE
+            ));
+        }
+        if p.partition_size < p.recall_range_size as u64 {
+            return Err(eyre::eyre!(
+                "partition_size {} is smaller than the recall_range_size {}",
+                p.partition_size,
+                p.recall_range_size
+            ));
+        }
+        if p.nonce_limiter_reset_frequency == 0 {
+            return Err(eyre::eyre!("nonce_limiter_reset_frequency must be positive"));
+        }
+        if p.vdf_sha_1s < NUM_CHECKPOINTS_IN_VDF_STEP as u64 {
+            return Err(eyre::eyre!(
+                "vdf_sha_1s {} is less than one iteration per checkpoint",
+                p.vdf_sha_1s
+            ));
+        }
+        if p.retarget_blocks == 0 {
+            return Err(eyre::eyre!("retarget_blocks must be positive"));
+        }
+        Ok(p)
+    }
+}
+
 pub const MIN_SPORA_DIFFICULTY: u64 = 2;
 
 pub const RETARGET_BLOCKS: u64 = 10;
@@ -26,6 +265,44 @@ pub const TARGET_TIME: u64 = 120;
 pub const RETARGET_TOLERANCE_UPPER_BOUND: u64 = (TARGET_TIME * RETARGET_BLOCKS) + TARGET_TIME;
 pub const RETARGET_TOLERANCE_LOWER_BOUND: u64 = (TARGET_TIME * RETARGET_BLOCKS) - TARGET_TIME;
 
+// Pricing and endowment constants (ar_pricing.hrl)
+pub const WINSTON_PER_AR: u64 = 1_000_000_000_000;
+pub const GENESIS_TOKENS: u64 = 55_000_000;
+pub const GIB: u64 = 1024 * 1024 * 1024;
+
+// The number of blocks between updates of the scheduled price per GiB minute.
+pub const PRICE_ADJUSTMENT_FREQUENCY: u64 = 50;
+
+// The price per GiB minute set at the 2.6 fork height.
+pub const PRICE_PER_GIB_MINUTE_PRE_TRANSITION: u64 = 8162;
+
+// The number of replicas of the weave the endowment pays miners to store.
+pub const N_REPLICATIONS: u64 = 20;
+
+// The share {Dividend, Divisor} of the transaction fees paid to the miner, the
+// rest goes to the endowment pool.
+pub const MINER_FEE_SHARE: (u64, u64) = (1, 21);
+
+// The endowment pool size above which the Kryder+ rate multiplier latch is
+// reset (in AR, redenominated according to the denomination at the time).
+pub const RESET_KRYDER_PLUS_LATCH_THRESHOLD: u64 = 10_000_000 * WINSTON_PER_AR;
+
+// Every redenomination multiplies Winston amounts by this value.
+pub const REDENOMINATION_MULTIPLIER: u64 = 1000;
+
+// The number of recent blocks whose rewards and hash rates are kept in the
+// reward history, used to estimate the price per GiB minute.
+pub const REWARD_HISTORY_BLOCKS: usize = 3 * 30 * 24 * 30;
+
+pub const BLOCKS_PER_YEAR: u64 = 525600 / (TARGET_TIME / 60);
+
+// ln(2) as a {Dividend, Divisor} fraction.
+pub const LN2: (u64, u64) = (6931471805599453, 10000000000000000);
+
+// The highest power of the Taylor series used to compute the natural exponent
+// in the inflation calculation.
+pub const INFLATION_NATURAL_EXPONENT_PRECISION: u32 = 24;
+
 pub const JOIN_CLOCK_TOLERANCE: u64 = 15;
 pub const CLOCK_DRIFT_MAX: u64 = 5;
 
@@ -37,13 +314,13 @@ pub const STRICT_DATA_SPLIT_THRESHOLD: u128 = 30607159107830;
 pub const NONCE_LIMITER_RESET_FREQUENCY: usize = 10 * 120;
 
 // 25 checkpoints 40 ms each = 1000 ms
-pub static NUM_CHECKPOINTS_IN_VDF_STEP: usize = 25;
+pub const NUM_CHECKPOINTS_IN_VDF_STEP: usize = 25;
 
 // Typical ryzen 5900X iterations for 1 sec
-pub static VDF_SHA_1S: u64 = 15_000_000;
+pub const VDF_SHA_1S: u64 = 15_000_000;
 
 // 90% of 4 TB.
-pub static PARTITION_SIZE: u64 = 3600000000000;
+pub const PARTITION_SIZE: u64 = 3600000000000;
 
 // The size of a recall range. The first range is randomly chosen from the given
 // mining partition. The second range is chosen from the entire weave.
@@ -89,11 +366,17 @@ pub fn get_chunk_entropy_input(
 
 /// Return the smallest multiple of 256 KiB counting from StrictDataSplitThreshold
 /// bigger than or equal to Offset.
-pub fn get_byte_offset(offset: U256, block_start_offset: u128, block_end_offset: u128) -> u128 {
-    if block_end_offset >= STRICT_DATA_SPLIT_THRESHOLD {
+pub fn get_byte_offset(
+    offset: U256,
+    block_start_offset: u128,
+    block_end_offset: u128,
+    params: &ConsensusParams,
+) -> u128 {
+    let strict_data_split_threshold = params.strict_data_split_threshold;
+    if block_end_offset >= strict_data_split_threshold {
         let new_offset = offset.as_u128() + 1;
-        let diff = new_offset - STRICT_DATA_SPLIT_THRESHOLD;
-        STRICT_DATA_SPLIT_THRESHOLD
+        let diff = new_offset - strict_data_split_threshold;
+        strict_data_split_threshold
             + ((diff - 1) / DATA_CHUNK_SIZE as u128 + 1) * DATA_CHUNK_SIZE as u128
             - DATA_CHUNK_SIZE as u128
             - block_start_offset
@@ -102,6 +385,34 @@ pub fn get_byte_offset(offset: U256, block_start_offset: u128, block_end_offset:
     }
 }
 
+/// Return the smallest multiple of 256 KiB counting from
+/// `strict_data_split_threshold` bigger than or equal to `offset`
+/// (ar_poa:get_padded_offset).
+///
+/// Only the offsets past the threshold are padded, any other offset is
+/// returned unchanged.
+pub fn get_padded_offset(offset: u64, strict_data_split_threshold: u64) -> u64 {
+    if offset <= strict_data_split_threshold {
+        return offset;
+    }
+    let diff = offset - strict_data_split_threshold;
+    strict_data_split_threshold
+        + ((diff - 1) / DATA_CHUNK_SIZE as u64 + 1) * DATA_CHUNK_SIZE as u64
+}
+
+/// Returns the number of bytes a transaction with `data_size` bytes of data
+/// adds to the weave. Since the 2.5 fork the data of every transaction is
+/// padded to a multiple of 256 KiB so its chunks fit into 256 KiB buckets.
+pub fn get_weave_size_increase(data_size: u64, height: u64, params: &ConsensusParams) -> u64 {
+    if data_size == 0 {
+        0
+    } else if height >= params.fork_2_5_height {
+        get_padded_offset(data_size, 0)
+    } else {
+        data_size
+    }
+}
+
 /// Generate a chunk ID used to construct the Merkle tree from the tx data chunks.
 pub fn generate_chunk_id(chunk: &[u8]) -> [u8; 32] {
     let mut hasher = sha::Sha256::new();
@@ -111,8 +422,8 @@ pub fn generate_chunk_id(chunk: &[u8]) -> [u8; 32] {
 
 /// Takes the `global_step_number` and calculates how many steps previous an
 /// entropy reset would have happened, returning the steps since a reset.
-pub fn get_vdf_steps_since_reset(global_step_number: u64) -> usize {
-    let reset_interval = NONCE_LIMITER_RESET_FREQUENCY as f64;
+pub fn get_vdf_steps_since_reset(global_step_number: u64, params: &ConsensusParams) -> usize {
+    let reset_interval = params.nonce_limiter_reset_frequency as f64;
     let num_vdf_resets = global_step_number as f64 / reset_interval;
     let remainder: f64 = num_vdf_resets.fract(); // Capture right of the decimal
     (remainder * reset_interval).round() as usize
@@ -133,35 +444,45 @@ pub struct SeedData {
 /// Mining Note: `next_vdf_difficulty` is not part of the seed data as it is computed
 /// using the `block_time_history` - which is a heavier operation handled separate
 /// from the (quick) seed data retrieval.
-pub fn get_seed_data(step_number: u64, previous_block: &ArweaveBlockHeader) -> SeedData {
+///
+/// Returns an error if `step_number` isn't past the `global_step_number` of
+/// the `previous_block`.
+pub fn get_seed_data(
+    step_number: u64,
+    previous_block: &ArweaveBlockHeader,
+    params: &ConsensusParams,
+) -> eyre::Result<SeedData> {
     let previous_info = &previous_block.nonce_limiter_info;
 
-    assert!(step_number > previous_info.global_step_number);
+    if step_number <= previous_info.global_step_number {
+        return Err(eyre::eyre!(
+            "step {step_number} is not past the previous blocks step {}",
+            previous_info.global_step_number
+        ));
+    }
 
-    let steps_since_reset = get_vdf_steps_since_reset(step_number) as u64;
+    let steps_since_reset = get_vdf_steps_since_reset(step_number, params) as u64;
     let steps_this_block = step_number - previous_info.global_step_number;
 
-    // println!("\nsteps_since_reset: {}\nsteps_this_block: {}", steps_since_reset, steps_this_block);
-
     // Was the entropy reset step crossed during this block
     if steps_this_block > steps_since_reset {
         // If so, the seed data should be the next_seed from the previous block
-        SeedData {
+        Ok(SeedData {
             seed: previous_info.next_seed,
             next_seed: previous_block.indep_hash,
             partition_upper_bound: previous_info.next_zone_upper_bound,
             next_partition_upper_bound: previous_block.weave_size,
-            vdf_difficulty: previous_info.next_vdf_difficulty.unwrap_or(VDF_SHA_1S),
-        }
+            vdf_difficulty: previous_info.next_vdf_difficulty.unwrap_or(params.vdf_sha_1s),
+        })
     } else {
         //...if not, just preserve the current seed data from the previous block
-        SeedData {
+        Ok(SeedData {
             seed: previous_info.seed,
             next_seed: previous_info.next_seed,
             partition_upper_bound: previous_info.zone_upper_bound,
             next_partition_upper_bound: previous_info.next_zone_upper_bound,
-            vdf_difficulty: previous_info.vdf_difficulty.unwrap_or(VDF_SHA_1S),
-        }
+            vdf_difficulty: previous_info.vdf_difficulty.unwrap_or(params.vdf_sha_1s),
+        })
     }
 }
 
@@ -209,20 +530,57 @@ pub fn compute_mining_hash(
     hash_array
 }
 
+/// Computes the one chunk solution of the first recall range chunk, returning
+/// the solution hash H1 and its preimage `SHA-256(H0 || nonce || chunk1)`,
+/// where `chunk1` is the packed chunk and the nonce is a 64 bit big-endian
+/// integer (ar_block:compute_h1).
+pub fn compute_h1(mining_hash: &[u8; 32], nonce: u64, chunk: &[u8]) -> ([u8; 32], H256) {
+    let mut hasher = sha::Sha256::new();
+    hasher.update(mining_hash);
+    hasher.update(&nonce.to_be_bytes());
+    hasher.update(chunk);
+    let preimage = H256(hasher.finish());
+    (compute_solution_hash(mining_hash, &preimage), preimage)
+}
+
+/// Computes the two chunk solution that adds the second recall range chunk,
+/// returning the solution hash H2 and its preimage `SHA-256(H1 || chunk2)`.
+/// `h1` is the [`compute_h1()`] solution hash, not its preimage
+/// (ar_block:compute_h2).
+pub fn compute_h2(h1: &[u8; 32], chunk: &[u8], mining_hash: &[u8; 32]) -> ([u8; 32], H256) {
+    let mut hasher = sha::Sha256::new();
+    hasher.update(h1);
+    hasher.update(chunk);
+    let preimage = H256(hasher.finish());
+    (compute_solution_hash(mining_hash, &preimage), preimage)
+}
+
+/// Combines the `mining_hash` (H0) with a `hash_preimage` (H1 or H2) into the
+/// solution hash compared against the difficulty (ar_block:compute_solution_h).
+pub fn compute_solution_hash(mining_hash: &[u8; 32], hash_preimage: &H256) -> [u8; 32] {
+    let mut hasher = sha::Sha256::new();
+    hasher.update(mining_hash);
+    hasher.update(hash_preimage.as_bytes());
+    hasher.finish()
+}
+
 /// Referenced from (ar_block.erl). Return `{RecallRange1Start, RecallRange2Start}`
 ///  - the start offsets of the two recall ranges.
 pub fn get_recall_range(
     mining_hash: &[u8; 32],
     partition_number: u64,
     partition_upper_bound: u64,
+    params: &ConsensusParams,
 ) -> (U256, U256) {
+    let partition_size = params.partition_size;
+
     // Decode the first 8 bytes of the mining_hash to an unsigned integer (big-endian)
     let recall_range1_offset =
         u64::from_be_bytes(mining_hash.get(0..8).unwrap_or(&[0; 8]).try_into().unwrap());
 
     // Calculate RecallRange1Start
-    let recall_range1_start = partition_number * PARTITION_SIZE
-        + recall_range1_offset % std::cmp::min(PARTITION_SIZE, partition_upper_bound);
+    let recall_range1_start = partition_number * partition_size
+        + recall_range1_offset % std::cmp::min(partition_size, partition_upper_bound);
 
     // Decode the entire mining_hash to an unsigned integer (big-endian)
     let recall_range2_start = U256::from_big_endian(mining_hash) % U256::from(partition_upper_bound);
@@ -0,0 +1,51 @@
+//! Implements the `deep_hash` algorithm used by Arweave to hash nested lists of
+//! binaries (ar_deep_hash.erl).
+//!
+//! Each element is tagged with its type (`blob` or `list`) and its length
+//! before being hashed. This makes the hash of a structure unambiguous, so two
+//! different nestings of the same bytes can never produce the same hash.
+use openssl::sha;
+
+/// A node in the nested structure passed to [`deep_hash()`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum DeepHashItem {
+    Blob(Vec<u8>),
+    List(Vec<DeepHashItem>),
+}
+
+impl DeepHashItem {
+    /// Convenience constructor for a blob from any byte slice.
+    pub fn from_bytes(bytes: &[u8]) -> Self {
+        DeepHashItem::Blob(bytes.to_vec())
+    }
+}
+
+/// Computes the `SHA-384` based deep hash of a [`DeepHashItem`].
+pub fn deep_hash(item: &DeepHashItem) -> [u8; 48] {
+    match item {
+        DeepHashItem::Blob(bytes) => {
+            let tag = format!("blob{}", bytes.len());
+            let mut hasher = sha::Sha384::new();
+            hasher.update(&sha384(tag.as_bytes()));
+            hasher.update(&sha384(bytes));
+            hasher.finish()
+        }
+        DeepHashItem::List(items) => {
+            let tag = format!("list{}", items.len());
+            // Fold each of the list elements into an accumulator hash that
+            // starts out as the hash of the tag
+            items.iter().fold(sha384(tag.as_bytes()), |acc, item| {
+                let mut hasher = sha::Sha384::new();
+                hasher.update(&acc);
+                hasher.update(&deep_hash(item));
+                hasher.finish()
+            })
+        }
+    }
+}
+
+fn sha384(bytes: &[u8]) -> [u8; 48] {
+    let mut hasher = sha::Sha384::new();
+    hasher.update(bytes);
+    hasher.finish()
+}
@@ -8,13 +8,17 @@
 use eyre::Error;
 use fixed_hash::construct_fixed_hash;
 use serde::{de, de::Error as _, Deserialize, Deserializer, Serialize, Serializer};
-use serde_derive::Deserialize;
 use std::{ops::Index, slice::SliceIndex, str::FromStr};
 use uint::construct_uint;
 
 /// Decodes hashes from `base64_url` encoded strings
 pub mod decode;
+pub mod block_hash;
 pub mod consensus;
+pub mod deep_hash;
+pub mod pricing;
+pub mod reward_history;
+pub mod transaction;
 use self::decode::DecodeHash;
 
 #[derive(Clone, Debug, Default, Deserialize)]
@@ -24,7 +28,8 @@ pub struct ArweaveBlockHeader {
     #[serde(with = "stringify")]
     pub block_size: u64,
 
-    /// `SHA-256` hash of the block_time_history log.
+    /// `SHA-256` hash of the block_time_history log (added in 2.7).
+    #[serde(default)]
     pub block_time_history_hash: H256,
 
     /// If the block was produced with a `poa2` proof it will optionally include
@@ -32,7 +37,8 @@ pub struct ArweaveBlockHeader {
     #[serde(default, with = "optional_hash")]
     pub chunk2_hash: Option<H256>,
 
-    /// `SHA-256` hash of the first PoA chunks (unencoded) bytes.
+    /// `SHA-256` hash of the first PoA chunks (unencoded) bytes (added in 2.7).
+    #[serde(default)]
     pub chunk_hash: H256,
 
     /// The sum of the average number of hashes computed by the network to
@@ -95,7 +101,9 @@ pub struct ArweaveBlockHeader {
     /// Unix timestamp of the last difficulty adjustment
     pub last_retarget: u64,
 
-    /// Chunk index (weave offset) at which merkle_rebase_support is enabled.
+    /// Chunk index (weave offset) at which merkle_rebase_support is enabled
+    /// (added in 2.7).
+    #[serde(default)]
     pub merkle_rebase_support_threshold: U256,
 
     /// The nonce used to produce the blocks solution_hash.
@@ -357,14 +365,14 @@ mod optional_hash {
 
     use super::{decode::DecodeHash, H256};
 
-    #[allow(dead_code)]
     pub fn serialize<S>(value: &Option<H256>, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: Serializer,
     {
         match value {
             Some(hash_bytes) => serializer.serialize_str(&base64_url::encode(&hash_bytes.0)),
-            None => serializer.serialize_none(),
+            // Arweave JSON represents missing hashes as empty strings
+            None => serializer.serialize_str(""),
         }
     }
 
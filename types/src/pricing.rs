@@ -0,0 +1,289 @@
+//! Computes the block reward, the endowment pool and the price per GiB minute
+//! of a block from its previous block (ar_pricing). Since the 2.6 fork the
+//! miner is paid from the inflation and its share of the transaction fees,
+//! topping up from the endowment pool when they don't cover the expected cost
+//! of storing [`N_REPLICATIONS`] replicas of the weave for the block interval.
+use crate::{consensus::*, transaction::ArweaveTransaction, *};
+use openssl::bn::{BigNum, BigNumContext};
+
+/// The transaction fees of a block split into the share added to the
+/// endowment pool and the share paid to the miner.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TransactionFees {
+    pub endowment_fee_share: U256,
+    pub miner_fee_share: U256,
+}
+
+/// The fields of the block being produced (or validated) that the reward
+/// computation depends on.
+#[derive(Debug, Clone, Default)]
+pub struct MinerRewardArgs {
+    pub height: u64,
+    pub weave_size: u64,
+    pub price_per_gib_minute: U256,
+    pub denomination: U256,
+    /// The number of seconds the reward pays for. [`TARGET_TIME`] before the
+    /// 2.7 fork, the average block interval of the block_time_history after.
+    pub block_interval: u64,
+    pub fees: TransactionFees,
+}
+
+/// Return value of [`get_miner_reward_endowment_pool_debt_supply()`], the
+/// expected values of the matching block header fields.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MinerReward {
+    pub reward: U256,
+    pub reward_pool: U256,
+    pub debt_supply: U256,
+    pub kryder_plus_rate_multiplier: U256,
+    pub kryder_plus_rate_multiplier_latch: U256,
+}
+
+/// Converts an `amount` of Winston from `base_denomination` to
+/// `denomination`. A `base_denomination` of 0 means the amount is already
+/// denominated at the current denomination.
+pub fn redenominate(amount: U256, base_denomination: U256, denomination: U256) -> U256 {
+    if base_denomination.is_zero() || base_denomination >= denomination {
+        return amount;
+    }
+    let steps = (denomination - base_denomination).as_u64();
+    (0..steps).fold(amount, |amount, _| amount * REDENOMINATION_MULTIPLIER)
+}
+
+/// Splits the fees (`reward`) of the `txs` into the endowment pool and miner
+/// shares, see [`MINER_FEE_SHARE`].
+pub fn distribute_transaction_fees(
+    txs: &[ArweaveTransaction],
+    denomination: U256,
+) -> TransactionFees {
+    let (dividend, divisor) = MINER_FEE_SHARE;
+    txs.iter().fold(TransactionFees::default(), |fees, tx| {
+        let tx_fee = redenominate(tx.reward, tx.denomination, denomination);
+        let miner_fee = tx_fee * dividend / divisor;
+        TransactionFees {
+            endowment_fee_share: fees.endowment_fee_share + tx_fee - miner_fee,
+            miner_fee_share: fees.miner_fee_share + miner_fee,
+        }
+    })
+}
+
+/// The number of Winston minted by the block at `height` (ar_inflation). The
+/// inflation halves every year following
+/// `0.2 * GENESIS_TOKENS * ln(2) / BLOCKS_PER_YEAR * 2^(-height / BLOCKS_PER_YEAR)`
+/// and is computed with exact fractions so it matches the reference
+/// implementation to the Winston.
+pub fn get_inflation(height: u64) -> U256 {
+    compute_inflation(height).expect("bignum arithmetic to succeed")
+}
+
+fn compute_inflation(height: u64) -> eyre::Result<U256> {
+    let mut ctx = BigNumContext::new()?;
+    let (ln2_dividend, ln2_divisor) = LN2;
+
+    // e ^ (height * ln(2) / BLOCKS_PER_YEAR)
+    let mut dividend = BigNum::from_dec_str(&height.to_string())?;
+    let ln2 = BigNum::from_dec_str(&ln2_dividend.to_string())?;
+    dividend = mul(&dividend, &ln2, &mut ctx)?;
+    let mut divisor = BigNum::from_dec_str(&BLOCKS_PER_YEAR.to_string())?;
+    divisor = mul(
+        &divisor,
+        &BigNum::from_dec_str(&ln2_divisor.to_string())?,
+        &mut ctx,
+    )?;
+    let (ex_dividend, ex_divisor) = natural_exponent(
+        &dividend,
+        &divisor,
+        INFLATION_NATURAL_EXPONENT_PRECISION,
+        &mut ctx,
+    )?;
+
+    // GENESIS_TOKENS * WINSTON_PER_AR * 2 * ln(2) / (10 * BLOCKS_PER_YEAR * e^x)
+    let mut numerator =
+        BigNum::from_dec_str(&(GENESIS_TOKENS as u128 * WINSTON_PER_AR as u128 * 2).to_string())?;
+    numerator = mul(&numerator, &ex_divisor, &mut ctx)?;
+    numerator = mul(&numerator, &ln2, &mut ctx)?;
+    let mut denominator =
+        BigNum::from_dec_str(&(10 * BLOCKS_PER_YEAR as u128 * ln2_divisor as u128).to_string())?;
+    denominator = mul(&denominator, &ex_dividend, &mut ctx)?;
+
+    let mut inflation = BigNum::new()?;
+    inflation.checked_div(&numerator, &denominator, &mut ctx)?;
+    Ok(U256::from_big_endian(&inflation.to_vec()))
+}
+
+/// Approximates `e ^ (dividend / divisor)` with the Taylor series up to the
+/// `precision`-th power, returning the exact sum as a `(dividend, divisor)`
+/// fraction (ar_fraction:natural_exponent).
+fn natural_exponent(
+    dividend: &BigNum,
+    divisor: &BigNum,
+    precision: u32,
+    ctx: &mut BigNumContext,
+) -> eyre::Result<(BigNum, BigNum)> {
+    // sum(x^i / i!) for i <= precision, brought to the common denominator
+    // divisor^precision * precision!
+    let mut sum = BigNum::new()?;
+    for i in 0..=precision {
+        let mut term = pow(dividend, i, ctx)?;
+        term = mul(&term, &pow(divisor, precision - i, ctx)?, ctx)?;
+        for k in (i + 1)..=precision {
+            term = mul(&term, &BigNum::from_u32(k)?, ctx)?;
+        }
+        let mut next = BigNum::new()?;
+        next.checked_add(&sum, &term)?;
+        sum = next;
+    }
+    let mut factorial = BigNum::from_u32(1)?;
+    for k in 1..=precision {
+        factorial = mul(&factorial, &BigNum::from_u32(k)?, ctx)?;
+    }
+    let common_divisor = mul(&pow(divisor, precision, ctx)?, &factorial, ctx)?;
+    Ok((sum, common_divisor))
+}
+
+fn mul(a: &BigNum, b: &BigNum, ctx: &mut BigNumContext) -> eyre::Result<BigNum> {
+    let mut result = BigNum::new()?;
+    result.checked_mul(a, b, ctx)?;
+    Ok(result)
+}
+
+fn pow(base: &BigNum, exponent: u32, ctx: &mut BigNumContext) -> eyre::Result<BigNum> {
+    let exponent = BigNum::from_u32(exponent)?;
+    let mut result = BigNum::new()?;
+    result.exp(base, &exponent, ctx)?;
+    Ok(result)
+}
+
+/// Computes the miner reward and the updated endowment pool, debt supply and
+/// Kryder+ rate multiplier (and latch) of a block following
+/// `previous_block_header` (ar_pricing:get_miner_reward_endowment_pool_debt_supply).
+///
+/// When the inflation and the miners fee share don't cover the expected
+/// storage cost the difference is taken from the endowment pool. Should the
+/// pool run dry the shortfall is minted as debt and the Kryder+ rate
+/// multiplier is doubled, once, until the latch is reset by the pool growing
+/// back over [`RESET_KRYDER_PLUS_LATCH_THRESHOLD`].
+pub fn get_miner_reward_endowment_pool_debt_supply(
+    previous_block_header: &ArweaveBlockHeader,
+    args: &MinerRewardArgs,
+) -> MinerReward {
+    let denomination = args.denomination;
+    let inflation = redenominate(get_inflation(args.height), U256::one(), denomination);
+    let expected_reward = U256::from(N_REPLICATIONS)
+        * args.weave_size
+        * args.block_interval
+        * args.price_per_gib_minute
+        / (60 * GIB);
+
+    let base_reward = inflation + args.fees.miner_fee_share;
+    let endowment_pool =
+        U256::from(previous_block_header.reward_pool) + args.fees.endowment_fee_share;
+    let debt_supply = previous_block_header.debt_supply;
+    let kryder_plus_rate_multiplier = previous_block_header.kryder_plus_rate_multiplier;
+    let kryder_plus_rate_multiplier_latch = previous_block_header.kryder_plus_rate_multiplier_latch;
+
+    if base_reward >= expected_reward {
+        return MinerReward {
+            reward: base_reward,
+            reward_pool: endowment_pool,
+            debt_supply,
+            kryder_plus_rate_multiplier,
+            kryder_plus_rate_multiplier_latch,
+        };
+    }
+
+    let take = expected_reward - base_reward;
+    let pool_exhausted = take > endowment_pool;
+    let (reward_pool, debt_supply) = if pool_exhausted {
+        (U256::zero(), debt_supply + take - endowment_pool)
+    } else {
+        (endowment_pool - take, debt_supply)
+    };
+
+    let (kryder_plus_rate_multiplier_latch, kryder_plus_rate_multiplier) =
+        match (pool_exhausted, kryder_plus_rate_multiplier_latch.is_zero()) {
+            (true, true) => (U256::one(), kryder_plus_rate_multiplier * 2),
+            (true, false) => (U256::one(), kryder_plus_rate_multiplier),
+            (false, _) => {
+                let threshold = redenominate(
+                    U256::from(RESET_KRYDER_PLUS_LATCH_THRESHOLD),
+                    U256::one(),
+                    denomination,
+                );
+                if reward_pool > threshold {
+                    (U256::zero(), kryder_plus_rate_multiplier)
+                } else {
+                    (
+                        kryder_plus_rate_multiplier_latch,
+                        kryder_plus_rate_multiplier,
+                    )
+                }
+            }
+        };
+
+    MinerReward {
+        reward: base_reward + take,
+        reward_pool,
+        debt_supply,
+        kryder_plus_rate_multiplier,
+        kryder_plus_rate_multiplier_latch,
+    }
+}
+
+/// Estimates the price of storing a GiB for a minute from the sums of the
+/// network hash rates and block rewards over a window of recent blocks
+/// (ar_pricing:get_price_per_gib_minute). The storage mined is estimated by
+/// dividing the hash rate by the number of solutions a single partition can
+/// produce per block. The returned price is at least 1 Winston.
+pub fn get_price_per_gib_minute(
+    hash_rate_total: U256,
+    reward_total: U256,
+    params: &ConsensusParams,
+) -> U256 {
+    // Each partition yields two recall ranges per VDF step (one per second)
+    let solutions_per_partition_per_second = 2 * params.max_nonce() as u64;
+    let solutions_per_partition_per_block = solutions_per_partition_per_second * TARGET_TIME;
+    let block_minutes = TARGET_TIME / 60;
+
+    let hash_rate_total = std::cmp::max(hash_rate_total, U256::one());
+    let reward_total = std::cmp::max(reward_total, U256::one());
+    let price = reward_total * solutions_per_partition_per_block * GIB
+        / (hash_rate_total * params.partition_size * block_minutes);
+    std::cmp::max(price, U256::one())
+}
+
+/// Returns the `(price_per_gib_minute, scheduled_price_per_gib_minute)` of the
+/// block following `previous_block_header`. Every
+/// [`PRICE_ADJUSTMENT_FREQUENCY`] blocks the scheduled price takes effect and
+/// a new price is scheduled from `estimate_price`, bounded to between half and
+/// double the price it replaces.
+pub fn recalculate_price_per_gib_minute<F>(
+    previous_block_header: &ArweaveBlockHeader,
+    params: &ConsensusParams,
+    estimate_price: F,
+) -> (U256, U256)
+where
+    F: FnOnce() -> U256,
+{
+    let height = previous_block_header.height + 1;
+    let price = previous_block_header.price_per_gib_minute;
+    let scheduled_price = previous_block_header.scheduled_price_per_gib_minute;
+
+    if height == params.fork_2_6_height {
+        let initial_price = U256::from(PRICE_PER_GIB_MINUTE_PRE_TRANSITION);
+        return (initial_price, initial_price);
+    }
+    if height % PRICE_ADJUSTMENT_FREQUENCY != 0 {
+        return (price, scheduled_price);
+    }
+
+    let (lower_bound, upper_bound) = scheduled_price_bounds(scheduled_price);
+    let new_price = estimate_price().clamp(lower_bound, upper_bound);
+    (scheduled_price, new_price)
+}
+
+/// The bounds a newly scheduled price has to stay within when it replaces
+/// `scheduled_price`.
+pub fn scheduled_price_bounds(scheduled_price: U256) -> (U256, U256) {
+    (scheduled_price / 2, scheduled_price * 2)
+}
@@ -0,0 +1,123 @@
+//! The reward history is the list of `{reward_addr, hash_rate, reward,
+//! denomination}` entries of the latest [`REWARD_HISTORY_BLOCKS`] blocks. The
+//! network hash rates and rewards it records are used to estimate the price
+//! per GiB minute, and block headers commit to it with the
+//! `reward_history_hash` (ar_rewards).
+use crate::{consensus::*, pricing::redenominate, *};
+use openssl::sha;
+
+/// The reward history entry of a single block.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RewardHistoryEntry {
+    pub reward_addr: H256,
+    /// The average number of hashes computed by the network to produce the
+    /// block, see [`get_hash_rate()`].
+    pub hash_rate: U256,
+    pub reward: U256,
+    pub denomination: U256,
+}
+
+impl RewardHistoryEntry {
+    /// Builds the reward history entry of `block_header`.
+    pub fn from_block_header(block_header: &ArweaveBlockHeader) -> Self {
+        RewardHistoryEntry {
+            reward_addr: block_header.reward_addr,
+            hash_rate: get_hash_rate(block_header.diff),
+            reward: U256::from(block_header.reward),
+            denomination: block_header.denomination,
+        }
+    }
+}
+
+/// The reward history entries of the latest blocks, most recent first.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RewardHistory(pub Vec<RewardHistoryEntry>);
+
+impl RewardHistory {
+    /// Adds the entry of `block_header` to the front of the history, dropping
+    /// the entries that fall out of the [`REWARD_HISTORY_BLOCKS`] window.
+    pub fn push_block(&mut self, block_header: &ArweaveBlockHeader) {
+        self.0
+            .insert(0, RewardHistoryEntry::from_block_header(block_header));
+        self.0.truncate(REWARD_HISTORY_BLOCKS);
+    }
+
+    /// Returns a copy of the history updated with `block_header`, the history
+    /// the block commits to with its `reward_history_hash`.
+    pub fn with_block(&self, block_header: &ArweaveBlockHeader) -> Self {
+        let mut reward_history = self.clone();
+        reward_history.push_block(block_header);
+        reward_history
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, RewardHistoryEntry> {
+        self.0.iter()
+    }
+
+    /// True if `reward_addr` mined one of the blocks in the history.
+    pub fn contains_reward_addr(&self, reward_addr: &H256) -> bool {
+        self.0.iter().any(|entry| entry.reward_addr == *reward_addr)
+    }
+
+    /// Sums the hash rates and the rewards (redenominated to `denomination`)
+    /// of the history, the inputs of
+    /// [`get_price_per_gib_minute()`](crate::pricing::get_price_per_gib_minute).
+    pub fn totals(&self, denomination: U256) -> (U256, U256) {
+        self.0.iter().fold(
+            (U256::zero(), U256::zero()),
+            |(hash_rate, reward), entry| {
+                (
+                    hash_rate + entry.hash_rate,
+                    reward + redenominate(entry.reward, entry.denomination, denomination),
+                )
+            },
+        )
+    }
+
+    /// Computes the `reward_history_hash` of the history. The entries are
+    /// serialized oldest first followed by the number of entries, as the
+    /// Erlang implementation accumulates them by prepending to an iolist.
+    pub fn hash(&self) -> H256 {
+        let entries = &self.0[..self.0.len().min(REWARD_HISTORY_BLOCKS)];
+
+        let mut hasher = sha::Sha256::new();
+        for entry in entries.iter().rev() {
+            hasher.update(entry.reward_addr.as_bytes());
+            hasher.update(&encode_int(entry.hash_rate));
+            hasher.update(&encode_int(entry.reward));
+            // The denomination is a 24 bit big-endian integer
+            let mut denomination = [0u8; 32];
+            entry.denomination.to_big_endian(&mut denomination);
+            hasher.update(&denomination[29..]);
+        }
+        hasher.update(&encode_int(U256::from(entries.len())));
+        H256(hasher.finish())
+    }
+}
+
+/// The average number of hashes the network computes to find a solution
+/// satisfying `diff`, the same increment added to the `cumulative_diff`
+/// (ar_difficulty:get_hash_rate).
+pub fn get_hash_rate(diff: U256) -> U256 {
+    let max_diff = U256::max_value();
+    max_diff / (max_diff - diff)
+}
+
+/// Serializes `value` as a one byte length followed by its minimal big-endian
+/// bytes (ar_serialize:encode_int/2 with an 8 bit size prefix).
+fn encode_int(value: U256) -> Vec<u8> {
+    let mut bytes = [0u8; 32];
+    value.to_big_endian(&mut bytes);
+    let start = bytes.iter().position(|&b| b != 0).unwrap_or(31);
+    let mut encoded = vec![(32 - start) as u8];
+    encoded.extend_from_slice(&bytes[start..]);
+    encoded
+}
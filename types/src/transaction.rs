@@ -0,0 +1,142 @@
+//! Run time types for Arweave transactions (including deserialization
+//! methods) and the consensus rules for computing their signature data.
+use crate::{
+    deep_hash::{deep_hash, DeepHashItem},
+    optional_hash, stringify, Base64, H256, U256,
+};
+use serde_derive::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+/// Stores deserialized fields from a JSON formatted Arweave transaction.
+pub struct ArweaveTransaction {
+    /// `1` for the original transaction format where the data is embedded in
+    /// the transaction. `2` for transactions whose data is uploaded separately
+    /// as chunks and committed to by the `data_root`.
+    pub format: u8,
+
+    /// The transaction identifier, the `SHA-256` hash of the `signature`.
+    pub id: H256,
+
+    /// The anchor of the transaction. Either the `id` of the last transaction
+    /// posted by the owner or the `indep_hash` of a recent block.
+    pub last_tx: Base64,
+
+    /// The RSA modulus (`n`) of the public key that signed the transaction.
+    /// The public exponent is always 65537.
+    pub owner: Base64,
+
+    /// A list of arbitrary name-value pairs. Names and values are binaries.
+    #[serde(default)]
+    pub tags: Vec<Tag>,
+
+    /// The address of the wallet receiving `quantity` Winstons, empty when
+    /// the transaction does not transfer any tokens.
+    #[serde(default)]
+    pub target: Base64,
+
+    /// The number of Winstons transferred to the `target`.
+    pub quantity: U256,
+
+    /// The transaction data. Always present for format 1 transactions,
+    /// usually empty for format 2 transactions.
+    #[serde(default)]
+    pub data: Base64,
+
+    /// The size of the transaction data in bytes.
+    #[serde(with = "stringify")]
+    pub data_size: u64,
+
+    /// The Merkle root of the tree of the transactions data chunks. Empty for
+    /// format 1 transactions and for transactions without data.
+    #[serde(default, with = "optional_hash")]
+    pub data_root: Option<H256>,
+
+    /// The fee in Winstons paid to the network for processing and storing
+    /// the transaction.
+    pub reward: U256,
+
+    /// The RSA-PSS signature of the transaction's signature data.
+    pub signature: Base64,
+
+    /// The denomination of `quantity` and `reward` (0 means the transaction
+    /// was created before redenomination support and is omitted in the JSON).
+    #[serde(default, skip_serializing_if = "U256::is_zero")]
+    pub denomination: U256,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+/// A name-value pair from the `tags` list of an [`ArweaveTransaction`].
+pub struct Tag {
+    pub name: Base64,
+    pub value: Base64,
+}
+
+impl ArweaveTransaction {
+    /// Returns the bytes signed by the owner of the transaction
+    /// (`ar_tx:signature_data_segment`).
+    ///
+    /// Format 2 transactions sign the `deep_hash` of a list of their fields,
+    /// format 1 transactions sign the concatenation of their fields.
+    pub fn signature_data(&self) -> Vec<u8> {
+        match self.format {
+            2 => self.signature_data_v2(),
+            _ => self.signature_data_v1(),
+        }
+    }
+
+    fn signature_data_v2(&self) -> Vec<u8> {
+        let tags = self
+            .tags
+            .iter()
+            .map(|tag| {
+                DeepHashItem::List(vec![
+                    DeepHashItem::from_bytes(tag.name.as_slice()),
+                    DeepHashItem::from_bytes(tag.value.as_slice()),
+                ])
+            })
+            .collect();
+
+        let data_root = self.data_root.map(|h| h.to_vec()).unwrap_or_default();
+
+        let mut items = vec![
+            DeepHashItem::from_bytes(self.format.to_string().as_bytes()),
+            DeepHashItem::from_bytes(self.owner.as_slice()),
+            DeepHashItem::from_bytes(self.target.as_slice()),
+            DeepHashItem::from_bytes(self.quantity.to_string().as_bytes()),
+            DeepHashItem::from_bytes(self.reward.to_string().as_bytes()),
+            DeepHashItem::from_bytes(self.last_tx.as_slice()),
+            DeepHashItem::List(tags),
+            DeepHashItem::from_bytes(self.data_size.to_string().as_bytes()),
+            DeepHashItem::Blob(data_root),
+        ];
+
+        // The denomination is only prepended once it has been set
+        if !self.denomination.is_zero() {
+            let denomination = self.denomination.to_string();
+            items.insert(0, DeepHashItem::from_bytes(denomination.as_bytes()));
+        }
+
+        deep_hash(&DeepHashItem::List(items)).to_vec()
+    }
+
+    fn signature_data_v1(&self) -> Vec<u8> {
+        let mut buff: Vec<u8> = Vec::new();
+
+        // The denomination is only prepended once it has been set
+        if !self.denomination.is_zero() {
+            buff.extend_from_slice(self.denomination.to_string().as_bytes());
+        }
+
+        buff.extend_from_slice(self.owner.as_slice());
+        buff.extend_from_slice(self.target.as_slice());
+        buff.extend_from_slice(self.data.as_slice());
+        buff.extend_from_slice(self.quantity.to_string().as_bytes());
+        buff.extend_from_slice(self.reward.to_string().as_bytes());
+        buff.extend_from_slice(self.last_tx.as_slice());
+        for tag in self.tags.iter() {
+            buff.extend_from_slice(tag.name.as_slice());
+            buff.extend_from_slice(tag.value.as_slice());
+        }
+        buff
+    }
+}
@@ -0,0 +1,92 @@
+use arweave_rs_types::{consensus::*, U256};
+
+const MAINNET: ConsensusParams = ConsensusParams::mainnet();
+
+/// A local network with small partitions and frequent VDF resets.
+fn localnet() -> ConsensusParams {
+    ConsensusParams::builder()
+        .fork_heights(0)
+        .partition_size(10 * RECALL_RANGE_SIZE as u64)
+        .nonce_limiter_reset_frequency(10)
+        .strict_data_split_threshold(0)
+        .build()
+        .unwrap()
+}
+
+#[test]
+fn presets() {
+    assert!(ConsensusParams::default() == MAINNET);
+    assert_eq!(
+        ConsensusParams::testnet().fork_at_height(0),
+        ConsensusFork::Fork2_7
+    );
+    assert_eq!(localnet().max_nonce(), MAINNET.max_nonce());
+}
+
+#[test]
+fn vdf_steps_since_reset() {
+    let localnet = localnet();
+    assert_eq!(get_vdf_steps_since_reset(1203, &MAINNET), 3);
+    assert_eq!(get_vdf_steps_since_reset(1203, &localnet), 3);
+    assert_eq!(get_vdf_steps_since_reset(1207, &localnet), 7);
+    assert_eq!(get_vdf_steps_since_reset(1208, &localnet), 8);
+}
+
+#[test]
+fn recall_range_within_partition() {
+    // The first recall range falls within the partition of the local network
+    let localnet = localnet();
+    let mining_hash = [0xffu8; 32];
+    let (recall_range1_start, _) =
+        get_recall_range(&mining_hash, 3, 100 * localnet.partition_size, &localnet);
+    assert!(recall_range1_start >= U256::from(3 * localnet.partition_size));
+    assert!(recall_range1_start < U256::from(4 * localnet.partition_size));
+}
+
+#[test]
+fn inconsistent_params_are_rejected() {
+    assert!(ConsensusParams::builder()
+        .fork_2_6_height(10)
+        .fork_2_7_height(5)
+        .build()
+        .is_err());
+    assert!(ConsensusParams::builder()
+        .recall_range_size(DATA_CHUNK_SIZE + 1)
+        .build()
+        .is_err());
+    assert!(ConsensusParams::builder()
+        .partition_size(DATA_CHUNK_SIZE as u64)
+        .build()
+        .is_err());
+    assert!(ConsensusParams::builder()
+        .nonce_limiter_reset_frequency(0)
+        .build()
+        .is_err());
+}
+
+#[test]
+fn padded_offsets() {
+    let threshold = MAINNET.strict_data_split_threshold as u64;
+    let chunk_size = DATA_CHUNK_SIZE as u64;
+
+    // Offsets past the threshold are rounded up to the next chunk boundary
+    assert_eq!(
+        get_padded_offset(threshold + 1, threshold),
+        threshold + chunk_size
+    );
+    assert_eq!(
+        get_padded_offset(threshold + chunk_size, threshold),
+        threshold + chunk_size
+    );
+
+    // Offsets at or below the threshold are left alone
+    assert_eq!(get_padded_offset(threshold, threshold), threshold);
+    assert_eq!(get_padded_offset(1, threshold), 1);
+    assert_eq!(get_padded_offset(0, 0), 0);
+
+    assert_eq!(get_weave_size_increase(0, 0, &MAINNET), 0);
+    assert_eq!(
+        get_weave_size_increase(100_000, MAINNET.fork_2_5_height, &MAINNET),
+        chunk_size
+    );
+}
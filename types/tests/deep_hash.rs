@@ -0,0 +1,44 @@
+use arweave_rs_types::deep_hash::{deep_hash, DeepHashItem};
+use openssl::sha::sha384;
+
+/// `sha384(sha384(tag) || sha384(bytes))`, written out from ar_deep_hash.erl.
+fn blob_hash(bytes: &[u8]) -> [u8; 48] {
+    let tag = format!("blob{}", bytes.len());
+    sha384(&[sha384(tag.as_bytes()), sha384(bytes)].concat())
+}
+
+#[test]
+fn deep_hash_blobs() {
+    assert_eq!(deep_hash(&DeepHashItem::from_bytes(b"")), blob_hash(b""));
+    assert_eq!(
+        deep_hash(&DeepHashItem::from_bytes(b"arweave")),
+        blob_hash(b"arweave")
+    );
+}
+
+#[test]
+fn deep_hash_lists() {
+    // An empty list is the hash of its tag alone
+    assert_eq!(deep_hash(&DeepHashItem::List(vec![])), sha384(b"list0"));
+
+    // Each element is folded into the accumulator in order
+    let expected = [b"a".as_slice(), b"bc"]
+        .iter()
+        .fold(sha384(b"list2"), |acc, bytes| {
+            sha384(&[acc, blob_hash(bytes)].concat())
+        });
+    let list = DeepHashItem::List(vec![
+        DeepHashItem::from_bytes(b"a"),
+        DeepHashItem::from_bytes(b"bc"),
+    ]);
+    assert_eq!(deep_hash(&list), expected);
+
+    // The same bytes nested differently must hash differently
+    let nested = DeepHashItem::List(vec![DeepHashItem::List(vec![
+        DeepHashItem::from_bytes(b"a"),
+        DeepHashItem::from_bytes(b"bc"),
+    ])]);
+    let joined = DeepHashItem::List(vec![DeepHashItem::from_bytes(b"abc")]);
+    assert_ne!(deep_hash(&list), deep_hash(&nested));
+    assert_ne!(deep_hash(&list), deep_hash(&joined));
+}
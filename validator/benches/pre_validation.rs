@@ -0,0 +1,34 @@
+use arweave_rs_randomx::RandomXMode;
+use arweave_rs_test_support::DevnetFixture;
+use arweave_rs_validator::pre_validate_block;
+use criterion::{criterion_group, criterion_main, Criterion};
+
+fn pre_validation(c: &mut Criterion) {
+    // A devnet block, so the proofs of access validate without the mainnet
+    // block index
+    let mut fixture = DevnetFixture::with_mode(b"bench_pre_validation", RandomXMode::FastHashing);
+    let (devnet, randomx_vm) = (&mut fixture.devnet, &fixture.randomx_vm);
+    devnet.produce_block(Some(randomx_vm)).unwrap();
+    let params = devnet.params();
+    let block_index = devnet.block_index();
+    let [previous_block_header, block_header] = devnet.blocks() else {
+        unreachable!("the genesis block and the produced block");
+    };
+
+    c.bench_function("pre_validate_block", |b| {
+        b.iter(|| {
+            pre_validate_block(
+                block_header,
+                previous_block_header,
+                &block_index,
+                Some(randomx_vm),
+                params,
+                None,
+            )
+            .unwrap()
+        })
+    });
+}
+
+criterion_group!(benches, pre_validation);
+criterion_main!(benches);
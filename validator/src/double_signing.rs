@@ -0,0 +1,117 @@
+//! Verifies the double-signing proofs included in block headers. A miner that
+//! signs two competing blocks (or extends two forks of equal cumulative
+//! difficulty) can be reported by the next block producer, which slashes the
+//! accused miners locked rewards, so a bogus proof invalidates the block.
+use crate::{compute_signed_hash, transaction::rsa_signature_is_valid};
+use arweave_rs_types::{consensus::ConsensusParams, reward_history::RewardHistory, *};
+use color_eyre::eyre::{eyre, Result};
+use openssl::sha;
+
+/// Checks the `double_signing_proof` of a block, blocks without a proof are
+/// valid. `reward_history` is the reward history of the previous block, the
+/// accused miner must have mined one of its blocks.
+pub fn validate_double_signing_proof(
+    block_header: &ArweaveBlockHeader,
+    reward_history: &RewardHistory,
+) -> Result<()> {
+    match verify_double_signing_proof(block_header)? {
+        Some(accused_addr) if !reward_history.contains_reward_addr(&accused_addr) => Err(eyre!(
+            "double signing proof accuses {} which is not in the reward history",
+            base64_url::encode(&accused_addr)
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Performs the checks of a `double_signing_proof` that only need the block
+/// header: both signatures are valid and sign distinct messages, the
+/// cumulative difficulties overlap and the accused miner isn't the block
+/// producer. Returns the address of the accused miner, `None` if there is no
+/// proof.
+pub fn verify_double_signing_proof(block_header: &ArweaveBlockHeader) -> Result<Option<H256>> {
+    let proof = &block_header.double_signing_proof;
+    let Some(pub_key) = &proof.pub_key else {
+        return Ok(None);
+    };
+
+    let (
+        Some(sig1),
+        Some(cdiff1),
+        Some(prev_cdiff1),
+        Some(preimage1),
+        Some(sig2),
+        Some(cdiff2),
+        Some(prev_cdiff2),
+        Some(preimage2),
+    ) = (
+        &proof.sig1,
+        proof.cdiff1,
+        proof.prev_cdiff1,
+        proof.preimage1,
+        &proof.sig2,
+        proof.cdiff2,
+        proof.prev_cdiff2,
+        proof.preimage2,
+    )
+    else {
+        return Err(eyre!("double signing proof is incomplete"));
+    };
+
+    let accused_addr = H256(sha::sha256(pub_key.as_slice()));
+    if accused_addr == block_header.reward_addr {
+        return Err(eyre!("double signing proof accuses the block producer"));
+    }
+
+    if sig1 == sig2 {
+        return Err(eyre!("double signing proof signatures are identical"));
+    }
+
+    // Either the same cumulative difficulty was signed twice or the two
+    // signed blocks extend competing forks
+    if !(cdiff1 == cdiff2 || (cdiff1 > prev_cdiff2 && cdiff2 > prev_cdiff1)) {
+        return Err(eyre!(
+            "double signing proof cumulative difficulties do not overlap"
+        ));
+    }
+
+    // Two signatures of the same message only prove the miner signed one
+    // block (RSA-PSS signatures are randomized)
+    let preimage1 = signature_preimage(cdiff1, prev_cdiff1, &preimage1);
+    let preimage2 = signature_preimage(cdiff2, prev_cdiff2, &preimage2);
+    if preimage1 == preimage2 {
+        return Err(eyre!("double signing proof signs the same preimage twice"));
+    }
+
+    if !rsa_signature_is_valid(pub_key.as_slice(), &preimage1, sig1.as_slice())
+        || !rsa_signature_is_valid(pub_key.as_slice(), &preimage2, sig2.as_slice())
+    {
+        return Err(eyre!("double signing proof signature is invalid"));
+    }
+
+    Ok(Some(accused_addr))
+}
+
+/// The preimage a miner signs for `block_header`, the
+/// `previous_solution_hash` followed by the blocks signed hash.
+pub fn signed_preimage(block_header: &ArweaveBlockHeader, params: &ConsensusParams) -> H512 {
+    let mut preimage = H512::zero();
+    preimage.0[..32].copy_from_slice(block_header.previous_solution_hash.as_bytes());
+    preimage.0[32..].copy_from_slice(&compute_signed_hash(block_header, params));
+    preimage
+}
+
+/// Builds the message a miner signs when producing a block,
+/// `cdiff || prev_cdiff || preimage` with both difficulties prefixed by a 2
+/// byte size.
+pub fn signature_preimage(cdiff: U256, prev_cdiff: U256, preimage: &H512) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    for value in [cdiff, prev_cdiff] {
+        let mut be_bytes = [0u8; 32];
+        value.to_big_endian(&mut be_bytes);
+        let start = be_bytes.iter().position(|&b| b != 0).unwrap_or(31);
+        bytes.extend_from_slice(&((32 - start) as u16).to_be_bytes());
+        bytes.extend_from_slice(&be_bytes[start..]);
+    }
+    bytes.extend_from_slice(preimage.as_bytes());
+    bytes
+}
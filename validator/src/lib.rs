@@ -4,168 +4,343 @@
 use arweave_rs_indexes::*;
 use arweave_rs_packing::{feistel::*, *};
 use arweave_rs_randomx::RandomXVM;
-use arweave_rs_types::{consensus::*, *};
+use arweave_rs_types::{block_hash::compute_indep_hash, consensus::*, *};
+use arweave_rs_vdf::verify::{checkpoints_is_valid, last_step_checkpoints_is_valid};
+use ::metrics::increment_counter;
 use color_eyre::eyre::{eyre, Result};
 use merkle::*;
+use metrics::{invalid, stage, time_stage, BLOCKS_VALIDATED};
 use openssl::sha;
+use report::{record_stage, ValidationReport, ValidationStage};
+use tracing::{debug, debug_span, trace};
 
+pub mod double_signing;
 pub mod merkle;
+pub mod metrics;
+pub mod poa;
+pub mod pricing;
+pub mod report;
+pub mod solution_cache;
+pub mod tip;
+pub mod transaction;
+
+pub use arweave_rs_types::block_hash::compute_signed_hash;
+
+/// Validates a recently proposed block at the tip of the chain. Runs the
+/// cheap timestamp and VDF step checks of [`tip::validate_tip_block()`] against
+/// the local node state before the [`pre_validate_block()`] checks, so stale
+/// or time-warped proposals are rejected before any PoW or PoA work.
+pub fn pre_validate_tip_block(
+    block_header: &ArweaveBlockHeader,
+    previous_block_header: &ArweaveBlockHeader,
+    block_index: &BlockIndex<Initialized>,
+    randomx_vm: Option<&RandomXVM>,
+    tip_validation: &tip::TipValidation,
+    params: &ConsensusParams,
+) -> Result<[u8; 32]> {
+    tip::validate_tip_block(block_header, previous_block_header, tip_validation)?;
+    pre_validate_block(
+        block_header,
+        previous_block_header,
+        block_index,
+        randomx_vm,
+        params,
+        None,
+    )
+}
 
 /// Sequentially performs all of the checks required to validate an Arweave
 /// block starting with the simplest (least expensive) checks and finishing with
 /// the most involved checks. Note: This excludes the VDF checkpoint validation
 /// which is performed separately.
+///
+/// `params` are the consensus parameters of the network the block belongs to,
+/// [`ConsensusParams::mainnet()`] for Arweave mainnet blocks. When a `report`
+/// is given the wall time of each stage is recorded in it, including those of
+/// a block failing validation up to the failed stage.
 pub fn pre_validate_block(
     block_header: &ArweaveBlockHeader,
     previous_block_header: &ArweaveBlockHeader,
     block_index: &BlockIndex<Initialized>,
     randomx_vm: Option<&RandomXVM>,
+    params: &ConsensusParams,
+    report: Option<&mut ValidationReport>,
+) -> Result<[u8; 32]> {
+    let _span = debug_span!("pre_validate_block", height = block_header.height).entered();
+    let result = pre_validation_checks(
+        block_header,
+        previous_block_header,
+        block_index,
+        randomx_vm,
+        params,
+        report,
+    );
+    let proof = match block_header.chunk2_hash {
+        Some(_) => "poa2",
+        None => "poa",
+    };
+    let result_label = match result {
+        Ok(_) => "valid",
+        Err(_) => "invalid",
+    };
+    increment_counter!(BLOCKS_VALIDATED, "proof" => proof, "result" => result_label);
+    debug!(proof, result = result_label, "pre-validated block");
+    result
+}
+
+fn pre_validation_checks(
+    block_header: &ArweaveBlockHeader,
+    previous_block_header: &ArweaveBlockHeader,
+    block_index: &BlockIndex<Initialized>,
+    randomx_vm: Option<&RandomXVM>,
+    params: &ConsensusParams,
+    mut report: Option<&mut ValidationReport>,
 ) -> Result<[u8; 32]> {
-    // =========================================================================
-    // Arweave 2.7 checks
-    // =========================================================================
     let block_height = block_header.height;
 
-    // Validate previous block poa and poa2 proof sizes
-    if !proof_size_is_valid(&previous_block_header.poa, block_height - 1) {
-        return Err(eyre!("previous blocks PoA proof has invalid size"));
+    // The genesis block has no previous block to be validated against
+    if block_height == 0 {
+        return Err(invalid("height", eyre!("the genesis block can't be pre-validated")));
+    }
+
+    // Dispatch on the consensus rules of the blocks era. SPoRA (pre 2.6)
+    // headers commit to a different set of fields and don't have the nonce
+    // limiter data the first 2.6 block is seeded from. Their PoW and PoA
+    // aren't implemented, see the Consensus Forks section of the README
+    match params.fork_at_height(block_height) {
+        ConsensusFork::Pre2_5 | ConsensusFork::Fork2_5 => {
+            return Err(invalid(
+                "unsupported_fork",
+                eyre!("block {block_height} predates the 2.6 fork, SPoRA blocks are not supported"),
+            ));
+        }
+        ConsensusFork::Fork2_6 if block_height == params.fork_2_6_height => {
+            return Err(invalid(
+                "unsupported_fork",
+                eyre!("the 2.6 fork block {block_height} extends a SPoRA block and is not supported"),
+            ));
+        }
+        ConsensusFork::Fork2_6 | ConsensusFork::Fork2_7 => {}
     }
 
-    if !proof_size_is_valid(&previous_block_header.poa2, block_height - 1) {
-        return Err(eyre!("previous blocks PoA2 proof has invalid size"));
-    }
+    // =========================================================================
+    // Arweave 2.7 checks
+    // =========================================================================
 
-    // Validate current blocks poa and poa2 proof sizes
-    if !proof_size_is_valid(&block_header.poa, block_height) {
-        return Err(eyre!("PoA proof has invalid size"));
-    }
+    record_stage(&mut report, ValidationStage::ProofSizes, || {
+        // Validate previous block poa and poa2 proof sizes
+        if !proof_size_is_valid(&previous_block_header.poa, block_height - 1, params) {
+            return Err(invalid("proof_size", eyre!("previous blocks PoA proof has invalid size")));
+        }
 
-    if !proof_size_is_valid(&block_header.poa2, block_height) {
-        return Err(eyre!("PoA2 proof has invalid size"));
-    }
+        if !proof_size_is_valid(&previous_block_header.poa2, block_height - 1, params) {
+            return Err(invalid("proof_size", eyre!("previous blocks PoA2 proof has invalid size")));
+        }
 
-    // Validate the chunk_hash to see if it matches the poa chunk
-    let chunk = &block_header.poa.chunk;
-    if !chunk_hash_is_valid(&block_header.chunk_hash, chunk, block_height) {
-        return Err(eyre!("chunk_hash does not match poa.chunk bytes"));
-    }
+        // Validate current blocks poa and poa2 proof sizes
+        if !proof_size_is_valid(&block_header.poa, block_height, params) {
+            return Err(invalid("proof_size", eyre!("PoA proof has invalid size")));
+        }
 
-    // Validate chunk2_hash to see that it matches the poa2 chunk if present
-    if block_header.chunk2_hash.is_some() {
-        let chunk = &block_header.poa2.chunk;
-        let chunk2_hash = block_header.chunk2_hash.unwrap_or_default();
-        if !chunk_hash_is_valid(&chunk2_hash, chunk, block_height) {
-            return Err(eyre!("chunk2_hash does not match poa2.chunk bytes"));
+        if !proof_size_is_valid(&block_header.poa2, block_height, params) {
+            return Err(invalid("proof_size", eyre!("PoA2 proof has invalid size")));
         }
-    }
+        Ok(())
+    })?;
+
+    record_stage(&mut report, ValidationStage::ChunkHashes, || {
+        // Validate the chunk_hash to see if it matches the poa chunk
+        let chunk = &block_header.poa.chunk;
+        if !chunk_hash_is_valid(&block_header.chunk_hash, chunk, block_height, params) {
+            return Err(invalid("chunk_hash", eyre!("chunk_hash does not match poa.chunk bytes")));
+        }
+
+        // Validate chunk2_hash to see that it matches the poa2 chunk if present
+        if block_header.chunk2_hash.is_some() {
+            let chunk = &block_header.poa2.chunk;
+            let chunk2_hash = block_header.chunk2_hash.unwrap_or_default();
+            if !chunk_hash_is_valid(&chunk2_hash, chunk, block_height, params) {
+                return Err(invalid("chunk_hash", eyre!("chunk2_hash does not match poa2.chunk bytes")));
+            }
+        }
+        Ok(())
+    })?;
 
     // =========================================================================
     // General Arweave checks
     // =========================================================================
 
     // Compute the block_hash and validate it against block_header.indep_hash
-    if !block_hash_is_valid(block_header) {
-        return Err(eyre!("indep_hash does not match calculated block_hash"));
+    let indep_hash_is_valid = record_stage(&mut report, ValidationStage::IndepHash, || {
+        time_stage(stage::HASH, || block_hash_is_valid(block_header, params))
+    });
+    if !indep_hash_is_valid {
+        return Err(invalid("indep_hash", eyre!("indep_hash does not match calculated block_hash")));
     }
 
     // ==============================
     // Recently proposed block checks
     // ------------------------------
-    // Validate timestamp
+    // The timestamp and VDF step range are validated by
+    // pre_validate_tip_block() in tip validation mode
 
-    // Validate existing Solution hash - has the solution  already been
-    // validated? (possibly report a double signing)
+    // Whether the solution was already validated (possibly reporting a double
+    // signing) is tracked across blocks by the callers solution_cache::SolutionCache
 
-    // Validate VDF step is within range of current
+    // Validate the double signing proof (the reward history check of the
+    // accused miner is done by double_signing::validate_double_signing_proof)
+    double_signing::verify_double_signing_proof(block_header)
+        .map_err(|err| invalid("double_signing", err))?;
 
     // ==============================
 
     // Validate the previous blocks indep_hash is the parent of the current
     if block_header.previous_block != previous_block_header.indep_hash {
-        return Err(eyre!("previous blocks indep_hash is not the parent block"));
+        return Err(invalid("previous_block", eyre!("previous blocks indep_hash is not the parent block")));
     }
 
-    // Validate last re-target
-    if !last_retarget_is_valid(block_header, previous_block_header) {
-        return Err(eyre!("last_retarget is invalid"));
-    }
+    record_stage(&mut report, ValidationStage::RetargetAndDiff, || {
+        // Validate last re-target
+        if !last_retarget_is_valid(block_header, previous_block_header, params) {
+            return Err(invalid("last_retarget", eyre!("last_retarget is invalid")));
+        }
 
-    // Validate difficulty
-    if !difficulty_is_valid(block_header, previous_block_header) {
-        return Err(eyre!("block difficulty is invalid"));
-    }
+        // Validate difficulty
+        if !difficulty_is_valid(block_header, previous_block_header, params) {
+            return Err(invalid("difficulty", eyre!("block difficulty is invalid")));
+        }
 
-    // Validate cumulative difficulty
-    if !cumulative_diff_is_valid(block_header, previous_block_header) {
-        return Err(eyre!("cumulative_diff is invalid"));
-    }
+        // Validate cumulative difficulty
+        if !cumulative_diff_is_valid(block_header, previous_block_header) {
+            return Err(invalid("cumulative_diff", eyre!("cumulative_diff is invalid")));
+        }
+        Ok(())
+    })?;
 
     // Validate "quick" PoW
-    let quick_pow_result = quick_pow_is_valid(block_header, previous_block_header, randomx_vm);
+    let quick_pow_result = record_stage(&mut report, ValidationStage::QuickPow, || {
+        time_stage(stage::POW, || {
+            quick_pow_is_valid(block_header, previous_block_header, randomx_vm)
+        })
+    });
 
     let (mining_hash, solution_hash) = match quick_pow_result {
         Ok(tuple) => tuple,
-        Err(err) => return Err(err),
+        Err(err) => return Err(invalid("pow", err)),
     };
 
-    // Validate Nonce Limiter seed data (ar_nonce_limiter:get_seed_data)
-    if !seed_data_is_valid(block_header, previous_block_header) {
-        return Err(eyre!("seed_data is invalid"));
-    }
+    record_stage(&mut report, ValidationStage::SeedData, || {
+        // Validate Nonce Limiter seed data (ar_nonce_limiter:get_seed_data)
+        let expected_seed_data = get_seed_data(
+            block_header.nonce_limiter_info.global_step_number,
+            previous_block_header,
+            params,
+        )
+        .map_err(|err| invalid("seed_data", err))?;
+        if !seed_data_is_valid(block_header, &expected_seed_data, params) {
+            return Err(invalid("seed_data", eyre!("seed_data is invalid")));
+        }
 
-    // Nonce Limiter: Block partition number below upper bound
-    if !partition_number_is_valid(block_header) {
-        return Err(eyre!("partition_number is invalid"));
-    }
+        // Nonce Limiter: Block partition number below upper bound
+        if !partition_number_is_valid(block_header, params) {
+            return Err(invalid("partition_number", eyre!("partition_number is invalid")));
+        }
 
-    // Nonce Limiter: Nonce is below Max Nonce limit
-    if !nonce_is_valid(block_header) {
-        return Err(eyre!("nonce is invalid"));
-    }
+        // Nonce Limiter: Nonce is below Max Nonce limit
+        if !nonce_is_valid(block_header, params) {
+            return Err(invalid("nonce", eyre!("nonce is invalid")));
+        }
+        Ok(())
+    })?;
 
     // Prevalidate PoA - recall range (mining_hash = H0)
-    let (recall_byte_1, recall_byte_2) = match recall_bytes_is_valid(block_header, &mining_hash) {
+    let recall_bytes_result = record_stage(&mut report, ValidationStage::RecallBytes, || {
+        recall_bytes_is_valid(block_header, &mining_hash, params)
+    });
+    let (recall_byte_1, recall_byte_2) = match recall_bytes_result {
         Ok(tuple) => tuple,
-        Err(err) => return Err(err),
+        Err(err) => return Err(invalid("recall_bytes", err)),
     };
 
-    // POA merkle proofs / chunk validation
-    if !poa_is_valid(
+    // POA merkle proofs / chunk validation, timed by stage in the report
+    if !poa_checks(
         &block_header.poa,
         recall_byte_1,
         block_index,
         &block_header.reward_addr,
         randomx_vm,
+        params,
+        report.as_deref_mut(),
     ) {
-        return Err(eyre!("poa is invalid"));
+        return Err(invalid("poa", eyre!("poa is invalid")));
     }
 
     // POA2 merkle proofs / chunk validation (if necessary)
     if let Some(recall_byte_2) = recall_byte_2 {
-        if !poa_is_valid(
-            &block_header.poa2,
-            recall_byte_2,
-            block_index,
-            &block_header.reward_addr,
-            randomx_vm,
-        ) {
-            return Err(eyre!("poa2 is invalid"));
+        let poa2_is_valid = record_stage(&mut report, ValidationStage::Poa2, || {
+            poa_is_valid(
+                &block_header.poa2,
+                recall_byte_2,
+                block_index,
+                &block_header.reward_addr,
+                randomx_vm,
+                params,
+            )
+        });
+        if !poa2_is_valid {
+            return Err(invalid("poa2", eyre!("poa2 is invalid")));
         }
     }
 
     Ok(solution_hash)
 }
 
-fn compute_solution_hash(mining_hash: &[u8; 32], hash_preimage: &H256) -> [u8; 32] {
-    let mut hasher = sha::Sha256::new();
-    hasher.update(mining_hash);
-    hasher.update(hash_preimage.as_bytes());
-    hasher.finish()
+/// Validates the VDF steps of `block_header` continue from those of
+/// `previous_block_header` and recomputes their checkpoints, the validation
+/// [`pre_validate_block()`] leaves out. Takes around a second of work per step
+/// spread across the available cores.
+pub fn vdf_is_valid(
+    block_header: &ArweaveBlockHeader,
+    previous_block_header: &ArweaveBlockHeader,
+    params: &ConsensusParams,
+) -> Result<()> {
+    let _span = debug_span!("vdf_is_valid", height = block_header.height).entered();
+    let nonce_info = &block_header.nonce_limiter_info;
+    let previous_nonce_info = &previous_block_header.nonce_limiter_info;
+
+    if nonce_info.prev_output != previous_nonce_info.output {
+        return Err(invalid(
+            "vdf",
+            eyre!("vdf prev_output is not the output of the previous block"),
+        ));
+    }
+    let num_steps = nonce_info
+        .global_step_number
+        .saturating_sub(previous_nonce_info.global_step_number);
+    if num_steps == 0 || nonce_info.checkpoints.len() as u64 != num_steps {
+        return Err(invalid(
+            "vdf",
+            eyre!("vdf has {} steps, expected {num_steps}", nonce_info.checkpoints.len()),
+        ));
+    }
+    if nonce_info.checkpoints.get(0) != Some(&nonce_info.output) {
+        return Err(invalid("vdf", eyre!("vdf output is not the latest step")));
+    }
+    time_stage(stage::VDF, || {
+        if !checkpoints_is_valid(nonce_info, params) {
+            return Err(eyre!("vdf checkpoints are invalid"));
+        }
+        if !last_step_checkpoints_is_valid(nonce_info, params) {
+            return Err(eyre!("vdf last_step_checkpoints are invalid"));
+        }
+        Ok(())
+    })
+    .map_err(|err| invalid("vdf", err))
 }
 
-fn proof_size_is_valid(poa_data: &PoaData, block_height: u64) -> bool {
-    // Don't do this validation check on pre 2.7 blocks
-    if block_height < FORK_2_7_HEIGHT {
+fn proof_size_is_valid(poa_data: &PoaData, block_height: u64, params: &ConsensusParams) -> bool {
+    // The proof size limits were introduced in 2.7
+    if params.fork_at_height(block_height) < ConsensusFork::Fork2_7 {
         return true;
     }
 
@@ -178,8 +353,14 @@ fn proof_size_is_valid(poa_data: &PoaData, block_height: u64) -> bool {
         && chunk.len() <= (DATA_CHUNK_SIZE as usize)
 }
 
-fn chunk_hash_is_valid(chunk_hash: &H256, chunk: &Base64, block_height: u64) -> bool {
-    if block_height < FORK_2_7_HEIGHT {
+fn chunk_hash_is_valid(
+    chunk_hash: &H256,
+    chunk: &Base64,
+    block_height: u64,
+    params: &ConsensusParams,
+) -> bool {
+    // Pre 2.7 blocks don't commit to their chunk hashes
+    if params.fork_at_height(block_height) < ConsensusFork::Fork2_7 {
         return true;
     }
 
@@ -192,8 +373,9 @@ fn chunk_hash_is_valid(chunk_hash: &H256, chunk: &Base64, block_height: u64) ->
 fn last_retarget_is_valid(
     block_header: &ArweaveBlockHeader,
     previous_block_header: &ArweaveBlockHeader,
+    params: &ConsensusParams,
 ) -> bool {
-    if is_retarget_height(block_header) {
+    if is_retarget_height(block_header, params) {
         block_header.last_retarget == block_header.timestamp
     } else {
         block_header.last_retarget == previous_block_header.last_retarget
@@ -203,22 +385,27 @@ fn last_retarget_is_valid(
 fn difficulty_is_valid(
     block_header: &ArweaveBlockHeader,
     previous_block_header: &ArweaveBlockHeader,
+    params: &ConsensusParams,
 ) -> bool {
-    if is_retarget_height(block_header) {
-        let result = calculate_difficulty(block_header, previous_block_header);
+    if is_retarget_height(block_header, params) {
+        let result = calculate_difficulty(block_header, previous_block_header, params);
         match result {
             Ok(computed_diff) => {
                 if computed_diff == block_header.diff {
                     true
                 } else {
-                    println!(
-                        "\ncomputed: {}\n  actual: {}",
-                        computed_diff, block_header.diff
+                    debug!(
+                        computed = %computed_diff,
+                        actual = %block_header.diff,
+                        "retarget difficulty mismatch"
                     );
                     false
                 }
             }
-            Err(_) => false,
+            Err(err) => {
+                debug!(error = %err, "failed to compute the retarget difficulty");
+                false
+            }
         }
     } else {
         block_header.diff == previous_block_header.diff
@@ -229,11 +416,12 @@ fn difficulty_is_valid(
 fn calculate_difficulty(
     block_header: &ArweaveBlockHeader,
     previous_block_header: &ArweaveBlockHeader,
+    params: &ConsensusParams,
 ) -> Result<U256> {
     let height = block_header.height;
     let timestamp = block_header.timestamp;
 
-    if height < FORK_2_5_HEIGHT {
+    if height < params.fork_2_5_height {
         return Err(eyre!(
             "Can't calculate difficulty for block height prior to Fork 2.5"
         ));
@@ -246,13 +434,13 @@ fn calculate_difficulty(
     let max_timestamp_deviation = JOIN_CLOCK_TOLERANCE * 2 + CLOCK_DRIFT_MAX;
 
     // Number of blocks between difficulty re-targets and the target block time
-    let target_time = RETARGET_BLOCKS * TARGET_TIME;
+    let target_time = params.retarget_blocks * TARGET_TIME;
 
     // The actual time since the last retarget
     let actual_time = std::cmp::max(timestamp - previous_last_retarget, max_timestamp_deviation);
 
-    if actual_time < RETARGET_TOLERANCE_UPPER_BOUND && actual_time > RETARGET_TOLERANCE_LOWER_BOUND
-    {
+    let (tolerance_lower_bound, tolerance_upper_bound) = params.retarget_tolerance_bounds();
+    if actual_time < tolerance_upper_bound && actual_time > tolerance_lower_bound {
         // Maintain difficulty from previous block
         Ok(previous_diff)
     } else {
@@ -326,41 +514,42 @@ fn quick_pow_is_valid(
 
 fn seed_data_is_valid(
     block_header: &ArweaveBlockHeader,
-    previous_block_header: &ArweaveBlockHeader,
+    expected_seed_data: &SeedData,
+    params: &ConsensusParams,
 ) -> bool {
     let nonce_info = &block_header.nonce_limiter_info;
-    let expected_seed_data = get_seed_data(
-        block_header.nonce_limiter_info.global_step_number,
-        previous_block_header,
-    );
 
     // Note: next_vdf_difficulty is not checked here as it is a heavier operation
     if expected_seed_data.seed == nonce_info.seed
         && expected_seed_data.next_seed == nonce_info.next_seed
         && expected_seed_data.next_partition_upper_bound == nonce_info.next_zone_upper_bound
         && expected_seed_data.partition_upper_bound == nonce_info.zone_upper_bound
-        && expected_seed_data.vdf_difficulty == nonce_info.vdf_difficulty.unwrap_or(VDF_SHA_1S)
+        && expected_seed_data.vdf_difficulty == nonce_info.vdf_difficulty.unwrap_or(params.vdf_sha_1s)
     {
         true
     } else {
-        println!(
-            "expected seed: {:?}\nfound seed: {:?}",
-            expected_seed_data.seed, nonce_info.seed
+        debug!(
+            expected_seed = %base64_url::encode(&expected_seed_data.seed),
+            found_seed = %base64_url::encode(&nonce_info.seed),
+            expected_partition_upper_bound = expected_seed_data.partition_upper_bound,
+            found_partition_upper_bound = nonce_info.zone_upper_bound,
+            expected_vdf_difficulty = expected_seed_data.vdf_difficulty,
+            "seed data mismatch"
         );
         false
     }
 }
 
-fn partition_number_is_valid(block_header: &ArweaveBlockHeader) -> bool {
-    let max = std::cmp::max(
-        0,
-        block_header.nonce_limiter_info.zone_upper_bound / PARTITION_SIZE - 1,
-    );
+fn partition_number_is_valid(block_header: &ArweaveBlockHeader, params: &ConsensusParams) -> bool {
+    // Like max(0, UpperBound div PartitionSize - 1) in the reference, an upper
+    // bound below a whole partition still allows partition 0
+    let max = (block_header.nonce_limiter_info.zone_upper_bound / params.partition_size)
+        .saturating_sub(1);
     block_header.partition_number <= max
 }
 
-fn nonce_is_valid(block_header: &ArweaveBlockHeader) -> bool {
-    let max = RECALL_RANGE_SIZE / DATA_CHUNK_SIZE;
+fn nonce_is_valid(block_header: &ArweaveBlockHeader, params: &ConsensusParams) -> bool {
+    let max = params.max_nonce();
     let nonce_value = block_header.nonce.0 as u32;
     nonce_value < max
 }
@@ -368,11 +557,18 @@ fn nonce_is_valid(block_header: &ArweaveBlockHeader) -> bool {
 fn recall_bytes_is_valid(
     block_header: &ArweaveBlockHeader,
     mining_hash: &[u8; 32],
+    params: &ConsensusParams,
 ) -> Result<(U256, Option<U256>)> {
+    // The recall ranges are offsets modulo the upper bound
+    if block_header.nonce_limiter_info.zone_upper_bound == 0 {
+        return Err(eyre!("zone_upper_bound is zero"));
+    }
+
     let (recall_range1_start, recall_range2_start) = get_recall_range(
         mining_hash,
         block_header.partition_number,
         block_header.nonce_limiter_info.zone_upper_bound,
+        params,
     );
 
     let recall_byte_1 = recall_range1_start + block_header.nonce.0 * DATA_CHUNK_SIZE as u64;
@@ -391,12 +587,30 @@ fn recall_bytes_is_valid(
     }
 }
 
-fn poa_is_valid(
+/// Validates the `tx_path` and `data_path` of the `poa_data` against the
+/// `tx_root` of the block containing the `recall_byte` and checks the chunk,
+/// unpacked for the `reward_addr`, matches the leaf of the `data_path`.
+pub fn poa_is_valid(
+    poa_data: &PoaData,
+    recall_byte: U256,
+    block_index: &BlockIndex<Initialized>,
+    reward_addr: &H256,
+    randomx_vm: Option<&RandomXVM>,
+    params: &ConsensusParams,
+) -> bool {
+    poa_checks(poa_data, recall_byte, block_index, reward_addr, randomx_vm, params, None)
+}
+
+/// The checks of [`poa_is_valid()`], recording the time of the PoA entropy,
+/// feistel and merkle stages in the `report`, if there is one.
+fn poa_checks(
     poa_data: &PoaData,
     recall_byte: U256,
     block_index: &BlockIndex<Initialized>,
     reward_addr: &H256,
     randomx_vm: Option<&RandomXVM>,
+    params: &ConsensusParams,
+    mut report: Option<&mut ValidationReport>,
 ) -> bool {
     // Use the block_index to look up the BlockStart, BlockEnd, and tx_root
     let block_bounds = block_index.get_block_bounds(recall_byte.as_u128());
@@ -405,38 +619,42 @@ fn poa_is_valid(
 
     // Test to see if the recall byte chunk index is between the start and end
     // chunk offsets of the block
-    if (start..=end).contains(&recall_byte.as_u128()) {
-        // println!(
-        //     "recall_byte falls within block_bounds {}..{} of block_height: {}",
-        //     block_bounds.block_start_offset, block_bounds.block_end_offset, block_bounds.height
-        // );
-    } else {
+    if !(start..=end).contains(&recall_byte.as_u128()) {
+        debug!(%recall_byte, start, end, "recall_byte is outside of the block bounds");
         return false;
     }
+    trace!(
+        start,
+        end,
+        recall_block_height = block_bounds.height as u64,
+        "recall_byte falls within the block bounds"
+    );
 
     let byte_offset_in_block = get_byte_offset(
         recall_byte,
         block_bounds.block_start_offset,
         block_bounds.block_end_offset,
+        params,
+    );
+    trace!(
+        tx_root = %base64_url::encode(&block_bounds.tx_root),
+        byte_offset_in_block,
+        "validating tx_path"
     );
-    // println!(
-    //     "tx_root: {:?} target_offset_in_block: {byte_offset_in_block}",
-    //     base64_url::encode(&block_bounds.tx_root)
-    // );
 
     // TX_PATH Validation
     // --------------------------------------------------------------
     // tx_root is the merkle root of the tree whose leaves are the data_roots 
     // of each of the transactions in the block. This path matches the tx_root
     // in the block header to a specific transactions data_path
-    let tx_path_result = match validate_path(
-        block_bounds.tx_root.0,
-        &poa_data.tx_path,
-        byte_offset_in_block,
-    ) {
+    let tx_path_result = match record_stage(&mut report, ValidationStage::Merkle, || {
+        time_stage(stage::MERKLE, || {
+            validate_path(block_bounds.tx_root.0, &poa_data.tx_path, byte_offset_in_block)
+        })
+    }) {
         Ok(result) => result,
-        Err(_) => {
-            println!("tx_path is invalid");
+        Err(err) => {
+            debug!(error = %err, "tx_path is invalid");
             return false;
         }
     };
@@ -444,48 +662,75 @@ fn poa_is_valid(
     // Find the offset of the recall byte relative to a specific TX
     let byte_offset_in_tx = byte_offset_in_block - tx_path_result.left_bound;
     let tx_start = 0;
-    let tx_end = tx_path_result.right_bound - tx_path_result.left_bound;
-    // println!("tx_start: {tx_start} tx_end: {tx_end} byte offset: {byte_offset_in_tx}");
+    let Some(tx_end) = tx_path_result.right_bound.checked_sub(tx_path_result.left_bound) else {
+        debug!("tx_path ends before its left bound");
+        return false;
+    };
 
     // Test to see if the byte falls within the bounds of the tx
-    if (tx_start..=tx_end).contains(&byte_offset_in_tx) || (tx_start == 0 && tx_end == 0) {
-        // println!("recall_byte falls within tx_bounds {tx_start}..={tx_end}");
-    } else {
+    if !((tx_start..=tx_end).contains(&byte_offset_in_tx) || (tx_start == 0 && tx_end == 0)) {
+        debug!(byte_offset_in_tx, tx_end, "recall_byte is outside of the tx bounds");
         return false;
     }
+    trace!(tx_end, byte_offset_in_tx, "recall_byte falls within the tx bounds");
 
     // DATA_PATH Validation
     // --------------------------------------------------------------
     // The leaf hash in the tx_path proof is the root of the data_path merkle
     // tree. This validates the path from the transactions data_root to the
     // specific chunk in the transactions data that is being provided in the poa
-    let data_path_result = match validate_path(
-        tx_path_result.leaf_hash,
-        &poa_data.data_path,
-        byte_offset_in_tx,
-    ) {
+    let data_path_result = match record_stage(&mut report, ValidationStage::Merkle, || {
+        time_stage(stage::MERKLE, || {
+            validate_path(tx_path_result.leaf_hash, &poa_data.data_path, byte_offset_in_tx)
+        })
+    }) {
         Ok(result) => result,
-        Err(_) => return false,
+        Err(err) => {
+            debug!(error = %err, "data_path is invalid");
+            return false;
+        }
     };
 
     // Get the chunk (end) offset
-    let chunk_size = (data_path_result.right_bound - data_path_result.left_bound) as usize;
+    let Some(chunk_size) = data_path_result.right_bound.checked_sub(data_path_result.left_bound)
+    else {
+        debug!("data_path ends before its left bound");
+        return false;
+    };
+    let chunk_size = chunk_size as usize;
     let chunk_offset =
         block_bounds.block_start_offset + tx_path_result.left_bound + data_path_result.right_bound;
 
-    // println!("leaf_hash: {}, left_bound: {}, right_bound: {}", base64_url::encode(&data_path_result.leaf_hash), data_path_result.left_bound, data_path_result.right_bound);
-    // println!("DATA_PATH is valid chunk_size: {chunk_size} target_byte: {byte_offset_in_tx}");
+    trace!(
+        leaf_hash = %base64_url::encode(&data_path_result.leaf_hash),
+        left_bound = data_path_result.left_bound,
+        right_bound = data_path_result.right_bound,
+        chunk_size,
+        "data_path is valid"
+    );
+
+    // Chunks are always packed as a whole DATA_CHUNK_SIZE
+    if poa_data.chunk.len() != DATA_CHUNK_SIZE as usize {
+        debug!(chunk_size = poa_data.chunk.len(), "packed chunk is not a whole chunk");
+        return false;
+    }
 
     // Create packed entropy scratchpad for the chunk + reward_address
     // randomx_long_with_entropy.cpp: 51
     let input = get_chunk_entropy_input(chunk_offset.into(), &block_bounds.tx_root, reward_addr);
     let randomx_program_count = RANDOMX_PACKING_ROUNDS_2_6;
-    let entropy = compute_entropy(&input, randomx_program_count, randomx_vm);
+    let entropy = record_stage(&mut report, ValidationStage::PoaEntropy, || {
+        time_stage(stage::RANDOMX_ENTROPY, || {
+            compute_entropy(&input, randomx_program_count, randomx_vm)
+        })
+    });
 
     // Use a feistel cypher + entropy to decrypt the chunk
     // randomx_long_with_entropy.cpp: 113
     let ciphertext = poa_data.chunk.as_slice();
-    let decrypted_chunk = feistel_decrypt(ciphertext, &entropy);
+    let decrypted_chunk = record_stage(&mut report, ValidationStage::Feistel, || {
+        time_stage(stage::FEISTEL, || feistel_decrypt(ciphertext, &entropy))
+    });
 
     // Because all chunks are packed as DATA_CHUNK_SIZE, if the proof chunk is
     // smaller we need to trim off the excess padding introduced by packing
@@ -496,272 +741,19 @@ fn poa_is_valid(
     let chunk_hash = generate_chunk_id(decrypted_chunk);
 
     // Check if the decrypted chunk_hash matches the one in the data_path
-    chunk_hash == data_path_result.leaf_hash
-}
-
-trait DoubleSigningProofBytes {
-    fn bytes(&self) -> Vec<u8>;
-}
-
-impl DoubleSigningProofBytes for DoubleSigningProof {
-    fn bytes(&self) -> Vec<u8> {
-        // If no DoubleSigningProof is provided, return a 0 byte
-        if self.pub_key.is_none() {
-            return vec![0];
-        }
-
-        let mut buff: Vec<u8> = Vec::new();
-
-        // If a DoubleSigningProof exists, the first byte should be 1
-        buff.extend_raw_buf(1, &[1])
-            .extend_optional_raw_buf(512, &self.pub_key)
-            .extend_optional_raw_buf(512, &self.sig1)
-            .extend_big(2, &self.cdiff1.unwrap_or_default())
-            .extend_big(2, &self.prev_cdiff1.unwrap_or_default())
-            .extend_raw_buf(64, self.preimage1.unwrap_or_default().as_bytes())
-            .extend_optional_raw_buf(512, &self.sig2)
-            .extend_big(2, &self.cdiff2.unwrap_or_default())
-            .extend_big(2, &self.prev_cdiff2.unwrap_or_default())
-            .extend_raw_buf(64, self.preimage2.unwrap_or_default().as_bytes());
-        buff
-    }
-}
-
-/// The extend_raw_* functions do not prepend any kind of size bytes to the
-/// bytes they append. The other extend_<type> functions append bigEndian size
-/// bytes before appending the bytes of <type>.
-trait ExtendBytes {
-    fn extend_raw_buf(&mut self, raw_size: usize, val: &[u8]) -> &mut Self;
-    fn extend_optional_raw_buf(&mut self, raw_size: usize, val: &Option<Base64>) -> &mut Self;
-    fn extend_raw_big(&mut self, raw_size: usize, val: &U256) -> &mut Self;
-    fn extend_u64(&mut self, size_bytes: usize, val: &u64) -> &mut Self;
-    fn extend_big(&mut self, size_bytes: usize, val: &U256) -> &mut Self;
-    fn extend_optional_big(&mut self, size_bytes: usize, val: &Option<U256>) -> &mut Self;
-    fn extend_optional_hash(&mut self, size_bytes: usize, val: &Option<H256>) -> &mut Self;
-    fn extend_buf(&mut self, size_bytes: usize, val: &[u8]) -> &mut Self;
-    fn extend_buf_list(&mut self, size_bytes: usize, val: &[Base64]) -> &mut Self;
-    fn extend_hash_list(&mut self, val: &[H256]) -> &mut Self;
-    fn trim_leading_zero_bytes(slice: &[u8]) -> &[u8] {
-        let mut non_zero_index = slice.iter().position(|&x| x != 0).unwrap_or(slice.len());
-        non_zero_index = std::cmp::min(non_zero_index, slice.len() - 1);
-        &slice[non_zero_index..]
-    }
-}
-
-impl ExtendBytes for Vec<u8> {
-    /// Extends a Vec<u8> by [raw_size] amount of bytes by copying the last
-    /// [raw_size] bytes from [val] and appending them to the Vec<u8>
-    fn extend_raw_buf(&mut self, raw_size: usize, val: &[u8]) -> &mut Self {
-        let mut bytes = vec![0u8; raw_size];
-
-        // Calculate the start position in 'val' to copy from
-        let start = if val.len() > raw_size {
-            val.len() - raw_size
-        } else {
-            0
-        };
-
-        // Copy the last 'buf_size' bytes of 'val' into 'bytes'
-        let insert = raw_size.saturating_sub(val.len());
-        bytes[insert..].copy_from_slice(&val[start..]);
-
-        // Extend 'self' with 'bytes'
-        self.extend_from_slice(&bytes);
-        self
-    }
-
-    fn extend_optional_raw_buf(&mut self, raw_size: usize, val: &Option<Base64>) -> &mut Self {
-        let mut bytes: Vec<u8> = Vec::new();
-        if let Some(val_bytes) = val {
-            bytes.extend_from_slice(val_bytes.as_slice());
-        }
-        self.extend_raw_buf(raw_size, &bytes)
-    }
-
-    fn extend_raw_big(&mut self, raw_size: usize, val: &U256) -> &mut Self {
-        let mut bytes = [0u8; 32];
-        val.to_big_endian(&mut bytes);
-        self.extend_raw_buf(raw_size, &bytes)
-    }
-
-    fn extend_u64(&mut self, num_size_bytes: usize, val: &u64) -> &mut Self {
-        let bytes = &val.to_be_bytes();
-        let bytes = Self::trim_leading_zero_bytes(bytes);
-        let num_val_bytes = bytes.len();
-        let size_bytes = num_val_bytes.to_be_bytes();
-        let start = size_bytes.len().saturating_sub(num_size_bytes);
-        self.extend_from_slice(&Vec::from(&size_bytes[start..]));
-        self.extend_from_slice(bytes);
-        self
-    }
-
-    fn extend_big(&mut self, num_size_bytes: usize, val: &U256) -> &mut Self {
-        let mut be_bytes = [0u8; 32];
-        val.to_big_endian(&mut be_bytes);
-        let bytes = Self::trim_leading_zero_bytes(&be_bytes);
-        let num_val_bytes = bytes.len();
-        let size_bytes = num_val_bytes.to_be_bytes();
-        let start = size_bytes.len().saturating_sub(num_size_bytes);
-        self.extend_from_slice(&Vec::from(&size_bytes[start..]));
-        self.extend_from_slice(bytes);
-        self
-    }
-
-    fn extend_optional_big(&mut self, size_bytes: usize, val: &Option<U256>) -> &mut Self {
-        if let Some(big_int) = val {
-            self.extend_big(size_bytes, big_int)
-        } else {
-            // This will append the correct number of size_bytes to store a size of 0
-            self.extend_buf(size_bytes, &[])
-        }
-    }
-
-    fn extend_buf(&mut self, num_size_bytes: usize, val: &[u8]) -> &mut Self {
-        let bytes = val;
-        let num_val_bytes = bytes.len();
-        let size_bytes = num_val_bytes.to_be_bytes();
-        let start = size_bytes.len().saturating_sub(num_size_bytes);
-        self.extend_from_slice(&Vec::from(&size_bytes[start..]));
-        self.extend_from_slice(bytes);
-        self
-    }
-
-    fn extend_optional_hash(&mut self, size_bytes: usize, val: &Option<H256>) -> &mut Self {
-        let mut bytes: Vec<u8> = Vec::new();
-        if let Some(val_bytes) = val {
-            bytes.extend_from_slice(&val_bytes[..]);
-        }
-        self.extend_buf(size_bytes, &bytes)
-    }
-
-    fn extend_buf_list(&mut self, size_bytes: usize, data: &[Base64]) -> &mut Self {
-        // Number of elements in the list, as 2 bytes
-        let num_elements = data.len() as u16;
-        self.extend_from_slice(&num_elements.to_be_bytes());
-        // Iterate over each element in the data vector
-        for elem in data.iter().rev() {
-            self.extend_buf(size_bytes, elem.as_slice());
-        }
-        self
-    }
-
-    fn extend_hash_list(&mut self, data: &[H256]) -> &mut Self {
-        // Number of hashes in the list, as 2 bytes
-        let num_elements = data.len() as u16;
-        self.extend_from_slice(&num_elements.to_be_bytes());
-        // Iterate over each hash in the data vector and append it
-        for elem in data.iter() {
-            self.extend_from_slice(elem.as_bytes());
-        }
-        self
+    if chunk_hash != data_path_result.leaf_hash {
+        debug!(%chunk_offset, "unpacked chunk does not match the data_path leaf");
+        return false;
     }
+    true
 }
 
-fn block_hash_is_valid(block_header: &ArweaveBlockHeader) -> bool {
-    let b = block_header;
-    let nonce_info = &b.nonce_limiter_info;
-    let mut diff_bytes: [u8; 32] = Default::default();
-    b.diff.to_big_endian(&mut diff_bytes);
-
-    let proof_bytes = b.double_signing_proof.bytes();
-
-    //let expected: Vec<u8> = vec![];
-
-    let mut buff: Vec<u8> = Vec::new();
-    buff.extend_buf(1, b.previous_block.as_bytes())
-        .extend_u64(1, &b.timestamp)
-        .extend_u64(2, &b.nonce.0)
-        .extend_u64(1, &b.height)
-        .extend_buf(2, &diff_bytes)
-        .extend_big(2, &b.cumulative_diff)
-        .extend_u64(1, &b.last_retarget)
-        .extend_buf(1, b.hash.as_bytes())
-        .extend_u64(2, &b.block_size)
-        .extend_u64(2, &b.weave_size)
-        .extend_buf(1, b.reward_addr.as_bytes())
-        .extend_optional_hash(1, &b.tx_root)
-        .extend_buf(1, b.wallet_list.as_bytes())
-        .extend_buf(1, b.hash_list_merkle.as_bytes())
-        .extend_u64(1, &b.reward_pool)
-        .extend_u64(1, &b.packing_2_5_threshold)
-        .extend_u64(1, &b.strict_data_split_threshold)
-        .extend_u64(1, &b.usd_to_ar_rate[0])
-        .extend_u64(1, &b.usd_to_ar_rate[1])
-        .extend_u64(1, &b.scheduled_usd_to_ar_rate[0])
-        .extend_u64(1, &b.scheduled_usd_to_ar_rate[1])
-        .extend_buf_list(2, &b.tags.0)
-        .extend_buf_list(1, &b.txs.0)
-        .extend_u64(1, &b.reward)
-        .extend_u64(2, &b.recall_byte)
-        .extend_buf(1, b.hash_preimage.as_bytes())
-        .extend_optional_big(2, &b.recall_byte2)
-        .extend_buf(2, b.reward_key.as_slice())
-        .extend_u64(1, &b.partition_number)
-        .extend_raw_buf(32, nonce_info.output.as_bytes())
-        .extend_raw_buf(8, &nonce_info.global_step_number.to_be_bytes())
-        .extend_raw_buf(48, nonce_info.seed.as_bytes())
-        .extend_raw_buf(48, nonce_info.next_seed.as_bytes())
-        .extend_raw_buf(32, &nonce_info.zone_upper_bound.to_be_bytes())
-        .extend_raw_buf(32, &nonce_info.next_zone_upper_bound.to_be_bytes())
-        .extend_buf(1, b.nonce_limiter_info.prev_output.as_bytes())
-        .extend_hash_list(&b.nonce_limiter_info.checkpoints.0)
-        .extend_hash_list(&b.nonce_limiter_info.last_step_checkpoints.0)
-        .extend_buf(1, b.previous_solution_hash.as_bytes())
-        .extend_big(1, &b.price_per_gib_minute)
-        .extend_big(1, &b.scheduled_price_per_gib_minute)
-        .extend_raw_buf(32, b.reward_history_hash.as_bytes())
-        .extend_big(1, &b.debt_supply)
-        .extend_raw_big(3, &b.kryder_plus_rate_multiplier)
-        .extend_raw_big(1, &b.kryder_plus_rate_multiplier_latch)
-        .extend_raw_big(3, &b.denomination)
-        .extend_u64(1, &b.redenomination_height)
-        .extend_raw_buf(proof_bytes.len(), &proof_bytes)
-        .extend_big(2, &b.previous_cumulative_diff)
-        // Added in 2.7
-        .extend_big(2, &b.merkle_rebase_support_threshold)
-        .extend_buf(3, b.poa.data_path.as_slice())
-        .extend_buf(3, b.poa.tx_path.as_slice())
-        .extend_buf(3, b.poa2.data_path.as_slice())
-        .extend_buf(3, b.poa2.tx_path.as_slice())
-        .extend_raw_buf(32, b.chunk_hash.as_bytes())
-        .extend_optional_hash(1, &b.chunk2_hash)
-        .extend_raw_buf(32, b.block_time_history_hash.as_bytes())
-        .extend_u64(1, &nonce_info.vdf_difficulty.unwrap_or_default())
-        .extend_u64(1, &nonce_info.next_vdf_difficulty.unwrap_or_default());
-
-    // if let Some(i) = first_mismatch_index(&expected, &buff) {
-    //     println!(
-    //         "Found mismatched byte at index: {i} found:{} expected:{}",
-    //         buff[i], expected[i]
-    //     );
-    // }
-
-    let mut hasher = sha::Sha256::new();
-    hasher.update(&buff);
-    let signed_hash = hasher.finish();
-
-    let mut hasher = sha::Sha384::new();
-    hasher.update(&signed_hash);
-    hasher.update(b.signature.as_slice());
-    let hash = H384::from(hasher.finish());
-
-    hash == b.indep_hash
+fn block_hash_is_valid(block_header: &ArweaveBlockHeader, params: &ConsensusParams) -> bool {
+    compute_indep_hash(block_header, params) == block_header.indep_hash
 }
 
-fn is_retarget_height(block_header: &ArweaveBlockHeader) -> bool {
+fn is_retarget_height(block_header: &ArweaveBlockHeader, params: &ConsensusParams) -> bool {
     let height = block_header.height;
-    height % RETARGET_BLOCKS == 0 && height != 0
+    height % params.retarget_blocks == 0 && height != 0
 }
 
-/// Utility function for debugging
-fn first_mismatch_index(vec1: &[u8], vec2: &[u8]) -> Option<usize> {
-    vec1.iter().zip(vec2.iter()).enumerate().find_map(
-        |(index, (&val1, &val2))| {
-            if val1 != val2 {
-                Some(index)
-            } else {
-                None
-            }
-        },
-    )
-}
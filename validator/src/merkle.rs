@@ -1,11 +1,12 @@
 //! Validates merkle tree proofs for the `Proof of Access` chunks in an Arweave
 //! block header.
-use arweave_rs_types::*;
+use arweave_rs_types::{consensus::generate_chunk_id, *};
 use borsh::BorshDeserialize;
 use borsh_derive::BorshDeserialize;
 use color_eyre::eyre::eyre;
 use eyre::Error;
 use openssl::sha;
+use tracing::{debug, trace};
 
 
 /// Single struct used for original data chunks (Leaves) and branch nodes (hashes of pairs of child nodes).
@@ -73,7 +74,7 @@ impl ProofDeserialize<BranchProof> for BranchProof {
 pub const MAX_CHUNK_SIZE: usize = 256 * 1024;
 pub const MIN_CHUNK_SIZE: usize = 32 * 1024;
 pub const HASH_SIZE: usize = 32;
-const NOTE_SIZE: usize = 32;
+pub const NOTE_SIZE: usize = 32;
 
 /// Includes a function to convert a number to a Vec of 32 bytes per the Arweave spec.
 pub trait Helpers<T> {
@@ -95,21 +96,33 @@ pub struct ValidatePathResult {
     pub right_bound: u128,
 }
 
+/// Splits a merkle path into its branch proofs, ordered from the root to the
+/// leaf, and the final leaf proof. Returns an error unless the path holds a
+/// leaf proof preceded by whole branch proofs.
+fn split_proof(path: &[u8]) -> Result<(Vec<BranchProof>, LeafProof), Error> {
+    let branches_size = path
+        .len()
+        .checked_sub(HASH_SIZE + NOTE_SIZE)
+        .ok_or_else(|| eyre!("merkle path is shorter than a leaf proof"))?;
+    if branches_size % (HASH_SIZE * 2 + NOTE_SIZE) != 0 {
+        return Err(eyre!("merkle path is not a whole number of branch proofs"));
+    }
+
+    let (branches, leaf) = path.split_at(branches_size);
+    let branch_proofs = branches
+        .chunks(HASH_SIZE * 2 + NOTE_SIZE)
+        .map(BranchProof::try_from_proof_slice)
+        .collect::<Result<Vec<_>, _>>()?;
+    let leaf_proof = LeafProof::try_from_proof_slice(leaf)?;
+    Ok((branch_proofs, leaf_proof))
+}
+
 pub fn validate_path(
     root_hash: [u8; HASH_SIZE],
     path_buff: &Base64,
     target_offset: u128,
 ) -> Result<ValidatePathResult, Error> {
-    // Split proof into branches and leaf. Leaf is the final proof and branches
-    // are ordered from root to leaf.
-    let (branches, leaf) = path_buff.split_at(path_buff.len() - HASH_SIZE - NOTE_SIZE);
-
-    // Deserialize proof.
-    let branch_proofs: Vec<BranchProof> = branches
-        .chunks(HASH_SIZE * 2 + NOTE_SIZE)
-        .map(|b| BranchProof::try_from_proof_slice(b).unwrap())
-        .collect();
-    let leaf_proof = LeafProof::try_from_proof_slice(leaf)?;
+    let (branch_proofs, leaf_proof) = split_proof(path_buff.as_slice())?;
 
     let mut left_bound: u128 = 0;
     let mut expected_path_hash = root_hash;
@@ -143,21 +156,20 @@ pub fn validate_path(
             left_bound = offset;
         }
 
-        // println!(
-        //     "BranchProof: left: {}{}, right: {}{},offset: {} => path_hash: {}",
-        //     if is_right_of_offset { "" } else { "✅" },
-        //     base64_url::encode(&branch_proof.left_id),
-        //     if is_right_of_offset { "✅" } else { "" },
-        //     base64_url::encode(&branch_proof.right_id),
-        //     branch_proof.offset(),
-        //     base64_url::encode(&path_hash)
-        // );
+        trace!(
+            left_id = %base64_url::encode(&branch_proof.left_id),
+            right_id = %base64_url::encode(&branch_proof.right_id),
+            offset = branch_proof.offset(),
+            path_hash = %base64_url::encode(&path_hash),
+            is_right_of_offset,
+            "branch proof"
+        );
     }
-    // println!(
-    //     "  LeafProof: data_hash: {}, offset: {}",
-    //     base64_url::encode(&leaf_proof.data_hash),
-    //     usize::from_be_bytes(leaf_proof.offset)
-    // );
+    trace!(
+        data_hash = %base64_url::encode(&leaf_proof.data_hash),
+        offset = leaf_proof.offset(),
+        "leaf proof"
+    );
 
     // Proof nodes (including leaf nodes) always contain their right bound
     let right_bound = leaf_proof.offset() as u128;
@@ -169,18 +181,9 @@ pub fn validate_path(
     })
 }
 
-/// Utility method for logging a proof out to the terminal.
+/// Utility method for logging a proof as `debug` events.
 pub fn print_debug(proof: &Vec<u8>, target_offset: u128) -> Result<([u8; 32], u128, u128), Error> {
-    // Split proof into branches and leaf. Leaf is at the end and branches are
-    // ordered from root to leaf.
-    let (branches, leaf) = proof.split_at(proof.len() - HASH_SIZE - NOTE_SIZE);
-
-    // Deserialize proof.
-    let branch_proofs: Vec<BranchProof> = branches
-        .chunks(HASH_SIZE * 2 + NOTE_SIZE)
-        .map(|b| BranchProof::try_from_proof_slice(b).unwrap())
-        .collect();
-    let leaf_proof = LeafProof::try_from_proof_slice(leaf)?;
+    let (branch_proofs, leaf_proof) = split_proof(proof)?;
 
     let mut left_bound: u128 = 0;
 
@@ -201,20 +204,19 @@ pub fn print_debug(proof: &Vec<u8>, target_offset: u128) -> Result<([u8; 32], u1
             left_bound = offset;
         }
 
-        println!(
-            "BranchProof: left: {}{}, right: {}{},offset: {} => path_hash: {}",
-            if is_right_of_offset { "" } else { "✅" },
-            base64_url::encode(&branch_proof.left_id),
-            if is_right_of_offset { "✅" } else { "" },
-            base64_url::encode(&branch_proof.right_id),
-            branch_proof.offset(),
-            base64_url::encode(&path_hash)
+        debug!(
+            left_id = %base64_url::encode(&branch_proof.left_id),
+            right_id = %base64_url::encode(&branch_proof.right_id),
+            offset = branch_proof.offset(),
+            path_hash = %base64_url::encode(&path_hash),
+            is_right_of_offset,
+            "branch proof"
         );
     }
-    println!(
-        "  LeafProof: data_hash: {}, offset: {}",
-        base64_url::encode(&leaf_proof.data_hash),
-        usize::from_be_bytes(leaf_proof.offset)
+    debug!(
+        data_hash = %base64_url::encode(&leaf_proof.data_hash),
+        offset = leaf_proof.offset(),
+        "leaf proof"
     );
 
     let right_bound = leaf_proof.offset() as u128;
@@ -281,6 +283,153 @@ pub fn validate_chunk(
     Ok(())
 }
 
+/// Creates a leaf [`Node`] for `data` (typically a chunk id or a data_root)
+/// whose bytes end at `max_byte_range` (ar_merkle:generate_leaves).
+pub fn generate_leaf(
+    data: &[u8],
+    min_byte_range: usize,
+    max_byte_range: usize,
+) -> Result<Node, Error> {
+    let id = hash_all_sha256(vec![data, &max_byte_range.to_note_vec()])?;
+    Ok(Node {
+        id,
+        data_hash: data.try_into().ok(),
+        min_byte_range,
+        max_byte_range,
+        left_child: None,
+        right_child: None,
+    })
+}
+
+/// Hashes together a pair of child [`Node`]s to create their parent branch
+/// [`Node`]. The note of a branch is the right bound of its left child.
+pub fn hash_branch(left: Node, right: Node) -> Result<Node, Error> {
+    let id = hash_all_sha256(vec![
+        &left.id,
+        &right.id,
+        &left.max_byte_range.to_note_vec(),
+    ])?;
+    Ok(Node {
+        id,
+        data_hash: None,
+        min_byte_range: left.min_byte_range,
+        max_byte_range: right.max_byte_range,
+        left_child: Some(Box::new(left)),
+        right_child: Some(Box::new(right)),
+    })
+}
+
+/// Builds the next layer of the tree by pairing up the [`Node`]s from left to
+/// right. An unpaired node at the end of the layer is promoted unchanged.
+pub fn build_layer(nodes: Vec<Node>) -> Result<Vec<Node>, Error> {
+    let mut layer = Vec::with_capacity(nodes.len() / 2 + 1);
+    let mut nodes_iter = nodes.into_iter();
+    while let Some(left) = nodes_iter.next() {
+        match nodes_iter.next() {
+            Some(right) => layer.push(hash_branch(left, right)?),
+            None => layer.push(left),
+        }
+    }
+    Ok(layer)
+}
+
+/// Builds the layers of the tree from its `leaves` until only the root
+/// [`Node`] remains. Returns `None` if there are no leaves.
+pub fn generate_root(leaves: Vec<Node>) -> Result<Option<Node>, Error> {
+    let mut layer = leaves;
+    while layer.len() > 1 {
+        layer = build_layer(layer)?;
+    }
+    Ok(layer.pop())
+}
+
+/// Walks the tree from `node` down to its leaves and returns the merkle
+/// [`Proof`] of every leaf, ordered left to right. `proof` is the partial path
+/// from the root to `node`, `None` when resolving from the root. The `offset`
+/// of each proof is the last byte of its leaf (ar_merkle:generate_path).
+pub fn resolve_proofs(node: Node, proof: Option<Proof>) -> Result<Vec<Proof>, Error> {
+    let mut proof = proof.unwrap_or(Proof {
+        offset: 0,
+        proof: Vec::new(),
+    });
+
+    match (node.left_child, node.right_child) {
+        (Some(left_child), Some(right_child)) => {
+            proof.proof.extend(left_child.id);
+            proof.proof.extend(right_child.id);
+            proof.proof.extend(left_child.max_byte_range.to_note_vec());
+
+            let mut proofs = resolve_proofs(*left_child, Some(proof.clone()))?;
+            proofs.extend(resolve_proofs(*right_child, Some(proof))?);
+            Ok(proofs)
+        }
+        (None, None) => {
+            let data_hash = node
+                .data_hash
+                .ok_or_else(|| eyre!("Leaf node is missing its data_hash"))?;
+            proof.offset = node.max_byte_range - 1;
+            proof.proof.extend(data_hash);
+            proof.proof.extend(node.max_byte_range.to_note_vec());
+            Ok(vec![proof])
+        }
+        _ => Err(eyre!("Branch node is missing a child")),
+    }
+}
+
+/// Walks the tree from the `root` down to the leaf containing the byte at
+/// `target_offset` and returns its merkle path, the proof [`validate_path()`]
+/// checks (ar_merkle:generate_path).
+pub fn generate_path(root: &Node, target_offset: usize) -> Result<Vec<u8>, Error> {
+    let mut path = Vec::new();
+    let mut node = root;
+    loop {
+        match (&node.left_child, &node.right_child) {
+            (Some(left_child), Some(right_child)) => {
+                path.extend(left_child.id);
+                path.extend(right_child.id);
+                path.extend(left_child.max_byte_range.to_note_vec());
+
+                // Bytes at or past the note of the branch are in the right child
+                node = if target_offset < left_child.max_byte_range {
+                    left_child
+                } else {
+                    right_child
+                };
+            }
+            (None, None) => {
+                let data_hash = node
+                    .data_hash
+                    .ok_or_else(|| eyre!("Leaf node is missing its data_hash"))?;
+                path.extend(data_hash);
+                path.extend(node.max_byte_range.to_note_vec());
+                return Ok(path);
+            }
+            _ => return Err(eyre!("Branch node is missing a child")),
+        }
+    }
+}
+
+/// Computes the data_root of a format 1 transaction from its `data`. The data
+/// is split into `MAX_CHUNK_SIZE` chunks where, like `ar_tx:chunk_binary`,
+/// data that is an exact multiple of the chunk size ends with an empty chunk.
+pub fn generate_data_root(data: &[u8]) -> Result<[u8; HASH_SIZE], Error> {
+    let mut leaves = Vec::new();
+    let mut offset = 0;
+    loop {
+        let chunk_size = MAX_CHUNK_SIZE.min(data.len() - offset);
+        let chunk = &data[offset..offset + chunk_size];
+        let chunk_id = generate_chunk_id(chunk);
+        leaves.push(generate_leaf(&chunk_id, offset, offset + chunk_size)?);
+        offset += chunk_size;
+        if chunk_size < MAX_CHUNK_SIZE {
+            break;
+        }
+    }
+
+    let root = generate_root(leaves)?.ok_or_else(|| eyre!("Unable to generate data_root"))?;
+    Ok(root.id)
+}
+
 pub fn hash_sha256(message: &[u8]) -> Result<[u8; 32], Error> {
     let mut hasher = sha::Sha256::new();
     hasher.update(message);
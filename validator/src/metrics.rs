@@ -0,0 +1,77 @@
+//! The metrics recorded while validating blocks. They're recorded through the
+//! `metrics` facade, so nothing is collected until a binary installs a
+//! recorder, e.g. a Prometheus exporter.
+use metrics::{describe_counter, describe_histogram, histogram, increment_counter, Unit};
+use std::time::Instant;
+use tracing::{debug, debug_span};
+
+/// Blocks run through [`pre_validate_block()`](crate::pre_validate_block),
+/// labelled with their `proof` (`poa` or `poa2`) and `result` (`valid` or
+/// `invalid`).
+pub const BLOCKS_VALIDATED: &str = "arweave_blocks_validated_total";
+/// Blocks failing validation, labelled with the `reason` naming the failed
+/// check.
+pub const VALIDATION_FAILURES: &str = "arweave_validation_failures_total";
+/// The time spent in each validation stage, labelled with the `stage`.
+pub const STAGE_SECONDS: &str = "arweave_validation_stage_seconds";
+/// Histogram buckets for [`STAGE_SECONDS`], from the sub millisecond hashes to
+/// the VDF checkpoints of a block taking a minute or more.
+pub const STAGE_SECONDS_BUCKETS: &[f64] = &[
+    0.0001, 0.0005, 0.001, 0.005, 0.01, 0.05, 0.1, 0.5, 1.0, 5.0, 30.0, 120.0,
+];
+
+/// The `stage` labels of [`STAGE_SECONDS`].
+pub mod stage {
+    /// Computing the `indep_hash` of the block.
+    pub const HASH: &str = "hash";
+    /// Computing the mining hash (H0) and solution hash of the quick PoW.
+    pub const POW: &str = "pow";
+    /// Computing the RandomX entropy a PoA chunk was packed with.
+    pub const RANDOMX_ENTROPY: &str = "randomx_entropy";
+    /// Unpacking a PoA chunk with the feistel cipher.
+    pub const FEISTEL: &str = "feistel";
+    /// Validating the `tx_path` and `data_path` merkle proofs of a PoA chunk.
+    pub const MERKLE: &str = "merkle";
+    /// Recomputing the VDF checkpoints of a block.
+    pub const VDF: &str = "vdf";
+}
+
+/// Describes the metrics of the validator and of the block index and client
+/// it uses to a recorder. Call it once the recorder is installed.
+pub fn describe_metrics() {
+    describe_counter!(
+        BLOCKS_VALIDATED,
+        Unit::Count,
+        "Blocks pre-validated, by proof (poa or poa2) and result"
+    );
+    describe_counter!(
+        VALIDATION_FAILURES,
+        Unit::Count,
+        "Blocks failing validation, by the reason they failed"
+    );
+    describe_histogram!(
+        STAGE_SECONDS,
+        Unit::Seconds,
+        "Time spent in each block validation stage"
+    );
+    arweave_rs_indexes::metrics::describe_metrics();
+}
+
+/// Records the time `f` takes as the latency of the validation `stage`, in a
+/// span with the `stage` as a field.
+pub(crate) fn time_stage<T>(stage: &'static str, f: impl FnOnce() -> T) -> T {
+    let _span = debug_span!("stage", stage).entered();
+    let start = Instant::now();
+    let result = f();
+    let elapsed = start.elapsed();
+    histogram!(STAGE_SECONDS, elapsed, "stage" => stage);
+    debug!(elapsed_ms = elapsed.as_secs_f64() * 1000.0, "stage completed");
+    result
+}
+
+/// Counts a block failing the `reason` check, returning its `err`.
+pub(crate) fn invalid(reason: &'static str, err: eyre::Report) -> eyre::Report {
+    increment_counter!(VALIDATION_FAILURES, "reason" => reason);
+    debug!(reason, error = %err, "block failed validation");
+    err
+}
@@ -0,0 +1,209 @@
+//! Assembles the `poa` and `poa2` proofs of access of a block being produced.
+//! The `tx_path` and `data_path` of a recall byte are generated from the merkle
+//! trees of the blocks transactions and their chunks, and written into the
+//! block header along with the hashes derived from the packed chunks.
+use crate::merkle::*;
+use arweave_rs_indexes::*;
+use arweave_rs_types::{consensus::*, *};
+use color_eyre::eyre::{eyre, Result};
+use openssl::sha;
+use std::collections::HashMap;
+
+/// Builds the proofs of access of recall bytes. The merkle trees of the blocks
+/// containing the recall bytes are added with [`PoaBuilder::add_block_txs()`]
+/// and the trees of the transactions containing them with
+/// [`PoaBuilder::add_tx_chunks()`].
+pub struct PoaBuilder<'a> {
+    block_index: &'a BlockIndex<Initialized>,
+    params: &'a ConsensusParams,
+    /// The tx_root trees of the added blocks keyed by their tx_root.
+    tx_trees: HashMap<H256, Node>,
+    /// The data_root trees of the added transactions keyed by their data_root.
+    data_trees: HashMap<[u8; HASH_SIZE], Node>,
+}
+
+impl<'a> PoaBuilder<'a> {
+    pub fn new(block_index: &'a BlockIndex<Initialized>, params: &'a ConsensusParams) -> Self {
+        PoaBuilder {
+            block_index,
+            params,
+            tx_trees: HashMap::new(),
+            data_trees: HashMap::new(),
+        }
+    }
+
+    /// Adds the `(data_root, end_offset)` pairs of the transactions of a block,
+    /// see [`crate::transaction::generate_size_tagged_txs()`]. Returns the
+    /// tx_root of the block.
+    pub fn add_block_txs(&mut self, size_tagged_txs: &[(Vec<u8>, u64)]) -> Result<H256> {
+        let mut leaves = Vec::with_capacity(size_tagged_txs.len());
+        let mut start_offset = 0;
+        for (data_root, end_offset) in size_tagged_txs {
+            let end_offset = *end_offset as usize;
+            leaves.push(generate_leaf(data_root, start_offset, end_offset)?);
+            start_offset = end_offset;
+        }
+        let root = generate_root(leaves)?.ok_or_else(|| eyre!("block has no transactions"))?;
+        let tx_root = H256(root.id);
+        self.tx_trees.insert(tx_root, root);
+        Ok(tx_root)
+    }
+
+    /// Adds the `(chunk_id, end_offset)` pairs of the chunks of a transactions
+    /// data, the end offsets are relative to the start of the data. Returns
+    /// the data_root of the transaction.
+    pub fn add_tx_chunks(&mut self, chunks: &[([u8; HASH_SIZE], u64)]) -> Result<H256> {
+        let mut leaves = Vec::with_capacity(chunks.len());
+        let mut start_offset = 0;
+        for (chunk_id, end_offset) in chunks {
+            let end_offset = *end_offset as usize;
+            leaves.push(generate_leaf(chunk_id, start_offset, end_offset)?);
+            start_offset = end_offset;
+        }
+        let root = generate_root(leaves)?.ok_or_else(|| eyre!("transaction has no chunks"))?;
+        let data_root = root.id;
+        self.data_trees.insert(data_root, root);
+        Ok(H256(data_root))
+    }
+
+    /// Builds the proof of access of the `packed_chunk` containing the
+    /// `recall_byte`. The block containing the byte is looked up in the block
+    /// index and both paths are checked with [`validate_path()`] as they are
+    /// generated.
+    pub fn build_poa(&self, recall_byte: u64, packed_chunk: &[u8]) -> Result<PoaData> {
+        if packed_chunk.len() != DATA_CHUNK_SIZE as usize {
+            return Err(eyre!("packed chunk is {} bytes", packed_chunk.len()));
+        }
+
+        // get_block_bounds expects the byte to be inside the indexed weave
+        let weave_size = match self.block_index.num_indexes() {
+            0 => 0,
+            num_indexes => {
+                self.block_index
+                    .get_item(num_indexes as usize - 1)
+                    .unwrap()
+                    .weave_size
+            }
+        };
+        if recall_byte as u128 >= weave_size {
+            return Err(eyre!(
+                "recall byte {recall_byte} is beyond the indexed weave"
+            ));
+        }
+        let block_bounds = self.block_index.get_block_bounds(recall_byte as u128);
+        let byte_offset_in_block = get_byte_offset(
+            U256::from(recall_byte),
+            block_bounds.block_start_offset,
+            block_bounds.block_end_offset,
+            self.params,
+        );
+
+        // TX_PATH from the tx_root to the data_root of the transaction
+        let tx_tree = self.tx_trees.get(&block_bounds.tx_root).ok_or_else(|| {
+            eyre!(
+                "transactions of the block with tx_root {} were not added",
+                base64_url::encode(&block_bounds.tx_root)
+            )
+        })?;
+        let tx_path = Base64(generate_path(tx_tree, byte_offset_in_block as usize)?);
+        let tx_path_result = validate_path(block_bounds.tx_root.0, &tx_path, byte_offset_in_block)?;
+
+        // DATA_PATH from the data_root to the chunk id
+        let data_tree = self
+            .data_trees
+            .get(&tx_path_result.leaf_hash)
+            .ok_or_else(|| {
+                eyre!(
+                    "chunks of the transaction with data_root {} were not added",
+                    base64_url::encode(&tx_path_result.leaf_hash)
+                )
+            })?;
+        let byte_offset_in_tx = byte_offset_in_block - tx_path_result.left_bound;
+        let data_path = Base64(generate_path(data_tree, byte_offset_in_tx as usize)?);
+        validate_path(tx_path_result.leaf_hash, &data_path, byte_offset_in_tx)?;
+
+        Ok(PoaData {
+            option: "1".to_string(),
+            tx_path,
+            data_path,
+            chunk: Base64(packed_chunk.to_vec()),
+        })
+    }
+
+    /// Builds the proofs of a solution mined with `mining_hash` (H0) and
+    /// `nonce`. A one chunk solution only has the `packed_chunk` of the
+    /// `recall_byte`, a two chunk solution also has the `second_chunk` of the
+    /// second recall range as `(recall_byte2, packed_chunk2)`.
+    pub fn build_solution(
+        &self,
+        mining_hash: &[u8; 32],
+        nonce: u64,
+        recall_byte: u64,
+        packed_chunk: &[u8],
+        second_chunk: Option<(u64, &[u8])>,
+    ) -> Result<SolutionProofs> {
+        let poa = self.build_poa(recall_byte, packed_chunk)?;
+        let h1 = compute_h1(mining_hash, nonce, packed_chunk);
+
+        let ((solution_hash, hash_preimage), recall_byte2, poa2) = match second_chunk {
+            Some((recall_byte2, packed_chunk2)) => (
+                compute_h2(&h1.0, packed_chunk2, mining_hash),
+                Some(recall_byte2),
+                self.build_poa(recall_byte2, packed_chunk2)?,
+            ),
+            None => (h1, None, PoaData::default()),
+        };
+
+        Ok(SolutionProofs {
+            nonce,
+            recall_byte,
+            recall_byte2,
+            hash_preimage,
+            solution_hash: H256(solution_hash),
+            poa,
+            poa2,
+        })
+    }
+}
+
+/// The proofs of a mining solution and the hashes derived from them.
+#[derive(Debug, Clone)]
+pub struct SolutionProofs {
+    pub nonce: u64,
+    pub recall_byte: u64,
+    /// The second recall byte of a two chunk solution.
+    pub recall_byte2: Option<u64>,
+    /// H2 for two chunk solutions, otherwise H1.
+    pub hash_preimage: H256,
+    pub solution_hash: H256,
+    pub poa: PoaData,
+    /// The proof of the second chunk, empty for one chunk solutions.
+    pub poa2: PoaData,
+}
+
+impl SolutionProofs {
+    /// `SHA-256` of the first packed chunk.
+    pub fn chunk_hash(&self) -> H256 {
+        H256(sha::sha256(self.poa.chunk.as_slice()))
+    }
+
+    /// `SHA-256` of the second packed chunk of a two chunk solution.
+    pub fn chunk2_hash(&self) -> Option<H256> {
+        self.recall_byte2
+            .map(|_| H256(sha::sha256(self.poa2.chunk.as_slice())))
+    }
+
+    /// Writes the solution fields of the block header: the `nonce`, recall
+    /// bytes, proofs, chunk hashes, `hash_preimage` and `hash`.
+    pub fn apply_to_header(&self, block_header: &mut ArweaveBlockHeader) {
+        block_header.nonce = Nonce(self.nonce);
+        block_header.recall_byte = self.recall_byte;
+        block_header.recall_byte2 = self.recall_byte2.map(U256::from);
+        block_header.poa = self.poa.clone();
+        block_header.poa2 = self.poa2.clone();
+        block_header.chunk_hash = self.chunk_hash();
+        block_header.chunk2_hash = self.chunk2_hash();
+        block_header.hash_preimage = self.hash_preimage;
+        block_header.hash = self.solution_hash;
+    }
+}
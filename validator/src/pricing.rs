@@ -0,0 +1,144 @@
+//! Validates the economic fields of a block header, the block reward, the
+//! endowment pool, the debt supply, the Kryder+ rate multiplier and the price
+//! per GiB minute, against the values derived from the previous block.
+use arweave_rs_types::{consensus::*, pricing::*, reward_history::*, transaction::*, *};
+use color_eyre::eyre::{eyre, Result};
+
+/// Recomputes the miner reward and the endowment pool updates of a block from
+/// its previous block and the fees of its `txs`, returning an error naming
+/// the first inconsistent field.
+///
+/// `block_interval` is the number of seconds the block reward pays storage
+/// for, [`TARGET_TIME`] before the 2.7 fork and the average interval of the
+/// block_time_history after.
+///
+/// This isn't one of the `pre_validate_block` stages: pre-validation only has
+/// the headers, while the rewards need the blocks transactions and, from the
+/// 2.7 fork, the block time history. Callers run it once they have fetched
+/// both.
+pub fn validate_block_rewards(
+    block_header: &ArweaveBlockHeader,
+    previous_block_header: &ArweaveBlockHeader,
+    txs: &[ArweaveTransaction],
+    block_interval: u64,
+    params: &ConsensusParams,
+) -> Result<()> {
+    if block_header.height < params.fork_2_6_height {
+        return Err(eyre!("block rewards are only validated from the 2.6 fork"));
+    }
+
+    if !price_per_gib_minute_is_valid(block_header, previous_block_header, params) {
+        return Err(eyre!(
+            "price_per_gib_minute or its scheduled price is invalid"
+        ));
+    }
+
+    // Like ar_node_utils:update_accounts the price and denomination in effect
+    // are those of the previous block, they differ from the blocks own at
+    // reschedule and redenomination heights
+    let denomination = previous_block_header.denomination;
+    let args = MinerRewardArgs {
+        height: block_header.height,
+        weave_size: block_header.weave_size,
+        price_per_gib_minute: previous_block_header.price_per_gib_minute,
+        denomination,
+        block_interval,
+        fees: distribute_transaction_fees(txs, denomination),
+    };
+    let expected = get_miner_reward_endowment_pool_debt_supply(previous_block_header, &args);
+
+    let actual = [
+        ("reward", U256::from(block_header.reward)),
+        ("reward_pool", U256::from(block_header.reward_pool)),
+        ("debt_supply", block_header.debt_supply),
+        (
+            "kryder_plus_rate_multiplier",
+            block_header.kryder_plus_rate_multiplier,
+        ),
+        (
+            "kryder_plus_rate_multiplier_latch",
+            block_header.kryder_plus_rate_multiplier_latch,
+        ),
+    ];
+    let computed = [
+        expected.reward,
+        expected.reward_pool,
+        expected.debt_supply,
+        expected.kryder_plus_rate_multiplier,
+        expected.kryder_plus_rate_multiplier_latch,
+    ];
+    for ((field, actual), computed) in actual.iter().zip(computed) {
+        if *actual != computed {
+            return Err(eyre!(
+                "{field} is invalid, computed: {computed} actual: {actual}"
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// Checks the price per GiB minute follows the rescheduling rules. Between
+/// adjustment heights both prices carry over unchanged, at an adjustment
+/// height the scheduled price takes effect and the newly scheduled price must
+/// stay within [`scheduled_price_bounds()`]. The exact value of the newly
+/// scheduled price depends on the reward history.
+fn price_per_gib_minute_is_valid(
+    block_header: &ArweaveBlockHeader,
+    previous_block_header: &ArweaveBlockHeader,
+    params: &ConsensusParams,
+) -> bool {
+    let scheduled_price = block_header.scheduled_price_per_gib_minute;
+    let (price, expected_scheduled_price) =
+        recalculate_price_per_gib_minute(previous_block_header, params, || scheduled_price);
+
+    block_header.price_per_gib_minute == price && scheduled_price == expected_scheduled_price
+}
+
+/// Checks the `reward_history_hash` of a block. `previous_reward_history` has
+/// to match the `reward_history_hash` of the previous block, the block then
+/// commits to it with its own entry added. At price adjustment heights the
+/// newly scheduled `price_per_gib_minute` is checked against the estimate
+/// from the previous history.
+///
+/// Returns the updated reward history for validating the next block.
+///
+/// Note: The exact estimate is only checked from the 2.6 fork up to the 2.6.8
+/// fork. The price transition that starts at the 2.6.8 fork and the 2.7
+/// estimate, which also depends on the block_time_history, aren't
+/// implemented, so after the 2.6.8 fork only the bounds are checked.
+pub fn validate_reward_history(
+    block_header: &ArweaveBlockHeader,
+    previous_block_header: &ArweaveBlockHeader,
+    previous_reward_history: &RewardHistory,
+    params: &ConsensusParams,
+) -> Result<RewardHistory> {
+    if previous_reward_history.hash() != previous_block_header.reward_history_hash {
+        return Err(eyre!(
+            "reward history does not match the previous blocks reward_history_hash"
+        ));
+    }
+
+    let reward_history = previous_reward_history.with_block(block_header);
+    if reward_history.hash() != block_header.reward_history_hash {
+        return Err(eyre!("reward_history_hash is invalid"));
+    }
+
+    if block_header.height < params.fork_2_6_8_height {
+        let (_, scheduled_price) =
+            recalculate_price_per_gib_minute(previous_block_header, params, || {
+                let (hash_rate_total, reward_total) =
+                    previous_reward_history.totals(previous_block_header.denomination);
+                get_price_per_gib_minute(hash_rate_total, reward_total, params)
+            });
+        if scheduled_price != block_header.scheduled_price_per_gib_minute {
+            return Err(eyre!(
+                "scheduled_price_per_gib_minute is invalid, computed: {} actual: {}",
+                scheduled_price,
+                block_header.scheduled_price_per_gib_minute
+            ));
+        }
+    }
+
+    Ok(reward_history)
+}
@@ -0,0 +1,115 @@
+//! The wall time of each stage of [`pre_validate_block()`](crate::pre_validate_block),
+//! for profiling where validation spends its time.
+use std::time::{Duration, Instant};
+
+/// The stages of pre-validation. The PoA stages are those of the first chunk,
+/// the whole validation of the second chunk is timed as [`Self::Poa2`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ValidationStage {
+    /// Checking the sizes of the PoA and PoA2 proofs of the block and its
+    /// parent.
+    ProofSizes,
+    /// Checking `chunk_hash` and `chunk2_hash` against the proof chunks.
+    ChunkHashes,
+    /// Computing the `indep_hash` of the block.
+    IndepHash,
+    /// Checking `last_retarget`, `diff` and `cumulative_diff`.
+    RetargetAndDiff,
+    /// Computing the mining hash (H0) and solution hash with RandomX.
+    QuickPow,
+    /// Checking the nonce limiter seed data, partition number and nonce.
+    SeedData,
+    /// Computing the recall bytes of the solution.
+    RecallBytes,
+    /// Computing the RandomX entropy the PoA chunk was packed with.
+    PoaEntropy,
+    /// Unpacking the PoA chunk with the feistel cipher.
+    Feistel,
+    /// Validating the `tx_path` and `data_path` merkle proofs of the PoA.
+    Merkle,
+    /// Validating the PoA2 of a two chunk solution.
+    Poa2,
+}
+
+impl ValidationStage {
+    /// Every stage, in the order they're reported.
+    pub const ALL: [ValidationStage; 11] = [
+        ValidationStage::ProofSizes,
+        ValidationStage::ChunkHashes,
+        ValidationStage::IndepHash,
+        ValidationStage::RetargetAndDiff,
+        ValidationStage::QuickPow,
+        ValidationStage::SeedData,
+        ValidationStage::RecallBytes,
+        ValidationStage::PoaEntropy,
+        ValidationStage::Feistel,
+        ValidationStage::Merkle,
+        ValidationStage::Poa2,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ValidationStage::ProofSizes => "proof_sizes",
+            ValidationStage::ChunkHashes => "chunk_hashes",
+            ValidationStage::IndepHash => "indep_hash",
+            ValidationStage::RetargetAndDiff => "retarget_and_diff",
+            ValidationStage::QuickPow => "quick_pow",
+            ValidationStage::SeedData => "seed_data",
+            ValidationStage::RecallBytes => "recall_bytes",
+            ValidationStage::PoaEntropy => "poa_entropy",
+            ValidationStage::Feistel => "feistel",
+            ValidationStage::Merkle => "merkle",
+            ValidationStage::Poa2 => "poa2",
+        }
+    }
+}
+
+/// The wall time of the stages a block ran through.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ValidationReport {
+    stages: [Option<Duration>; ValidationStage::ALL.len()],
+}
+
+impl ValidationReport {
+    /// The time spent in `stage`, `None` if validation failed before reaching
+    /// it or, for [`ValidationStage::Poa2`], the block has a one chunk
+    /// solution.
+    pub fn get(&self, stage: ValidationStage) -> Option<Duration> {
+        self.stages[stage as usize]
+    }
+
+    /// The stages that ran and the time spent in each, in the order of
+    /// [`ValidationStage::ALL`].
+    pub fn stages(&self) -> impl Iterator<Item = (ValidationStage, Duration)> + '_ {
+        ValidationStage::ALL
+            .into_iter()
+            .filter_map(|stage| self.get(stage).map(|elapsed| (stage, elapsed)))
+    }
+
+    /// The time spent in all of the stages.
+    pub fn total(&self) -> Duration {
+        self.stages().map(|(_, elapsed)| elapsed).sum()
+    }
+
+    /// Adds `elapsed` to the time of `stage`, the merkle stage validates two
+    /// paths.
+    fn record(&mut self, stage: ValidationStage, elapsed: Duration) {
+        let total = self.stages[stage as usize].get_or_insert(Duration::ZERO);
+        *total += elapsed;
+    }
+}
+
+/// Runs `f`, recording the time it takes as `stage` of the `report`, if there
+/// is one.
+pub(crate) fn record_stage<T>(
+    report: &mut Option<&mut ValidationReport>,
+    stage: ValidationStage,
+    f: impl FnOnce() -> T,
+) -> T {
+    let start = Instant::now();
+    let result = f();
+    if let Some(report) = report {
+        report.record(stage, start.elapsed());
+    }
+    result
+}
@@ -0,0 +1,179 @@
+//! Tracks the solutions of recently validated blocks so a block that was
+//! already validated can be rejected before repeating the expensive checks,
+//! and so a miner signing two different blocks at the same cumulative
+//! difficulty can be reported with a [`DoubleSigningProof`].
+use crate::double_signing::signed_preimage;
+use arweave_rs_types::{consensus::ConsensusParams, *};
+use std::collections::{HashMap, HashSet, VecDeque};
+
+/// The number of blocks a [`SolutionCache`] remembers by default.
+pub const DEFAULT_SOLUTION_CACHE_SIZE: usize = 1000;
+
+/// The fields of a validated block needed to detect duplicate solutions and
+/// to build a double signing proof.
+#[derive(Debug, Clone)]
+pub struct SolutionCacheEntry {
+    pub solution_hash: H256,
+    pub reward_addr: H256,
+    pub cumulative_diff: U256,
+    pub indep_hash: H384,
+    pub previous_cumulative_diff: U256,
+    pub reward_key: Base64,
+    pub signature: Base64,
+    /// The `previous_solution_hash || signed_hash` preimage the miner signed.
+    pub signature_preimage: H512,
+}
+
+impl SolutionCacheEntry {
+    pub fn from_block_header(block_header: &ArweaveBlockHeader, params: &ConsensusParams) -> Self {
+        SolutionCacheEntry {
+            solution_hash: block_header.hash,
+            reward_addr: block_header.reward_addr,
+            cumulative_diff: block_header.cumulative_diff,
+            indep_hash: block_header.indep_hash,
+            previous_cumulative_diff: block_header.previous_cumulative_diff,
+            reward_key: block_header.reward_key.clone(),
+            signature: block_header.signature.clone(),
+            signature_preimage: signed_preimage(block_header, params),
+        }
+    }
+}
+
+/// The result of looking a block up in the [`SolutionCache`].
+#[derive(Debug, Clone)]
+pub enum SolutionCheck {
+    /// Neither the block nor its solution were seen before.
+    New,
+    /// The block itself was already validated.
+    AlreadyValidated,
+    /// Another block with the same solution was already validated.
+    DuplicateSolution,
+    /// The miner of the block signed another block at an overlapping
+    /// cumulative difficulty, the proof can be included in the next block.
+    DoubleSigning(Box<DoubleSigningProof>),
+}
+
+/// A bounded cache of the solutions of recently validated blocks, the oldest
+/// entries are evicted first.
+#[derive(Debug, Clone)]
+pub struct SolutionCache {
+    capacity: usize,
+    params: ConsensusParams,
+    entries: VecDeque<SolutionCacheEntry>,
+    indep_hashes: HashSet<H384>,
+    solution_hashes: HashMap<H256, usize>,
+    reward_addrs: HashMap<H256, usize>,
+}
+
+impl Default for SolutionCache {
+    fn default() -> Self {
+        SolutionCache::new(DEFAULT_SOLUTION_CACHE_SIZE, &ConsensusParams::mainnet())
+    }
+}
+
+impl SolutionCache {
+    /// Creates a cache for the blocks of the network configured by `params`,
+    /// which determine the signed hash the double signing proofs are built
+    /// from.
+    pub fn new(capacity: usize, params: &ConsensusParams) -> Self {
+        SolutionCache {
+            capacity,
+            params: params.clone(),
+            entries: VecDeque::with_capacity(capacity),
+            indep_hashes: HashSet::new(),
+            solution_hashes: HashMap::new(),
+            reward_addrs: HashMap::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Looks up `block_header` before validating it. Only the hash lookups
+    /// are performed for blocks whose miner has no other cached blocks.
+    pub fn check(&self, block_header: &ArweaveBlockHeader) -> SolutionCheck {
+        if self.indep_hashes.contains(&block_header.indep_hash) {
+            return SolutionCheck::AlreadyValidated;
+        }
+
+        if self.reward_addrs.contains_key(&block_header.reward_addr) {
+            let entry = SolutionCacheEntry::from_block_header(block_header, &self.params);
+            let conflicting_entry = self.entries.iter().find(|cached| {
+                cached.reward_addr == entry.reward_addr
+                    && cached.indep_hash != entry.indep_hash
+                    && cumulative_diffs_overlap(cached, &entry)
+            });
+            if let Some(cached) = conflicting_entry {
+                return SolutionCheck::DoubleSigning(Box::new(build_double_signing_proof(
+                    cached, &entry,
+                )));
+            }
+        }
+
+        if self.solution_hashes.contains_key(&block_header.hash) {
+            return SolutionCheck::DuplicateSolution;
+        }
+
+        SolutionCheck::New
+    }
+
+    /// Adds a validated block to the cache, evicting the oldest entry when
+    /// the cache is full.
+    pub fn insert(&mut self, block_header: &ArweaveBlockHeader) {
+        if self.capacity == 0 || self.indep_hashes.contains(&block_header.indep_hash) {
+            return;
+        }
+        if self.entries.len() == self.capacity {
+            if let Some(evicted) = self.entries.pop_front() {
+                self.indep_hashes.remove(&evicted.indep_hash);
+                decrement(&mut self.solution_hashes, &evicted.solution_hash);
+                decrement(&mut self.reward_addrs, &evicted.reward_addr);
+            }
+        }
+
+        let entry = SolutionCacheEntry::from_block_header(block_header, &self.params);
+        self.indep_hashes.insert(entry.indep_hash);
+        *self.solution_hashes.entry(entry.solution_hash).or_default() += 1;
+        *self.reward_addrs.entry(entry.reward_addr).or_default() += 1;
+        self.entries.push_back(entry);
+    }
+}
+
+/// Two blocks were double signed if they have the same cumulative difficulty
+/// or each extends a chain with less cumulative difficulty than the other.
+fn cumulative_diffs_overlap(entry1: &SolutionCacheEntry, entry2: &SolutionCacheEntry) -> bool {
+    entry1.cumulative_diff == entry2.cumulative_diff
+        || (entry1.cumulative_diff > entry2.previous_cumulative_diff
+            && entry2.cumulative_diff > entry1.previous_cumulative_diff)
+}
+
+fn build_double_signing_proof(
+    entry1: &SolutionCacheEntry,
+    entry2: &SolutionCacheEntry,
+) -> DoubleSigningProof {
+    DoubleSigningProof {
+        pub_key: Some(entry1.reward_key.clone()),
+        sig1: Some(entry1.signature.clone()),
+        cdiff1: Some(entry1.cumulative_diff),
+        prev_cdiff1: Some(entry1.previous_cumulative_diff),
+        preimage1: Some(entry1.signature_preimage),
+        sig2: Some(entry2.signature.clone()),
+        cdiff2: Some(entry2.cumulative_diff),
+        prev_cdiff2: Some(entry2.previous_cumulative_diff),
+        preimage2: Some(entry2.signature_preimage),
+    }
+}
+
+fn decrement<K: std::hash::Hash + Eq>(counts: &mut HashMap<K, usize>, key: &K) {
+    if let Some(count) = counts.get_mut(key) {
+        *count -= 1;
+        if *count == 0 {
+            counts.remove(key);
+        }
+    }
+}
@@ -0,0 +1,106 @@
+//! Checks that only apply to recently proposed blocks at the tip of the chain,
+//! rejecting stale or time-warped proposals before the more expensive PoW and
+//! PoA validation. Historical blocks are validated without them as their
+//! timestamps and VDF steps are necessarily far behind the local clock.
+use arweave_rs_types::{consensus::*, *};
+use color_eyre::eyre::{eyre, Result};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The maximum number of seconds the clocks of two nodes may deviate by, the
+/// join tolerance of both nodes plus the allowed drift.
+pub const MAX_NODES_CLOCK_DEVIATION: u64 = JOIN_CLOCK_TOLERANCE * 2 + CLOCK_DRIFT_MAX;
+
+/// Provides the current unix time in seconds, so tip validation can be run
+/// against a fixed time.
+pub trait Clock {
+    fn now(&self) -> u64;
+}
+
+/// A [`Clock`] reading the system time.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |duration| duration.as_secs())
+    }
+}
+
+/// A [`Clock`] always returning the same time.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FixedClock(pub u64);
+
+impl Clock for FixedClock {
+    fn now(&self) -> u64 {
+        self.0
+    }
+}
+
+/// The local state a proposed block is checked against in tip validation
+/// mode.
+pub struct TipValidation<'a> {
+    pub clock: &'a dyn Clock,
+    /// The latest VDF step computed (or received) by the local node.
+    pub local_vdf_step: u64,
+    /// The number of steps the blocks `global_step_number` may differ from
+    /// `local_vdf_step` by, one step is computed roughly every second.
+    pub vdf_step_tolerance: u64,
+}
+
+impl<'a> TipValidation<'a> {
+    /// Creates a [`TipValidation`] allowing the VDF steps to deviate as much as
+    /// the node clocks, [`MAX_NODES_CLOCK_DEVIATION`] steps.
+    pub fn new(clock: &'a dyn Clock, local_vdf_step: u64) -> Self {
+        TipValidation {
+            clock,
+            local_vdf_step,
+            vdf_step_tolerance: MAX_NODES_CLOCK_DEVIATION,
+        }
+    }
+}
+
+/// Performs the recently proposed block checks, the block `timestamp` must be
+/// within the clock deviation of the local clock and not earlier than its
+/// parents, and its VDF `global_step_number` must be close to the locally
+/// known VDF step.
+pub fn validate_tip_block(
+    block_header: &ArweaveBlockHeader,
+    previous_block_header: &ArweaveBlockHeader,
+    tip_validation: &TipValidation,
+) -> Result<()> {
+    if !timestamp_is_valid(block_header, previous_block_header, tip_validation.clock) {
+        return Err(eyre!(
+            "timestamp {} is invalid, previous: {} now: {}",
+            block_header.timestamp,
+            previous_block_header.timestamp,
+            tip_validation.clock.now()
+        ));
+    }
+
+    let step_number = block_header.nonce_limiter_info.global_step_number;
+    if step_number.abs_diff(tip_validation.local_vdf_step) > tip_validation.vdf_step_tolerance {
+        return Err(eyre!(
+            "VDF step {} is out of range of the local VDF step {}",
+            step_number,
+            tip_validation.local_vdf_step
+        ));
+    }
+
+    Ok(())
+}
+
+/// ar_block:verify_timestamp
+fn timestamp_is_valid(
+    block_header: &ArweaveBlockHeader,
+    previous_block_header: &ArweaveBlockHeader,
+    clock: &dyn Clock,
+) -> bool {
+    let timestamp = block_header.timestamp;
+    let earliest = previous_block_header
+        .timestamp
+        .saturating_sub(MAX_NODES_CLOCK_DEVIATION);
+    let latest = clock.now() + MAX_NODES_CLOCK_DEVIATION;
+    timestamp >= earliest && timestamp <= latest
+}
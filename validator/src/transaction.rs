@@ -0,0 +1,185 @@
+//! Validates the identifiers and signatures of Arweave transactions and the
+//! fields of a block header that are derived from its transactions.
+use crate::merkle::{generate_data_root, generate_leaf, generate_root};
+use arweave_rs_types::{consensus::*, transaction::*, *};
+use color_eyre::eyre::{eyre, Result};
+use openssl::{
+    bn::BigNum,
+    hash::MessageDigest,
+    pkey::PKey,
+    rsa::{Padding, Rsa},
+    sha,
+    sign::{RsaPssSaltlen, Verifier},
+};
+
+/// The public exponent of all Arweave RSA keys.
+const RSA_PUBLIC_EXPONENT: u32 = 65537;
+
+/// The data_root used for the padding leaves of the tx_root merkle tree.
+const PADDING_NODE_DATA_ROOT: &[u8] = &[];
+
+/// Performs all of the stateless checks on a transaction, verifying that it
+/// has a supported format, that its `id` is derived from its `signature` and
+/// that the `signature` was produced by the `owner`.
+pub fn validate_transaction(tx: &ArweaveTransaction) -> Result<()> {
+    if tx.format != 1 && tx.format != 2 {
+        return Err(eyre!("unsupported transaction format {}", tx.format));
+    }
+
+    if !tx_id_is_valid(tx) {
+        return Err(eyre!("transaction id does not match the signature hash"));
+    }
+
+    if !tx_signature_is_valid(tx) {
+        return Err(eyre!("transaction signature is invalid"));
+    }
+
+    Ok(())
+}
+
+/// A transactions `id` is the `SHA-256` hash of its `signature`.
+pub fn tx_id_is_valid(tx: &ArweaveTransaction) -> bool {
+    let mut hasher = sha::Sha256::new();
+    hasher.update(tx.signature.as_slice());
+    H256::from(hasher.finish()) == tx.id
+}
+
+/// Verifies the RSA-PSS `signature` of the transactions signature data using
+/// the `owner` as the public key.
+pub fn tx_signature_is_valid(tx: &ArweaveTransaction) -> bool {
+    let signature_data = tx.signature_data();
+    rsa_signature_is_valid(
+        tx.owner.as_slice(),
+        &signature_data,
+        tx.signature.as_slice(),
+    )
+}
+
+/// Verifies an Arweave RSA-PSS (`SHA-256`) signature. `public_key` is the
+/// modulus of the key, Arweave keys always use the public exponent 65537.
+pub fn rsa_signature_is_valid(public_key: &[u8], message: &[u8], signature: &[u8]) -> bool {
+    rsa_verify(public_key, message, signature).unwrap_or(false)
+}
+
+fn rsa_verify(public_key: &[u8], message: &[u8], signature: &[u8]) -> Result<bool> {
+    let n = BigNum::from_slice(public_key)?;
+    let e = BigNum::from_u32(RSA_PUBLIC_EXPONENT)?;
+    let rsa = Rsa::from_public_components(n, e)?;
+    let pkey = PKey::from_rsa(rsa)?;
+
+    let mut verifier = Verifier::new(MessageDigest::sha256(), &pkey)?;
+    verifier.set_rsa_padding(Padding::PKCS1_PSS)?;
+    // Arweave clients sign with different salt lengths, -2 tells OpenSSL to
+    // recover the salt length from the signature when verifying
+    verifier.set_rsa_pss_saltlen(RsaPssSaltlen::custom(-2))?;
+    verifier.update(message)?;
+    Ok(verifier.verify(signature)?)
+}
+
+/// Validates the fields of a block header that are derived from the blocks
+/// transactions. Rebuilds the `tx_root` from the `data_root` and `data_size`
+/// of each of the `txs` and checks the `block_size` and `weave_size`
+/// accounting against the `weave_size` of the previous block.
+///
+/// Note: format 1 transactions must include their `data` as their data_root is
+/// computed from it.
+pub fn validate_block_txs(
+    block_header: &ArweaveBlockHeader,
+    previous_block_header: &ArweaveBlockHeader,
+    txs: &[ArweaveTransaction],
+    params: &ConsensusParams,
+) -> Result<()> {
+    if block_header.previous_block != previous_block_header.indep_hash {
+        return Err(eyre!("previous blocks indep_hash is not the parent block"));
+    }
+
+    // The transactions must be exactly the ones listed in the block header
+    let mut expected_ids: Vec<&[u8]> = block_header.txs.0.iter().map(|id| id.as_slice()).collect();
+    let mut tx_ids: Vec<&[u8]> = txs.iter().map(|tx| tx.id.as_bytes()).collect();
+    expected_ids.sort();
+    tx_ids.sort();
+    if expected_ids != tx_ids {
+        return Err(eyre!("transactions do not match the block header txs"));
+    }
+
+    if let Some(tx) = txs.iter().find(|tx| !tx_id_is_valid(tx)) {
+        return Err(eyre!(
+            "transaction id {} is invalid",
+            base64_url::encode(&tx.id)
+        ));
+    }
+
+    let size_tagged_txs = generate_size_tagged_txs(txs, block_header.height, params)?;
+
+    let tx_root = compute_tx_root(&size_tagged_txs)?;
+    if tx_root != block_header.tx_root {
+        return Err(eyre!("tx_root does not match the blocks transactions"));
+    }
+
+    let block_size = size_tagged_txs
+        .last()
+        .map_or(0, |(_, end_offset)| *end_offset);
+    if block_size != block_header.block_size {
+        return Err(eyre!(
+            "block_size is invalid, computed: {} actual: {}",
+            block_size,
+            block_header.block_size
+        ));
+    }
+
+    let weave_size = previous_block_header.weave_size + block_size;
+    if weave_size != block_header.weave_size {
+        return Err(eyre!(
+            "weave_size is invalid, computed: {} actual: {}",
+            weave_size,
+            block_header.weave_size
+        ));
+    }
+
+    Ok(())
+}
+
+/// Returns a list of `(data_root, end_offset)` pairs for the transactions in a
+/// block (ar_block:generate_size_tagged_list_from_txs). The transactions are
+/// sorted by format and id, and from the 2.5 fork on each transaction whose
+/// data is not a multiple of 256 KiB is followed by a padding entry.
+pub fn generate_size_tagged_txs(
+    txs: &[ArweaveTransaction],
+    height: u64,
+    params: &ConsensusParams,
+) -> Result<Vec<(Vec<u8>, u64)>> {
+    let mut sorted_txs: Vec<&ArweaveTransaction> = txs.iter().collect();
+    sorted_txs.sort_by_key(|tx| (tx.format, tx.id));
+
+    let mut size_tagged_txs = Vec::with_capacity(sorted_txs.len());
+    let mut position = 0;
+    for tx in sorted_txs {
+        let data_root = match tx.format {
+            1 => generate_data_root(tx.data.as_slice())?.to_vec(),
+            _ => tx.data_root.map(|h| h.to_vec()).unwrap_or_default(),
+        };
+        let end_offset = position + tx.data_size;
+        size_tagged_txs.push((data_root, end_offset));
+
+        let padding = get_weave_size_increase(tx.data_size, height, params) - tx.data_size;
+        if padding > 0 {
+            size_tagged_txs.push((PADDING_NODE_DATA_ROOT.to_vec(), end_offset + padding));
+        }
+        position = end_offset + padding;
+    }
+    Ok(size_tagged_txs)
+}
+
+/// Computes the `tx_root` merkle root of a list of size tagged transactions,
+/// `None` if the block has no transactions.
+pub fn compute_tx_root(size_tagged_txs: &[(Vec<u8>, u64)]) -> Result<Option<H256>> {
+    let mut leaves = Vec::with_capacity(size_tagged_txs.len());
+    let mut start_offset = 0;
+    for (data_root, end_offset) in size_tagged_txs {
+        let end_offset = *end_offset as usize;
+        leaves.push(generate_leaf(data_root, start_offset, end_offset)?);
+        start_offset = end_offset;
+    }
+    let root = generate_root(leaves)?;
+    Ok(root.map(|node| H256::from(node.id)))
+}
@@ -0,0 +1,75 @@
+use arweave_rs_test_support::fixtures::*;
+use arweave_rs_types::{consensus::*, *};
+use arweave_rs_validator::{compute_signed_hash, pre_validate_block};
+
+const MAINNET: ConsensusParams = ConsensusParams::mainnet();
+
+#[test]
+fn fork_heights() {
+    assert_eq!(MAINNET.fork_at_height(FORK_2_5_HEIGHT - 1), ConsensusFork::Pre2_5);
+    assert_eq!(MAINNET.fork_at_height(FORK_2_5_HEIGHT), ConsensusFork::Fork2_5);
+    assert_eq!(MAINNET.fork_at_height(FORK_2_6_HEIGHT), ConsensusFork::Fork2_6);
+    assert_eq!(MAINNET.fork_at_height(FORK_2_7_HEIGHT - 1), ConsensusFork::Fork2_6);
+    assert_eq!(MAINNET.fork_at_height(FORK_2_7_HEIGHT), ConsensusFork::Fork2_7);
+}
+
+#[test]
+fn headers_2_6() {
+    // 2.6 headers don't have the fields added in 2.7
+    let mut json = block_header_json(cases::NO_TX);
+    let fields = json.as_object_mut().unwrap();
+    for field in [
+        "block_time_history_hash",
+        "chunk_hash",
+        "merkle_rebase_support_threshold",
+    ] {
+        fields.remove(field);
+    }
+    assert!(serde_json::from_value::<ArweaveBlockHeader>(json).is_ok());
+
+    // The signed hash of 2.6 blocks doesn't commit to the 2.7 fields
+    let block_header = block_header(cases::NO_TX);
+    let mut block_2_6 = block_header.clone();
+    block_2_6.height = FORK_2_7_HEIGHT - 1;
+    let signed_hash_2_6 = compute_signed_hash(&block_2_6, &MAINNET);
+    block_2_6.chunk_hash = H256::zero();
+    assert_eq!(signed_hash_2_6, compute_signed_hash(&block_2_6, &MAINNET));
+    assert_ne!(signed_hash_2_6, compute_signed_hash(&block_header, &MAINNET));
+}
+
+#[test]
+fn spora_blocks_are_rejected() {
+    // SPoRA blocks are rejected before any other check, so the block index
+    // isn't needed
+    let (block_header, previous_block_header) = block_header_pair(cases::DOUBLE_SIGNING_PROOF);
+    let mut spora_block = block_header.clone();
+    spora_block.height = FORK_2_6_HEIGHT - 1;
+    let result = pre_validate_block(
+        &spora_block,
+        &previous_block_header,
+        &empty_block_index(),
+        None,
+        &MAINNET,
+        None,
+    );
+    assert!(result.is_err());
+}
+
+#[test]
+fn genesis_block_is_rejected() {
+    // A network with the 2.7 rules from its genesis block would check the
+    // proofs of the block before it
+    let (block_header, previous_block_header) = block_header_pair(cases::DOUBLE_SIGNING_PROOF);
+    let mut genesis_block = block_header.clone();
+    genesis_block.height = 0;
+    let err = pre_validate_block(
+        &genesis_block,
+        &previous_block_header,
+        &empty_block_index(),
+        None,
+        &ConsensusParams::testnet(),
+        None,
+    )
+    .expect_err("the genesis block to be rejected");
+    assert!(err.to_string().contains("genesis"));
+}
@@ -0,0 +1,166 @@
+use arweave_rs_test_support::fixtures::*;
+use arweave_rs_types::{consensus::ConsensusParams, reward_history::*, *};
+use arweave_rs_validator::{
+    double_signing::{
+        signature_preimage, signed_preimage, validate_double_signing_proof,
+        verify_double_signing_proof,
+    },
+    solution_cache::{SolutionCache, SolutionCheck},
+};
+use openssl::{
+    hash::MessageDigest,
+    pkey::{PKey, Private},
+    rsa::{Padding, Rsa},
+    sha,
+    sign::{RsaPssSaltlen, Signer},
+};
+
+const MAINNET: ConsensusParams = ConsensusParams::mainnet();
+
+/// Signs `message` with the RSA-PSS parameters of an Arweave miner.
+fn sign(keypair: &PKey<Private>, message: &[u8]) -> Base64 {
+    let mut signer = Signer::new(MessageDigest::sha256(), keypair).unwrap();
+    signer.set_rsa_padding(Padding::PKCS1_PSS).unwrap();
+    signer
+        .set_rsa_pss_saltlen(RsaPssSaltlen::DIGEST_LENGTH)
+        .unwrap();
+    signer.update(message).unwrap();
+    Base64(signer.sign_to_vec().unwrap())
+}
+
+#[test]
+fn double_signing_proof() {
+    // The mainnet proof holds two signatures of the same preimage, which only
+    // shows the miner signed one block
+    let (mainnet_block_header, _) = block_header_pair(cases::DOUBLE_SIGNING_PROOF);
+    let proof = &mainnet_block_header.double_signing_proof;
+    assert_eq!(proof.preimage1, proof.preimage2);
+    assert_eq!(
+        (proof.cdiff1, proof.prev_cdiff1),
+        (proof.cdiff2, proof.prev_cdiff2)
+    );
+    assert_ne!(proof.sig1, proof.sig2);
+    assert!(verify_double_signing_proof(&mainnet_block_header).is_err());
+
+    // Sign two blocks at the same cumulative difficulty with the accused key
+    let keypair = PKey::from_rsa(Rsa::generate(4096).unwrap()).unwrap();
+    let pub_key = Base64(keypair.rsa().unwrap().n().to_vec());
+    let mut block_header = mainnet_block_header.clone();
+    let proof = &mut block_header.double_signing_proof;
+    proof.pub_key = Some(pub_key.clone());
+    proof.preimage2 = Some(H512::repeat_byte(2));
+    for (sig, preimage) in [
+        (&mut proof.sig1, proof.preimage1),
+        (&mut proof.sig2, proof.preimage2),
+    ] {
+        let message = signature_preimage(
+            proof.cdiff1.unwrap(),
+            proof.prev_cdiff1.unwrap(),
+            &preimage.unwrap(),
+        );
+        *sig = Some(sign(&keypair, &message));
+    }
+
+    // The accused miner has to be in the reward history
+    let accused_entry = RewardHistoryEntry {
+        reward_addr: H256(sha::sha256(pub_key.as_slice())),
+        ..Default::default()
+    };
+    let reward_history = RewardHistory(vec![accused_entry]);
+    assert!(validate_double_signing_proof(&block_header, &reward_history).is_ok());
+    assert!(validate_double_signing_proof(&block_header, &RewardHistory::default()).is_err());
+
+    // Blocks without a proof don't need a reward history
+    let (no_proof_block_header, _) = block_header_pair(cases::NO_TX);
+    assert!(
+        validate_double_signing_proof(&no_proof_block_header, &RewardHistory::default()).is_ok()
+    );
+
+    let mut accuses_producer = block_header.clone();
+    accuses_producer.reward_addr = reward_history.0[0].reward_addr;
+    assert!(validate_double_signing_proof(&accuses_producer, &reward_history).is_err());
+
+    let mut bad_signature = block_header.clone();
+    bad_signature.double_signing_proof.sig2 = bad_signature.double_signing_proof.sig1.clone();
+    assert!(validate_double_signing_proof(&bad_signature, &reward_history).is_err());
+
+    let mut bad_cdiff = block_header.clone();
+    bad_cdiff.double_signing_proof.cdiff2 = bad_cdiff.double_signing_proof.prev_cdiff1;
+    assert!(validate_double_signing_proof(&bad_cdiff, &reward_history).is_err());
+
+    let mut bad_preimage = block_header.clone();
+    bad_preimage.double_signing_proof.preimage2 = Some(H512::zero());
+    assert!(validate_double_signing_proof(&bad_preimage, &reward_history).is_err());
+
+    // A second signature of the first preimage proves nothing
+    let mut same_preimage = block_header.clone();
+    let proof = &mut same_preimage.double_signing_proof;
+    let message = signature_preimage(
+        proof.cdiff1.unwrap(),
+        proof.prev_cdiff1.unwrap(),
+        &proof.preimage1.unwrap(),
+    );
+    proof.preimage2 = proof.preimage1;
+    proof.sig2 = Some(sign(&keypair, &message));
+    assert!(validate_double_signing_proof(&same_preimage, &reward_history).is_err());
+}
+
+#[test]
+fn solution_cache() {
+    let (block_header, previous_block_header) = block_header_pair(cases::NO_TX);
+
+    // A miner signs two different blocks at the same cumulative difficulty
+    let keypair = PKey::from_rsa(Rsa::generate(4096).unwrap()).unwrap();
+    let reward_key = Base64(keypair.rsa().unwrap().n().to_vec());
+    let sign_block = |block_header: &mut ArweaveBlockHeader, indep_hash_byte: u8| {
+        block_header.reward_key = reward_key.clone();
+        block_header.reward_addr = H256(sha::sha256(reward_key.as_slice()));
+        let message = signature_preimage(
+            block_header.cumulative_diff,
+            block_header.previous_cumulative_diff,
+            &signed_preimage(block_header, &MAINNET),
+        );
+        block_header.signature = sign(&keypair, &message);
+        block_header.indep_hash = H384::repeat_byte(indep_hash_byte);
+    };
+    let mut block1 = block_header.clone();
+    sign_block(&mut block1, 1);
+    let mut block2 = block_header.clone();
+    block2.timestamp += 1;
+    sign_block(&mut block2, 2);
+
+    let mut solution_cache = SolutionCache::new(10, &MAINNET);
+    assert!(matches!(solution_cache.check(&block1), SolutionCheck::New));
+    solution_cache.insert(&block1);
+    assert!(matches!(
+        solution_cache.check(&block1),
+        SolutionCheck::AlreadyValidated
+    ));
+
+    // Another miner can't have found the same solution
+    let mut other_miner_block = block_header.clone();
+    other_miner_block.hash = block1.hash;
+    assert!(matches!(
+        solution_cache.check(&other_miner_block),
+        SolutionCheck::DuplicateSolution
+    ));
+
+    // The double signing proof built from the cache verifies when included
+    // in the next block
+    let SolutionCheck::DoubleSigning(proof) = solution_cache.check(&block2) else {
+        panic!("expected the second block to be double signing");
+    };
+    let mut next_block = block_header.clone();
+    next_block.double_signing_proof = *proof;
+    assert!(matches!(
+        verify_double_signing_proof(&next_block),
+        Ok(Some(accused_addr)) if accused_addr == block1.reward_addr
+    ));
+
+    // Blocks are evicted once the cache is full
+    let mut small_cache = SolutionCache::new(1, &MAINNET);
+    small_cache.insert(&block1);
+    small_cache.insert(&previous_block_header);
+    assert_eq!(small_cache.len(), 1);
+    assert!(matches!(small_cache.check(&block1), SolutionCheck::New));
+}
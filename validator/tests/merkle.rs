@@ -0,0 +1,31 @@
+use arweave_rs_types::Base64;
+use arweave_rs_validator::merkle::*;
+
+#[test]
+fn malformed_paths() {
+    // A tree of four leaves, the path of each holds two branch proofs
+    let leaves = [(1u8, 0, 10), (2, 10, 20), (3, 20, 30), (4, 30, 40)]
+        .iter()
+        .map(|&(byte, min, max)| generate_leaf(&[byte; HASH_SIZE], min, max).unwrap())
+        .collect();
+    let root = generate_root(leaves).unwrap().unwrap();
+    let path = generate_path(&root, 25).unwrap();
+
+    let result = validate_path(root.id, &Base64(path.clone()), 25).unwrap();
+    assert_eq!(
+        path.len(),
+        2 * (2 * HASH_SIZE + NOTE_SIZE) + HASH_SIZE + NOTE_SIZE
+    );
+    assert_eq!(result.leaf_hash, [3; HASH_SIZE]);
+    assert_eq!((result.left_bound, result.right_bound), (20, 30));
+
+    // Paths shorter than a leaf proof or with a partial branch proof are
+    // rejected instead of panicking
+    for size in [0, HASH_SIZE, HASH_SIZE + NOTE_SIZE + 1, path.len() - 1] {
+        let truncated = Base64(path[path.len() - size..].to_vec());
+        assert!(validate_path(root.id, &truncated, 25).is_err());
+    }
+    let mut extended = path.clone();
+    extended.push(0);
+    assert!(validate_path(root.id, &Base64(extended), 25).is_err());
+}
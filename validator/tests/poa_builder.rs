@@ -0,0 +1,142 @@
+use arweave_rs_devnet::{weave, DevnetConfig};
+use arweave_rs_indexes::{block_index::BlockIndexItem, BlockIndex};
+use arweave_rs_packing::{compute_entropy, feistel::feistel_encrypt};
+use arweave_rs_randomx::{create_randomx_vm, RandomXMode};
+use arweave_rs_test_support::{fixtures::empty_block_index, serial};
+use arweave_rs_types::{consensus::*, *};
+use arweave_rs_validator::{poa::PoaBuilder, poa_is_valid, transaction::compute_tx_root};
+use openssl::sha;
+
+#[test]
+fn poa_builder() {
+    let _serial = serial();
+    let randomx_vm = create_randomx_vm(RandomXMode::FastInitialization, RANDOMX_PACKING_KEY);
+    let params = DevnetConfig::localnet_params();
+    let reward_addr = H256(sha::sha256(b"test_poa_builder"));
+    let chunk_size = DATA_CHUNK_SIZE as u64;
+
+    // A block with a two chunk transaction, a 100,000 byte transaction padded
+    // to 256 KiB and a one chunk transaction
+    let chunks: Vec<Vec<u8>> = (0..4)
+        .map(|index| {
+            let mut chunk = weave::chunk_data(b"test_poa_builder", index);
+            if index == 2 {
+                chunk.truncate(100_000);
+            }
+            chunk
+        })
+        .collect();
+    let chunk_id = |index: usize| generate_chunk_id(&chunks[index]);
+    let tx_chunks = [
+        vec![(chunk_id(0), chunk_size), (chunk_id(1), 2 * chunk_size)],
+        vec![(chunk_id(2), 100_000)],
+        vec![(chunk_id(3), chunk_size)],
+    ];
+    let chunk_end_offsets = [chunk_size, 2 * chunk_size, 2 * chunk_size + 100_000, 4 * chunk_size];
+
+    // The block index holds the tx_root, which commits to the data_roots
+    let empty_block_index = empty_block_index();
+    let mut data_root_builder = PoaBuilder::new(&empty_block_index, &params);
+    let data_roots: Vec<Vec<u8>> = tx_chunks
+        .iter()
+        .map(|chunks| data_root_builder.add_tx_chunks(chunks).unwrap().to_vec())
+        .collect();
+    let size_tagged_txs = vec![
+        (data_roots[0].clone(), 2 * chunk_size),
+        (data_roots[1].clone(), 2 * chunk_size + 100_000),
+        (vec![], 3 * chunk_size),
+        (data_roots[2].clone(), 4 * chunk_size),
+    ];
+    let tx_root = compute_tx_root(&size_tagged_txs).unwrap().unwrap();
+    let block_index = BlockIndex::new().init_from_items(vec![BlockIndexItem {
+        block_hash: H384::zero(),
+        weave_size: 4 * chunk_size as u128,
+        tx_root,
+    }]);
+
+    let mut builder = PoaBuilder::new(&block_index, &params);
+    assert!(builder.build_poa(0, &vec![0u8; chunk_size as usize]).is_err());
+    for chunks in &tx_chunks {
+        builder.add_tx_chunks(chunks).unwrap();
+    }
+    assert_eq!(builder.add_block_txs(&size_tagged_txs).unwrap(), tx_root);
+
+    // Pack the chunks for the reward address, padding the short chunk
+    let packed_chunks: Vec<Vec<u8>> = chunks
+        .iter()
+        .zip(chunk_end_offsets)
+        .map(|(chunk, chunk_end_offset)| {
+            let input =
+                get_chunk_entropy_input(U256::from(chunk_end_offset), &tx_root, &reward_addr);
+            let entropy = compute_entropy(&input, RANDOMX_PACKING_ROUNDS_2_6, Some(&randomx_vm));
+            let mut plaintext = chunk.clone();
+            plaintext.resize(chunk_size as usize, 0);
+            feistel_encrypt(&plaintext, &entropy)
+        })
+        .collect();
+
+    // Recall bytes in every chunk, including the padding after the short one
+    let recall_bytes = [
+        (0, 0),
+        (chunk_size + 5, 1),
+        (2 * chunk_size + 99_999, 2),
+        (2 * chunk_size + 200_000, 2),
+        (3 * chunk_size + 1, 3),
+    ];
+    let poa_is_valid_at = |poa: &PoaData, recall_byte: u64| {
+        poa_is_valid(
+            poa,
+            U256::from(recall_byte),
+            &block_index,
+            &reward_addr,
+            Some(&randomx_vm),
+            &params,
+        )
+    };
+    for (recall_byte, index) in recall_bytes {
+        let poa = builder.build_poa(recall_byte, &packed_chunks[index]).unwrap();
+        assert!(poa_is_valid_at(&poa, recall_byte), "recall_byte {recall_byte}");
+    }
+
+    // A proof doesn't validate for a recall byte in another chunk
+    let poa = builder.build_poa(0, &packed_chunks[0]).unwrap();
+    assert!(!poa_is_valid_at(&poa, 3 * chunk_size + 1));
+
+    // The solution fields of the header are derived from the packed chunks
+    let mining_hash = sha::sha256(b"test_poa_builder mining_hash");
+    let recall_byte2 = 3 * chunk_size + 1;
+    let solution = builder
+        .build_solution(
+            &mining_hash,
+            1,
+            0,
+            &packed_chunks[0],
+            Some((recall_byte2, &packed_chunks[3])),
+        )
+        .unwrap();
+    let mut block_header = ArweaveBlockHeader::default();
+    solution.apply_to_header(&mut block_header);
+    // Spelled out rather than with the consensus helpers: H1 is the solution
+    // hash of the first chunk and H2 chains it (not its preimage) with the
+    // second chunk (ar_block:compute_h1, compute_h2). The helpers are checked
+    // against a mainnet PoA2 block in the mining tests
+    let sha256 = |parts: &[&[u8]]| {
+        let mut hasher = sha::Sha256::new();
+        parts.iter().for_each(|part| hasher.update(part));
+        hasher.finish()
+    };
+    let h1_preimage = sha256(&[&mining_hash, &1u64.to_be_bytes(), &packed_chunks[0]]);
+    let h1 = sha256(&[&mining_hash, &h1_preimage]);
+    let h2_preimage = sha256(&[&h1, &packed_chunks[3]]);
+    assert_eq!(block_header.hash_preimage, H256(h2_preimage));
+    assert_eq!(block_header.hash, H256(sha256(&[&mining_hash, &h2_preimage])));
+    assert_eq!(block_header.chunk_hash, H256(sha::sha256(&packed_chunks[0])));
+    assert_eq!(
+        block_header.chunk2_hash,
+        Some(H256(sha::sha256(&packed_chunks[3])))
+    );
+    assert_eq!(block_header.recall_byte, 0);
+    assert_eq!(block_header.recall_byte2, Some(U256::from(recall_byte2)));
+    assert_eq!(block_header.nonce.0, 1);
+    assert_eq!(block_header.poa2.chunk.as_slice(), packed_chunks[3].as_slice());
+}
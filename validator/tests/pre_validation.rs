@@ -0,0 +1,104 @@
+//! Pre-validates mainnet blocks. Their proofs of access are validated against
+//! the mainnet block index, so the cases that pass need `data/index.dat` or
+//! the network and only run with `cargo test -- --ignored`. Without it every
+//! check up to the proof of access still runs.
+use arweave_rs_indexes::BlockIndex;
+use arweave_rs_randomx::{create_randomx_vm, RandomXMode};
+use arweave_rs_test_support::{fixtures::*, serial};
+use arweave_rs_types::{consensus::*, *};
+use arweave_rs_validator::{
+    pre_validate_block,
+    report::{ValidationReport, ValidationStage},
+};
+
+const MAINNET: ConsensusParams = ConsensusParams::mainnet();
+
+#[test]
+#[ignore = "needs the mainnet block index"]
+fn pre_validation() {
+    let _serial = serial();
+    let (block_header, previous_block_header) = block_header_pair(cases::NO_TX);
+    let block_index = mainnet_block_index();
+    let randomx_vm = create_randomx_vm(RandomXMode::FastInitialization, RANDOMX_PACKING_KEY);
+
+    let solution_hash = pre_validate_block(
+        &block_header,
+        &previous_block_header,
+        &block_index,
+        Some(&randomx_vm),
+        &MAINNET,
+        None,
+    )
+    .unwrap();
+
+    let solution_hash_value_big: U256 = U256::from_big_endian(&solution_hash);
+    assert!(solution_hash_value_big > block_header.diff);
+}
+
+#[test]
+fn pre_validation_without_the_recall_block() {
+    let _serial = serial();
+    let (block_header, previous_block_header) = block_header_pair(cases::NO_TX);
+    let randomx_vm = create_randomx_vm(RandomXMode::FastInitialization, RANDOMX_PACKING_KEY);
+
+    // Only the parent is indexed, so the recall byte is looked up in its
+    // bounds and the tx_path doesn't lead to its tx_root
+    let block_index = BlockIndex::new().init_from_items(vec![(&previous_block_header).into()]);
+    let mut report = ValidationReport::default();
+    let err = pre_validate_block(
+        &block_header,
+        &previous_block_header,
+        &block_index,
+        Some(&randomx_vm),
+        &MAINNET,
+        Some(&mut report),
+    )
+    .expect_err("the poa to be invalid");
+    assert_eq!(err.to_string(), "poa is invalid");
+
+    let stages: Vec<_> = report.stages().map(|(stage, _)| stage).collect();
+    assert_eq!(
+        stages,
+        [
+            ValidationStage::ProofSizes,
+            ValidationStage::ChunkHashes,
+            ValidationStage::IndepHash,
+            ValidationStage::RetargetAndDiff,
+            ValidationStage::QuickPow,
+            ValidationStage::SeedData,
+            ValidationStage::RecallBytes,
+            ValidationStage::Merkle,
+        ]
+    );
+}
+
+/// Asserts pre-validation of the block at `height` fails on its `poa`.
+fn assert_poa_is_invalid(height: u64) {
+    let _serial = serial();
+    let (block_header, previous_block_header) = block_header_pair(height);
+    let block_index = mainnet_block_index();
+    let randomx_vm = create_randomx_vm(RandomXMode::FastInitialization, RANDOMX_PACKING_KEY);
+
+    let err = pre_validate_block(
+        &block_header,
+        &previous_block_header,
+        &block_index,
+        Some(&randomx_vm),
+        &MAINNET,
+        None,
+    )
+    .expect_err("the poa to be invalid");
+    assert_eq!(err.to_string(), "poa is invalid");
+}
+
+#[test]
+#[ignore = "needs the mainnet block index"]
+fn poa_failed_case() {
+    assert_poa_is_invalid(cases::POA_FAILED);
+}
+
+#[test]
+#[ignore = "needs the mainnet block index"]
+fn bad_tx_path_case() {
+    assert_poa_is_invalid(cases::BAD_TX_PATH);
+}
@@ -0,0 +1,60 @@
+//! Records the time of each pre-validation stage of devnet blocks, whose
+//! proofs of access validate without the mainnet block index.
+use arweave_rs_test_support::devnet_fixture;
+use arweave_rs_validator::{
+    pre_validate_block,
+    report::{ValidationReport, ValidationStage},
+};
+
+#[test]
+fn stages_are_recorded() {
+    let mut fixture = devnet_fixture(b"test_validation_report");
+    let (devnet, randomx_vm) = (&mut fixture.devnet, &fixture.randomx_vm);
+    let block_header = devnet.produce_block(Some(randomx_vm)).unwrap().clone();
+    let blocks = devnet.blocks();
+    let previous_block_header = &blocks[blocks.len() - 2];
+
+    let mut report = ValidationReport::default();
+    pre_validate_block(
+        &block_header,
+        previous_block_header,
+        &devnet.block_index(),
+        Some(randomx_vm),
+        devnet.params(),
+        Some(&mut report),
+    )
+    .unwrap();
+
+    // Every stage ran, PoA2 only for a two chunk solution
+    for stage in ValidationStage::ALL {
+        let expected = stage != ValidationStage::Poa2 || block_header.chunk2_hash.is_some();
+        assert_eq!(report.get(stage).is_some(), expected, "{}", stage.name());
+    }
+    assert_eq!(
+        report.total(),
+        report.stages().map(|(_, elapsed)| elapsed).sum()
+    );
+
+    // A failing block records the stages up to the failed one
+    let mut tampered_block = block_header.clone();
+    tampered_block.timestamp += 1;
+    let mut report = ValidationReport::default();
+    let result = pre_validate_block(
+        &tampered_block,
+        previous_block_header,
+        &devnet.block_index(),
+        Some(randomx_vm),
+        devnet.params(),
+        Some(&mut report),
+    );
+    assert!(result.is_err());
+    let stages: Vec<_> = report.stages().map(|(stage, _)| stage).collect();
+    assert_eq!(
+        stages,
+        [
+            ValidationStage::ProofSizes,
+            ValidationStage::ChunkHashes,
+            ValidationStage::IndepHash,
+        ]
+    );
+}
@@ -0,0 +1,182 @@
+use arweave_rs_test_support::fixtures::*;
+use arweave_rs_types::{consensus::*, reward_history::*, transaction::*, *};
+use arweave_rs_validator::pricing::{validate_block_rewards, validate_reward_history};
+use openssl::sha::sha256;
+
+const MAINNET: ConsensusParams = ConsensusParams::mainnet();
+
+#[test]
+fn block_rewards() {
+    // With no transactions the reward is the inflation alone
+    let (block_header, previous_block_header) = block_header_pair(cases::NO_TX);
+    let validate = |block_header: &ArweaveBlockHeader| {
+        validate_block_rewards(block_header, &previous_block_header, &[], TARGET_TIME, &MAINNET)
+    };
+    assert!(validate(&block_header).is_ok());
+
+    // The inflation covers the cost of storing the weave for this block, so
+    // the result doesn't depend on the interval taken from the block time
+    // history (which isn't part of the fixtures)
+    for block_interval in [TARGET_TIME / 2, TARGET_TIME * 2, TARGET_TIME * 10] {
+        let result = validate_block_rewards(
+            &block_header,
+            &previous_block_header,
+            &[],
+            block_interval,
+            &MAINNET,
+        );
+        assert!(result.is_ok());
+    }
+
+    let mut bad_reward = block_header.clone();
+    bad_reward.reward += 1;
+    assert!(validate(&bad_reward).is_err());
+
+    // Prices only change at adjustment heights
+    let mut bad_price = block_header.clone();
+    bad_price.scheduled_price_per_gib_minute += U256::one();
+    assert!(validate(&bad_price).is_err());
+}
+
+#[test]
+fn transaction_fees() {
+    // The miner gets 1/21 of the fees, the rest goes to the endowment pool
+    let (block_header, previous_block_header) = block_header_pair(cases::NO_TX);
+    let tx = ArweaveTransaction {
+        reward: U256::from(21_000),
+        denomination: block_header.denomination,
+        ..Default::default()
+    };
+    let fees =
+        pricing::distribute_transaction_fees(std::slice::from_ref(&tx), block_header.denomination);
+    assert_eq!(fees.miner_fee_share, U256::from(1_000));
+    assert_eq!(fees.endowment_fee_share, U256::from(20_000));
+
+    let mut with_fees = block_header.clone();
+    with_fees.reward += 1_000;
+    with_fees.reward_pool += 20_000;
+    let validate = |block_header: &ArweaveBlockHeader| {
+        validate_block_rewards(
+            block_header,
+            &previous_block_header,
+            std::slice::from_ref(&tx),
+            TARGET_TIME,
+            &MAINNET,
+        )
+    };
+    assert!(validate(&with_fees).is_ok());
+
+    // Paying the whole fee to the miner is rejected
+    let mut all_to_miner = block_header.clone();
+    all_to_miner.reward += 21_000;
+    assert!(validate(&all_to_miner).is_err());
+}
+
+#[test]
+fn kryder_plus_rate_multiplier() {
+    // An empty endowment pool mints debt and doubles the Kryder+ multiplier once
+    let (block_header, previous_block_header) = block_header_pair(cases::NO_TX);
+    let mut empty_pool = previous_block_header.clone();
+    empty_pool.reward_pool = 0;
+    let args = pricing::MinerRewardArgs {
+        height: block_header.height,
+        weave_size: block_header.weave_size,
+        price_per_gib_minute: U256::from(1_000_000),
+        denomination: block_header.denomination,
+        block_interval: TARGET_TIME,
+        fees: pricing::TransactionFees::default(),
+    };
+    let reward = pricing::get_miner_reward_endowment_pool_debt_supply(&empty_pool, &args);
+    assert!(reward.reward_pool.is_zero());
+    assert!(!reward.debt_supply.is_zero());
+    assert_eq!(
+        reward.kryder_plus_rate_multiplier,
+        empty_pool.kryder_plus_rate_multiplier * 2
+    );
+    assert_eq!(reward.kryder_plus_rate_multiplier_latch, U256::one());
+
+    empty_pool.kryder_plus_rate_multiplier_latch = U256::one();
+    let reward = pricing::get_miner_reward_endowment_pool_debt_supply(&empty_pool, &args);
+    assert_eq!(
+        reward.kryder_plus_rate_multiplier,
+        empty_pool.kryder_plus_rate_multiplier
+    );
+}
+
+#[test]
+fn reward_history() {
+    let (block_header, previous_block_header) = block_header_pair(cases::NO_TX);
+
+    // The hash rate of a block is its increment of the cumulative_diff
+    let hash_rate = get_hash_rate(block_header.diff);
+    assert_eq!(
+        hash_rate,
+        block_header.cumulative_diff - previous_block_header.cumulative_diff
+    );
+
+    // The window is trimmed to the latest REWARD_HISTORY_BLOCKS entries
+    let mut reward_history = RewardHistory::default();
+    for _ in 0..=REWARD_HISTORY_BLOCKS {
+        reward_history.push_block(&previous_block_header);
+    }
+    assert_eq!(reward_history.len(), REWARD_HISTORY_BLOCKS);
+
+    // Commit the headers to a short history and validate the update
+    let previous_reward_history = RewardHistory(vec![RewardHistoryEntry::from_block_header(
+        &previous_block_header,
+    )]);
+    let mut previous_block_header = previous_block_header.clone();
+    previous_block_header.reward_history_hash = previous_reward_history.hash();
+    let mut block_header = block_header.clone();
+    block_header.reward_history_hash = previous_reward_history.with_block(&block_header).hash();
+
+    let reward_history = validate_reward_history(
+        &block_header,
+        &previous_block_header,
+        &previous_reward_history,
+        &MAINNET,
+    )
+    .unwrap();
+    assert_eq!(reward_history.len(), 2);
+    assert!(reward_history.contains_reward_addr(&block_header.reward_addr));
+
+    block_header.reward += 1;
+    let result = validate_reward_history(
+        &block_header,
+        &previous_block_header,
+        &previous_reward_history,
+        &MAINNET,
+    );
+    assert!(result.is_err());
+}
+
+#[test]
+fn reward_history_hash() {
+    // ar_rewards:reward_history_hash prepends each entry, most recent first,
+    // to an iolist ending with the entry count, so the entries are hashed
+    // oldest first. Integers are encoded as a one byte length followed by
+    // their big-endian bytes and the denomination as a 24 bit integer
+    let newest = RewardHistoryEntry {
+        reward_addr: H256::repeat_byte(1),
+        hash_rate: U256::from(0x0102),
+        reward: U256::from(902100567043_u64),
+        denomination: U256::one(),
+    };
+    let oldest = RewardHistoryEntry {
+        reward_addr: H256::repeat_byte(2),
+        hash_rate: U256::zero(),
+        reward: U256::from(7),
+        denomination: U256::from(2),
+    };
+    let reward_history = RewardHistory(vec![newest, oldest]);
+
+    let mut preimage = Vec::new();
+    preimage.extend_from_slice(&[2; 32]);
+    preimage.extend_from_slice(&[1, 0, 1, 7, 0, 0, 2]);
+    preimage.extend_from_slice(&[1; 32]);
+    preimage.extend_from_slice(&[2, 0x01, 0x02]);
+    preimage.extend_from_slice(&[5, 0xd2, 0x09, 0x62, 0x44, 0x03]);
+    preimage.extend_from_slice(&[0, 0, 1]);
+    preimage.extend_from_slice(&[1, 2]);
+    assert_eq!(reward_history.hash(), H256(sha256(&preimage)));
+}
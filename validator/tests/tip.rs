@@ -0,0 +1,32 @@
+use arweave_rs_test_support::fixtures::*;
+use arweave_rs_validator::tip::{
+    validate_tip_block, FixedClock, TipValidation, MAX_NODES_CLOCK_DEVIATION,
+};
+
+#[test]
+fn tip_validation() {
+    let (block_header, previous_block_header) = block_header_pair(cases::NO_TX);
+    let timestamp = block_header.timestamp;
+    let step = block_header.nonce_limiter_info.global_step_number;
+
+    // A block received shortly after it was produced
+    let clock = FixedClock(timestamp + 2);
+    let tip = TipValidation::new(&clock, step + 2);
+    assert!(validate_tip_block(&block_header, &previous_block_header, &tip).is_ok());
+
+    // The block timestamp is too far in the future of the local clock
+    let clock = FixedClock(timestamp - MAX_NODES_CLOCK_DEVIATION - 1);
+    let tip = TipValidation::new(&clock, step);
+    assert!(validate_tip_block(&block_header, &previous_block_header, &tip).is_err());
+
+    // The block timestamp is too far before its parents
+    let clock = FixedClock(timestamp);
+    let tip = TipValidation::new(&clock, step);
+    let mut time_warped = block_header.clone();
+    time_warped.timestamp = previous_block_header.timestamp - MAX_NODES_CLOCK_DEVIATION - 1;
+    assert!(validate_tip_block(&time_warped, &previous_block_header, &tip).is_err());
+
+    // A proposal far behind the local VDF is stale
+    let tip = TipValidation::new(&clock, step + 1000);
+    assert!(validate_tip_block(&block_header, &previous_block_header, &tip).is_err());
+}
@@ -0,0 +1,136 @@
+use arweave_rs_test_support::fixtures::*;
+use arweave_rs_types::{
+    consensus::ConsensusParams,
+    deep_hash::{deep_hash, DeepHashItem},
+    transaction::*,
+    *,
+};
+use arweave_rs_validator::transaction::{validate_block_txs, validate_transaction};
+use openssl::{
+    hash::MessageDigest,
+    pkey::{PKey, Private},
+    rsa::{Padding, Rsa},
+    sha,
+    sign::{RsaPssSaltlen, Signer},
+};
+
+const MAINNET: ConsensusParams = ConsensusParams::mainnet();
+
+/// Signs `message` the same way an Arweave client would.
+fn sign(keypair: &PKey<Private>, message: &[u8]) -> Base64 {
+    let mut signer = Signer::new(MessageDigest::sha256(), keypair).unwrap();
+    signer.set_rsa_padding(Padding::PKCS1_PSS).unwrap();
+    signer
+        .set_rsa_pss_saltlen(RsaPssSaltlen::DIGEST_LENGTH)
+        .unwrap();
+    signer.update(message).unwrap();
+    Base64(signer.sign_to_vec().unwrap())
+}
+
+/// Round trips a signed transaction through its JSON representation.
+fn round_trip(tx: &ArweaveTransaction) -> ArweaveTransaction {
+    let json = serde_json::to_string(tx).unwrap();
+    serde_json::from_str(&json).unwrap()
+}
+
+#[test]
+fn transaction_signature_format_1() {
+    let rsa = Rsa::generate(4096).unwrap();
+    let mut tx = ArweaveTransaction {
+        format: 1,
+        owner: Base64(rsa.n().to_vec()),
+        target: Base64(vec![3; 32]),
+        quantity: U256::from(1_000_000),
+        reward: U256::from(42_000),
+        last_tx: Base64(vec![9; 32]),
+        data: Base64::from_utf8_str("hello world").unwrap(),
+        data_size: 11,
+        tags: vec![Tag {
+            name: Base64::from_utf8_str("Content-Type").unwrap(),
+            value: Base64::from_utf8_str("text/plain").unwrap(),
+        }],
+        ..Default::default()
+    };
+
+    // Format 1 signs the plain concatenation of the fields (ar_tx.erl)
+    let message = [
+        tx.owner.as_slice(),
+        tx.target.as_slice(),
+        b"hello world",
+        b"1000000",
+        b"42000",
+        tx.last_tx.as_slice(),
+        b"Content-Type",
+        b"text/plain",
+    ]
+    .concat();
+    assert_eq!(tx.signature_data(), message);
+
+    let keypair = PKey::from_rsa(rsa).unwrap();
+    tx.signature = sign(&keypair, &message);
+    tx.id = H256(sha::sha256(tx.signature.as_slice()));
+
+    let mut tx = round_trip(&tx);
+    assert!(validate_transaction(&tx).is_ok());
+
+    // The data is signed directly, so changing it invalidates the signature
+    tx.data = Base64::from_utf8_str("hello earth").unwrap();
+    assert!(validate_transaction(&tx).is_err());
+}
+
+#[test]
+fn transaction_signature_format_2() {
+    let rsa = Rsa::generate(4096).unwrap();
+    let mut tx = ArweaveTransaction {
+        format: 2,
+        owner: Base64(rsa.n().to_vec()),
+        quantity: U256::from(1_000_000),
+        reward: U256::from(42_000),
+        data_size: 11,
+        data_root: Some(H256::repeat_byte(7)),
+        tags: vec![Tag {
+            name: Base64::from_utf8_str("Content-Type").unwrap(),
+            value: Base64::from_utf8_str("text/plain").unwrap(),
+        }],
+        ..Default::default()
+    };
+
+    // Format 2 signs the deep hash of the list of fields (ar_tx.erl)
+    let blob = |bytes: &[u8]| DeepHashItem::from_bytes(bytes);
+    let message = deep_hash(&DeepHashItem::List(vec![
+        blob(b"2"),
+        blob(tx.owner.as_slice()),
+        blob(b""),
+        blob(b"1000000"),
+        blob(b"42000"),
+        blob(b""),
+        DeepHashItem::List(vec![DeepHashItem::List(vec![
+            blob(b"Content-Type"),
+            blob(b"text/plain"),
+        ])]),
+        blob(b"11"),
+        blob(&[7; 32]),
+    ]));
+    assert_eq!(tx.signature_data(), message);
+
+    let keypair = PKey::from_rsa(rsa).unwrap();
+    tx.signature = sign(&keypair, &message);
+    tx.id = H256(sha::sha256(tx.signature.as_slice()));
+
+    let mut tx = round_trip(&tx);
+    assert!(validate_transaction(&tx).is_ok());
+
+    // Tampering with any of the signed fields must invalidate the signature
+    tx.quantity = U256::from(2_000_000);
+    assert!(validate_transaction(&tx).is_err());
+}
+
+#[test]
+fn block_txs_no_tx() {
+    let (block_header, previous_block_header) = block_header_pair(cases::NO_TX);
+    assert!(validate_block_txs(&block_header, &previous_block_header, &[], &MAINNET).is_ok());
+
+    // A block with transactions can't be validated without them
+    let (block_header, previous_block_header) = block_header_pair(cases::POA2);
+    assert!(validate_block_txs(&block_header, &previous_block_header, &[], &MAINNET).is_err());
+}
@@ -0,0 +1,20 @@
+use arweave_rs_test_support::fixtures::{nonce_limiter_infos, vdf_cases};
+use arweave_rs_types::consensus::ConsensusParams;
+use arweave_rs_vdf::verify::last_step_checkpoints_is_valid;
+use criterion::{criterion_group, criterion_main, Criterion};
+
+fn last_step_checkpoints(c: &mut Criterion) {
+    let params = ConsensusParams::mainnet();
+    let nonce_info = &nonce_limiter_infos(vdf_cases::NO_RESET)[1];
+
+    // Each iteration computes a full VDF step, around a second of work
+    let mut group = c.benchmark_group("vdf");
+    group.sample_size(10);
+    group.bench_function("last_step_checkpoints", |b| {
+        b.iter(|| assert!(last_step_checkpoints_is_valid(nonce_info, &params)))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, last_step_checkpoints);
+criterion_main!(benches);
@@ -3,6 +3,7 @@
 use arweave_rs_types::{consensus::*, *};
 use rayon::prelude::*;
 use openssl::sha;
+use tracing::{debug, debug_span};
 
 // erlang consensus constants
 // ================================================
@@ -28,10 +29,10 @@ pub fn step_number_to_salt_number(step_number: usize) -> usize {
 /// Between Arweave v2.6 and v2.7 the vdf difficulty was stored in a constant so
 /// when parsing NonceLimiterInfo where there is no `vdf_difficulty` header,
 /// this method returns the correct constant difficulty.
-fn get_vdf_difficulty(nonce_info: &NonceLimiterInfo) -> usize {
+fn get_vdf_difficulty(nonce_info: &NonceLimiterInfo, params: &ConsensusParams) -> usize {
     match nonce_info.vdf_difficulty {
         Some(diff) => diff as usize,
-        None => VDF_SHA_1S as usize / NUM_CHECKPOINTS_IN_VDF_STEP,
+        None => params.vdf_sha_1s as usize / NUM_CHECKPOINTS_IN_VDF_STEP,
     }
 }
 
@@ -129,19 +130,35 @@ pub fn vdf_sha2(
 /// # Arguments
 ///
 /// * `nonce_info` - The NonceLimiterInput from the block header to validate.
+/// * `params` - The consensus parameters of the network, providing the VDF
+///   reset frequency and the default VDF difficulty.
 ///
 /// # Returns
 ///
 /// - `bool` - `true` if the checkpoints are valid, `false` otherwise.
-pub fn last_step_checkpoints_is_valid(nonce_info: &NonceLimiterInfo) -> bool {
-    let num_iterations = get_vdf_difficulty(nonce_info);
+pub fn last_step_checkpoints_is_valid(
+    nonce_info: &NonceLimiterInfo,
+    params: &ConsensusParams,
+) -> bool {
+    let _span = debug_span!(
+        "last_step_checkpoints_is_valid",
+        global_step_number = nonce_info.global_step_number
+    )
+    .entered();
+    let num_iterations = get_vdf_difficulty(nonce_info, params);
     let global_step_number: usize = nonce_info.global_step_number as usize;
     
-    let mut _seed = *nonce_info.checkpoints.get(1).unwrap();
+    // The last step is seeded by the step before it, which is the output of
+    // the previous block when the block only advanced the VDF by one step
+    let mut _seed = nonce_info
+        .checkpoints
+        .get(1)
+        .copied()
+        .unwrap_or(nonce_info.prev_output);
     let mut checkpoint_hashes = nonce_info.last_step_checkpoints.clone();
 
     // If the vdf reset happened on this step, apply the entropy to the seed
-    if (global_step_number as f64 / NUM_CHECKPOINTS_IN_VDF_STEP as f64).fract() == 0.0 {
+    if global_step_number % params.nonce_limiter_reset_frequency == 0 {
         let reset_seed = nonce_info.seed;
         _seed = apply_reset_seed(_seed, reset_seed);
     }
@@ -181,9 +198,11 @@ pub fn last_step_checkpoints_is_valid(nonce_info: &NonceLimiterInfo) -> bool {
             .collect();
 
         for (index, a, b) in mismatches {
-            println!(
-                "Mismatched hashes at index {}: expected {} got {}",
-                index, base64_url::encode(a), base64_url::encode(b)
+            debug!(
+                index,
+                expected = %base64_url::encode(a),
+                computed = %base64_url::encode(b),
+                "mismatched checkpoint"
             );
         }
     }
@@ -196,12 +215,20 @@ pub fn last_step_checkpoints_is_valid(nonce_info: &NonceLimiterInfo) -> bool {
 /// # Arguments
 ///
 /// * `nonce_info` - The NonceLimiterInput from the block header to validate.
+/// * `params` - The consensus parameters of the network, providing the VDF
+///   reset frequency and the default VDF difficulty.
 ///
 /// # Returns
 ///
 /// - `bool` - `true` if the checkpoints are valid, false otherwise.
-pub fn checkpoints_is_valid(nonce_info: &NonceLimiterInfo) -> bool {
-    let num_iterations = get_vdf_difficulty(nonce_info);
+pub fn checkpoints_is_valid(nonce_info: &NonceLimiterInfo, params: &ConsensusParams) -> bool {
+    let _span = debug_span!(
+        "checkpoints_is_valid",
+        global_step_number = nonce_info.global_step_number,
+        steps = nonce_info.checkpoints.len()
+    )
+    .entered();
+    let num_iterations = get_vdf_difficulty(nonce_info, params);
 
     let previous_seed = nonce_info.prev_output;
     let reset_seed = nonce_info.seed;
@@ -217,7 +244,7 @@ pub fn checkpoints_is_valid(nonce_info: &NonceLimiterInfo) -> bool {
 
     // Make a read only copy for parallel iterating
     let steps = step_hashes.clone();
-    let steps_since_reset = get_vdf_steps_since_reset(nonce_info.global_step_number);
+    let steps_since_reset = get_vdf_steps_since_reset(nonce_info.global_step_number, params);
      // -2 here because we need the step before the reset (-1), and -1 because 
      // we pushed previous_seed to step_hashes making steps.len() one longer.
      // We use i64 intentionally because the steps_since_reset may be larger
@@ -259,9 +286,11 @@ pub fn checkpoints_is_valid(nonce_info: &NonceLimiterInfo) -> bool {
             .collect();
 
         for (index, a, b) in mismatches {
-            println!(
-                "Mismatched hashes at index {}: expected {} got {}",
-                index, base64_url::encode(a), base64_url::encode(b)
+            debug!(
+                index,
+                expected = %base64_url::encode(a),
+                computed = %base64_url::encode(b),
+                "mismatched checkpoint"
             );
         }
     }
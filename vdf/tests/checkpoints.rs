@@ -0,0 +1,78 @@
+//! Recomputes the VDF checkpoints of mainnet blocks around the VDF resets.
+//! Validating all the checkpoints of a block takes minutes, those cases only
+//! run with `cargo test -- --ignored`.
+use arweave_rs_test_support::{fixtures::*, serial};
+use arweave_rs_types::consensus::ConsensusParams;
+use arweave_rs_vdf::verify::*;
+
+const MAINNET: ConsensusParams = ConsensusParams::mainnet();
+
+#[test]
+fn last_step_checkpoints_base() {
+    let _serial = serial();
+    let base_infos = nonce_limiter_infos(vdf_cases::NO_RESET);
+    assert!(last_step_checkpoints_is_valid(&base_infos[1], &MAINNET));
+}
+
+#[test]
+fn last_step_checkpoints_with_last_step_reset() {
+    let _serial = serial();
+    let reset_last_infos = nonce_limiter_infos(vdf_cases::RESET_LAST_STEP);
+    assert!(last_step_checkpoints_is_valid(&reset_last_infos[0], &MAINNET));
+}
+
+#[test]
+#[ignore = "recomputes the checkpoints of a whole block"]
+fn checkpoints_base() {
+    let _serial = serial();
+    let base_infos = nonce_limiter_infos(vdf_cases::NO_RESET);
+    assert!(checkpoints_is_valid(&base_infos[1], &MAINNET));
+}
+
+#[test]
+#[ignore = "recomputes the checkpoints of a whole block"]
+fn checkpoints_reset() {
+    let _serial = serial();
+    let reset_infos = nonce_limiter_infos(vdf_cases::RESET);
+    assert!(checkpoints_is_valid(&reset_infos[1], &MAINNET));
+}
+
+#[test]
+#[ignore = "recomputes the checkpoints of a whole block"]
+fn checkpoints_reset_first_step() {
+    let _serial = serial();
+    let reset_first_infos = nonce_limiter_infos(vdf_cases::RESET_FIRST_STEP);
+    assert!(checkpoints_is_valid(&reset_first_infos[0], &MAINNET));
+}
+
+#[test]
+#[ignore = "recomputes the checkpoints of a whole block"]
+fn checkpoints_reset_last_step() {
+    let _serial = serial();
+    let reset_last_infos = nonce_limiter_infos(vdf_cases::RESET_LAST_STEP);
+    assert!(checkpoints_is_valid(&reset_last_infos[0], &MAINNET));
+}
+
+#[test]
+#[ignore = "recomputes the checkpoints of a whole block"]
+fn checkpoints_reset_last_step_next() {
+    let _serial = serial();
+    let reset_last_infos = nonce_limiter_infos(vdf_cases::RESET_LAST_STEP);
+    assert!(checkpoints_is_valid(&reset_last_infos[1], &MAINNET));
+}
+
+#[test]
+#[ignore = "recomputes the checkpoints of a whole block"]
+fn checkpoints_reset_2nd_to_last_step() {
+    let _serial = serial();
+    let reset_2nd_to_last_infos = nonce_limiter_infos(vdf_cases::RESET_2ND_TO_LAST_STEP);
+    assert!(checkpoints_is_valid(&reset_2nd_to_last_infos[0], &MAINNET));
+}
+
+#[test]
+#[ignore = "recomputes the checkpoints of a whole block"]
+fn checkpoints_reset_3rd_to_last_step() {
+    let _serial = serial();
+    let reset_3rd_to_last_infos = nonce_limiter_infos(vdf_cases::RESET_3RD_TO_LAST_STEP);
+    assert!(checkpoints_is_valid(&reset_3rd_to_last_infos[0], &MAINNET));
+}